@@ -0,0 +1,7 @@
+-- The checks of `:checkhealth compleet` are implemented in Rust, this module
+-- only exists so that Neovim can find them.
+return {
+  check = function()
+    require("compleet").check_health()
+  end,
+}
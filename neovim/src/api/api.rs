@@ -8,7 +8,8 @@ impl<'a> Api<'a> {
     }
 }
 
-/// TODO: docs
+/// The log levels of `vim.log.levels`.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum LogLevel {
     Trace = 0,
     Debug = 1,
@@ -3,6 +3,18 @@ use mlua::{Function, Result, Table};
 use super::Api;
 
 impl<'a> Api<'a> {
+    /// Binding to `vim.api.nvim_clear_autocmds`.
+    ///
+    /// Clears all the autocmds matching the requirements passed to `opts`.
+    ///
+    /// # Arguments
+    ///
+    /// * `opts`  Optional parameters. See `:h nvim_clear_autocmds` for
+    ///   details.
+    pub fn clear_autocmds(&self, opts: Table) -> Result<()> {
+        self.0.get::<&str, Function>("nvim_clear_autocmds")?.call(opts)
+    }
+
     /// Binding to `vim.api.nvim_create_augroup`.
     ///
     /// Creates or gets an augroup. Returns the id to be used in
@@ -87,6 +99,22 @@ impl<'a> Api<'a> {
             .call((events, opts))
     }
 
+    /// Binding to `vim.api.nvim_exec_autocmds`.
+    ///
+    /// Execute all autocommands for `events` that match the corresponding
+    /// `opts`.
+    ///
+    /// # Arguments
+    ///
+    /// * `events`  The event or events to execute.
+    /// * `opts`    Optional parameters. See `:h nvim_exec_autocmds` for
+    ///   details.
+    pub fn exec_autocmds(&self, events: &[&str], opts: Table) -> Result<()> {
+        self.0
+            .get::<&str, Function>("nvim_exec_autocmds")?
+            .call((events, opts))
+    }
+
     /// Binding to `vim.api.nvim_get_autocmds`.
     ///
     /// Get autocmds that match the requirements passed to `opts`.
@@ -0,0 +1,85 @@
+use mlua::{Function, Lua, Result, Table, ToLuaMulti, Value};
+
+use super::Api;
+
+/// The key of the Lua registry where the function flushing the batches is
+/// stored.
+const FLUSH: &str = "neovim_batch_flush";
+
+/// Makes all the calls of a batch, stopping at the first one that fails.
+/// Every call is a `{ name, args }` list, where `args` has its length in the
+/// `n` field since the arguments can contain `nil`s.
+const FLUSH_CHUNK: &str = r#"
+local calls = ...
+local api = vim.api
+for _, call in ipairs(calls) do
+  api[call[1]](unpack(call[2], 1, call[2].n))
+end
+"#;
+
+/// A list of API calls queued to be made all at once by `flush`, which only
+/// crosses into Lua one time instead of once per call. `nvim_call_atomic`
+/// can only be called by remote clients, so the calls are made by a Lua
+/// chunk instead.
+pub struct Batch<'a> {
+    lua: &'a Lua,
+    calls: Vec<Table<'a>>,
+}
+
+impl<'a> Api<'a> {
+    /// Returns an empty batch of API calls.
+    pub fn batch(&self, lua: &'a Lua) -> Batch<'a> {
+        Batch {
+            lua,
+            calls: Vec::new(),
+        }
+    }
+}
+
+impl<'a> Batch<'a> {
+    /// Queues a call to `vim.api.{name}` with the given arguments.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`  Name of the API function, e.g. `nvim_buf_set_extmark`.
+    /// * `args`  Arguments the function is called with.
+    pub fn call<A: ToLuaMulti<'a>>(&mut self, name: &str, args: A) -> Result<()> {
+        let values = args.to_lua_multi(self.lua)?.into_vec();
+        let len = values.len();
+
+        let args = self.lua.create_table_with_capacity(len as i32, 1)?;
+        for (i, value) in values.into_iter().enumerate() {
+            args.raw_set(i + 1, value)?;
+        }
+        args.raw_set("n", len)?;
+
+        let call = self.lua.create_sequence_from([
+            Value::String(self.lua.create_string(name)?),
+            Value::Table(args),
+        ])?;
+
+        self.calls.push(call);
+        Ok(())
+    }
+
+    /// Whether no calls have been queued.
+    pub fn is_empty(&self) -> bool { self.calls.is_empty() }
+
+    /// Makes all the queued calls.
+    pub fn flush(self) -> Result<()> {
+        if self.calls.is_empty() {
+            return Ok(());
+        }
+
+        let flush = match self.lua.named_registry_value::<_, Value>(FLUSH)? {
+            Value::Function(flush) => flush,
+            _ => {
+                let flush: Function = self.lua.load(FLUSH_CHUNK).into_function()?;
+                self.lua.set_named_registry_value(FLUSH, flush.clone())?;
+                flush
+            },
+        };
+
+        flush.call(self.lua.create_sequence_from(self.calls)?)
+    }
+}
@@ -1,4 +1,4 @@
-use mlua::{FromLua, Function, Result, Table};
+use mlua::{FromLua, Function, Result, Table, ToLua};
 
 use super::Api;
 
@@ -69,6 +69,33 @@ impl<'a> Api<'a> {
         ))
     }
 
+    /// Binding to `vim.api.nvim_buf_get_name`.
+    ///
+    /// Gets the full file name of a buffer, or an empty string if the buffer
+    /// isn't associated to a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `bufnr`  Buffer handle, or 0 for current buffer.
+    pub fn buf_get_name(&self, bufnr: u32) -> Result<String> {
+        self.0.get::<&str, Function>("nvim_buf_get_name")?.call(bufnr)
+    }
+
+    /// Binding to `vim.api.nvim_buf_get_offset`.
+    ///
+    /// Returns the byte offset of a line (0-indexed). Indexing the line past
+    /// the last one returns the total size of the buffer in bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `bufnr`  Buffer handle, or 0 for current buffer.
+    /// * `index`  Line index.
+    pub fn buf_get_offset(&self, bufnr: u32, index: u32) -> Result<u32> {
+        self.0
+            .get::<&str, Function>("nvim_buf_get_offset")?
+            .call((bufnr, index))
+    }
+
     /// Binding to `vim.api.nvim_buf_get_lines`.
     ///
     /// Gets a line range from the buffer. Indexing is zero-based,
@@ -92,6 +119,29 @@ impl<'a> Api<'a> {
             .call((bufnr, name))
     }
 
+    /// Binding to `vim.api.nvim_buf_is_valid`.
+    ///
+    /// Checks if a buffer is valid. Even if a buffer is valid it may have
+    /// been unloaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `bufnr`  Buffer handle, or 0 for current buffer.
+    pub fn buf_is_valid(&self, bufnr: u32) -> Result<bool> {
+        self.0.get::<&str, Function>("nvim_buf_is_valid")?.call(bufnr)
+    }
+
+    /// Binding to `vim.api.nvim_buf_line_count`.
+    ///
+    /// Returns the number of lines in the buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `bufnr`  Buffer handle, or 0 for current buffer.
+    pub fn buf_line_count(&self, bufnr: u32) -> Result<u32> {
+        self.0.get::<&str, Function>("nvim_buf_line_count")?.call(bufnr)
+    }
+
     /// Binding to `vim.api.nvim_buf_set_lines`.
     ///
     /// Sets (replaces) a line-range in the buffer. Out-of-bounds indices are
@@ -124,6 +174,27 @@ impl<'a> Api<'a> {
         ))
     }
 
+    /// Binding to `vim.api.nvim_buf_set_option`.
+    ///
+    /// Sets a buffer option value. Passing `None` as a value deletes the
+    /// option (only works if there's a global fallback).
+    ///
+    /// # Arguments
+    ///
+    /// * `bufnr`  Buffer handle, or 0 for current buffer.
+    /// * `name`   Option name.
+    /// * `value`  Option value.
+    pub fn buf_set_option<V: ToLua<'a>>(
+        &self,
+        bufnr: u32,
+        name: &str,
+        value: V,
+    ) -> Result<()> {
+        self.0
+            .get::<&str, Function>("nvim_buf_set_option")?
+            .call((bufnr, name, value))
+    }
+
     /// Binding to `vim.api.nvim_buf_set_text`.
     ///
     /// Sets (replaces) a range in the buffer. Recommended over
@@ -57,6 +57,69 @@ impl<'a> Api<'a> {
             .call::<_, ()>((bufnr, ns_id, line_start, line_end))
     }
 
+    /// Binding to `vim.api.nvim_buf_del_extmark`.
+    ///
+    /// Removes an extmark. Returns whether the extmark was found.
+    ///
+    /// # Arguments
+    ///
+    /// * `bufnr`  Buffer handle, or 0 for current buffer.
+    /// * `ns_id`  Namespace id.
+    /// * `id`     Extmark id.
+    pub fn buf_del_extmark(
+        &self,
+        bufnr: u32,
+        ns_id: u32,
+        id: u32,
+    ) -> Result<bool> {
+        self.0
+            .get::<&str, Function>("nvim_buf_del_extmark")?
+            .call((bufnr, ns_id, id))
+    }
+
+    /// Binding to `vim.api.nvim_buf_get_extmark_by_id`.
+    ///
+    /// Returns the `(row, col)` position (0-indexed) of an extmark together
+    /// with the `(end_row, end_col)` position of the range it spans, or `None`
+    /// if the extmark doesn't exist. The end position is only returned if
+    /// `opts` contains `details = true`.
+    ///
+    /// # Arguments
+    ///
+    /// * `bufnr`  Buffer handle, or 0 for current buffer.
+    /// * `ns_id`  Namespace id.
+    /// * `id`     Extmark id.
+    /// * `opts`   Optional parameters. See `:h nvim_buf_get_extmark_by_id`
+    ///   for details.
+    pub fn buf_get_extmark_by_id(
+        &self,
+        bufnr: u32,
+        ns_id: u32,
+        id: u32,
+        opts: Table,
+    ) -> Result<Option<((u32, u32), Option<(u32, u32)>)>> {
+        let mark = self
+            .0
+            .get::<&str, Function>("nvim_buf_get_extmark_by_id")?
+            .call::<_, Table>((bufnr, ns_id, id, opts))?;
+
+        if mark.raw_len() == 0 {
+            return Ok(None);
+        }
+
+        let start = (mark.get(1)?, mark.get(2)?);
+
+        let end = match mark.get::<_, Option<Table>>(3)? {
+            Some(details) => match details.get::<_, Option<u32>>("end_row")? {
+                Some(end_row) => Some((end_row, details.get("end_col")?)),
+                None => None,
+            },
+            None => None,
+        };
+
+        Ok(Some((start, end)))
+    }
+
     /// Binding to `vim.api.nvim_buf_set_extmark`.
     ///
     /// Creates or updates an extmark. To create a new extmark pass `ns_id =
@@ -1,4 +1,4 @@
-use mlua::{FromLua, Function, Result, Table};
+use mlua::{FromLua, Function, Result, Table, ToLua};
 
 use super::Api;
 
@@ -39,6 +39,40 @@ impl<'a> Api<'a> {
             .call((listed, scratch))
     }
 
+    /// Binding to `vim.api.nvim_create_user_command`.
+    ///
+    /// Creates a new user command.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`     Name of the new user command. Must begin with an uppercase letter.
+    /// * `command`  Replacement command to execute when this user command is executed.
+    /// * `opts`     Optional parameters. See `:h nvim_create_user_command` for  details.
+    pub fn create_user_command(
+        &self,
+        name: &str,
+        command: Function,
+        opts: Table,
+    ) -> Result<()> {
+        self.0
+            .get::<&str, Function>("nvim_create_user_command")?
+            .call((name, command, opts))
+    }
+
+    /// Binding to `vim.api.nvim_del_keymap`
+    ///
+    /// Unmaps a global mapping for the given mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode`    Mode short name.
+    /// * `lhs`     Left-hand-side of the mapping.
+    pub fn del_keymap(&self, mode: &str, lhs: &str) -> Result<()> {
+        self.0
+            .get::<&str, Function>("nvim_del_keymap")?
+            .call((mode, lhs))
+    }
+
     /// Binding to `vim.api.nvim_echo`.
     ///
     /// Echoes a message.
@@ -69,6 +103,37 @@ impl<'a> Api<'a> {
         ))
     }
 
+    /// Binding to `vim.api.nvim_feedkeys`.
+    ///
+    /// Sends input-keys to Neovim, subject to various quirks controlled by
+    /// `mode` flags. This is a blocking call, unlike `nvim_input`.
+    ///
+    /// # Arguments
+    ///
+    /// * `keys`       To be typed, usually converted with `replace_termcodes`.
+    /// * `mode`       Behavior flags, see `:h feedkeys()`.
+    /// * `escape_ks`  If true, escape K_SPECIAL bytes in `keys`.
+    pub fn feedkeys(
+        &self,
+        keys: &std::ffi::CStr,
+        mode: &str,
+        escape_ks: bool,
+    ) -> Result<()> {
+        self.0
+            .get::<&str, Function>("nvim_feedkeys")?
+            .call((keys, mode, escape_ks))
+    }
+
+    /// Binding to `vim.api.nvim_get_all_options_info`
+    ///
+    /// Returns a table whose keys are the names of all the options and whose
+    /// values are dictionaries describing them.
+    pub fn get_all_options_info(&self) -> Result<Table<'a>> {
+        self.0
+            .get::<&str, Function>("nvim_get_all_options_info")?
+            .call(())
+    }
+
     /// Binding to `vim.api.nvim_get_current_buf`
     ///
     /// Returns the current buffer handle.
@@ -88,6 +153,39 @@ impl<'a> Api<'a> {
             .call(())?)
     }
 
+    /// Binding to `vim.api.nvim_get_current_tabpage`
+    ///
+    /// Returns the current tabpage handle.
+    pub fn get_current_tabpage(&self) -> Result<u32> {
+        self.0
+            .get::<&str, Function>("nvim_get_current_tabpage")?
+            .call(())
+    }
+
+    /// Binding to `vim.api.nvim_get_current_win`
+    ///
+    /// Returns the current window handle.
+    pub fn get_current_win(&self) -> Result<u32> {
+        self.0
+            .get::<&str, Function>("nvim_get_current_win")?
+            .call(())
+    }
+
+    /// Binding to `vim.api.nvim_get_hl_by_name`
+    ///
+    /// Returns a table with the attributes of a highlight group, following
+    /// its links.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`   Highlight group name.
+    /// * `rgb`    Whether to export RGB colors instead of cterm ones.
+    pub fn get_hl_by_name(&self, name: &str, rgb: bool) -> Result<Table<'a>> {
+        self.0
+            .get::<&str, Function>("nvim_get_hl_by_name")?
+            .call((name, rgb))
+    }
+
     /// Binding to `vim.api.nvim_get_mode`
     ///
     /// Returns a `(mode, is_blocking)` tuple.
@@ -111,6 +209,25 @@ impl<'a> Api<'a> {
         self.0.get::<&str, Function>("nvim_get_option")?.call(name)
     }
 
+    /// Binding to `vim.api.nvim_get_vvar`.
+    ///
+    /// Gets a `v:` variable.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`         Variable name.
+    pub fn get_vvar<V: FromLua<'a>>(&self, name: &str) -> Result<V> {
+        self.0.get::<&str, Function>("nvim_get_vvar")?.call(name)
+    }
+
+    /// Binding to `vim.api.nvim_list_bufs`.
+    ///
+    /// Gets the current list of buffer handles, including unlisted
+    /// (unloaded/deleted) buffers.
+    pub fn list_bufs(&self) -> Result<Vec<u32>> {
+        self.0.get::<&str, Function>("nvim_list_bufs")?.call(())
+    }
+
     /// Binding to `vim.api.nvim_notify`.
     ///
     /// Notify the user with a message.
@@ -190,4 +307,32 @@ impl<'a> Api<'a> {
             .get::<&str, Function>("nvim_set_keymap")?
             .call((mode, lhs, rhs, opts))
     }
+
+    /// Binding to `vim.api.nvim_set_option`
+    ///
+    /// Sets the global value of an option.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`   Option name.
+    /// * `value`  New option value.
+    pub fn set_option<V: ToLua<'a>>(&self, name: &str, value: V) -> Result<()> {
+        self.0
+            .get::<&str, Function>("nvim_set_option")?
+            .call((name, value))
+    }
+
+    /// Binding to `vim.api.nvim_set_var`
+    ///
+    /// Sets a global (`g:`) variable.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`   Variable name.
+    /// * `value`  Variable value.
+    pub fn set_var<V: ToLua<'a>>(&self, name: &str, value: V) -> Result<()> {
+        self.0
+            .get::<&str, Function>("nvim_set_var")?
+            .call((name, value))
+    }
 }
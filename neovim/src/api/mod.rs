@@ -1,5 +1,6 @@
 mod api;
 mod autocmd;
+mod batch;
 mod buffer;
 mod extmark;
 mod global;
@@ -8,3 +9,4 @@ mod win_config;
 mod window;
 
 pub use api::{Api, LogLevel};
+pub use batch::Batch;
@@ -17,6 +17,20 @@ impl<'a> Api<'a> {
             .call((winid, force))
     }
 
+    /// Binding to `vim.api.nvim_win_call`.
+    ///
+    /// Calls a function with `winid` as the temporary current window.
+    ///
+    /// # Arguments
+    ///
+    /// * `winid`  Window handle, or 0 for current window.
+    /// * `fun`    Function to call inside the window.
+    pub fn win_call(&self, winid: u32, fun: Function) -> Result<()> {
+        self.0
+            .get::<&str, Function>("nvim_win_call")?
+            .call((winid, fun))
+    }
+
     /// Binding to `vim.api.nvim_win_get_cursor`
     ///
     /// Returns the (1,0)-indexed cursor position as a tuple.
@@ -95,6 +109,19 @@ impl<'a> Api<'a> {
             .call(winid)
     }
 
+    /// Binding to `vim.api.nvim_win_get_tabpage`.
+    ///
+    /// Returns the handle of the tabpage containing the window.
+    ///
+    /// # Arguments
+    ///
+    /// * `winid`  Window handle, or 0 for current window.
+    pub fn win_get_tabpage(&self, winid: u32) -> Result<u32> {
+        self.0
+            .get::<&str, Function>("nvim_win_get_tabpage")?
+            .call(winid)
+    }
+
     /// Binding to `vim.api.nvim_win_hide`.
     ///
     /// Closes the window and hides the buffer it contains.
@@ -106,6 +133,17 @@ impl<'a> Api<'a> {
         self.0.get::<&str, Function>("nvim_win_hide")?.call(winid)
     }
 
+    /// Binding to `vim.api.nvim_win_is_valid`.
+    ///
+    /// Checks if a window is valid.
+    ///
+    /// # Arguments
+    ///
+    /// * `winid`  Window handle.
+    pub fn win_is_valid(&self, winid: u32) -> Result<bool> {
+        self.0.get::<&str, Function>("nvim_win_is_valid")?.call(winid)
+    }
+
     /// Binding to `vim.api.nvim_win_set_cursor`
     ///
     /// Sets the (1,0)-indexed cursor position in the window.
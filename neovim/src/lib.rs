@@ -4,5 +4,17 @@ pub use api::Api;
 mod keymap;
 pub use keymap::Keymap;
 
+mod lsp;
+pub use lsp::Lsp;
+
 pub mod neovim;
 pub use neovim::Neovim;
+
+mod regex;
+pub use regex::Regex;
+
+pub mod treesitter;
+pub use treesitter::Treesitter;
+
+mod uv;
+pub use uv::Uv;
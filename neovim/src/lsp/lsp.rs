@@ -0,0 +1,94 @@
+use mlua::{Function, Result, Table, Value};
+
+pub struct Lsp<'a>(Table<'a>);
+
+impl<'a> Lsp<'a> {
+    pub(crate) fn new(vim: Table<'a>) -> Result<Lsp<'a>> {
+        Ok(Lsp(vim.get::<&str, Table>("lsp")?))
+    }
+}
+
+impl<'a> Lsp<'a> {
+    /// Binding to `vim.lsp.buf_get_clients`.
+    ///
+    /// Returns a table of the clients attached to a buffer, indexed by client
+    /// id.
+    ///
+    /// # Arguments
+    ///
+    /// * `bufnr`  Buffer handle, or 0 for current buffer.
+    pub fn buf_get_clients(&self, bufnr: u32) -> Result<Table<'a>> {
+        self.0.get::<&str, Function>("buf_get_clients")?.call(bufnr)
+    }
+
+    /// Binding to `vim.lsp.get_active_clients`.
+    ///
+    /// Returns a list of all the clients that are currently running.
+    pub fn get_active_clients(&self) -> Result<Table<'a>> {
+        self.0.get::<&str, Function>("get_active_clients")?.call(())
+    }
+
+    /// Binding to `vim.lsp.get_client_by_id`.
+    ///
+    /// Returns the client with the given id, or `None` if there's no such
+    /// client.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_id`  The id of the client.
+    pub fn get_client_by_id(&self, client_id: u32) -> Result<Option<Table<'a>>> {
+        self.0.get::<&str, Function>("get_client_by_id")?.call(client_id)
+    }
+
+    /// Binding to `vim.lsp.buf_request`.
+    ///
+    /// Sends an async request for all the active clients attached to a
+    /// buffer. Returns a function that can be called to cancel all the sent
+    /// requests.
+    ///
+    /// # Arguments
+    ///
+    /// * `bufnr`    Buffer handle, or 0 for current buffer.
+    /// * `method`   LSP method name.
+    /// * `params`   Parameters to send to the server.
+    /// * `handler`  Function called once for every client's response. See `:h
+    ///   lsp-handler` for its signature.
+    pub fn buf_request(
+        &self,
+        bufnr: u32,
+        method: &str,
+        params: Table<'a>,
+        handler: Function<'a>,
+    ) -> Result<Function<'a>> {
+        let (_, cancel) = self
+            .0
+            .get::<&str, Function>("buf_request")?
+            .call::<_, (Value, Function)>((bufnr, method, params, handler))?;
+
+        Ok(cancel)
+    }
+
+    /// Binding to `vim.lsp.util.stylize_markdown`.
+    ///
+    /// Converts markdown into syntax highlighted regions of a buffer, removing
+    /// the code block fences and the escapes. Returns the lines that were set
+    /// in the buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `bufnr`     Buffer handle, or 0 for current buffer.
+    /// * `contents`  The lines of markdown to render.
+    /// * `opts`      Optional parameters. See `:h
+    ///   vim.lsp.util.stylize_markdown()` for details.
+    pub fn util_stylize_markdown(
+        &self,
+        bufnr: u32,
+        contents: &[String],
+        opts: Table<'a>,
+    ) -> Result<Vec<String>> {
+        self.0
+            .get::<&str, Table>("util")?
+            .get::<&str, Function>("stylize_markdown")?
+            .call((bufnr, contents, opts))
+    }
+}
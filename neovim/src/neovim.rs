@@ -2,6 +2,10 @@ use mlua::{Function, Lua, Result, Table};
 
 use crate::api::Api;
 use crate::keymap::Keymap;
+use crate::lsp::Lsp;
+use crate::regex::Regex;
+use crate::treesitter::Treesitter;
+use crate::uv::Uv;
 
 pub struct Neovim<'a> {
     /// TODO: docs
@@ -43,6 +47,10 @@ impl<'a> Neovim<'a> {
             .call::<_, String>(t)
     }
 
+    /// Returns the `vim.lsp` module. It isn't stored in the struct since it's
+    /// lazily loaded by Neovim the first time it's accessed.
+    pub fn lsp(&self) -> Result<Lsp<'a>> { Lsp::new(self.vim.clone()) }
+
     /// TODO: docs
     pub fn print<S: std::fmt::Display>(&self, msg: S) -> Result<()> {
         self._g
@@ -50,10 +58,39 @@ impl<'a> Neovim<'a> {
             .call::<_, ()>(msg.to_string())
     }
 
+    /// Binding to `vim.regex`.
+    ///
+    /// Compiles a Vim regex. The `magic` and `ignorecase` options are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `re`  The regex to compile.
+    pub fn regex(&self, re: &str) -> Result<Regex<'a>> {
+        Regex::new(self.vim.clone(), re)
+    }
+
     /// TODO: docs
     pub fn schedule(&self, callback: Function) -> Result<()> {
         self.vim
             .get::<&str, Function>("schedule")?
             .call::<_, ()>(callback)
     }
+
+    /// Returns the `vim.treesitter` module. It isn't stored in the struct
+    /// since it's lazily loaded by Neovim the first time it's accessed.
+    pub fn treesitter(&self) -> Result<Treesitter<'a>> {
+        Treesitter::new(self.vim.clone())
+    }
+
+    /// Returns the `vim.loop` module.
+    pub fn uv(&self) -> Result<Uv<'a>> { Uv::new(self.vim.clone()) }
+
+    /// Binding to `vim.uri_from_bufnr`.
+    ///
+    /// Returns the URI of the file loaded in a buffer.
+    pub fn uri_from_bufnr(&self, bufnr: u32) -> Result<String> {
+        self.vim
+            .get::<&str, Function>("uri_from_bufnr")?
+            .call::<_, String>(bufnr)
+    }
 }
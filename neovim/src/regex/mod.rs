@@ -0,0 +1,3 @@
+mod regex;
+
+pub use regex::Regex;
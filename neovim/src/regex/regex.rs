@@ -0,0 +1,35 @@
+use mlua::{AnyUserData, Function, Result, Table};
+
+/// Binding to the regex objects returned by `vim.regex`.
+pub struct Regex<'a>(AnyUserData<'a>);
+
+impl<'a> Regex<'a> {
+    pub(crate) fn new(vim: Table<'a>, re: &str) -> Result<Regex<'a>> {
+        Ok(Regex(vim.get::<&str, Function>("regex")?.call(re)?))
+    }
+
+    /// Wraps a regex object previously unwrapped with `Regex::into_inner`,
+    /// e.g. to store it in the Lua registry.
+    pub fn from_userdata(regex: AnyUserData<'a>) -> Regex<'a> { Regex(regex) }
+
+    /// Returns the underlying regex object.
+    pub fn into_inner(self) -> AnyUserData<'a> { self.0 }
+}
+
+impl<'a> Regex<'a> {
+    /// Binding to `regex:match_str`.
+    ///
+    /// Matches the regex against a string, returning the `(start, end)` byte
+    /// offsets of the match, or `None` if there's no match.
+    ///
+    /// # Arguments
+    ///
+    /// * `str`  The string to match against.
+    pub fn match_str(&self, str: &str) -> Result<Option<(usize, usize)>> {
+        // The regex methods are stored in the object's metatable.
+        let match_str = self.0.get_metatable()?.get::<_, Function>("match_str")?;
+        let (start, end) = match_str
+            .call::<_, (Option<usize>, Option<usize>)>((self.0.clone(), str))?;
+        Ok(start.zip(end))
+    }
+}
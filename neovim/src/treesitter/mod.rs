@@ -0,0 +1,5 @@
+mod node;
+mod treesitter;
+
+pub use node::TSNode;
+pub use treesitter::{LanguageTree, Treesitter};
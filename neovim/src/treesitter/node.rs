@@ -0,0 +1,50 @@
+use mlua::{AnyUserData, FromLuaMulti, Function, Result, ToLuaMulti};
+
+/// Binding to the node objects of the treesitter syntax trees.
+pub struct TSNode<'a>(pub(crate) AnyUserData<'a>);
+
+impl<'a> TSNode<'a> {
+    /// Calls one of the node's methods, which are stored in its metatable.
+    /// `args` should start with the node itself.
+    fn call<A, R>(&self, method: &str, args: A) -> Result<R>
+    where
+        A: ToLuaMulti<'a>,
+        R: FromLuaMulti<'a>,
+    {
+        self.0.get_metatable()?.get::<_, Function>(method)?.call(args)
+    }
+}
+
+impl<'a> TSNode<'a> {
+    /// Binding to `tsnode:named_child`.
+    ///
+    /// Returns the `index`-th named child of the node.
+    ///
+    /// # Arguments
+    ///
+    /// * `index`  0-indexed position of the child.
+    pub fn named_child(&self, index: u32) -> Result<TSNode<'a>> {
+        Ok(TSNode(self.call("named_child", (self.0.clone(), index))?))
+    }
+
+    /// Binding to `tsnode:named_child_count`.
+    ///
+    /// Returns the number of named children of the node.
+    pub fn named_child_count(&self) -> Result<u32> {
+        self.call("named_child_count", self.0.clone())
+    }
+
+    /// Binding to `tsnode:range`.
+    ///
+    /// Returns the `(start_row, start_col, end_row, end_col)` range of the
+    /// node, with the rows and the byte columns 0-indexed and the end
+    /// exclusive.
+    pub fn range(&self) -> Result<(u32, u32, u32, u32)> {
+        self.call("range", self.0.clone())
+    }
+
+    /// Binding to `tsnode:type`.
+    ///
+    /// Returns the type of the node, e.g. `identifier`.
+    pub fn r#type(&self) -> Result<String> { self.call("type", self.0.clone()) }
+}
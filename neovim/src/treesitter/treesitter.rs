@@ -0,0 +1,54 @@
+use mlua::{AnyUserData, Function, Result, Table};
+
+use super::TSNode;
+
+/// Bindings to `vim.treesitter`.
+pub struct Treesitter<'a>(Table<'a>);
+
+impl<'a> Treesitter<'a> {
+    pub(crate) fn new(vim: Table<'a>) -> Result<Treesitter<'a>> {
+        Ok(Treesitter(vim.get::<&str, Table>("treesitter")?))
+    }
+}
+
+impl<'a> Treesitter<'a> {
+    /// Binding to `vim.treesitter.get_parser`.
+    ///
+    /// Returns the parser of a buffer, creating it if it doesn't exist yet.
+    /// Fails if there's no parser installed for the buffer's language.
+    ///
+    /// # Arguments
+    ///
+    /// * `bufnr`  Buffer handle, or 0 for current buffer.
+    pub fn get_parser(&self, bufnr: u32) -> Result<LanguageTree<'a>> {
+        Ok(LanguageTree(
+            self.0.get::<&str, Function>("get_parser")?.call(bufnr)?,
+        ))
+    }
+}
+
+/// Binding to the `LanguageTree` objects returned by
+/// `vim.treesitter.get_parser`.
+pub struct LanguageTree<'a>(Table<'a>);
+
+impl<'a> LanguageTree<'a> {
+    /// Binding to `LanguageTree:parse`.
+    ///
+    /// Parses the buffer if it changed since the last parse, returning the
+    /// root nodes of its syntax trees.
+    pub fn parse(&self) -> Result<Vec<TSNode<'a>>> {
+        let trees = self
+            .0
+            .get::<&str, Function>("parse")?
+            .call::<_, Vec<AnyUserData>>(self.0.clone())?;
+
+        trees
+            .into_iter()
+            .map(|tree| {
+                // The tree methods are stored in the object's metatable.
+                let root = tree.get_metatable()?.get::<_, Function>("root")?;
+                Ok(TSNode(root.call(tree)?))
+            })
+            .collect()
+    }
+}
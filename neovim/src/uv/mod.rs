@@ -0,0 +1,3 @@
+mod uv;
+
+pub use uv::Uv;
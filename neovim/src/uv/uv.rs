@@ -0,0 +1,117 @@
+use mlua::{AnyUserData, Function, Result, Table, Value};
+
+/// Bindings to `vim.loop`, which exposes the functionality of the libuv
+/// library via the `luv` Lua module.
+pub struct Uv<'a>(Table<'a>);
+
+impl<'a> Uv<'a> {
+    pub(crate) fn new(vim: Table<'a>) -> Result<Uv<'a>> {
+        Ok(Uv(vim.get::<&str, Table>("loop")?))
+    }
+}
+
+impl<'a> Uv<'a> {
+    /// Binding to `vim.loop.close`.
+    ///
+    /// Closes a handle, e.g. a timer. The handle can't be used after this.
+    ///
+    /// # Arguments
+    ///
+    /// * `handle`  The handle to close.
+    pub fn close(&self, handle: AnyUserData) -> Result<()> {
+        self.0.get::<&str, Function>("close")?.call(handle)
+    }
+
+    /// Binding to `vim.loop.fs_scandir`.
+    ///
+    /// Asynchronously reads the entries of a directory. The `callback` is
+    /// called with an `(err, handle)` tuple, where `handle` can be passed to
+    /// `Uv::fs_scandir_next` to iterate over the entries.
+    ///
+    /// NOTE: the callback is executed in a fast event context, meaning most
+    /// `vim.api` functions can't be called from it. See `:h lua-loop-callbacks`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`      Path of the directory.
+    /// * `callback`  Function called once the directory has been read.
+    pub fn fs_scandir(&self, path: &str, callback: Function) -> Result<()> {
+        self.0
+            .get::<&str, Function>("fs_scandir")?
+            .call::<_, Value>((path, callback))?;
+
+        Ok(())
+    }
+
+    /// Binding to `vim.loop.fs_scandir_next`.
+    ///
+    /// Returns the `(name, type)` tuple of the next entry of a directory, or
+    /// `None` if there are no more entries. The type can be `None` on some
+    /// file systems.
+    ///
+    /// # Arguments
+    ///
+    /// * `handle`  The handle passed to the `Uv::fs_scandir` callback.
+    pub fn fs_scandir_next(
+        &self,
+        handle: Value<'a>,
+    ) -> Result<Option<(String, Option<String>)>> {
+        let (name, r#type) = self
+            .0
+            .get::<&str, Function>("fs_scandir_next")?
+            .call::<_, (Option<String>, Option<String>)>(handle)?;
+
+        Ok(name.map(|name| (name, r#type)))
+    }
+
+    /// Binding to `vim.loop.new_timer`.
+    ///
+    /// Creates a new timer handle, to be used with `Uv::timer_start` and
+    /// `Uv::timer_stop`.
+    pub fn new_timer(&self) -> Result<AnyUserData<'a>> {
+        self.0.get::<&str, Function>("new_timer")?.call(())
+    }
+
+    /// Binding to `vim.loop.timer_start`.
+    ///
+    /// Starts a timer, executing `callback` after `timeout` milliseconds and
+    /// then every `repeat` milliseconds if `repeat` is not zero. Like the one
+    /// passed to `Uv::fs_scandir`, the callback is executed in a fast event
+    /// context.
+    ///
+    /// # Arguments
+    ///
+    /// * `timer`     The timer handle.
+    /// * `timeout`   Milliseconds before the first execution of `callback`.
+    /// * `repeat`    Milliseconds between the following executions, or 0 to
+    ///   only execute `callback` once.
+    /// * `callback`  The function to execute.
+    pub fn timer_start(
+        &self,
+        timer: AnyUserData,
+        timeout: u64,
+        repeat: u64,
+        callback: Function,
+    ) -> Result<()> {
+        self.0
+            .get::<&str, Function>("timer_start")?
+            .call::<_, Value>((timer, timeout, repeat, callback))?;
+
+        Ok(())
+    }
+
+    /// Binding to `vim.loop.timer_stop`.
+    ///
+    /// Stops a timer. It can be started again with `Uv::timer_start`.
+    ///
+    /// # Arguments
+    ///
+    /// * `timer`  The timer handle.
+    pub fn timer_stop(&self, timer: AnyUserData) -> Result<()> {
+        self.0
+            .get::<&str, Function>("timer_stop")?
+            .call::<_, Value>(timer)?;
+
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 use mlua::prelude::{Lua, LuaResult};
 use neovim::Neovim;
 
+use crate::completion;
 use crate::state::State;
 
 /// Executed by the `require("compleet").has_completions` Lua function.
@@ -13,21 +14,7 @@ pub fn has_completions(lua: &Lua, state: &mut State) -> LuaResult<bool> {
         return Ok(false);
     }
 
-    let cursor = &mut state.cursor;
-    let completions = &mut state.completions;
+    completion::complete_at_cursor(lua, &api, state)?;
 
-    cursor.bytes = api.win_get_cursor(0)?.1;
-    cursor.line = api.get_current_line()?;
-
-    completions.clear();
-    for source in state
-        .sources
-        .get(&bufnr)
-        .expect("The buffer is attached so it has sources")
-        .iter()
-    {
-        completions.append(&mut source.complete(&api, &cursor)?);
-    }
-
-    Ok(!completions.is_empty())
+    Ok(!state.buffers.current().completions.is_empty())
 }
@@ -0,0 +1,53 @@
+use mlua::prelude::{Lua, LuaResult};
+
+use crate::mappings;
+use crate::phase::{Action, Phase};
+use crate::state::State;
+
+/// Executed by the `require("compleet").select` Lua function. Selects the
+/// `index`-th (1-indexed) completion in the visible menu, or goes back to no
+/// selection if it's `0`. Returns whether the completion could be selected.
+pub fn select(lua: &Lua, state: &mut State, index: usize) -> LuaResult<bool> {
+    if !state.phase().allows(Action::Select)
+        || index > state.buffers.current().completions.len()
+    {
+        return Ok(false);
+    }
+
+    let new_index = index.checked_sub(1);
+    if new_index != state.phase().selected() {
+        mappings::select_index(lua, state, new_index)?;
+    }
+
+    Ok(true)
+}
+
+/// Executed by the `require("compleet").confirm` Lua function. Inserts the
+/// `index`-th (1-indexed) completion in the visible menu, or the selected
+/// one if `index` is `nil`, using the `completion.behavior` option like
+/// `<Plug>(compleet-confirm-completion)`. Returns whether a completion was
+/// inserted.
+pub fn confirm(
+    lua: &Lua,
+    state: &mut State,
+    index: Option<usize>,
+) -> LuaResult<bool> {
+    let phase = state.phase();
+
+    let index = match (index, phase) {
+        (None, Phase::Selected(index)) => index,
+        (Some(n), _)
+            if phase.is_menu_visible()
+                && (1..=state.buffers.current().completions.len())
+                    .contains(&n) =>
+        {
+            n - 1
+        },
+        _ => return Ok(false),
+    };
+
+    let behavior = state.settings.completion.behavior;
+    mappings::insert_completion(lua, state, index, behavior)?;
+
+    Ok(true)
+}
@@ -1,5 +1,21 @@
 mod has_completions;
+mod menu;
+mod multicursor;
+mod reconfigure;
+mod register_external_source;
+mod register_inline_provider;
+mod register_source;
 mod setup;
+mod status;
+mod validate;
 
 pub use has_completions::has_completions;
+pub use menu::{confirm, select};
+pub use multicursor::{extra_cursors, set_multicursor_hook};
+use reconfigure::reconfigure;
+pub use register_external_source::register_external_source;
+pub use register_inline_provider::register_inline_provider;
+pub use register_source::register_source;
 pub use setup::setup;
+pub use status::{lualine_component, status};
+pub use validate::validate;
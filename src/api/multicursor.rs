@@ -0,0 +1,41 @@
+use mlua::prelude::{Lua, LuaFunction, LuaResult, LuaValue};
+
+/// The key in the Lua registry where the function set by
+/// `set_multicursor_hook` is stored.
+pub const MULTICURSOR_HOOK: &str = "compleet_multicursor_hook";
+
+/// Executed by the `require("compleet").set_multicursor_hook` Lua function.
+/// The hook is called when a completion is confirmed and returns the
+/// positions of the other cursors of a multicursor plugin, where the
+/// completion is also inserted. Passing `nil` removes it.
+pub fn set_multicursor_hook(
+    lua: &Lua,
+    hook: Option<LuaFunction>,
+) -> LuaResult<()> {
+    match hook {
+        Some(hook) => lua.set_named_registry_value(MULTICURSOR_HOOK, hook),
+        None => lua.set_named_registry_value(MULTICURSOR_HOOK, LuaValue::Nil),
+    }
+}
+
+/// Returns the positions of the other cursors as `(row, col)` tuples, where
+/// the row is 0-indexed and the column is a byte offset. The hook returns
+/// them with 1-indexed rows, like `nvim_win_get_cursor`.
+pub fn extra_cursors(lua: &Lua) -> LuaResult<Vec<(u32, u32)>> {
+    let hook = match lua
+        .named_registry_value::<_, Option<LuaFunction>>(MULTICURSOR_HOOK)?
+    {
+        Some(hook) => hook,
+        None => return Ok(Vec::new()),
+    };
+
+    Ok(hook
+        .call::<_, Option<Vec<Vec<u32>>>>(())?
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|position| match position[..] {
+            [row, col] if row > 0 => Some((row - 1, col)),
+            _ => None,
+        })
+        .collect())
+}
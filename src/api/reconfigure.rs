@@ -0,0 +1,111 @@
+use mlua::prelude::{Lua, LuaFunction, LuaResult, LuaTable, LuaValue};
+use neovim::{Api, Neovim};
+
+use crate::completion::{self, Pipeline};
+use crate::settings::sources::PREDICATES;
+use crate::settings::Settings;
+use crate::state::State;
+
+/// Executed when `require("compleet").setup` is called again after the first
+/// time, once the new settings have replaced the `old` ones. Applies them to
+/// the buffers that are already attached and to the visible UI, so that
+/// changing the config doesn't require restarting Neovim.
+pub fn reconfigure(
+    lua: &Lua,
+    api: &Api,
+    state: &mut State,
+    old: &Settings,
+) -> LuaResult<()> {
+    let was_menu_visible = state.phase().is_menu_visible();
+
+    // The UI was laid out using the old settings (borders, columns, etc.), so
+    // it's closed and reopened below.
+    state.preview.commit();
+    state.close_ui(lua, api)?;
+    state.cmdline.close(lua, api)?;
+    state.terminal.close(lua, api)?;
+    state.buffers.current_mut().clear_completions();
+
+    // The results of the jobs started by the old sources are discarded.
+    state.pipeline = Pipeline::default();
+
+    if sources_changed(lua, old, &state.settings)? {
+        reattach_sources(lua, api, state)?;
+    }
+
+    // Reopen the menu with the new settings once the completions have been
+    // recomputed.
+    if was_menu_visible && api.get_mode()?.0 == "i" {
+        completion::schedule_refresh(lua)?;
+    }
+
+    Ok(())
+}
+
+/// Whether the sources config has changed. Sources are trait objects, so
+/// their `Debug` representations are compared. Functions passed as the
+/// `enable` option can't be compared, so if there are any the sources are
+/// always considered changed. A new `completion.max_buffer_size` can also
+/// change which sources are attached to the large buffers, and new
+/// `completion.filetypes` and `completion.buftypes` which buffers are
+/// attached.
+fn sources_changed(
+    lua: &Lua,
+    old: &Settings,
+    new: &Settings,
+) -> LuaResult<bool> {
+    let has_predicates =
+        match lua.named_registry_value::<_, Option<LuaTable>>(PREDICATES)? {
+            Some(predicates) => {
+                predicates.pairs::<LuaValue, LuaValue>().next().is_some()
+            },
+            None => false,
+        };
+
+    Ok(has_predicates
+        || old.completion.max_buffer_size != new.completion.max_buffer_size
+        || old.completion.filetypes != new.completion.filetypes
+        || old.completion.buftypes != new.completion.buftypes
+        || format!("{:?}", old.sources) != format!("{:?}", new.sources))
+}
+
+/// Asks the new sources which of the attached buffers they want to attach to,
+/// detaching the buffers no source is interested in anymore.
+fn reattach_sources(lua: &Lua, api: &Api, state: &mut State) -> LuaResult<()> {
+    // The sources of the buffers detached by `CompleetStop` are resolved
+    // again if they get reattached.
+    let attached = state.attached_buffers.clone();
+    state.sources.retain(|bufnr, _| attached.contains(bufnr));
+
+    for bufnr in attached {
+        let sources = state.attachable_sources(lua, api, bufnr)?;
+
+        if !sources.is_empty() {
+            state.sources.insert(bufnr, sources);
+            continue;
+        }
+
+        // Like in `CompleetStop`, the buffer is detached the next time
+        // `completion::on_bytes` is called.
+        state.attached_buffers.retain(|&b| b != bufnr);
+        state.buffers_to_be_detached.push(bufnr);
+        state.sources.remove(&bufnr);
+
+        for autocmd_id in state
+            .buffer_local_autocmds
+            .remove(&bufnr)
+            .unwrap_or_default()
+        {
+            api.del_autocmd(autocmd_id)?;
+        }
+    }
+
+    // The newly enabled sources may want to attach to the current buffer.
+    // Attaching also locks the state, so it has to be scheduled.
+    if let Some(key) = state.try_buf_attach.as_ref() {
+        let try_buf_attach = lua.registry_value::<LuaFunction>(key)?;
+        Neovim::new(lua)?.schedule(try_buf_attach)?;
+    }
+
+    Ok(())
+}
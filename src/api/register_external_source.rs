@@ -0,0 +1,96 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use mlua::prelude::{Lua, LuaResult, LuaTable, LuaValue};
+use neovim::Neovim;
+
+use super::register_source::{add_user_source, static_name};
+use crate::completion::sources::External;
+use crate::state::State;
+use crate::ui::messages::{self, Message};
+
+/// How long to wait for the completions of an external source if the spec
+/// doesn't set a `timeout_ms`.
+const DEFAULT_TIMEOUT_MS: u64 = 500;
+
+/// Executed by the `require("compleet").register_external_source` Lua
+/// function. Registering a source with the same name as an already
+/// registered one replaces it, which also restarts its process.
+pub fn register_external_source(
+    lua: &Lua,
+    state: &mut State,
+    spec: LuaValue,
+) -> LuaResult<()> {
+    let source = match parse_spec(spec) {
+        Some(Spec {
+            name,
+            cmd,
+            filetypes,
+            timeout_ms,
+            trigger_characters,
+        }) => External::new(
+            static_name(state, name),
+            cmd,
+            filetypes,
+            Duration::from_millis(timeout_ms),
+            trigger_characters,
+        ),
+        None => {
+            let message = Message::error()
+                .text("Invalid source. Please pass a table with a ")
+                .hl("name", "CompleetErrorMsgField")
+                .text(" string and a non-empty ")
+                .hl("cmd", "CompleetErrorMsgField")
+                .text(" list to ")
+                .hl("register_external_source", "Statement");
+            let api = Neovim::new(lua)?.api;
+            messages::send(lua, &api, &state.settings.ui.messages, message)?;
+            return Ok(());
+        },
+    };
+
+    add_user_source(lua, state, Arc::new(source), false)
+}
+
+struct Spec {
+    name: String,
+    cmd: Vec<String>,
+    filetypes: Vec<String>,
+    timeout_ms: u64,
+    trigger_characters: Vec<String>,
+}
+
+/// Parses the spec of an external source, returning `None` if it's invalid.
+fn parse_spec(spec: LuaValue) -> Option<Spec> {
+    let spec = match spec {
+        LuaValue::Table(spec) => spec,
+        _ => return None,
+    };
+
+    let strings = |key| {
+        spec.get::<_, Option<LuaTable>>(key)
+            .ok()?
+            .map(|t| t.sequence_values().collect::<LuaResult<Vec<String>>>())
+            .transpose()
+            .ok()
+            .map(Option::unwrap_or_default)
+    };
+
+    let name = spec.get::<_, String>("name").ok()?;
+    let cmd = strings("cmd")?;
+
+    if name.is_empty() || cmd.is_empty() {
+        return None;
+    }
+
+    Some(Spec {
+        name,
+        cmd,
+        filetypes: strings("filetypes")?,
+        timeout_ms: spec
+            .get::<_, Option<u64>>("timeout_ms")
+            .ok()?
+            .unwrap_or(DEFAULT_TIMEOUT_MS),
+        trigger_characters: strings("trigger_characters")?,
+    })
+}
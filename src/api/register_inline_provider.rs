@@ -0,0 +1,50 @@
+use mlua::prelude::{Lua, LuaFunction, LuaResult, LuaValue};
+use neovim::Neovim;
+
+use crate::state::State;
+use crate::ui::messages::{self, Message};
+
+/// Executed by the `require("compleet").register_inline_provider` Lua
+/// function. Registering a provider with the same name as an already
+/// registered one replaces it.
+pub fn register_inline_provider(
+    lua: &Lua,
+    state: &mut State,
+    spec: LuaValue,
+) -> LuaResult<()> {
+    let (name, suggest) = match parse_spec(spec) {
+        Some(parsed) => parsed,
+        None => {
+            let api = Neovim::new(lua)?.api;
+            let message = Message::error()
+                .text("Invalid inline provider. Please pass a table with a ")
+                .hl("name", "CompleetErrorMsgField")
+                .text(" string and a ")
+                .hl("suggest", "CompleetErrorMsgField")
+                .text(" function to ")
+                .hl("register_inline_provider", "Statement");
+            messages::send(lua, &api, &state.settings.ui.messages, message)?;
+            return Ok(());
+        },
+    };
+
+    state
+        .inline
+        .register(name, lua.create_registry_value(suggest)?);
+
+    Ok(())
+}
+
+/// Returns the `(name, suggest)` tuple of a provider spec, or `None` if it's
+/// invalid.
+fn parse_spec(spec: LuaValue) -> Option<(String, LuaFunction)> {
+    let spec = match spec {
+        LuaValue::Table(spec) => spec,
+        _ => return None,
+    };
+
+    let name = spec.get::<_, String>("name").ok()?;
+    let suggest = spec.get::<_, LuaFunction>("suggest").ok()?;
+
+    (!name.is_empty()).then_some((name, suggest))
+}
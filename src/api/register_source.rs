@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use mlua::prelude::{Lua, LuaFunction, LuaResult, LuaTable, LuaValue};
+use neovim::Neovim;
+
+use crate::completion::sources::UserSource;
+use crate::completion::CompletionSource;
+use crate::state::State;
+use crate::ui::messages::{self, Message};
+
+/// Executed by the `require("compleet").register_source` Lua function.
+/// Registering a source with the same name as an already registered one
+/// replaces it.
+pub fn register_source(
+    lua: &Lua,
+    state: &mut State,
+    spec: LuaValue,
+) -> LuaResult<()> {
+    let api = Neovim::new(lua)?.api;
+
+    let (name, complete, trigger_characters) = match parse_spec(spec) {
+        Some(parsed) => parsed,
+        None => {
+            let message = Message::error()
+                .text("Invalid source. Please pass a table with a ")
+                .hl("name", "CompleetErrorMsgField")
+                .text(" string and a ")
+                .hl("complete", "CompleetErrorMsgField")
+                .text(" function to ")
+                .hl("register_source", "Statement");
+            messages::send(lua, &api, &state.settings.ui.messages, message)?;
+            return Ok(());
+        },
+    };
+
+    let source = Arc::new(UserSource::new(
+        static_name(state, name),
+        lua.create_registry_value(complete)?,
+        trigger_characters,
+    ));
+
+    add_user_source(lua, state, source, true)
+}
+
+/// Completion items refer to their source with a `&'static str`, so the
+/// name of a new source is leaked. Sources are only registered a handful of
+/// times per session, and re-registering reuses the old name.
+pub(super) fn static_name(state: &State, name: String) -> &'static str {
+    match state.user_sources.iter().find(|s| s.name() == name) {
+        Some(source) => source.name(),
+        None => Box::leak(name.into_boxed_str()),
+    }
+}
+
+/// Adds a source registered from Lua, replacing the one with the same name.
+/// If `attaches_to_all` isn't set, the source is only added to the attached
+/// buffers its `attach` method accepts.
+pub(super) fn add_user_source(
+    lua: &Lua,
+    state: &mut State,
+    source: Arc<dyn CompletionSource>,
+    attaches_to_all: bool,
+) -> LuaResult<()> {
+    let api = Neovim::new(lua)?.api;
+    let name = source.name();
+
+    state.user_sources.retain(|s| s.name() != name);
+    state.user_sources.push(source.clone());
+
+    // Lua sources attach to every buffer, so they're added to the ones that
+    // are already attached. Deleted buffers can't be queried anymore, so
+    // errors count as the source not attaching.
+    for (&bufnr, sources) in state.sources.iter_mut() {
+        sources.retain(|s| s.name() != name);
+        if attaches_to_all
+            || matches!(source.attach(lua, &api, bufnr), Ok(true))
+        {
+            sources.push(source.clone());
+        }
+    }
+
+    // The buffers that weren't attached because no source wanted them can be
+    // attached now. Attaching also locks the state, so it has to be
+    // scheduled.
+    if let Some(key) = state.try_buf_attach.as_ref() {
+        let try_buf_attach = lua.registry_value::<LuaFunction>(key)?;
+        Neovim::new(lua)?.schedule(try_buf_attach)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the `(name, complete, trigger_characters)` tuple of a source spec,
+/// or `None` if it's invalid.
+fn parse_spec(spec: LuaValue) -> Option<(String, LuaFunction, Vec<String>)> {
+    let spec = match spec {
+        LuaValue::Table(spec) => spec,
+        _ => return None,
+    };
+
+    let name = spec.get::<_, String>("name").ok()?;
+    let complete = spec.get::<_, LuaFunction>("complete").ok()?;
+    let trigger_characters = spec
+        .get::<_, Option<LuaTable>>("trigger_characters")
+        .ok()?
+        .map(|t| t.sequence_values().collect::<LuaResult<Vec<String>>>())
+        .transpose()
+        .ok()?
+        .unwrap_or_default();
+
+    (!name.is_empty()).then_some((name, complete, trigger_characters))
+}
@@ -1,11 +1,26 @@
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
-use mlua::prelude::{Lua, LuaError, LuaResult, LuaValue};
+use mlua::prelude::{Lua, LuaResult, LuaValue};
 use neovim::Neovim;
 
-use crate::settings::Settings;
+use crate::completion::KeywordPattern;
+use crate::settings::ui::{menu, messages::MessagesSettings};
+use crate::settings::{self, completion, sources, SettingsError};
 use crate::state::State;
-use crate::{autocmds, commands, hlgroups, mappings};
+use crate::ui::highlights;
+use crate::ui::messages::{self, Message};
+use crate::{
+    autocmds,
+    cmdline,
+    commands,
+    disk_cache,
+    inline,
+    logging,
+    mappings,
+    profiling,
+    terminal,
+};
 
 /// Executed by the `require("compleet").setup` Lua function.
 pub fn setup(
@@ -16,69 +31,107 @@ pub fn setup(
     let api = Neovim::new(lua)?.api;
 
     // Here we create the highlight groups used in the error messages.
-    hlgroups::setup_error_msg(lua, &api)?;
+    highlights::error_msg(lua, &api)?;
 
     // If the Neovim version isn't >= 0.7 we echo an error message and return
     // early.
-    if !api.call_function::<_, u8>("has", vec!["nvim-0.7"])? == 1 {
-        let chunks = [
-            ("[nvim-compleet]", Some("CompleetErrorMsgTag")),
-            (" Neovim v0.7+ is required", None),
-        ];
-        api.echo(&chunks, true)?;
+    if api.call_function::<_, u8>("has", vec!["nvim-0.7"])? == 0 {
+        let message = Message::error().text("Neovim v0.7+ is required");
+        messages::send(lua, &api, &MessagesSettings::default(), message)?;
         return Ok(());
     }
 
     let _state = state.clone();
     let _state = &mut _state.lock().unwrap();
 
-    _state.settings = match preferences {
-        LuaValue::Table(t) => {
-            // Using the `serde_path_to_error` crate to get the full path
-            // of the option where the error occured.
-            match serde_path_to_error::deserialize::<_, Settings>(
-                mlua::serde::Deserializer::new(LuaValue::Table(t)),
-            ) {
-                Ok(settings) => settings,
-
-                Err(e) => match e.inner() {
-                    LuaError::DeserializeError(msg) => {
-                        let path = e.path().to_string();
-                        let mut chunks = vec![
-                            ("[nvim-compleet]", Some("ErrorMsg")),
-                            (" Error for `", None),
-                            (&path, Some("CompleetErrorMsgOptionPath")),
-                            ("`: ", None),
-                        ];
-
-                        chunks.append(&mut to_chunks(msg));
-
-                        api.echo(&chunks, true)?;
-
-                        return Ok(());
-                    },
-
-                    _ => return Err(e.into_inner()),
-                },
-            }
-        },
+    // Errors in the new config are reported with the old settings.
+    let messages_settings = &_state.settings.ui.messages;
+
+    // Functions can't be deserialized, so the ones passed as the `enable`
+    // option of the sources, as the `ui.menu.format` option and in the
+    // `completion.sorting.comparators` list are taken out of the config
+    // first.
+    let (preferences, predicates) =
+        sources::take_predicates(lua, preferences)?;
+    let (preferences, formatter) = menu::take_formatter(lua, preferences)?;
+    let (preferences, comparators) =
+        completion::take_comparators(lua, preferences)?;
+    let (preferences, filter) = completion::take_filter(lua, preferences)?;
+
+    if !matches!(preferences, LuaValue::Table(_) | LuaValue::Nil) {
+        let message = Message::error()
+            .text("Invalid value '")
+            .hl(format!("{:?}", preferences), "Statement")
+            .text("'. Please pass either a table or ")
+            .hl("nil", "Statement")
+            .text(" to the setup function");
+        messages::send(lua, &api, messages_settings, message)?;
+        return Ok(());
+    }
+
+    let mut settings = match settings::validate(lua, preferences)? {
+        Ok(settings) => settings,
 
-        LuaValue::Nil => Settings::default(),
-
-        _ => {
-            let chunks = [
-                ("[nvim-compleet]", Some("CompleetErrorMsgTag")),
-                (" Invalid value '", None),
-                (&format!("{:?}", preferences), Some("Statement")),
-                ("'. Please pass either a table or ", None),
-                ("nil", Some("Statement")),
-                (" to the setup function", None),
-            ];
-            api.echo(&chunks, true)?;
+        Err(errors) => {
+            for error in &errors {
+                logging::warn(
+                    "setup",
+                    format!("invalid option `{}`: {}", error.path, error.msg),
+                );
+            }
+            messages::send(
+                lua,
+                &api,
+                messages_settings,
+                errors_message(&errors),
+            )?;
             return Ok(());
         },
     };
 
+    // The keyword pattern is compiled once here instead of on every
+    // keystroke. It's already been validated.
+    let keyword_pattern = KeywordPattern::new(
+        lua,
+        settings.completion.keyword_pattern.as_deref(),
+    )?;
+
+    lua.set_named_registry_value(sources::PREDICATES, predicates)?;
+    lua.set_named_registry_value(menu::MENU_FORMATTER, formatter)?;
+    lua.set_named_registry_value(completion::FILTER, filter)?;
+
+    let (positions, functions): (Vec<_>, Vec<_>) =
+        comparators.into_iter().unzip();
+    settings
+        .completion
+        .sorting
+        .insert_lua_comparators(positions);
+    lua.set_named_registry_value(
+        completion::COMPARATORS,
+        lua.create_sequence_from(functions)?,
+    )?;
+
+    logging::init(&api, &settings.logging)?;
+    disk_cache::init(&api, settings.completion.disk_cache)?;
+    profiling::enable(settings.debug.profile);
+
+    let old_settings = std::mem::replace(&mut _state.settings, settings);
+    let was_mouse_enabled = _state.did_setup && old_settings.ui.menu.mouse;
+    _state.keyword_pattern = keyword_pattern;
+
+    // The frecency of the completions is saved under Neovim's data directory
+    // to be shared across sessions.
+    let history_path =
+        match _state.settings.completion.sorting.persist_frecency {
+            true => {
+                let data =
+                    api.call_function::<_, String>("stdpath", vec!["data"])?;
+                Some(PathBuf::from(data).join("compleet").join("history"))
+            },
+            false => None,
+        };
+    _state.history.persist(history_path);
+
     #[cfg(debug)]
     {
         let nvim = Neovim::new(lua)?;
@@ -91,13 +144,33 @@ pub fn setup(
         _state.augroup_id = Some(aux.0);
         _state.try_buf_attach = Some(aux.1);
 
+        cmdline::setup(lua, &api, state)?;
+        terminal::setup(lua, &api, state)?;
         commands::setup(lua, &api, state)?;
-        hlgroups::setup(lua, &api)?;
-        mappings::setup(lua, &api, state)?;
+        let (on_suggestion, on_debounced) = inline::setup(lua, &api, state)?;
+        _state.inline.on_suggestion = Some(on_suggestion);
+        _state.inline.on_debounced = Some(on_debounced);
+        _state.on_inserted = Some(mappings::setup(lua, &api, state)?);
 
         _state.did_setup = true;
+        logging::info("setup", "set up");
+    }
+    // Calling `setup` again applies the new settings without having to
+    // restart Neovim.
+    else {
+        super::reconfigure(lua, &api, _state, &old_settings)?;
+        logging::info("setup", "applied the new config");
+    }
+
+    let is_mouse_enabled = _state.settings.ui.menu.mouse;
+    if is_mouse_enabled != was_mouse_enabled {
+        mappings::setup_mouse(lua, &api, state, is_mouse_enabled)?;
     }
 
+    // The highlight groups can depend on the settings, so they're defined
+    // again every time.
+    highlights::apply(lua, &api, &_state.settings.ui)?;
+
     #[cfg(debug)]
     {
         let nvim = Neovim::new(lua)?;
@@ -110,17 +183,18 @@ pub fn setup(
     Ok(())
 }
 
-fn to_chunks(msg: &str) -> Vec<(&'_ str, Option<&'static str>)> {
-    msg.split('`')
-        .enumerate()
-        .map(|(i, str)| match i % 2 == 1 {
-            true => vec![
-                ("`", None),
-                (str, Some("CompleetErrorMsgField")),
-                ("`", None),
-            ],
-            false => vec![(str, None)],
-        })
-        .flatten()
-        .collect()
+/// Returns a message listing all the invalid options, one per line.
+fn errors_message(errors: &[SettingsError]) -> Message {
+    let mut message = Message::error();
+    for (i, error) in errors.iter().enumerate() {
+        if i > 0 {
+            message = message.text("\n");
+        }
+        message = message
+            .text("Error for `")
+            .hl(error.path.clone(), "CompleetErrorMsgOptionPath")
+            .text("`: ")
+            .fields(&error.msg);
+    }
+    message
 }
@@ -0,0 +1,73 @@
+use mlua::prelude::{Lua, LuaResult, LuaTable};
+
+use crate::completion::CompletionItem;
+use crate::state::State;
+
+/// Executed by the `require("compleet").status` Lua function. Returns a table
+/// with the `label`, `kind` and `source` of the selected completion, either
+/// in the buffer or in the command line, or `nil` if no completion is
+/// selected.
+pub fn status<'lua>(
+    lua: &'lua Lua,
+    state: &State,
+) -> LuaResult<Option<LuaTable<'lua>>> {
+    let completion = match selected(state) {
+        Some(completion) => completion,
+        None => return Ok(None),
+    };
+
+    let status = lua.create_table_with_capacity(0, 3)?;
+    status.set("label", completion.format.trim())?;
+    status.set("kind", completion.kind)?;
+    status.set("source", completion.source)?;
+    Ok(Some(status))
+}
+
+/// Executed by the `require("compleet").lualine_component` Lua function,
+/// which can be used as a lualine component. Returns the selected
+/// completion formatted as `label Kind [Source]`, or an empty string if no
+/// completion is selected.
+pub fn lualine_component(state: &State) -> String {
+    selected(state).map(format_status).unwrap_or_default()
+}
+
+fn selected(state: &State) -> Option<&CompletionItem> {
+    state
+        .phase()
+        .selected()
+        .and_then(|index| state.buffers.current().completions.get(index))
+        .or_else(|| state.cmdline.selected())
+}
+
+fn format_status(completion: &CompletionItem) -> String {
+    let mut status = completion.format.trim().to_owned();
+    if let Some(kind) = completion.kind {
+        status.push(' ');
+        status.push_str(kind);
+    }
+    status.push_str(&format!(" [{}]", completion.source));
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_status;
+    use crate::completion::CompletionItem;
+
+    #[test]
+    fn status_line() {
+        let item = |kind| CompletionItem {
+            format: " foo".into(),
+            kind,
+            source: "Lsp",
+            text: "foo".into(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            "foo Function [Lsp]",
+            format_status(&item(Some("Function")))
+        );
+        assert_eq!("foo [Lsp]", format_status(&item(None)));
+    }
+}
@@ -0,0 +1,33 @@
+use mlua::prelude::{Lua, LuaResult, LuaTable, LuaValue};
+
+use crate::settings::{self, completion, sources, ui::menu};
+
+/// Executed by the `require("compleet").validate` Lua function. Returns a
+/// list of the invalid options of a config, each one a table with the `path`
+/// of the option and a `message`. The list is empty if the config is valid.
+pub fn validate<'lua>(
+    lua: &'lua Lua,
+    preferences: LuaValue<'lua>,
+) -> LuaResult<LuaTable<'lua>> {
+    let (preferences, _) = sources::take_predicates(lua, preferences)?;
+    let (preferences, _) = menu::take_formatter(lua, preferences)?;
+    let (preferences, _) = completion::take_comparators(lua, preferences)?;
+    let (preferences, _) = completion::take_filter(lua, preferences)?;
+
+    let errors = match settings::validate(lua, preferences)? {
+        Ok(_) => Vec::new(),
+        Err(errors) => errors,
+    };
+
+    lua.create_sequence_from(
+        errors
+            .into_iter()
+            .map(|error| {
+                lua.create_table_from([
+                    ("path", error.path),
+                    ("message", error.msg),
+                ])
+            })
+            .collect::<LuaResult<Vec<LuaTable>>>()?,
+    )
+}
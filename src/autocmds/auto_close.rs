@@ -0,0 +1,123 @@
+use mlua::prelude::{Lua, LuaResult, LuaTable};
+use neovim::{Api, Neovim};
+
+use crate::state::State;
+
+/// The events closing the menu if the matching `ui.menu.close_on` options
+/// are set.
+pub const EVENTS: &[&str] = &["ModeChanged", "WinLeave"];
+
+/// Executed on the `EVENTS` in attached buffers.
+pub fn on_event(
+    lua: &Lua,
+    state: &mut State,
+    args: LuaTable,
+) -> LuaResult<()> {
+    let close_on = state.settings.ui.menu.close_on;
+
+    let should_close = match args.get::<_, String>("event")?.as_str() {
+        "ModeChanged" => {
+            close_on.mode_changed
+                && leaves_insert_mode(&args.get::<_, String>("match")?)
+        },
+        "WinLeave" => close_on.win_leave,
+        _ => false,
+    };
+
+    match should_close {
+        true => close(lua, &Neovim::new(lua)?.api, state),
+        false => Ok(()),
+    }
+}
+
+/// Executed on every `CursorMovedI` event in attached buffers, returning
+/// whether the menu was closed because the cursor was moved out of the
+/// range matched by the completions, or because the text in that range was
+/// edited without the completions being recomputed (e.g. by deleting a
+/// character when `completion.while_deleting` is off).
+pub fn on_cursor_moved(
+    lua: &Lua,
+    api: &Api,
+    state: &mut State,
+) -> LuaResult<bool> {
+    let range = match &state.buffers.current().matched_range {
+        Some(range) if !state.buffers.current().completions.is_empty() => {
+            range
+        },
+        _ => return Ok(false),
+    };
+
+    let (row, col) = api.win_get_cursor(0)?;
+    let is_outside = state.settings.ui.menu.close_on.cursor_moved
+        && !range.contains(row - 1, col);
+
+    if !is_outside
+        && state
+            .buffers
+            .current()
+            .is_matched_range_intact(row - 1, &api.get_current_line()?)
+    {
+        return Ok(false);
+    }
+
+    close(lua, api, state)?;
+    Ok(true)
+}
+
+/// Executed by `completion::on_bytes` when the keyword before the cursor
+/// used to start at the `old` `(row, col)` position, closing the menu if
+/// it's a different word than the one the menu was opened for.
+pub fn on_keyword_changed(
+    lua: &Lua,
+    api: &Api,
+    state: &mut State,
+    old: (u32, u32),
+) -> LuaResult<()> {
+    let new = (
+        state.buffers.current().cursor.row,
+        state.buffers.current().cursor.keyword_start_col(),
+    );
+
+    if state.settings.ui.menu.close_on.new_word
+        && new != old
+        && state.phase().is_menu_visible()
+    {
+        state.close_ui(lua, api)?;
+    }
+
+    Ok(())
+}
+
+/// Closes the UI and forgets the completions, so that moving the cursor
+/// doesn't open the menu again until the next typed character.
+fn close(lua: &Lua, api: &Api, state: &mut State) -> LuaResult<()> {
+    state.preview.commit();
+    state.buffers.current_mut().clear_completions();
+    state.pipeline.stop_loading();
+    state.close_ui(lua, api)
+}
+
+/// Whether the `old:new` modes matched by a `ModeChanged` event switch from
+/// insert mode to a mode other than its completion submodes (e.g. the one
+/// used to show the native popup menu).
+fn leaves_insert_mode(modes: &str) -> bool {
+    match modes.split_once(':') {
+        Some((old, new)) => old.starts_with('i') && !new.starts_with('i'),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::leaves_insert_mode;
+
+    #[test]
+    fn mode_changes() {
+        assert!(leaves_insert_mode("i:niI"));
+        assert!(leaves_insert_mode("i:n"));
+        assert!(leaves_insert_mode("ic:R"));
+        assert!(!leaves_insert_mode("i:ic"));
+        assert!(!leaves_insert_mode("n:i"));
+        assert!(!leaves_insert_mode(""));
+    }
+}
@@ -0,0 +1,50 @@
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Neovim;
+
+use crate::completion::MatchedRange;
+use crate::events::{self, Event};
+use crate::mappings;
+use crate::state::State;
+use crate::ui::menu::native;
+
+/// Executed on every `CompleteDone` event. If the completions were shown in
+/// the native popup menu and one of them was confirmed, Neovim has already
+/// inserted its text, so it's only recorded in the history and confirmed to
+/// its source. In any case the menu was closed, so the completions are
+/// cleared and the user's `completeopt` is restored.
+pub fn complete_done(lua: &Lua, state: &mut State) -> LuaResult<()> {
+    let nvim = Neovim::new(lua)?;
+    native::restore_completeopt(lua, &nvim.api)?;
+
+    if !state.ui.is_native_menu_visible() {
+        return Ok(());
+    }
+
+    // The completions may have changed since the menu was last filled, in
+    // which case the index doesn't point to the confirmed completion.
+    let completion =
+        native::completed_item(&nvim.api)?.and_then(|(index, word)| {
+            state
+                .buffers
+                .current()
+                .completions
+                .get(index)
+                .filter(|c| c.text == word)
+        });
+
+    if let Some(completion) = completion {
+        let range =
+            MatchedRange::new(completion, &state.buffers.current().cursor);
+        let filetype = nvim.api.buf_get_option::<String>(0, "filetype")?;
+        state
+            .history
+            .record(&filetype, &range.text, &completion.text);
+
+        mappings::schedule_confirm(lua, &nvim, &state.sources, completion)?;
+        events::fire(lua, Event::ItemConfirmed(completion))?;
+    }
+
+    state.buffers.current_mut().clear_completions();
+    state.pipeline.stop_loading();
+    state.close_ui(lua, &nvim.api)
+}
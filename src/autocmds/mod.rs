@@ -1,5 +1,9 @@
+mod auto_close;
+mod complete_done;
 mod setup;
 mod try_buf_attach;
 
+pub use auto_close::on_keyword_changed;
+use complete_done::complete_done;
 pub use setup::setup;
 use try_buf_attach::try_buf_attach;
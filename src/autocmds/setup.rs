@@ -1,10 +1,14 @@
 use std::sync::{Arc, Mutex};
 
-use mlua::prelude::{Lua, LuaRegistryKey, LuaResult};
+use mlua::prelude::{Lua, LuaRegistryKey, LuaResult, LuaTable};
 use neovim::{Api, Neovim};
 
+use crate::boundary;
 use crate::completion;
+use crate::phase::Transition;
+use crate::session;
 use crate::state::State;
+use crate::ui::highlights;
 
 pub fn setup(
     lua: &Lua,
@@ -13,22 +17,42 @@ pub fn setup(
 ) -> LuaResult<(u32, LuaRegistryKey)> {
     let _state = state.clone();
     let cleanup_ui = move |lua: &Lua, ()| {
-        let api = Neovim::new(&lua)?.api;
-        let ui = &mut _state.lock().unwrap().ui;
-        ui.cleanup(&api)
+        boundary::guard(lua, &_state, "cleanup_ui", |state| {
+            let api = Neovim::new(lua)?.api;
+            // Leaving insert mode also ends the active snippet session and
+            // keeps the previewed completion.
+            state.snippet.stop(&api)?;
+            state.preview.commit();
+            state.close_ui(lua, &api)
+        })
     };
 
     let _state = state.clone();
     let update_ui = move |lua: &Lua, ()| {
-        let api = Neovim::new(lua)?.api;
-        let state = &mut *_state.lock().unwrap();
-        state.ui.update(
-            lua,
-            &api,
-            &state.completions,
-            &state.cursor,
-            &state.settings,
-        )
+        boundary::guard(lua, &_state, "update_ui", |state| {
+            let api = Neovim::new(lua)?.api;
+
+            // The cursor is moved when a completion is previewed, and the UI
+            // should stay as it is in that case. If the cursor has moved
+            // anywhere else the previewed text is kept.
+            if state.preview.is_active() {
+                let (row, col) = api.win_get_cursor(0)?;
+                if state.preview.is_cursor_at_end(row - 1, col) {
+                    return Ok(());
+                }
+                state.preview.commit();
+                state.ui.completion_menu.selected_index = None;
+                state.transition(Transition::Select(None));
+            }
+
+            if super::auto_close::on_cursor_moved(lua, &api, state)? {
+                return Ok(());
+            }
+
+            state.update_ui(lua, &api)?;
+
+            completion::resolve_selected(lua, &api, state)
+        })
     };
 
     let _state = state.clone();
@@ -48,28 +72,100 @@ pub fn setup(
             _,
             bytes_added,
         ): (String, _, u32, _, _, u32, _, u32, _, _, u32, _)| {
-            completion::on_bytes(
-                lua,
-                &mut _state.lock().unwrap(),
-                bufnr,
-                start_row,
-                start_col,
-                rows_deleted,
-                bytes_deleted,
-                rows_added,
-                bytes_added,
-            )
+            boundary::guard(lua, &_state, "on_bytes", |state| {
+                completion::on_bytes(
+                    lua,
+                    state,
+                    bufnr,
+                    start_row,
+                    start_col,
+                    rows_deleted,
+                    bytes_deleted,
+                    rows_added,
+                    bytes_added,
+                )
+            })
         };
 
+    let _state = state.clone();
+    let refresh = lua.create_function(move |lua: &Lua, ()| {
+        boundary::guard(lua, &_state, "refresh", |state| {
+            completion::refresh(lua, state)
+        })
+    })?;
+
+    let _state = state.clone();
+    let reapply_highlights = lua.create_function(move |lua: &Lua, ()| {
+        boundary::guard(lua, &_state, "reapply_highlights", |state| {
+            let api = Neovim::new(lua)?.api;
+            highlights::apply(lua, &api, &state.settings.ui)
+        })
+    })?;
+
+    let _state = state.clone();
+    let save_session = lua.create_function(move |lua: &Lua, ()| {
+        boundary::guard(lua, &_state, "save_session", |state| {
+            let api = Neovim::new(lua)?.api;
+            session::save(&api, state)
+        })
+    })?;
+
+    // Neovim doesn't wait for the background threads when exiting, so the
+    // history is written before returning then.
+    let _state = state.clone();
+    let flush_history =
+        lua.create_function(move |lua: &Lua, args: LuaTable| {
+            boundary::guard(lua, &_state, "flush_history", |state| {
+                let handle = state.history.flush();
+                if let (Some(handle), "VimLeavePre") =
+                    (handle, &*args.get::<_, String>("event")?)
+                {
+                    let _ = handle.join();
+                }
+                Ok(())
+            })
+        })?;
+
+    let _state = state.clone();
+    let restore_session = lua.create_function(move |lua: &Lua, ()| {
+        boundary::guard(lua, &_state, "restore_session", |state| {
+            let api = Neovim::new(lua)?.api;
+            session::restore(&api, state)
+        })
+    })?;
+
+    let _state = state.clone();
+    let auto_close = move |lua: &Lua, args: LuaTable| {
+        boundary::guard(lua, &_state, "auto_close", |state| {
+            super::auto_close::on_event(lua, state, args)
+        })
+    };
+
+    // The event can also be fired by the `complete()` calls made while
+    // updating the native popup menu, when the state is already locked and
+    // there's nothing to do.
+    let _state = state.clone();
+    let complete_done = lua.create_function(move |lua, ()| {
+        if _state.try_lock().is_err() {
+            return Ok(());
+        }
+        boundary::guard(lua, &_state, "complete_done", |state| {
+            super::complete_done(lua, state)
+        })
+    })?;
+
     let _state = state.clone();
     let try_buf_attach = lua.create_function(move |lua: &Lua, ()| {
-        super::try_buf_attach(
-            lua,
-            &mut _state.lock().unwrap(),
-            lua.create_function(on_bytes.clone())?,
-            lua.create_function(update_ui.clone())?,
-            lua.create_function(cleanup_ui.clone())?,
-        )
+        boundary::guard(lua, &_state, "try_buf_attach", |state| {
+            super::try_buf_attach(
+                lua,
+                state,
+                lua.create_function(on_bytes.clone())?,
+                lua.create_function(update_ui.clone())?,
+                lua.create_function(cleanup_ui.clone())?,
+                lua.create_function(auto_close.clone())?,
+            )
+        })
     })?;
 
     // Create the `Compleet` augroup which will hold all the autocmds.
@@ -81,5 +177,41 @@ pub fn setup(
     opts.set("callback", try_buf_attach.clone())?;
     api.create_autocmd(&["BufEnter"], opts.clone())?;
 
+    opts.set("callback", complete_done)?;
+    api.create_autocmd(&["CompleteDone"], opts)?;
+
+    // Asynchronous sources fire a `User CompleetSourceUpdate` event when they
+    // have new results. This autocmd is not part of the `Compleet` augroup so
+    // that it survives a `CompleetStop!`, since `completion::refresh` is a
+    // no-op in buffers that aren't attached anyway.
+    let opts = lua.create_table_with_capacity(0, 2)?;
+    opts.set("pattern", "CompleetSourceUpdate")?;
+    opts.set("callback", refresh)?;
+    api.create_autocmd(&["User"], opts)?;
+
+    // Switching colorscheme clears all the highlight groups it doesn't
+    // define, so ours are defined again. Like the one above, this autocmd
+    // isn't part of the `Compleet` augroup.
+    let opts = lua.create_table_with_capacity(0, 1)?;
+    opts.set("callback", reapply_highlights)?;
+    api.create_autocmd(&["ColorScheme"], opts)?;
+
+    // The state of `:mksession` users is saved when Neovim exits and restored
+    // when the session is loaded again. These aren't part of the `Compleet`
+    // augroup either.
+    let opts = lua.create_table_with_capacity(0, 1)?;
+    opts.set("callback", save_session)?;
+    api.create_autocmd(&["VimLeavePre"], opts)?;
+
+    let opts = lua.create_table_with_capacity(0, 1)?;
+    opts.set("callback", restore_session)?;
+    api.create_autocmd(&["SessionLoadPost"], opts)?;
+
+    // The history of the inserted completions is written lazily, batching
+    // the ones inserted before leaving insert mode.
+    let opts = lua.create_table_with_capacity(0, 1)?;
+    opts.set("callback", flush_history)?;
+    api.create_autocmd(&["InsertLeave", "VimLeavePre"], opts)?;
+
     Ok((augroup_id, lua.create_registry_value(try_buf_attach)?))
 }
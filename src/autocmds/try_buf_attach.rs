@@ -1,7 +1,8 @@
 use mlua::prelude::{Lua, LuaFunction, LuaResult};
-use neovim::{api::LogLevel, Neovim};
+use neovim::Neovim;
 
-use crate::state::{Sources, State};
+use crate::state::State;
+use crate::ui::messages::{self, Message};
 
 /// Executed on every `BufEnter` event and by the `CompleetStart{!}` user
 /// command.
@@ -11,43 +12,59 @@ pub fn try_buf_attach(
     on_bytes: LuaFunction,
     update_ui: LuaFunction,
     cleanup_ui: LuaFunction,
+    auto_close: LuaFunction,
 ) -> LuaResult<()> {
     let api = Neovim::new(lua)?.api;
 
     let bufnr = api.get_current_buf()?;
 
-    // Collect all the completion sources that want to attach to the current
-    // buffer.
-    let sources = state
-        .settings
-        .sources
-        .iter()
-        .filter(|&s| s.attach(&api, bufnr).unwrap_or(false))
-        .map(|s| s.clone())
-        .collect::<Sources>();
+    // Every buffer keeps its own completions, even when it's shown in several
+    // windows, so the ones of the buffer we're leaving are kept apart.
+    state.enter_buffer(lua, &api, bufnr)?;
 
     // Don't attach if:
     //
-    // 1. we've already attached;
+    // 1. we've already attached, or completion was stopped in the buffer with
+    //    `:Compleet stop`;
     //
     // 2. the buffer has the `modifiable` option turned off. This should catch
     //    a large number of buffers we'd like to ignore like netwr, startify,
     //    terminal buffers, help buffers, etc.
-    //
-    // 3. there are no compatible sources for the current buffer;
     if state.attached_buffers.contains(&bufnr)
+        || state.stopped_buffers.contains(&bufnr)
         || !api.buf_get_option::<bool>(0, "modifiable")?
-        || sources.is_empty()
     {
         return Ok(());
     }
 
+    // 3. the buffer is in a floating window, unless the
+    //    `completion.enable_in_floats` option is set.
+    if !state.settings.completion.enable_in_floats
+        && !api
+            .win_get_config(0)?
+            .get::<_, String>("relative")?
+            .is_empty()
+    {
+        return Ok(());
+    }
+
+    // Collect all the completion sources that want to attach to the current
+    // buffer.
+    let sources = state.attachable_sources(lua, &api, bufnr)?;
+
+    // 4. Also don't attach if there are no compatible sources for the current
+    //    buffer, which is also the case if its filetype or buftype is
+    //    excluded.
+    if sources.is_empty() {
+        return Ok(());
+    }
+
     let opts = lua.create_table_from([("on_bytes", on_bytes)])?;
 
     if api.buf_attach(0, false, opts)? {
         state.attached_buffers.push(bufnr);
 
-        let mut buffer_autocmd_ids = Vec::with_capacity(2);
+        let mut buffer_autocmd_ids = Vec::with_capacity(4);
 
         let opts = lua.create_table_with_capacity(0, 3)?;
         opts.set("group", state.augroup_id.expect("The augroup is set"))?;
@@ -57,10 +74,22 @@ pub fn try_buf_attach(
         buffer_autocmd_ids
             .push(api.create_autocmd(&["CursorMovedI"], opts.clone())?);
 
+        // Scrolling the window moves the cursor on the screen, so the menu
+        // has to be repositioned.
+        buffer_autocmd_ids
+            .push(api.create_autocmd(&["WinScrolled"], opts.clone())?);
+
         opts.set("callback", cleanup_ui)?;
         buffer_autocmd_ids
             .push(api.create_autocmd(&["InsertLeave"], opts.clone())?);
 
+        // Always created, since the `ui.menu.close_on` options are checked
+        // when the events fire and they can change after attaching.
+        opts.set("callback", auto_close)?;
+        buffer_autocmd_ids.push(
+            api.create_autocmd(super::auto_close::EVENTS, opts.clone())?,
+        );
+
         state
             .buffer_local_autocmds
             .insert(bufnr, buffer_autocmd_ids);
@@ -78,10 +107,8 @@ pub fn try_buf_attach(
             nvim.print(format!("{:?}", &state.attached_buffers))?;
         }
     } else {
-        api.notify(
-            "[nvim-compleet] Couldn't attach to buffer",
-            LogLevel::Error,
-        )?;
+        let message = Message::error().text("Couldn't attach to buffer");
+        messages::send(lua, &api, &state.settings.ui.messages, message)?;
     }
 
     Ok(())
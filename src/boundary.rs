@@ -0,0 +1,120 @@
+use std::any::Any;
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Mutex;
+
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Neovim;
+
+use crate::logging;
+use crate::state::State;
+use crate::ui::messages::{self, Message};
+
+thread_local! {
+    /// How many guarded entry points are running on this thread, which can
+    /// be more than one if a guarded callback is called from another one.
+    static GUARDED: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Whether a panic raised right now on this thread would be caught by
+/// `guard`.
+pub fn is_guarded() -> bool { GUARDED.with(|g| g.get() > 0) }
+
+/// Runs one of the entry points called by Neovim on autocmds and mappings
+/// with the state locked, catching the Lua errors and the Rust panics it
+/// raises. Instead of failing again on every keystroke the plugin is then
+/// stopped in the current buffer, and a single message tells how to start it
+/// again. The `entry` point and the error are written to the log.
+pub fn guard<R: Default>(
+    lua: &Lua,
+    state: &Mutex<State>,
+    entry: &str,
+    f: impl FnOnce(&mut State) -> LuaResult<R>,
+) -> LuaResult<R> {
+    GUARDED.with(|g| g.set(g.get() + 1));
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        f(&mut state.lock().unwrap())
+    }));
+    GUARDED.with(|g| g.set(g.get() - 1));
+
+    let error = match result {
+        Ok(Ok(value)) => return Ok(value),
+        Ok(Err(err)) => err.to_string(),
+        Err(payload) => {
+            // The panic poisoned the state, which is still consistent enough
+            // to stop the plugin and to be started again.
+            state.clear_poison();
+            format!("panicked: {}", panic_message(&*payload))
+        },
+    };
+
+    logging::error(entry, error);
+    stop(lua, &mut state.lock().unwrap())?;
+    Ok(R::default())
+}
+
+/// Stops the completion in the current buffer after an error, until it's
+/// started again by `:Compleet restart`. Only the first error since the last
+/// restart is reported.
+fn stop(lua: &Lua, state: &mut State) -> LuaResult<()> {
+    let api = Neovim::new(lua)?.api;
+    let bufnr = api.get_current_buf()?;
+
+    if state.attached_buffers.contains(&bufnr) {
+        state.detach(&api, bufnr)?;
+    }
+
+    if !state.stopped_buffers.contains(&bufnr) {
+        state.stopped_buffers.push(bufnr);
+    }
+
+    state.buffers.current_mut().clear_completions();
+    state.pipeline.stop_loading();
+
+    // The error could have come from the UI itself.
+    if let Err(err) = state.close_ui(lua, &api) {
+        logging::error("boundary", err);
+    }
+
+    let is_first = state.crashed_buffers.is_empty();
+
+    if !state.crashed_buffers.contains(&bufnr) {
+        state.crashed_buffers.push(bufnr);
+    }
+
+    if is_first {
+        let message = Message::error()
+            .text("Completion was stopped in this buffer after an error, see ")
+            .hl(":Compleet log", "Statement")
+            .text(". Run ")
+            .hl(":Compleet restart", "Statement")
+            .text(" to start it again");
+        messages::send(lua, &api, &state.settings.ui.messages, message)?;
+    }
+
+    Ok(())
+}
+
+/// The message of a panic, which is usually either a `&str` or a `String`.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    match (
+        payload.downcast_ref::<&str>(),
+        payload.downcast_ref::<String>(),
+    ) {
+        (Some(msg), _) => msg,
+        (_, Some(msg)) => msg,
+        _ => "no message",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::panic_message;
+
+    #[test]
+    fn panic_messages() {
+        assert_eq!("foo", panic_message(&"foo"));
+        assert_eq!("bar", panic_message(&String::from("bar")));
+        assert_eq!("no message", panic_message(&1));
+    }
+}
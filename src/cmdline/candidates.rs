@@ -0,0 +1,119 @@
+use mlua::prelude::{Lua, LuaResult, LuaValue};
+use neovim::Api;
+
+use crate::completion::{matcher, CompletionItem};
+
+/// The maximum number of history entries to look through.
+const HISTORY_SIZE: i64 = 100;
+
+/// Returns the completions for the `text` typed in a command line of type
+/// `cmdtype` (see `:h getcmdtype()`). Ex commands are completed using
+/// `getcompletion()`, while both commands and searches are matched against
+/// the entries of their history if `history` is set.
+pub fn complete(
+    lua: &Lua,
+    api: &Api,
+    cmdtype: &str,
+    text: &str,
+    history: bool,
+) -> LuaResult<Vec<CompletionItem>> {
+    if text.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut completions = Vec::new();
+
+    if cmdtype == ":" {
+        let prefix = last_token(text);
+
+        let candidates = api.call_function::<_, Vec<String>>(
+            "getcompletion",
+            vec![text, "cmdline"],
+        )?;
+
+        completions.extend(
+            candidates
+                .into_iter()
+                .filter(|candidate| candidate != prefix)
+                .filter_map(|candidate| {
+                    let m = matcher::fuzzy_match(prefix, &candidate)?;
+                    Some(item(candidate, m, prefix.len(), "Cmdline"))
+                }),
+        );
+    }
+
+    if history {
+        let last = api.call_function::<_, i64>("histnr", vec![cmdtype])?;
+        let mut seen = Vec::<String>::new();
+
+        for index in (1.max(last - HISTORY_SIZE + 1)..=last).rev() {
+            let entry = api.call_function::<_, String>(
+                "histget",
+                vec![
+                    LuaValue::String(lua.create_string(cmdtype)?),
+                    LuaValue::Integer(index),
+                ],
+            )?;
+
+            if entry.is_empty() || entry == text || seen.contains(&entry) {
+                continue;
+            }
+
+            if let Some(m) = matcher::fuzzy_match(text, &entry) {
+                completions.push(item(
+                    entry.clone(),
+                    m,
+                    text.len(),
+                    "History",
+                ));
+            }
+
+            seen.push(entry);
+        }
+    }
+
+    Ok(completions)
+}
+
+fn item(
+    text: String,
+    m: matcher::Match,
+    matched_bytes: usize,
+    source: &'static str,
+) -> CompletionItem {
+    CompletionItem {
+        details: None,
+        format: format!(" {text}"),
+        hl_ranges: m.hl_ranges(1),
+        kind: None,
+        matched_bytes: matched_bytes as u32,
+        preselect: false,
+        score: m.score,
+        snippet: None,
+        source,
+        text,
+        ..Default::default()
+    }
+}
+
+/// Returns the last whitespace-separated token of `text`, which is the part
+/// of the command line completed by `getcompletion()`.
+fn last_token(text: &str) -> &str {
+    text.rsplit(char::is_whitespace).next().unwrap_or(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::last_token;
+
+    #[test]
+    fn command_name() {
+        assert_eq!("vsp", last_token("vsp"));
+    }
+
+    #[test]
+    fn command_argument() {
+        assert_eq!("~/.con", last_token("e ~/.con"));
+        assert_eq!("", last_token("set "));
+    }
+}
@@ -0,0 +1,7 @@
+mod candidates;
+mod positioning;
+mod setup;
+mod state;
+
+pub use setup::setup;
+pub use state::Cmdline;
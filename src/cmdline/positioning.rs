@@ -0,0 +1,71 @@
+use std::cmp;
+
+use mlua::prelude::LuaResult;
+use neovim::Api;
+
+use crate::completion::unicode::display_width;
+use crate::settings::ui::menu::MenuSettings;
+use crate::ui::menu::Line;
+use crate::ui::WindowPosition;
+
+/// Figures out where to position the completion menu of the command line,
+/// placing it right above the command line and aligning it with the start of
+/// the part of the `text` matched by the first completion. The position is
+/// relative to the editor.
+pub fn get_position(
+    api: &Api,
+    lines: &[Line],
+    text: &str,
+    matched_bytes: u32,
+    settings: &MenuSettings,
+) -> LuaResult<Option<WindowPosition>> {
+    let longest_line = lines
+        .iter()
+        .map(|line| display_width(&line.text))
+        .max()
+        .expect("There's at least one completion");
+
+    let width: u32 = (longest_line + 1).try_into().unwrap();
+
+    let total_rows = api.get_option::<u32>("lines")?;
+    let total_cols = api.get_option::<u32>("columns")?;
+    let cmdheight = api.get_option::<u32>("cmdheight")?;
+
+    let border = &settings.border;
+    let border_height = if border.has_bottom_edge() { 1 } else { 0 }
+        + if border.has_top_edge() { 1 } else { 0 };
+
+    let rows_above = match total_rows.checked_sub(cmdheight + border_height) {
+        Some(rows) if rows > 0 => rows,
+        _ => return Ok(None),
+    };
+
+    let height = match settings.max_height {
+        None => lines.len() as u32,
+        Some(height) => cmp::min(u32::from(height), lines.len() as u32),
+    };
+    let height = cmp::min(height, rows_above);
+
+    let row = (rows_above - height).try_into().unwrap();
+
+    // The command line starts with the one column wide type character (e.g.
+    // `:`), which compensates for the leading space of the completions.
+    let text_pre = &text[..text.len() - matched_bytes as usize];
+    // If the left edge of the border is present the menu is moved one more
+    // column to the left.
+    let col = u32::try_from(display_width(text_pre))
+        .unwrap()
+        .saturating_sub(if border.has_left_edge() { 1 } else { 0 });
+
+    // Don't let the menu go past the right edge of the screen.
+    let col = cmp::min(col, total_cols.saturating_sub(width))
+        .try_into()
+        .unwrap();
+
+    Ok(Some(WindowPosition {
+        height,
+        width,
+        row,
+        col,
+    }))
+}
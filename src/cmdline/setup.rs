@@ -0,0 +1,103 @@
+use std::sync::{Arc, Mutex};
+
+use mlua::prelude::{Lua, LuaResult};
+use neovim::{Api, Neovim};
+
+use crate::boundary;
+use crate::state::State;
+
+/// Creates the autocommands used to complete in the command line, together
+/// with the command line mode versions of the `<Plug>` mappings.
+pub fn setup(
+    lua: &Lua,
+    api: &Api,
+    state: &Arc<Mutex<State>>,
+) -> LuaResult<()> {
+    let _state = state.clone();
+    let on_changed = lua.create_function(move |lua, ()| {
+        boundary::guard(lua, &_state, "cmdline_on_changed", |state| {
+            let api = Neovim::new(lua)?.api;
+            if !state.settings.cmdline.enable {
+                return Ok(());
+            }
+            state.cmdline.on_changed(lua, &api, &state.settings)
+        })
+    })?;
+
+    let _state = state.clone();
+    let on_leave = lua.create_function(move |lua, ()| {
+        boundary::guard(lua, &_state, "cmdline_on_leave", |state| {
+            let api = Neovim::new(lua)?.api;
+            state.cmdline.close(lua, &api)
+        })
+    })?;
+
+    let _state = state.clone();
+    let select_completion = lua.create_function(move |lua, step| {
+        boundary::guard(lua, &_state, "cmdline_select_completion", |state| {
+            let api = Neovim::new(lua)?.api;
+            state.cmdline.select(lua, &api, step, &state.settings)
+        })
+    })?;
+
+    let _state = state.clone();
+    let confirm_completion = lua.create_function(move |lua, ()| {
+        boundary::guard(lua, &_state, "cmdline_confirm_completion", |state| {
+            let api = Neovim::new(lua)?.api;
+            state.cmdline.confirm(lua, &api)
+        })
+    })?;
+
+    let _state = state.clone();
+    let abort = lua.create_function(move |lua, ()| {
+        boundary::guard(lua, &_state, "cmdline_abort", |state| {
+            let api = Neovim::new(lua)?.api;
+            state.cmdline.abort(lua, &api)
+        })
+    })?;
+
+    // Only complete Ex commands and searches.
+    let pattern = lua.create_sequence_from([":", "/", "?"])?;
+
+    let opts = lua.create_table_from([("clear", true)])?;
+    let augroup_id = api.create_augroup("CompleetCmdline", opts)?;
+
+    let opts = lua.create_table_with_capacity(0, 3)?;
+    opts.set("group", augroup_id)?;
+    opts.set("pattern", pattern)?;
+
+    opts.set("callback", on_changed)?;
+    api.create_autocmd(&["CmdlineChanged"], opts.clone())?;
+
+    opts.set("callback", on_leave)?;
+    api.create_autocmd(&["CmdlineLeave"], opts)?;
+
+    let opts = lua.create_table_from([("silent", true)])?;
+
+    opts.set("callback", select_completion.bind(1)?)?;
+    api.set_keymap("c", "<Plug>(compleet-next-completion)", "", opts.clone())?;
+
+    opts.set("callback", select_completion.bind(-1)?)?;
+    api.set_keymap("c", "<Plug>(compleet-prev-completion)", "", opts.clone())?;
+
+    opts.set("callback", confirm_completion.clone())?;
+    api.set_keymap(
+        "c",
+        "<Plug>(compleet-insert-selected-completion)",
+        "",
+        opts.clone(),
+    )?;
+
+    opts.set("callback", confirm_completion)?;
+    api.set_keymap(
+        "c",
+        "<Plug>(compleet-confirm-completion)",
+        "",
+        opts.clone(),
+    )?;
+
+    opts.set("callback", abort)?;
+    api.set_keymap("c", "<Plug>(compleet-abort)", "", opts)?;
+
+    Ok(())
+}
@@ -0,0 +1,223 @@
+use std::collections::VecDeque;
+
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Api;
+
+use super::{candidates, positioning};
+use crate::completion::CompletionItem;
+use crate::settings::Settings;
+use crate::ui::menu::{self, CompletionMenu};
+
+/// The name of the global variable used to pass the text of the selected
+/// completion to the command line.
+const CMDLINE_TEXT_VAR: &str = "compleet_cmdline_text";
+
+/// Holds the state of the completions of the command line, which are
+/// independent from the ones of the buffers.
+#[derive(Debug)]
+pub struct Cmdline {
+    /// The completions available for the text in the command line.
+    completions: Vec<CompletionItem>,
+
+    /// A completion menu shown right above the command line.
+    menu: CompletionMenu,
+
+    /// The text typed in the command line, restored when going back to no
+    /// selection.
+    typed: String,
+
+    /// The texts the command line is going to be set to by the selected
+    /// completions, oldest first. Used to tell apart the changes made by
+    /// selecting a completion from the ones made by the user.
+    expected: VecDeque<String>,
+}
+
+impl Cmdline {
+    pub fn new(api: &Api) -> LuaResult<Self> {
+        Ok(Cmdline {
+            completions: Vec::new(),
+            menu: CompletionMenu::new(api, "editor")?,
+            typed: String::new(),
+            expected: VecDeque::new(),
+        })
+    }
+}
+
+impl Cmdline {
+    /// Closes the completion menu and forgets the completions. Executed on
+    /// every `CmdlineLeave` event.
+    pub fn close(&mut self, lua: &Lua, api: &Api) -> LuaResult<()> {
+        self.completions.clear();
+        self.expected.clear();
+        self.menu.close(lua, api)
+    }
+
+    /// Whether a completion is currently selected.
+    pub fn is_item_selected(&self) -> bool { self.menu.is_item_selected() }
+
+    /// Whether the completion menu is visible.
+    pub fn is_menu_visible(&self) -> bool { self.menu.is_visible() }
+
+    /// Returns the currently selected completion, if any.
+    pub fn selected(&self) -> Option<&CompletionItem> {
+        self.menu
+            .selected_index
+            .and_then(|index| self.completions.get(index))
+    }
+
+    /// Executed on every `CmdlineChanged` event. Recomputes the completions
+    /// for the new text and updates the menu.
+    pub fn on_changed(
+        &mut self,
+        lua: &Lua,
+        api: &Api,
+        settings: &Settings,
+    ) -> LuaResult<()> {
+        let text =
+            api.call_function::<u8, String>("getcmdline", Vec::new())?;
+
+        // The change was caused by selecting a completion.
+        if let Some(pos) = self.expected.iter().position(|t| t == &text) {
+            self.expected.drain(..=pos);
+            return Ok(());
+        }
+        self.expected.clear();
+
+        let cmdtype =
+            api.call_function::<u8, String>("getcmdtype", Vec::new())?;
+
+        self.completions = candidates::complete(
+            lua,
+            api,
+            &cmdtype,
+            &text,
+            settings.cmdline.history,
+        )?;
+
+        self.typed = text;
+
+        if self.completions.is_empty() {
+            return self.menu.close(lua, api);
+        }
+
+        let lines = menu::render(lua, &self.completions, &settings.ui.menu)?;
+
+        let position = match positioning::get_position(
+            api,
+            &lines,
+            &self.typed,
+            self.completions[0].matched_bytes,
+            &settings.ui.menu,
+        )? {
+            Some(position) => position,
+            None => return self.menu.close(lua, api),
+        };
+
+        if self.menu.is_visible() {
+            self.menu.shift(lua, api, &position)?;
+            self.menu.select(lua, api, None)?;
+        } else if !self.menu.spawn(lua, api, &position, &settings.ui.menu)? {
+            return Ok(());
+        }
+
+        self.menu.fill(
+            lua,
+            api,
+            &self.completions,
+            &lines,
+            &settings.ui.menu.highlights,
+        )?;
+
+        if settings.ui.menu.scrollbar {
+            self.menu
+                .update_scrollbar(lua, api, self.completions.len())?;
+        }
+
+        // Neovim doesn't redraw the screen while the command line is active.
+        api.command("redraw")
+    }
+
+    /// Selects either the next (if `step` is `1`) or the previous (if `step`
+    /// is `-1`) completion, replacing the matched part of the command line
+    /// with its text. Going back to no selection restores the typed text.
+    pub fn select(
+        &mut self,
+        lua: &Lua,
+        api: &Api,
+        step: i8,
+        settings: &Settings,
+    ) -> LuaResult<()> {
+        if !self.menu.is_visible() {
+            return Ok(());
+        }
+
+        let new_index = self.menu.next_index(
+            self.completions.len(),
+            step,
+            settings.ui.menu.cycle,
+        );
+
+        if new_index.is_some() && new_index == self.menu.selected_index {
+            return Ok(());
+        }
+
+        self.menu.select(lua, api, new_index)?;
+
+        if settings.ui.menu.scrollbar {
+            self.menu
+                .update_scrollbar(lua, api, self.completions.len())?;
+        }
+
+        let text = match new_index {
+            Some(index) => {
+                let completion = &self.completions[index];
+                let start =
+                    self.typed.len() - completion.matched_bytes as usize;
+                format!("{}{}", &self.typed[..start], completion.text)
+            },
+            None => self.typed.clone(),
+        };
+
+        self.set_text(api, text)?;
+
+        api.command("redraw")
+    }
+
+    /// Closes the menu, putting back the typed text if a completion was
+    /// selected.
+    pub fn abort(&mut self, lua: &Lua, api: &Api) -> LuaResult<()> {
+        if self.menu.is_item_selected() {
+            self.set_text(api, self.typed.clone())?;
+        }
+        self.completions.clear();
+        self.menu.close(lua, api)
+    }
+
+    /// Closes the menu, keeping the text of the selected completion in the
+    /// command line.
+    pub fn confirm(&mut self, lua: &Lua, api: &Api) -> LuaResult<()> {
+        self.completions.clear();
+        self.expected.clear();
+        self.menu.close(lua, api)?;
+        api.command("redraw")
+    }
+
+    /// Replaces the contents of the command line with `text`. There's no API
+    /// to do that directly, so the text is passed through a global variable
+    /// that's then evaluated with `c_CTRL-\_e`.
+    fn set_text(&mut self, api: &Api, text: String) -> LuaResult<()> {
+        api.set_var(CMDLINE_TEXT_VAR, text.as_str())?;
+
+        let keys = api.replace_termcodes(
+            &format!("<C-\\>eg:{CMDLINE_TEXT_VAR}<CR>"),
+            true,
+            false,
+            true,
+        )?;
+        api.feedkeys(&keys, "n", false)?;
+
+        self.expected.push_back(text);
+
+        Ok(())
+    }
+}
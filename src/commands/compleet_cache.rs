@@ -0,0 +1,26 @@
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Api;
+
+use crate::disk_cache;
+use crate::settings::ui::messages::MessageId;
+use crate::ui::messages::{self, Message};
+use crate::State;
+
+/// Executed by `:Compleet cache clear`. Deletes the indexes of the sources
+/// cached to disk.
+pub fn compleet_cache_clear(
+    lua: &Lua,
+    api: &Api,
+    state: &State,
+) -> LuaResult<()> {
+    let message = match disk_cache::clear() {
+        Ok(1) => Message::info(MessageId::CacheCleared)
+            .text("Deleted 1 cached index"),
+        Ok(deleted) => Message::info(MessageId::CacheCleared)
+            .text(format!("Deleted {deleted} cached indexes")),
+        Err(err) => {
+            Message::error().text(format!("Couldn't clear the cache: {err}"))
+        },
+    };
+    messages::send(lua, api, &state.settings.ui.messages, message)
+}
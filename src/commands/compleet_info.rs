@@ -0,0 +1,133 @@
+use std::sync::{Arc, Mutex};
+
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Api;
+
+use crate::boundary;
+use crate::completion::SourceStats;
+use crate::State;
+
+/// Executed by `:Compleet info`. Opens a scratch buffer showing the enabled
+/// sources, how they performed in the last completion round, the attached
+/// buffers and the current settings.
+pub fn compleet_info(
+    lua: &Lua,
+    api: &Api,
+    state: &Arc<Mutex<State>>,
+) -> LuaResult<()> {
+    // The state has to be unlocked before opening the window, since that
+    // triggers the `BufEnter` autocmd trying to attach to the new buffer.
+    let lines = boundary::guard(lua, state, "compleet_info", |state| {
+        get_lines(api, state)
+    })?;
+
+    // Getting the lines failed and the error was already reported.
+    if lines.is_empty() {
+        return Ok(());
+    }
+
+    let bufnr = api.create_buf(false, true)?;
+    api.buf_set_lines(bufnr, 0, -1, false, &lines)?;
+    api.buf_set_option(bufnr, "bufhidden", "wipe")?;
+    api.buf_set_option(bufnr, "modifiable", false)?;
+    api.command(&format!("botright sbuffer {bufnr}"))
+}
+
+fn get_lines(api: &Api, state: &State) -> LuaResult<Vec<String>> {
+    let mut lines = vec!["nvim-compleet".to_owned(), String::new()];
+
+    lines.push("Enabled sources:".into());
+    if state.all_sources().next().is_none() {
+        lines.push("  none".into());
+    }
+    for source in state.all_sources() {
+        lines.push(format!("  {}", source.name()));
+    }
+    lines.push(String::new());
+
+    let bufnr = api.get_current_buf()?;
+    lines.push(format!("Last completion round in buffer {bufnr}:"));
+    match state.sources.get(&bufnr) {
+        Some(sources) => {
+            for (i, source) in sources.iter().enumerate() {
+                let stats = state.pipeline.stats(i);
+                lines
+                    .push(format!("  {}", format_stats(source.name(), stats)));
+            }
+        },
+        None => lines.push("  not attached".into()),
+    }
+    if let Some(skipped) = state.skipped_sources.get(&bufnr) {
+        lines.push(format!(
+            "  skipped in this large buffer: {}",
+            skipped.join(", ")
+        ));
+    }
+    lines.push(String::new());
+
+    let mut attached = state.attached_buffers.clone();
+    attached.sort_unstable();
+    lines.push(format!(
+        "Attached buffers: {}",
+        match attached.is_empty() {
+            true => "none".to_owned(),
+            false => attached
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<String>>()
+                .join(", "),
+        }
+    ));
+    lines.push(String::new());
+
+    lines.push("Settings:".into());
+    lines.extend(
+        format!("{:#?}", state.settings)
+            .lines()
+            .map(|line| format!("  {line}")),
+    );
+
+    Ok(lines)
+}
+
+/// Formats how the source named `name` performed in the last completion
+/// round.
+fn format_stats(name: &str, stats: Option<SourceStats>) -> String {
+    match stats {
+        Some(SourceStats::Done { items, duration }) => format!(
+            "{name:<10} {items:>5} items {:>9.2}ms",
+            duration.as_secs_f64() * 1000.0
+        ),
+        Some(SourceStats::Running) => format!("{name:<10} running"),
+        None => format!("{name:<10} not run yet"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::format_stats;
+    use crate::completion::SourceStats;
+
+    #[test]
+    fn done() {
+        let stats = SourceStats::Done {
+            items: 12,
+            duration: Duration::from_micros(1250),
+        };
+        assert_eq!(
+            "Buffer        12 items      1.25ms",
+            format_stats("Buffer", Some(stats))
+        );
+    }
+
+    #[test]
+    fn running() {
+        assert_eq!(
+            "Lsp        running",
+            format_stats("Lsp", Some(SourceStats::Running))
+        );
+        assert_eq!("Path       not run yet", format_stats("Path", None));
+    }
+}
@@ -0,0 +1,20 @@
+use mlua::prelude::LuaResult;
+use neovim::Api;
+
+use crate::logging;
+
+/// Executed by `:Compleet log`. Opens the log file in a new window.
+pub fn compleet_log(api: &Api) -> LuaResult<()> {
+    // The logger is set up by `setup`, which also creates this command.
+    let path = match logging::path() {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+
+    let path = api.call_function::<_, String>(
+        "fnameescape",
+        vec![path.display().to_string()],
+    )?;
+
+    api.command(&format!("botright split {path}"))
+}
@@ -0,0 +1,16 @@
+use mlua::prelude::LuaResult;
+use neovim::Api;
+
+use crate::profiling;
+
+/// Executed by `:Compleet profile`. Opens a scratch buffer showing how long
+/// the stages of the completion rounds took.
+pub fn compleet_profile(api: &Api) -> LuaResult<()> {
+    let lines = profiling::report();
+
+    let bufnr = api.create_buf(false, true)?;
+    api.buf_set_lines(bufnr, 0, -1, false, &lines)?;
+    api.buf_set_option(bufnr, "bufhidden", "wipe")?;
+    api.buf_set_option(bufnr, "modifiable", false)?;
+    api.command(&format!("botright sbuffer {bufnr}"))
+}
@@ -0,0 +1,48 @@
+use mlua::prelude::{Lua, LuaFunction, LuaResult};
+use neovim::Neovim;
+
+use crate::settings::ui::messages::MessageId;
+use crate::ui::messages::{self, Message};
+use crate::State;
+
+/// Executed by `:Compleet restart`. Starts the completion again in the
+/// buffers it was stopped in after an error. The current buffer is attached
+/// to right away, the others the next time they're entered.
+pub fn compleet_restart(lua: &Lua, state: &mut State) -> LuaResult<()> {
+    let nvim = Neovim::new(lua)?;
+    let api = &nvim.api;
+
+    if state.crashed_buffers.is_empty() {
+        let message = Message::warn(MessageId::AlreadyOn)
+            .text("Completion wasn't stopped by an error");
+        messages::send(lua, api, &state.settings.ui.messages, message)?;
+        return Ok(());
+    }
+
+    // Like in `:Compleet start`, the buffers don't need to be detached from
+    // the buffer update events anymore.
+    for bufnr in state.crashed_buffers.drain(..) {
+        state.stopped_buffers.retain(|&b| b != bufnr);
+        state.buffers_to_be_detached.retain(|&b| b != bufnr);
+    }
+
+    // Without the augroup completion was stopped in all the buffers, which
+    // only `:Compleet start all` undoes.
+    if state.augroup_id.is_some() {
+        let try_buf_attach = lua.registry_value::<LuaFunction>(
+            state
+                .try_buf_attach
+                .as_ref()
+                .expect("`try_buf_attach` has already been created"),
+        )?;
+
+        // Scheduled for the same reason explained in `compleet_start`.
+        nvim.schedule(try_buf_attach)?;
+    }
+
+    let message =
+        Message::info(MessageId::Started).text("Restarted completion");
+    messages::send(lua, api, &state.settings.ui.messages, message)?;
+
+    Ok(())
+}
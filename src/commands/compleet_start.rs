@@ -1,9 +1,11 @@
 use mlua::prelude::{Lua, LuaFunction, LuaResult};
-use neovim::{api::LogLevel, Neovim};
+use neovim::{api::Api, Neovim};
 
+use crate::settings::ui::messages::MessageId;
+use crate::ui::messages::{self, Message};
 use crate::State;
 
-/// Executed by the `CompleetStart` user command.
+/// Executed by `:Compleet start`.
 pub fn compleet_start(
     lua: &Lua,
     state: &mut State,
@@ -21,25 +23,16 @@ fn attach_all_buffers(lua: &Lua, state: &mut State) -> LuaResult<()> {
     let nvim = Neovim::new(lua)?;
     let api = &nvim.api;
 
-    // If the augroup is already set we check if the autocmd for the `BufEnter`
-    // event exists. If it does, completion is already on.
-    if let Some(id) = state.augroup_id {
-        let is_bufenter_autocmd_set = {
-            let opts = lua.create_table_with_capacity(0, 2)?;
-            opts.set("group", id)?;
-            opts.set("event", "BufEnter")?;
-            api.get_autocmds(opts)?.raw_len() != 0
-        };
-        if is_bufenter_autocmd_set {
-            api.notify(
-                "[nvim-compleet] Completion is already on",
-                LogLevel::Error,
-            )?;
-            return Ok(());
-        }
+    if is_on_in_all_buffers(lua, api, state)? {
+        let message = Message::warn(MessageId::AlreadyOn)
+            .text("Completion is already on");
+        messages::send(lua, api, &state.settings.ui.messages, message)?;
+        return Ok(());
     }
 
     state.buffers_to_be_detached.clear();
+    state.stopped_buffers.clear();
+    state.crashed_buffers.clear();
 
     let try_buf_attach = lua.registry_value::<LuaFunction>(
         state
@@ -65,14 +58,33 @@ fn attach_all_buffers(lua: &Lua, state: &mut State) -> LuaResult<()> {
 
     state.augroup_id = Some(augroup_id);
 
-    api.notify(
-        "[nvim-compleet] Started completion in all buffers",
-        LogLevel::Info,
-    )?;
+    let message = Message::info(MessageId::Started)
+        .text("Started completion in all buffers");
+    messages::send(lua, api, &state.settings.ui.messages, message)?;
 
     Ok(())
 }
 
+/// Whether completion is on in all the buffers, i.e. whether new buffers are
+/// attached to when they're entered.
+pub fn is_on_in_all_buffers(
+    lua: &Lua,
+    api: &Api,
+    state: &State,
+) -> LuaResult<bool> {
+    // If the augroup is set we check if the autocmd for the `BufEnter` event
+    // exists. If it does, completion is on.
+    match state.augroup_id {
+        Some(id) => {
+            let opts = lua.create_table_with_capacity(0, 2)?;
+            opts.set("group", id)?;
+            opts.set("event", "BufEnter")?;
+            Ok(api.get_autocmds(opts)?.raw_len() != 0)
+        },
+        None => Ok(false),
+    }
+}
+
 fn attach_current_buffer(lua: &Lua, state: &mut State) -> LuaResult<()> {
     let nvim = Neovim::new(lua)?;
     let api = &nvim.api;
@@ -80,10 +92,16 @@ fn attach_current_buffer(lua: &Lua, state: &mut State) -> LuaResult<()> {
     let bufnr = api.get_current_buf()?;
 
     if state.attached_buffers.contains(&bufnr) {
-        api.notify(
-            "[nvim-compleet] Completion is already on in this buffer",
-            LogLevel::Error,
-        )?;
+        let message = Message::warn(MessageId::AlreadyOn)
+            .text("Completion is already on in this buffer");
+        messages::send(lua, api, &state.settings.ui.messages, message)?;
+        return Ok(());
+    }
+
+    if state.attachable_sources(lua, api, bufnr)?.is_empty() {
+        let message = Message::warn(MessageId::NoSources)
+            .text("No sources can attach to this buffer");
+        messages::send(lua, api, &state.settings.ui.messages, message)?;
         return Ok(());
     }
 
@@ -94,6 +112,9 @@ fn attach_current_buffer(lua: &Lua, state: &mut State) -> LuaResult<()> {
         state.buffers_to_be_detached.retain(|&b| b != bufnr);
     }
 
+    state.stopped_buffers.retain(|&b| b != bufnr);
+    state.crashed_buffers.retain(|&b| b != bufnr);
+
     // If there's currently no `Compleet` augroup we need to recreate it.
     if state.augroup_id.is_none() {
         let opts = lua.create_table_from([("clear", true)])?;
@@ -112,10 +133,9 @@ fn attach_current_buffer(lua: &Lua, state: &mut State) -> LuaResult<()> {
 
     // TODO: only display this once we've successfully attached to the
     // buffer.
-    api.notify(
-        &format!("[nvim-compleet] Started completion in buffer {bufnr}"),
-        LogLevel::Info,
-    )?;
+    let message = Message::info(MessageId::Started)
+        .text(format!("Started completion in buffer {bufnr}"));
+    messages::send(lua, api, &state.settings.ui.messages, message)?;
 
     Ok(())
 }
@@ -1,10 +1,13 @@
-use mlua::prelude::LuaResult;
-use neovim::api::{Api, LogLevel};
+use mlua::prelude::{Lua, LuaResult};
+use neovim::api::Api;
 
+use crate::settings::ui::messages::MessageId;
+use crate::ui::messages::{self, Message};
 use crate::State;
 
-/// Executed by the `CompleetStop` user command.
+/// Executed by `:Compleet stop`.
 pub fn compleet_stop(
+    lua: &Lua,
     api: &Api,
     state: &mut State,
     bang: bool,
@@ -14,18 +17,28 @@ pub fn compleet_stop(
     // need to return `true` the next time the `on_bytes` function is
     // called.
     match bang {
-        true => detach_all_buffers(api, state),
-        false => detach_current_buffer(api, state),
+        true => detach_all_buffers(lua, api, state),
+        false => detach_current_buffer(lua, api, state),
     }
 }
 
-fn detach_all_buffers(api: &Api, state: &mut State) -> LuaResult<()> {
+fn detach_all_buffers(
+    lua: &Lua,
+    api: &Api,
+    state: &mut State,
+) -> LuaResult<()> {
     if let Some(id) = state.augroup_id {
         // Delete the `Compleet` augroup containing all the autocmds.
         api.del_augroup_by_id(id)?;
 
         state.augroup_id = None;
 
+        // Deleting the augroup also deleted the buffer-local autocmds.
+        state.buffer_local_autocmds.clear();
+        state.buffers.clear();
+        state.stopped_buffers.clear();
+        state.crashed_buffers.clear();
+
         // Move all the buffer numbers from the `attached_buffers` vector to
         // `buffers_to_be_detached`.
         state
@@ -35,51 +48,42 @@ fn detach_all_buffers(api: &Api, state: &mut State) -> LuaResult<()> {
         // Cleanup the UI in case the user has somehow executed
         // `CompleetStop!` without exiting insert mode (for example via an
         // autocmd. Unlikely but possible).
-        state.ui.cleanup(api)?;
+        state.close_ui(lua, api)?;
 
-        api.notify(
-            "[nvim-compleet] Stopped completion in all buffers",
-            LogLevel::Info,
-        )?;
+        let message = Message::info(MessageId::Stopped)
+            .text("Stopped completion in all buffers");
+        messages::send(lua, api, &state.settings.ui.messages, message)?;
     } else {
-        api.notify(
-            "[nvim-compleet] Completion is already off",
-            LogLevel::Error,
-        )?;
+        let message = Message::warn(MessageId::AlreadyOff)
+            .text("Completion is already off");
+        messages::send(lua, api, &state.settings.ui.messages, message)?;
     }
 
     Ok(())
 }
 
-fn detach_current_buffer(api: &Api, state: &mut State) -> LuaResult<()> {
+fn detach_current_buffer(
+    lua: &Lua,
+    api: &Api,
+    state: &mut State,
+) -> LuaResult<()> {
     let bufnr = api.get_current_buf()?;
 
     if !state.attached_buffers.contains(&bufnr) {
-        api.notify(
-            "[nvim-compleet] Completion is already off in this buffer",
-            LogLevel::Error,
-        )?;
+        let message = Message::warn(MessageId::AlreadyOff)
+            .text("Completion is already off in this buffer");
+        messages::send(lua, api, &state.settings.ui.messages, message)?;
         return Ok(());
     }
 
-    state.attached_buffers.retain(|&b| b != bufnr);
-    state.buffers_to_be_detached.push(bufnr);
+    state.detach(api, bufnr)?;
+    state.stopped_buffers.push(bufnr);
 
-    state.ui.cleanup(api)?;
-
-    // Delete all the buffer-local autocmds we had set for this buffer.
-    for autocmd_id in state
-        .buffer_local_autocmds
-        .get(&bufnr)
-        .expect("If the buffer was attached it had some buffer-local autocmds")
-    {
-        api.del_autocmd(*autocmd_id)?;
-    }
+    state.close_ui(lua, api)?;
 
-    api.notify(
-        &format!("[nvim-compleet] Stopped completion for buffer {bufnr}"),
-        LogLevel::Info,
-    )?;
+    let message = Message::info(MessageId::Stopped)
+        .text(format!("Stopped completion for buffer {bufnr}"));
+    messages::send(lua, api, &state.settings.ui.messages, message)?;
 
     Ok(())
 }
@@ -0,0 +1,25 @@
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Neovim;
+
+use crate::State;
+
+/// Executed by `:Compleet toggle`. Like `:Compleet start` and `:Compleet
+/// stop`, the version with `bang` set affects all the buffers while the one
+/// without it only affects the current buffer.
+pub fn compleet_toggle(
+    lua: &Lua,
+    state: &mut State,
+    bang: bool,
+) -> LuaResult<()> {
+    let api = Neovim::new(lua)?.api;
+
+    let is_on = match bang {
+        true => super::is_on_in_all_buffers(lua, &api, state)?,
+        false => state.attached_buffers.contains(&api.get_current_buf()?),
+    };
+
+    match is_on {
+        true => super::compleet_stop(lua, &api, state, bang),
+        false => super::compleet_start(lua, state, bang),
+    }
+}
@@ -1,7 +1,20 @@
+mod compleet_cache;
+mod compleet_info;
+mod compleet_log;
+mod compleet_profile;
+mod compleet_restart;
 mod compleet_start;
 mod compleet_stop;
+mod compleet_toggle;
+mod registry;
 mod setup;
 
-use compleet_start::compleet_start;
+use compleet_cache::compleet_cache_clear;
+use compleet_info::compleet_info;
+use compleet_log::compleet_log;
+use compleet_profile::compleet_profile;
+use compleet_restart::compleet_restart;
+use compleet_start::{compleet_start, is_on_in_all_buffers};
 use compleet_stop::compleet_stop;
+use compleet_toggle::compleet_toggle;
 pub use setup::setup;
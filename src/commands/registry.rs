@@ -0,0 +1,285 @@
+use std::sync::{Arc, Mutex};
+
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Api;
+
+use crate::boundary;
+use crate::State;
+
+/// The subcommands of the `Compleet` user command.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Subcommand {
+    Cache,
+    Info,
+    Log,
+    Profile,
+    Restart,
+    Start,
+    Stop,
+    Toggle,
+}
+
+/// A parsed invocation of the `Compleet` user command.
+#[derive(Debug, PartialEq)]
+pub struct Command {
+    pub subcommand: Subcommand,
+
+    /// Whether the command affects all the buffers instead of only the
+    /// current one, set either by the `all` argument or by a bang `!`.
+    pub all: bool,
+}
+
+/// The names of the subcommands, in the order they're completed.
+const SUBCOMMANDS: &[(&str, Subcommand)] = &[
+    ("cache", Subcommand::Cache),
+    ("info", Subcommand::Info),
+    ("log", Subcommand::Log),
+    ("profile", Subcommand::Profile),
+    ("restart", Subcommand::Restart),
+    ("start", Subcommand::Start),
+    ("stop", Subcommand::Stop),
+    ("toggle", Subcommand::Toggle),
+];
+
+/// The standalone commands from before the `Compleet` command existed, kept
+/// as aliases of its subcommands.
+pub const ALIASES: &[(&str, Subcommand)] = &[
+    ("CompleetInfo", Subcommand::Info),
+    ("CompleetLog", Subcommand::Log),
+    ("CompleetProfile", Subcommand::Profile),
+    ("CompleetStart", Subcommand::Start),
+    ("CompleetStop", Subcommand::Stop),
+    ("CompleetToggle", Subcommand::Toggle),
+];
+
+impl Subcommand {
+    /// The arguments accepted by the subcommand.
+    fn arguments(&self) -> &'static [&'static str] {
+        match self {
+            Subcommand::Start | Subcommand::Stop | Subcommand::Toggle => {
+                &["all"]
+            },
+            Subcommand::Cache => &["clear"],
+            Subcommand::Info
+            | Subcommand::Log
+            | Subcommand::Profile
+            | Subcommand::Restart => &[],
+        }
+    }
+
+    /// Whether the subcommand can only be called with one of its arguments.
+    fn requires_argument(&self) -> bool { matches!(self, Subcommand::Cache) }
+
+    /// Whether the subcommand can be used with a bang `!`.
+    pub fn takes_bang(&self) -> bool { self.arguments().contains(&"all") }
+}
+
+/// Parses the arguments of the `Compleet` user command, returning the error
+/// message to display if they're not valid.
+pub fn parse(args: &str, bang: bool) -> Result<Command, String> {
+    let mut args = args.split_whitespace();
+
+    let name = match args.next() {
+        Some(name) => name,
+        None => return Err("Missing subcommand".into()),
+    };
+
+    let subcommand = match SUBCOMMANDS.iter().find(|(n, _)| *n == name) {
+        Some(&(_, subcommand)) => subcommand,
+        None => return Err(format!("Unknown subcommand `{name}`")),
+    };
+
+    let mut all = bang && subcommand.takes_bang();
+    let mut has_argument = false;
+
+    for arg in args {
+        match subcommand.arguments().contains(&arg) {
+            true => {
+                all |= arg == "all";
+                has_argument = true;
+            },
+            false => {
+                return Err(format!("Invalid argument `{arg}` for `{name}`"))
+            },
+        }
+    }
+
+    if subcommand.requires_argument() && !has_argument {
+        return Err(format!("Missing argument for `{name}`"));
+    }
+
+    Ok(Command { subcommand, all })
+}
+
+/// Returns the completions of the `arg_lead` being typed in the `cmd_line`
+/// with the cursor at the `cursor_pos` byte, either the names of the
+/// subcommands or the arguments of the one already typed.
+pub fn complete(
+    arg_lead: &str,
+    cmd_line: &str,
+    cursor_pos: usize,
+) -> Vec<String> {
+    let typed = cmd_line.get(..cursor_pos).unwrap_or(cmd_line);
+
+    // The words typed before the one being completed, without the command.
+    let words = typed
+        .strip_suffix(arg_lead)
+        .unwrap_or(typed)
+        .split_whitespace()
+        .skip(1)
+        .collect::<Vec<&str>>();
+
+    let candidates = match words.as_slice() {
+        [] => SUBCOMMANDS.iter().map(|(name, _)| *name).collect(),
+        [name, rest @ ..] => match SUBCOMMANDS.iter().find(|(n, _)| n == name)
+        {
+            Some((_, subcommand)) => subcommand
+                .arguments()
+                .iter()
+                .filter(|arg| !rest.contains(arg))
+                .copied()
+                .collect(),
+            None => Vec::new(),
+        },
+    };
+
+    candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(arg_lead))
+        .map(String::from)
+        .collect()
+}
+
+/// Executes a parsed command. The subcommands using the state are guarded
+/// like the autocmds and the mappings, while `log` and `profile` only open a
+/// file.
+pub fn execute(
+    lua: &Lua,
+    api: &Api,
+    state: &Arc<Mutex<State>>,
+    command: Command,
+) -> LuaResult<()> {
+    let all = command.all;
+    match command.subcommand {
+        Subcommand::Cache => {
+            boundary::guard(lua, state, "compleet_cache", |state| {
+                super::compleet_cache_clear(lua, api, state)
+            })
+        },
+        Subcommand::Info => super::compleet_info(lua, api, state),
+        Subcommand::Log => super::compleet_log(api),
+        Subcommand::Profile => super::compleet_profile(api),
+        Subcommand::Restart => {
+            boundary::guard(lua, state, "compleet_restart", |state| {
+                super::compleet_restart(lua, state)
+            })
+        },
+        Subcommand::Start => {
+            boundary::guard(lua, state, "compleet_start", |state| {
+                super::compleet_start(lua, state, all)
+            })
+        },
+        Subcommand::Stop => {
+            boundary::guard(lua, state, "compleet_stop", |state| {
+                super::compleet_stop(lua, api, state, all)
+            })
+        },
+        Subcommand::Toggle => {
+            boundary::guard(lua, state, "compleet_toggle", |state| {
+                super::compleet_toggle(lua, state, all)
+            })
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{complete, parse, Command, Subcommand};
+
+    #[test]
+    fn parse_subcommands() {
+        assert_eq!(
+            Ok(Command {
+                subcommand: Subcommand::Start,
+                all: false
+            }),
+            parse("start", false)
+        );
+        assert_eq!(
+            Ok(Command {
+                subcommand: Subcommand::Stop,
+                all: true
+            }),
+            parse(" stop  all ", false)
+        );
+        assert_eq!(
+            Ok(Command {
+                subcommand: Subcommand::Toggle,
+                all: true
+            }),
+            parse("toggle", true)
+        );
+        assert_eq!(
+            Ok(Command {
+                subcommand: Subcommand::Info,
+                all: false
+            }),
+            parse("info", true)
+        );
+        assert_eq!(
+            Ok(Command {
+                subcommand: Subcommand::Cache,
+                all: false
+            }),
+            parse("cache clear", true)
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Err("Missing subcommand".into()), parse("", false));
+        assert_eq!(
+            Err("Unknown subcommand `foo`".into()),
+            parse("foo", false)
+        );
+        assert_eq!(
+            Err("Invalid argument `all` for `info`".into()),
+            parse("info all", false)
+        );
+        assert_eq!(
+            Err("Missing argument for `cache`".into()),
+            parse("cache", false)
+        );
+    }
+
+    fn completions(arg_lead: &str, cmd_line: &str) -> Vec<String> {
+        complete(arg_lead, cmd_line, cmd_line.len())
+    }
+
+    #[test]
+    fn complete_subcommands() {
+        assert_eq!(
+            vec![
+                "cache", "info", "log", "profile", "restart", "start", "stop",
+                "toggle"
+            ],
+            completions("", "Compleet ")
+        );
+        assert_eq!(vec!["start", "stop"], completions("st", "Compleet st"));
+        // The cursor is after `st` in the middle of the line.
+        assert_eq!(
+            vec!["start", "stop"],
+            complete("st", "Compleet st all", 11)
+        );
+    }
+
+    #[test]
+    fn complete_arguments() {
+        assert_eq!(vec!["all"], completions("", "Compleet stop "));
+        assert_eq!(vec!["all"], completions("a", "Compleet! start a"));
+        assert!(completions("", "Compleet stop all ").is_empty());
+        assert_eq!(vec!["clear"], completions("", "Compleet cache "));
+        assert!(completions("", "Compleet info ").is_empty());
+        assert!(completions("", "Compleet foo ").is_empty());
+    }
+}
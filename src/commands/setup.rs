@@ -1,11 +1,10 @@
 use std::sync::{Arc, Mutex};
 
-use mlua::{
-    prelude::{Lua, LuaResult},
-    Table,
-};
+use mlua::prelude::{Lua, LuaResult, LuaTable};
 use neovim::{Api, Neovim};
 
+use super::registry::{self, Command, ALIASES};
+use crate::ui::messages::{self, Message};
 use crate::State;
 
 pub fn setup(
@@ -13,23 +12,55 @@ pub fn setup(
     api: &Api,
     state: &Arc<Mutex<State>>,
 ) -> LuaResult<()> {
+    // `:Compleet {subcommand} [args]`.
     let _state = state.clone();
-    let start = lua.create_function(move |lua, opts: Table| {
+    let compleet = lua.create_function(move |lua, opts: LuaTable| {
+        let api = Neovim::new(lua)?.api;
+        let args = opts.get::<_, String>("args")?;
         let bang = opts.get::<_, bool>("bang")?;
-        super::compleet_start(lua, &mut _state.lock().unwrap(), bang)
-    })?;
 
-    let _state = state.clone();
-    let stop = lua.create_function(move |lua, opts: Table| {
-        let bang = opts.get::<_, bool>("bang")?;
-        let api = Neovim::new(lua)?.api;
-        super::compleet_stop(&api, &mut _state.lock().unwrap(), bang)
+        match registry::parse(&args, bang) {
+            Ok(command) => registry::execute(lua, &api, &_state, command),
+            Err(error) => {
+                let settings = &_state.lock().unwrap().settings.ui.messages;
+                messages::send(
+                    lua,
+                    &api,
+                    settings,
+                    Message::error().text(error),
+                )
+            },
+        }
     })?;
 
-    let opts = lua.create_table_from([("bang", true)])?;
+    let complete = lua.create_function(
+        |_, (arg_lead, cmd_line, cursor_pos): (String, String, usize)| {
+            Ok(registry::complete(&arg_lead, &cmd_line, cursor_pos))
+        },
+    )?;
+
+    let opts = lua.create_table_with_capacity(0, 3)?;
+    opts.set("bang", true)?;
+    opts.set("nargs", "+")?;
+    opts.set("complete", complete)?;
+    api.create_user_command("Compleet", compleet, opts)?;
+
+    // The old standalone commands, e.g. `:CompleetStart!` for
+    // `:Compleet start all`.
+    for &(name, subcommand) in ALIASES {
+        let _state = state.clone();
+        let alias = lua.create_function(move |lua, opts: LuaTable| {
+            let api = Neovim::new(lua)?.api;
+            let all =
+                subcommand.takes_bang() && opts.get::<_, bool>("bang")?;
+            let command = Command { subcommand, all };
+            registry::execute(lua, &api, &_state, command)
+        })?;
 
-    api.add_user_command("CompleetStart", start, opts.clone())?;
-    api.add_user_command("CompleetStop", stop, opts)?;
+        let opts =
+            lua.create_table_from([("bang", subcommand.takes_bang())])?;
+        api.create_user_command(name, alias, opts)?;
+    }
 
     Ok(())
 }
@@ -0,0 +1,102 @@
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Api;
+
+use super::{dedup, filter, ranker, CompletionItem, Cursor, History};
+use crate::settings::completion::CompletionSettings;
+use crate::state::{Sources, State};
+
+/// Collects the completion items of all the `sources` at the current cursor
+/// position, ranked according to the `completion.sorting` settings.
+pub fn complete(
+    lua: &Lua,
+    api: &Api,
+    sources: &Sources,
+    cursor: &Cursor,
+    settings: &CompletionSettings,
+    history: &History,
+) -> LuaResult<Vec<CompletionItem>> {
+    let mut completions = Vec::new();
+    for source in sources.iter() {
+        completions.append(&mut source.complete(lua, api, cursor)?);
+    }
+
+    process(lua, api, completions, sources, cursor, settings, history)
+}
+
+/// Filters the completions collected from the sources, merges the ones with
+/// the same text if `completion.dedup` is set and ranks them. Shared by the
+/// completions computed while typing and the ones computed on demand.
+pub(super) fn process(
+    lua: &Lua,
+    api: &Api,
+    mut completions: Vec<CompletionItem>,
+    sources: &Sources,
+    cursor: &Cursor,
+    settings: &CompletionSettings,
+    history: &History,
+) -> LuaResult<Vec<CompletionItem>> {
+    filter::filter(lua, api, &mut completions, cursor)?;
+
+    if settings.dedup {
+        completions = dedup::dedup(completions, |source| {
+            sources
+                .iter()
+                .any(|s| s.name() == source && s.has_callbacks())
+        });
+    }
+
+    ranker::rank(
+        lua,
+        api,
+        &mut completions,
+        cursor,
+        &settings.sorting,
+        history,
+    )?;
+
+    Ok(completions)
+}
+
+/// Updates the cursor and computes the completions available at its current
+/// position in the current buffer, which has to be attached.
+pub fn complete_at_cursor(
+    lua: &Lua,
+    api: &Api,
+    state: &mut State,
+) -> LuaResult<()> {
+    let bufnr = api.get_current_buf()?;
+
+    let cursor = &mut state.buffers.current_mut().cursor;
+
+    let (row, bytes) = api.win_get_cursor(0)?;
+    cursor.row = row - 1;
+    cursor.bytes = bytes;
+    cursor.line = api.get_current_line()?;
+    state.keyword_pattern.update(lua, cursor)?;
+
+    let completions = complete(
+        lua,
+        api,
+        state
+            .sources
+            .get(&bufnr)
+            .expect("The buffer is attached so it has sources"),
+        cursor,
+        &state.settings.completion,
+        &state.history,
+    )?;
+    state.buffers.current_mut().set_completions(completions);
+
+    Ok(())
+}
+
+/// Whether the completions should be computed as the user types. They always
+/// are while the completion menu is visible, otherwise only if they're going
+/// to be displayed either in the menu or in the hint.
+pub fn is_autocompleting(state: &State) -> bool {
+    let settings = &state.settings;
+
+    state.phase().is_menu_visible()
+        || (settings.completion.autoshow
+            && (settings.ui.menu.autoshow || settings.ui.hint.enable))
+}
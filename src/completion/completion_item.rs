@@ -1,9 +1,13 @@
 use std::ops::Range;
 
-#[derive(Debug, Clone)]
+use mlua::prelude::{Lua, LuaResult, LuaTable};
+
+use super::Cursor;
+
+#[derive(Debug, Default, Clone)]
 pub struct CompletionItem {
-    /// The text to display in the details window as a vector of strings.
-    pub details: Option<Vec<String>>,
+    /// The text to display in the details window.
+    pub details: Option<Details>,
 
     /// The formatted completion item as shown inside the completion menu.
     pub format: String,
@@ -12,10 +16,39 @@ pub struct CompletionItem {
     /// is highlighted with the `hl_group` highlight group.
     pub hl_ranges: Vec<(Range<usize>, &'static str)>,
 
+    /// An id the source can use to find what it knows about the completion
+    /// when it's resolved or confirmed, e.g. the index of the item it was
+    /// created from in a cache. Unlike the `format` it's never changed by
+    /// the menu.
+    pub id: u64,
+
+    /// The kind of the completion (e.g. `Function`, `Variable`, etc.), using
+    /// the names of the `CompletionItemKind`s defined by the LSP spec. It's
+    /// shown in a column of the completion menu after the formatted item.
+    pub kind: Option<&'static str>,
+
     /// The number of bytes before the current cursor position that are
     /// matched by the completion item.
     pub matched_bytes: u32,
 
+    /// The number of bytes after the current cursor position that are
+    /// replaced by the completion item when it's confirmed, whatever the
+    /// `completion.behavior`, e.g. the rest of a misspelled word.
+    pub matched_bytes_post: u32,
+
+    /// Whether the source asked for the completion to be selected when the
+    /// menu is opened, used by the `lsp` preselect behavior.
+    pub preselect: bool,
+
+    /// The score given to the completion by the fuzzy matcher. Higher is
+    /// better.
+    pub score: i32,
+
+    /// The body of the snippet to expand if the completion is selected,
+    /// written using the LSP snippet syntax. If this is set `text` should
+    /// contain the text of the expanded snippet.
+    pub snippet: Option<String>,
+
     /// The name of the source this completion comes from.
     pub source: &'static str,
 
@@ -23,3 +56,40 @@ pub struct CompletionItem {
     /// selected.
     pub text: String,
 }
+
+/// The text shown in the details window when a completion is selected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Details {
+    /// The lines of text.
+    pub lines: Vec<String>,
+
+    /// Whether the lines are written in markdown, in which case they're
+    /// rendered with highlighted code blocks if the `ui.details.markdown`
+    /// option is set.
+    pub is_markdown: bool,
+}
+
+impl CompletionItem {
+    /// Returns the part of the completion's text that comes after the
+    /// matched bytes before the cursor, or `None` if the completion's text
+    /// doesn't start with them (which can happen with fuzzy matches).
+    pub fn text_after_match(&self, cursor: &Cursor) -> Option<&str> {
+        let line_pre = &cursor.line[..cursor.bytes as usize];
+        let matched =
+            &line_pre[line_pre.len() - self.matched_bytes as usize..];
+
+        self.text.strip_prefix(matched)
+    }
+
+    /// Returns a table with the `label`, `text`, `kind`, `source` and `score`
+    /// of the completion, passed to the Lua functions of the config.
+    pub fn to_lua<'lua>(&self, lua: &'lua Lua) -> LuaResult<LuaTable<'lua>> {
+        let table = lua.create_table_with_capacity(0, 5)?;
+        table.set("label", self.format.trim())?;
+        table.set("text", self.text.as_str())?;
+        table.set("kind", self.kind)?;
+        table.set("source", self.source)?;
+        table.set("score", self.score)?;
+        Ok(table)
+    }
+}
@@ -1,21 +1,105 @@
 use std::fmt::Debug;
 
-use mlua::prelude::LuaResult;
+use mlua::prelude::{Lua, LuaResult};
 use neovim::Api;
 
 // use serde::Deserialize;
-use super::{CompletionItem, Cursor};
+use super::{CompletionItem, Cursor, Job};
+use crate::health::Report;
 
 pub trait CompletionSource: Debug /* + Default + Deserialize<'_> */ {
-    /// Whether to attach the source to a buffer.
-    fn attach(&self, api: &Api, bufnr: u32) -> LuaResult<bool>;
+    /// The name of the source, which should match the `source` field of the
+    /// completion items it returns.
+    fn name(&self) -> &'static str;
 
-    /// The function used to get completion results. Takes in an `api` field
-    /// (providing the functionality of `vim.api`) and the current cursor
-    /// position.
+    /// Whether to attach the source to a buffer. Can be called multiple
+    /// times for the same buffer.
+    fn attach(&self, lua: &Lua, api: &Api, bufnr: u32) -> LuaResult<bool>;
+
+    /// The function used to get completion results. Takes in the Lua state
+    /// (needed by sources that have to call into Lua modules other than
+    /// `vim.api`), an `api` field (providing the functionality of `vim.api`)
+    /// and the current cursor position.
     fn complete(
         &self,
+        lua: &Lua,
         api: &Api,
         cursor: &Cursor,
     ) -> LuaResult<Vec<CompletionItem>>;
+
+    /// Whether the source is still computing the completions of the last
+    /// call to `complete` asynchronously, e.g. waiting for the response of a
+    /// server. Used to show the `ui.menu.loading_indicator`.
+    fn is_loading(&self) -> bool { false }
+
+    /// Whether the source does work proportional to the size of the buffer
+    /// (e.g. indexing all its words), in which case it's not attached to the
+    /// buffers bigger than the `completion.max_buffer_size` option.
+    fn is_expensive(&self) -> bool { false }
+
+    /// The characters (or sequences of characters) that make the completions
+    /// get computed right away when typed, e.g. `.` for a field access. A
+    /// source should return completions after one of its triggers even if
+    /// there's no keyword before the cursor.
+    fn trigger_characters(
+        &self,
+        _lua: &Lua,
+        _api: &Api,
+    ) -> LuaResult<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    /// Called when one of the source's completions is selected, letting the
+    /// source fetch details that are too expensive to compute for every
+    /// completion (e.g. with an LSP `completionItem/resolve` request). Sources
+    /// resolving their completions asynchronously should store the result and
+    /// call `completion::schedule_refresh` once it's available, so that the
+    /// completions are recomputed with the new details.
+    fn resolve(
+        &self,
+        _lua: &Lua,
+        _api: &Api,
+        _completion: &CompletionItem,
+    ) -> LuaResult<()> {
+        Ok(())
+    }
+
+    /// Called once one of the source's completions has been inserted in the
+    /// buffer, with the cursor right after it. It runs in Neovim's event
+    /// loop, so it's free to change the buffer (e.g. to expand a snippet
+    /// trigger through a snippet engine).
+    fn confirm(
+        &self,
+        _lua: &Lua,
+        _api: &Api,
+        _completion: &CompletionItem,
+    ) -> LuaResult<()> {
+        Ok(())
+    }
+
+    /// Whether the source implements `resolve` or `confirm`. When
+    /// `completion.dedup` merges its completions with the ones of other
+    /// sources, its own are kept so that those still get called.
+    fn has_callbacks(&self) -> bool { false }
+
+    /// Checks the prerequisites of the source, e.g. the executables it runs,
+    /// for `:checkhealth compleet`. Sources without prerequisites return an
+    /// empty list.
+    fn check_health(&self, _lua: &Lua, _api: &Api) -> LuaResult<Vec<Report>> {
+        Ok(Vec::new())
+    }
+
+    /// Returns a job computing the completions on a background thread, or
+    /// `None` if the source should be queried with `complete` on the main
+    /// thread instead. Sources doing expensive work that doesn't need to call
+    /// into Neovim should prefer this, since jobs whose results are outdated
+    /// are cancelled as soon as a new character is typed.
+    fn complete_in_background(
+        &self,
+        _lua: &Lua,
+        _api: &Api,
+        _cursor: &Cursor,
+    ) -> LuaResult<Option<Job>> {
+        Ok(None)
+    }
 }
@@ -1,8 +1,13 @@
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Cursor {
     /// The number of bytes between the start of the line and the cursor.
     pub bytes: u32,
 
+    /// The byte offset of the start of the keyword before the cursor, as
+    /// matched by the `completion.keyword_pattern` option, or `None` if the
+    /// option isn't set.
+    pub keyword_start: Option<u32>,
+
     /// The text in the row the cursor is currently on.
     pub line: String,
 
@@ -10,23 +15,39 @@ pub struct Cursor {
     pub row: u32,
 }
 
-impl Cursor {
-    pub fn new() -> Self {
-        Cursor {
-            bytes: 0,
-            line: "".to_string(),
-            row: 0,
-        }
-    }
-}
-
 impl Cursor {
     /// Whether the cursor is at the end of the line.
-    pub fn is_at_eol(&self) -> bool { self.bytes as usize == self.line.len() }
+    pub fn _is_at_eol(&self) -> bool { self.bytes as usize == self.line.len() }
 
     /// Whether the cursor is at the start of the line.
     pub fn _is_at_sol(&self) -> bool { self.bytes == 0 }
 
+    /// The number of bytes between the cursor and the first non-keyword
+    /// character before it, where keyword characters are alphanumerics and
+    /// underscores unless the `completion.keyword_pattern` option is set.
+    fn keyword_bytes_pre(&self) -> usize {
+        if let Some(start) = self.keyword_start {
+            return (self.bytes - start) as usize;
+        }
+
+        self.line[..self.bytes as usize]
+            .chars()
+            .rev()
+            .take_while(|&c| is_keyword_char(c))
+            .map(char::len_utf8)
+            .sum()
+    }
+
+    /// The number of bytes between the cursor and the first non-keyword
+    /// character after it.
+    fn keyword_bytes_post(&self) -> usize {
+        self.line[self.bytes as usize..]
+            .chars()
+            .take_while(|&c| is_keyword_char(c))
+            .map(char::len_utf8)
+            .sum()
+    }
+
     /// The number of bytes between the cursor and the first whitespace
     /// character before it.
     fn non_whitespace_bytes_pre(&self) -> usize {
@@ -53,6 +74,26 @@ impl Cursor {
             ..self.bytes as usize + self._non_whitespace_bytes_post()]
     }
 
+    /// The part of the keyword the cursor is on that's before the cursor.
+    /// Unlike `Cursor::word_pre`, this stops at punctuation, so for `foo.ba|`
+    /// it returns `ba` instead of `foo.ba`.
+    pub fn keyword_pre(&self) -> &'_ str {
+        &self.line[self.bytes as usize - self.keyword_bytes_pre()
+            ..self.bytes as usize]
+    }
+
+    /// The byte offset of the start of the keyword before the cursor.
+    pub fn keyword_start_col(&self) -> u32 {
+        self.bytes - self.keyword_bytes_pre() as u32
+    }
+
+    /// The part of the keyword the cursor is on that's after the cursor. For
+    /// `foo.b|ar()` it returns `ar`.
+    pub fn keyword_post(&self) -> &'_ str {
+        &self.line[self.bytes as usize
+            ..self.bytes as usize + self.keyword_bytes_post()]
+    }
+
     /// The part of the word the cursor is on that's before the cursor.
     pub fn word_pre(&self) -> &'_ str {
         &self.line[self.bytes as usize - self.non_whitespace_bytes_pre()
@@ -66,6 +107,9 @@ impl Cursor {
     }
 }
 
+/// Whether `c` can be part of a keyword.
+pub fn is_keyword_char(c: char) -> bool { c.is_alphanumeric() || c == '_' }
+
 fn _get_matched_bytes(line: &str, bytes_before_cursor: usize) -> usize {
     line[..bytes_before_cursor]
         .bytes()
@@ -76,7 +120,7 @@ fn _get_matched_bytes(line: &str, bytes_before_cursor: usize) -> usize {
 
 #[cfg(test)]
 mod tests {
-    use super::_get_matched_bytes;
+    use super::{_get_matched_bytes, Cursor};
 
     // NOTE: the `|` in the following comments indicates the cursor position.
 
@@ -131,4 +175,64 @@ mod tests {
     fn cursor_inside_word_multibyte_chars() {
         assert_eq!("fö".len(), _get_matched_bytes("föö", 3))
     }
+
+    #[test]
+    // `foo.ba|`
+    fn keyword_after_dot() {
+        let cursor = Cursor {
+            bytes: 6,
+            keyword_start: None,
+            line: "foo.ba".into(),
+            row: 0,
+        };
+        assert_eq!("ba", cursor.keyword_pre())
+    }
+
+    #[test]
+    // `foo |`
+    fn keyword_after_space() {
+        let cursor = Cursor {
+            bytes: 4,
+            keyword_start: None,
+            line: "foo ".into(),
+            row: 0,
+        };
+        assert_eq!("", cursor.keyword_pre())
+    }
+
+    #[test]
+    // `(föö_b|ar` (every `ö` is 2 bytes long)
+    fn keyword_multibyte_chars() {
+        let cursor = Cursor {
+            bytes: "(föö_b".len() as u32,
+            keyword_start: None,
+            line: "(föö_bar".into(),
+            row: 0,
+        };
+        assert_eq!("föö_b", cursor.keyword_pre())
+    }
+
+    #[test]
+    // `foo.b|ör()` (every `ö` is 2 bytes long)
+    fn keyword_after_cursor() {
+        let cursor = Cursor {
+            bytes: "foo.b".len() as u32,
+            keyword_start: None,
+            line: "foo.bör()".into(),
+            row: 0,
+        };
+        assert_eq!("ör", cursor.keyword_post())
+    }
+
+    #[test]
+    // `$foo-ba|` with a keyword pattern matching `foo-ba`
+    fn keyword_from_pattern() {
+        let cursor = Cursor {
+            bytes: 7,
+            keyword_start: Some(1),
+            line: "$foo-ba".into(),
+            row: 0,
+        };
+        assert_eq!("foo-ba", cursor.keyword_pre())
+    }
 }
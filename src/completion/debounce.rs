@@ -0,0 +1,103 @@
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+use mlua::prelude::{Lua, LuaFunction, LuaResult, LuaValue};
+use neovim::Neovim;
+
+/// The key of the Lua registry where the timer used to debounce the
+/// completions is stored.
+const DEBOUNCE_TIMER: &str = "compleet_debounce_timer";
+
+/// The key of the Lua registry where the timer computing the completions at
+/// the end of a throttling window is stored.
+const THROTTLE_TIMER: &str = "compleet_throttle_timer";
+
+thread_local! {
+    /// When the current throttling window ends, or `None` if the completions
+    /// haven't been computed yet.
+    static THROTTLED_UNTIL: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+/// Schedules a refresh of the completions once `ms` milliseconds have passed
+/// without this function being called again, so that no completions are
+/// computed while the user is typing fast.
+pub fn debounce(lua: &Lua, ms: u32) -> LuaResult<()> {
+    let refresh =
+        lua.create_function(|lua, ()| super::schedule_refresh(lua))?;
+    debounce_with(lua, DEBOUNCE_TIMER, ms, refresh)
+}
+
+/// Calls `callback` once `ms` milliseconds have passed without this function
+/// being called again with the same `timer`, which is the key of the Lua
+/// registry where the timer is stored. The callback is called in a fast event
+/// context.
+pub fn debounce_with(
+    lua: &Lua,
+    timer: &'static str,
+    ms: u32,
+    callback: LuaFunction,
+) -> LuaResult<()> {
+    let uv = Neovim::new(lua)?.uv()?;
+
+    let key = timer;
+    let timer = match lua.named_registry_value::<_, LuaValue>(key)? {
+        LuaValue::UserData(timer) => timer,
+        _ => {
+            let timer = uv.new_timer()?;
+            lua.set_named_registry_value(key, timer.clone())?;
+            timer
+        },
+    };
+
+    // Starting a timer that's already running resets its timeout.
+    uv.timer_start(timer, ms.into(), 0, callback)
+}
+
+/// Whether the completions can be computed right away, which is at most once
+/// every `ms` milliseconds. If they can't, a refresh is scheduled for the end
+/// of the current window, so that the last keystroke is always completed.
+pub fn throttle(lua: &Lua, ms: u32) -> LuaResult<bool> {
+    let now = Instant::now();
+
+    match remaining(THROTTLED_UNTIL.get(), now) {
+        Some(remaining) => {
+            // Rounded up, so that the refresh isn't throttled again.
+            let ms = remaining.as_micros().div_ceil(1000) as u32;
+            let refresh =
+                lua.create_function(|lua, ()| super::schedule_refresh(lua))?;
+            debounce_with(lua, THROTTLE_TIMER, ms, refresh)?;
+            Ok(false)
+        },
+        None => {
+            let window = Duration::from_millis(ms.into());
+            THROTTLED_UNTIL.set(Some(now + window));
+            Ok(true)
+        },
+    }
+}
+
+/// How long is left of the throttling window ending at `until`, or `None` if
+/// it's over.
+fn remaining(until: Option<Instant>, now: Instant) -> Option<Duration> {
+    until
+        .and_then(|until| until.checked_duration_since(now))
+        .filter(|remaining| !remaining.is_zero())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::remaining;
+
+    #[test]
+    fn throttling_window() {
+        let now = Instant::now();
+        let ms = Duration::from_millis;
+
+        assert_eq!(None, remaining(None, now));
+        assert_eq!(Some(ms(30)), remaining(Some(now + ms(30)), now));
+        assert_eq!(None, remaining(Some(now), now));
+        assert_eq!(None, remaining(Some(now), now + ms(10)));
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use super::CompletionItem;
+
+/// Merges the completions with the same text into a single one, keeping the
+/// position of the first one and the highest score. The merged completion is
+/// the one whose source `has_callbacks`, since its `resolve` and `confirm`
+/// would be lost otherwise, or else the one with the highest score. The
+/// metadata it's missing (details, kind and snippet) is taken from the
+/// others. Used when the `completion.dedup` option is set.
+pub fn dedup(
+    completions: Vec<CompletionItem>,
+    has_callbacks: impl Fn(&str) -> bool,
+) -> Vec<CompletionItem> {
+    let mut deduped = Vec::<CompletionItem>::with_capacity(completions.len());
+
+    // A hashmap where the keys are the texts of the completions and the
+    // values are their indexes in `deduped`.
+    let mut indexes = HashMap::<String, usize>::new();
+
+    for item in completions {
+        let kept = match indexes.get(&item.text) {
+            Some(&index) => &mut deduped[index],
+            None => {
+                indexes.insert(item.text.clone(), deduped.len());
+                deduped.push(item);
+                continue;
+            },
+        };
+
+        let rank = |c: &CompletionItem| {
+            (has_callbacks(c.source), c.score, richness(c))
+        };
+
+        let (mut best, other) = match rank(&item) > rank(kept) {
+            true => (item, kept.clone()),
+            false => (kept.clone(), item),
+        };
+
+        best.score = best.score.max(other.score);
+
+        if best.details.is_none() {
+            best.details = other.details;
+        }
+        if best.kind.is_none() || best.kind == Some("Text") {
+            best.kind = other.kind.or(best.kind);
+        }
+        if best.snippet.is_none() {
+            best.snippet = other.snippet;
+        }
+
+        *kept = best;
+    }
+
+    deduped
+}
+
+/// How much metadata a completion has, used to break the ties between
+/// completions with the same score.
+fn richness(item: &CompletionItem) -> u8 {
+    item.details.is_some() as u8
+        + matches!(item.kind, Some(kind) if kind != "Text") as u8
+        + item.snippet.is_some() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::completion::{CompletionItem, Details};
+
+    /// Only the Lsp source has callbacks.
+    fn dedup(completions: Vec<CompletionItem>) -> Vec<CompletionItem> {
+        super::dedup(completions, |source| source == "Lsp")
+    }
+
+    fn item(
+        text: &str,
+        score: i32,
+        kind: Option<&'static str>,
+        source: &'static str,
+    ) -> CompletionItem {
+        CompletionItem {
+            format: format!(" {text}"),
+            kind,
+            score,
+            source,
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn keeps_the_first_position() {
+        let deduped = dedup(vec![
+            item("foo", 1, None, "Buffer"),
+            item("bar", 1, None, "Buffer"),
+            item("foo", 1, None, "Dictionary"),
+        ]);
+        assert_eq!(
+            vec![("foo", "Buffer"), ("bar", "Buffer")],
+            deduped
+                .iter()
+                .map(|c| (c.text.as_str(), c.source))
+                .collect::<Vec<(&str, &str)>>()
+        );
+    }
+
+    #[test]
+    fn keeps_the_best_score() {
+        let deduped = dedup(vec![
+            item("foo", 1, Some("Text"), "Buffer"),
+            item("foo", 5, None, "Treesitter"),
+        ]);
+        assert_eq!(1, deduped.len());
+        assert_eq!(5, deduped[0].score);
+        assert_eq!("Treesitter", deduped[0].source);
+        assert_eq!(Some("Text"), deduped[0].kind);
+    }
+
+    #[test]
+    fn merges_the_metadata() {
+        let details = Details {
+            lines: vec!["fn foo()".into()],
+            is_markdown: false,
+        };
+        let lsp = CompletionItem {
+            details: Some(details.clone()),
+            ..item("foo", 3, Some("Function"), "Lsp")
+        };
+        let deduped = dedup(vec![item("foo", 3, Some("Text"), "Buffer"), lsp]);
+        assert_eq!(1, deduped.len());
+        assert_eq!("Lsp", deduped[0].source);
+        assert_eq!(Some("Function"), deduped[0].kind);
+        assert_eq!(Some(details.clone()), deduped[0].details);
+
+        let deduped = dedup(vec![
+            item("foo", 4, Some("Text"), "Buffer"),
+            CompletionItem {
+                details: Some(details.clone()),
+                ..item("foo", 3, Some("Function"), "Treesitter")
+            },
+        ]);
+        assert_eq!("Buffer", deduped[0].source);
+        assert_eq!(Some("Function"), deduped[0].kind);
+        assert_eq!(Some(details), deduped[0].details);
+    }
+
+    #[test]
+    fn keeps_the_completion_with_callbacks() {
+        let deduped = dedup(vec![
+            item("foo", 4, Some("Text"), "Buffer"),
+            item("foo", 3, Some("Function"), "Lsp"),
+        ]);
+        assert_eq!(1, deduped.len());
+        assert_eq!("Lsp", deduped[0].source);
+        assert_eq!(4, deduped[0].score);
+        assert_eq!(Some("Function"), deduped[0].kind);
+    }
+}
@@ -0,0 +1,94 @@
+use mlua::prelude::{Lua, LuaFunction, LuaResult, LuaValue};
+use neovim::Api;
+
+use super::{CompletionItem, Cursor};
+use crate::settings::completion::FILTER;
+
+/// The key of the Lua registry where the function running the filter over a
+/// batch of completions is stored.
+const FILTER_BATCH: &str = "compleet_filter_batch";
+
+/// Calls the filter with every completion of the list, returning a list of
+/// booleans telling which ones to keep.
+const FILTER_BATCH_CHUNK: &str = r#"
+local filter, items, ctx = ...
+local keep = {}
+for i, item in ipairs(items) do
+  keep[i] = filter(item, ctx) and true or false
+end
+return keep
+"#;
+
+/// Removes the completions rejected by the `completion.filter` function, if
+/// one is set. The filter is run over all the completions by a Lua chunk, so
+/// that there's a single call into Lua instead of one per completion.
+pub fn filter(
+    lua: &Lua,
+    api: &Api,
+    completions: &mut Vec<CompletionItem>,
+    cursor: &Cursor,
+) -> LuaResult<()> {
+    let filter = match lua.named_registry_value::<_, LuaValue>(FILTER)? {
+        LuaValue::Function(filter) => filter,
+        _ => return Ok(()),
+    };
+
+    if completions.is_empty() {
+        return Ok(());
+    }
+
+    let batch = match lua.named_registry_value::<_, LuaValue>(FILTER_BATCH)? {
+        LuaValue::Function(batch) => batch,
+        _ => {
+            let batch: LuaFunction =
+                lua.load(FILTER_BATCH_CHUNK).into_function()?;
+            lua.set_named_registry_value(FILTER_BATCH, batch.clone())?;
+            batch
+        },
+    };
+
+    let items = lua.create_sequence_from(
+        completions
+            .iter()
+            .map(|completion| completion.to_lua(lua))
+            .collect::<LuaResult<Vec<_>>>()?,
+    )?;
+
+    let ctx = lua.create_table_with_capacity(0, 5)?;
+    ctx.set("bufnr", api.get_current_buf()?)?;
+    ctx.set("row", cursor.row)?;
+    ctx.set("col", cursor.bytes)?;
+    ctx.set("line", cursor.line.as_str())?;
+    ctx.set("keyword", cursor.keyword_pre())?;
+
+    let keep = batch.call::<_, Vec<bool>>((filter, items, ctx))?;
+    retain(completions, &keep);
+
+    Ok(())
+}
+
+/// Keeps the items whose matching entry in `keep` is `true`. Items without a
+/// matching entry are kept.
+fn retain<T>(items: &mut Vec<T>, keep: &[bool]) {
+    let mut keep = keep.iter();
+    items.retain(|_| keep.next().copied().unwrap_or(true));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::retain;
+
+    #[test]
+    fn keeps_the_accepted_items() {
+        let mut items = vec!["foo", "bar", "baz", "qux"];
+        retain(&mut items, &[true, false, false, true]);
+        assert_eq!(items, vec!["foo", "qux"]);
+    }
+
+    #[test]
+    fn keeps_the_items_without_an_answer() {
+        let mut items = vec!["foo", "bar", "baz"];
+        retain(&mut items, &[false]);
+        assert_eq!(items, vec!["bar", "baz"]);
+    }
+}
@@ -0,0 +1,492 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The maximum number of entries kept in the history file. The least recently
+/// used ones are dropped first.
+const MAX_ENTRIES: usize = 5000;
+
+/// How many entries over `MAX_ENTRIES` are kept in memory before pruning
+/// them, so that they're not sorted on every insertion.
+const PRUNE_SLACK: usize = MAX_ENTRIES / 10;
+
+/// The `(max age in days, weight)` tuples used to weigh the number of times a
+/// completion was used by how recently that happened.
+const RECENCY_WEIGHTS: [(u64, u32); 4] =
+    [(4, 100), (14, 70), (31, 50), (90, 30)];
+
+/// The weight of the completions last used more than 90 days ago.
+const OLD_WEIGHT: u32 = 10;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// The tags of the lines written by `History::to_session`.
+const RECENT: &str = "recent";
+const ENTRY: &str = "entry";
+
+/// Remembers which completions were inserted and when, both in the current
+/// session (used by the `recently_used` comparator) and across sessions (used
+/// by the `frecency` comparator).
+#[derive(Debug, Default)]
+pub struct History {
+    /// Incremented every time a completion is inserted.
+    tick: u64,
+
+    /// A hashmap where the keys are the texts of the inserted completions and
+    /// the values are the ticks they were last inserted at.
+    last_used: HashMap<String, u64>,
+
+    /// How many times and when every completion was inserted. It's shared
+    /// with the thread loading the history file.
+    entries: Arc<Mutex<HashMap<Key, Entry>>>,
+
+    /// The file the entries are saved to, or `None` if they're only kept in
+    /// memory.
+    path: Option<PathBuf>,
+
+    /// Incremented every time the entries are saved.
+    saves: u64,
+
+    /// Whether completions were inserted since the entries were last saved.
+    is_dirty: bool,
+
+    /// The last save that was written to disk. Saves are written by
+    /// background threads, and older saves finishing after newer ones are
+    /// discarded.
+    last_written: Arc<Mutex<u64>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Key {
+    /// The filetype of the buffer the completion was inserted in.
+    filetype: String,
+
+    /// The text before the cursor matched by the completion.
+    prefix: String,
+
+    /// The text of the completion.
+    text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Entry {
+    /// The number of times the completion was inserted.
+    count: u32,
+
+    /// When the completion was last inserted, in seconds since the Unix
+    /// epoch.
+    last_used: u64,
+}
+
+impl History {
+    /// Records that a completion with the given text was just inserted after
+    /// typing `prefix` in a buffer of the given filetype.
+    pub fn record(&mut self, filetype: &str, prefix: &str, text: &str) {
+        self.tick += 1;
+        self.last_used.insert(text.to_owned(), self.tick);
+
+        let key = Key {
+            filetype: filetype.to_owned(),
+            prefix: prefix.to_owned(),
+            text: text.to_owned(),
+        };
+
+        let now = now();
+        self.entries
+            .lock()
+            .unwrap()
+            .entry(key)
+            .and_modify(|entry| {
+                entry.count += 1;
+                entry.last_used = now;
+            })
+            .or_insert(Entry {
+                count: 1,
+                last_used: now,
+            });
+
+        prune(&mut self.entries.lock().unwrap(), MAX_ENTRIES + PRUNE_SLACK);
+        self.is_dirty = true;
+    }
+
+    /// Writes the entries to the history file on a background thread if
+    /// completions were inserted since they were last written. They're
+    /// written lazily when leaving insert mode and when Neovim exits, which
+    /// waits for the returned thread.
+    pub fn flush(&mut self) -> Option<JoinHandle<()>> {
+        if !self.is_dirty || self.path.is_none() {
+            return None;
+        }
+        self.is_dirty = false;
+        Some(self.save())
+    }
+
+    /// The tick a completion with the given text was last inserted at in the
+    /// current session, if it was.
+    pub fn last_used(&self, text: &str) -> Option<u64> {
+        self.last_used.get(text).copied()
+    }
+
+    /// Returns a score for the completion with the given text, which is
+    /// higher the more often and the more recently it was inserted in buffers
+    /// of the same filetype after a prefix compatible with `prefix` (i.e.
+    /// either one starts with the other).
+    pub fn frecency(&self, filetype: &str, prefix: &str, text: &str) -> u32 {
+        let now = now();
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(key, _)| {
+                key.text == text
+                    && key.filetype == filetype
+                    && (key.prefix.starts_with(prefix)
+                        || prefix.starts_with(&key.prefix))
+            })
+            .map(|(_, entry)| frecency(entry, now))
+            .sum()
+    }
+
+    /// Sets the file the entries are saved to, or `None` to only keep them in
+    /// memory. The entries already saved in the file are loaded on a
+    /// background thread.
+    pub fn persist(&mut self, path: Option<PathBuf>) {
+        if path == self.path {
+            return;
+        }
+
+        if let Some(path) = path.clone() {
+            let entries = self.entries.clone();
+            thread::spawn(move || {
+                let contents = match fs::read_to_string(&path) {
+                    Ok(contents) => contents,
+                    Err(_) => return,
+                };
+                let entries = &mut *entries.lock().unwrap();
+                for (key, entry) in parse(&contents) {
+                    // The entries recorded while loading are more up to date.
+                    entries.entry(key).or_insert(entry);
+                }
+                prune(entries, MAX_ENTRIES + PRUNE_SLACK);
+            });
+        }
+
+        self.path = path;
+    }
+
+    /// Returns the lines saved in a session file by `:mksession` users to
+    /// restore the history with `restore_session`: the texts of the
+    /// completions inserted in the current session, least recently used
+    /// first, followed by the entries if they aren't already persisted in
+    /// the history file.
+    pub fn to_session(&self) -> Vec<String> {
+        let mut recent = self.last_used.iter().collect::<Vec<_>>();
+        recent.sort_by_key(|(_, &tick)| tick);
+
+        let mut lines = recent
+            .into_iter()
+            .filter(|(text, _)| !text.contains('\n'))
+            .map(|(text, _)| format!("{RECENT}\t{text}"))
+            .collect::<Vec<String>>();
+
+        if self.path.is_none() {
+            lines.extend(
+                serialize(&self.entries.lock().unwrap())
+                    .lines()
+                    .map(|line| format!("{ENTRY}\t{line}")),
+            );
+        }
+
+        lines
+    }
+
+    /// Restores the history saved by `to_session`, ignoring the lines it
+    /// didn't write. The completions inserted since Neovim was started are
+    /// considered more recent than the restored ones.
+    pub fn restore_session<'a, L>(&mut self, lines: L)
+    where
+        L: IntoIterator<Item = &'a str>,
+    {
+        let mut recent = Vec::new();
+        let mut entries = String::new();
+
+        for line in lines {
+            match line.split_once('\t') {
+                Some((RECENT, text)) => recent.push(text),
+                Some((ENTRY, entry)) => {
+                    entries.push_str(entry);
+                    entries.push('\n');
+                },
+                _ => {},
+            }
+        }
+
+        let restored = recent.len() as u64;
+        for tick in self.last_used.values_mut() {
+            *tick += restored;
+        }
+        for (tick, text) in (1..).zip(recent) {
+            self.last_used.entry(text.to_owned()).or_insert(tick);
+        }
+        self.tick += restored;
+
+        let current = &mut *self.entries.lock().unwrap();
+        for (key, entry) in parse(&entries) {
+            current.entry(key).or_insert(entry);
+        }
+        prune(current, MAX_ENTRIES + PRUNE_SLACK);
+    }
+
+    /// Writes the entries to the history file on a background thread.
+    fn save(&mut self) -> JoinHandle<()> {
+        let path = self.path.clone().expect("The history is persisted");
+        let contents = serialize(&self.entries.lock().unwrap());
+
+        self.saves += 1;
+        let save = self.saves;
+        let last_written = self.last_written.clone();
+
+        thread::spawn(move || {
+            let last_written = &mut *last_written.lock().unwrap();
+            if *last_written > save {
+                return;
+            }
+            if let Some(dir) = path.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            if fs::write(&path, contents).is_ok() {
+                *last_written = save;
+            }
+        })
+    }
+}
+
+/// Returns the number of seconds since the Unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// Weighs the number of times a completion was inserted by how long ago it
+/// was last inserted, where `now` is in seconds since the Unix epoch.
+fn frecency(entry: &Entry, now: u64) -> u32 {
+    let age_days = now.saturating_sub(entry.last_used) / SECONDS_PER_DAY;
+    let weight = RECENCY_WEIGHTS
+        .iter()
+        .find(|(max_age, _)| age_days < *max_age)
+        .map(|(_, weight)| *weight)
+        .unwrap_or(OLD_WEIGHT);
+    entry.count.saturating_mul(weight)
+}
+
+/// Drops the least recently used entries if there are more than `max`,
+/// keeping the `MAX_ENTRIES` most recent ones.
+fn prune(entries: &mut HashMap<Key, Entry>, max: usize) {
+    if entries.len() <= max {
+        return;
+    }
+    let mut last_used =
+        entries.values().map(|e| e.last_used).collect::<Vec<u64>>();
+    last_used.sort_unstable_by(|a, b| b.cmp(a));
+    let oldest_kept = last_used[MAX_ENTRIES.min(max) - 1];
+    entries.retain(|_, entry| entry.last_used >= oldest_kept);
+}
+
+/// Serializes the entries as lines of tab separated
+/// `filetype, prefix, text, count, last_used` fields, keeping only the
+/// `MAX_ENTRIES` most recently used ones.
+fn serialize(entries: &HashMap<Key, Entry>) -> String {
+    let mut entries = entries
+        .iter()
+        // Prefixes and completions can't contain tabs or newlines, or the
+        // file couldn't be parsed.
+        .filter(|(key, _)| {
+            [&key.filetype, &key.prefix, &key.text]
+                .iter()
+                .all(|field| !field.contains(['\t', '\n']))
+        })
+        .collect::<Vec<(&Key, &Entry)>>();
+
+    entries.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.last_used));
+    entries.truncate(MAX_ENTRIES);
+
+    entries
+        .into_iter()
+        .map(|(key, entry)| {
+            format!(
+                "{}\t{}\t{}\t{}\t{}\n",
+                key.filetype,
+                key.prefix,
+                key.text,
+                entry.count,
+                entry.last_used
+            )
+        })
+        .collect()
+}
+
+/// Parses the contents of a history file, skipping the malformed lines.
+fn parse(contents: &str) -> HashMap<Key, Entry> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split('\t').collect::<Vec<&str>>();
+            match fields[..] {
+                [filetype, prefix, text, count, last_used] => Some((
+                    Key {
+                        filetype: filetype.into(),
+                        prefix: prefix.into(),
+                        text: text.into(),
+                    },
+                    Entry {
+                        count: count.parse().ok()?,
+                        last_used: last_used.parse().ok()?,
+                    },
+                )),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn key(prefix: &str, text: &str) -> Key {
+        Key {
+            filetype: "rust".into(),
+            prefix: prefix.into(),
+            text: text.into(),
+        }
+    }
+
+    #[test]
+    fn recent_entries_weigh_more() {
+        let now = 100 * SECONDS_PER_DAY;
+        let entry = |count, days_ago| Entry {
+            count,
+            last_used: now - days_ago * SECONDS_PER_DAY,
+        };
+        assert_eq!(300, frecency(&entry(3, 1), now));
+        assert_eq!(140, frecency(&entry(2, 10), now));
+        assert_eq!(50, frecency(&entry(5, 95), now));
+    }
+
+    #[test]
+    fn serialize_then_parse() {
+        let entries = HashMap::from([
+            (
+                key("fo", "foobar"),
+                Entry {
+                    count: 2,
+                    last_used: 10,
+                },
+            ),
+            (
+                key("", "bar baz"),
+                Entry {
+                    count: 1,
+                    last_used: 20,
+                },
+            ),
+        ]);
+        assert_eq!(entries, parse(&serialize(&entries)));
+    }
+
+    #[test]
+    fn skip_malformed_lines() {
+        let entries =
+            parse("rust\tfo\tfoobar\t2\t10\nrust\tfoo\nrust\ta\tb\tc\t1\n");
+        assert_eq!(
+            HashMap::from([(
+                key("fo", "foobar"),
+                Entry {
+                    count: 2,
+                    last_used: 10
+                }
+            )]),
+            entries
+        );
+    }
+
+    #[test]
+    fn prune_least_recently_used() {
+        let mut entries = (0..MAX_ENTRIES as u64 + 2)
+            .map(|last_used| {
+                (
+                    key("", &last_used.to_string()),
+                    Entry {
+                        count: 1,
+                        last_used,
+                    },
+                )
+            })
+            .collect::<HashMap<_, _>>();
+
+        prune(&mut entries, MAX_ENTRIES + 2);
+        assert_eq!(MAX_ENTRIES + 2, entries.len());
+
+        prune(&mut entries, MAX_ENTRIES + 1);
+        assert_eq!(MAX_ENTRIES, entries.len());
+        assert!(!entries.contains_key(&key("", "0")));
+        assert!(!entries.contains_key(&key("", "1")));
+        assert!(entries.contains_key(&key("", "2")));
+    }
+
+    #[test]
+    fn flush_only_when_dirty() {
+        let path = std::env::temp_dir()
+            .join(format!("compleet-history-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut history = History::default();
+        history.persist(Some(path.clone()));
+        assert!(history.flush().is_none());
+
+        history.record("rust", "fo", "foo");
+        history.flush().unwrap().join().unwrap();
+        assert!(history.flush().is_none());
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .starts_with("rust\tfo\tfoo\t1\t"));
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn restore_from_session() {
+        let mut old = History::default();
+        old.record("rust", "fo", "foo");
+        old.record("rust", "ba", "bar");
+
+        let mut new = History::default();
+        new.record("rust", "ba", "baz");
+        new.restore_session(old.to_session().iter().map(String::as_str));
+
+        assert_eq!(Some(1), new.last_used("foo"));
+        assert_eq!(Some(2), new.last_used("bar"));
+        assert_eq!(Some(3), new.last_used("baz"));
+        assert_eq!(100, new.frecency("rust", "f", "foo"));
+    }
+
+    #[test]
+    fn frecency_of_compatible_prefixes() {
+        let mut history = History::default();
+        history.record("rust", "fo", "foobar");
+        history.record("rust", "foob", "foobar");
+        history.record("lua", "fo", "foobar");
+
+        assert_eq!(200, history.frecency("rust", "foo", "foobar"));
+        assert_eq!(200, history.frecency("rust", "f", "foobar"));
+        assert_eq!(100, history.frecency("lua", "foo", "foobar"));
+        assert_eq!(0, history.frecency("rust", "bar", "foobar"));
+    }
+}
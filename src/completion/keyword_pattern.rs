@@ -0,0 +1,49 @@
+use mlua::prelude::{Lua, LuaAnyUserData, LuaRegistryKey, LuaResult};
+use neovim::{Neovim, Regex};
+
+use super::Cursor;
+
+/// The compiled `completion.keyword_pattern`, used to find where the keyword
+/// before the cursor starts. It's compiled once every time the settings
+/// change and reused on every keystroke.
+#[derive(Debug, Default)]
+pub struct KeywordPattern {
+    /// A registry key pointing to the compiled Vim regex, or `None` if no
+    /// pattern is set and the default keyword characters are used.
+    regex: Option<LuaRegistryKey>,
+}
+
+impl KeywordPattern {
+    /// Compiles the Vim regex `pattern`, anchored to the end of the text
+    /// before the cursor.
+    pub fn new(lua: &Lua, pattern: Option<&str>) -> LuaResult<Self> {
+        let regex = match pattern {
+            Some(pattern) => {
+                let regex =
+                    Neovim::new(lua)?.regex(&format!("\\%({pattern}\\)$"))?;
+                Some(lua.create_registry_value(regex.into_inner())?)
+            },
+            None => None,
+        };
+        Ok(KeywordPattern { regex })
+    }
+
+    /// Sets the start of the keyword before the `cursor`, which has to be
+    /// called every time the cursor is updated.
+    pub fn update(&self, lua: &Lua, cursor: &mut Cursor) -> LuaResult<()> {
+        cursor.keyword_start = match &self.regex {
+            Some(key) => {
+                let regex = Regex::from_userdata(
+                    lua.registry_value::<LuaAnyUserData>(key)?,
+                );
+                regex
+                    .match_str(&cursor.line[..cursor.bytes as usize])?
+                    .map(|(start, _)| start as u32)
+                    // If the pattern doesn't match there's no keyword.
+                    .or(Some(cursor.bytes))
+            },
+            None => None,
+        };
+        Ok(())
+    }
+}
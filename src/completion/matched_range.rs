@@ -0,0 +1,182 @@
+use super::{CompletionItem, Cursor};
+
+/// The bytes of a line of the buffer replaced by a completion when it's
+/// confirmed, going from the start of the text it matched (which for the
+/// language servers is the start of the item's `textEdit`) to the cursor the
+/// completions were computed at, followed by the `matched_bytes_post` after
+/// it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchedRange {
+    /// The 0-indexed row of the line.
+    pub row: u32,
+
+    /// The byte offset of the start of the range.
+    pub start: u32,
+
+    /// The byte offset of the end of the range, i.e. of the cursor.
+    pub end: u32,
+
+    /// The text of the line in the range when the completions were computed.
+    pub text: String,
+
+    /// The text of the line after the cursor matched by the completion, i.e.
+    /// its `matched_bytes_post`.
+    pub post_text: String,
+}
+
+impl MatchedRange {
+    /// The range replaced by the `completion` at the `cursor`.
+    pub fn new(completion: &CompletionItem, cursor: &Cursor) -> Self {
+        Self::around(
+            cursor,
+            completion.matched_bytes,
+            completion.matched_bytes_post,
+        )
+    }
+
+    /// The range covering the ones of all the `completions`, or `None` if
+    /// there are no completions.
+    pub fn covering(
+        completions: &[CompletionItem],
+        cursor: &Cursor,
+    ) -> Option<Self> {
+        let pre = completions.iter().map(|c| c.matched_bytes).max()?;
+        let post = completions.iter().map(|c| c.matched_bytes_post).max()?;
+        Some(Self::around(cursor, pre, post))
+    }
+
+    /// The range of the `pre` bytes before the `cursor` and the `post` bytes
+    /// after it.
+    fn around(cursor: &Cursor, pre: u32, post: u32) -> Self {
+        let start = cursor.bytes.saturating_sub(pre);
+        let (line_pre, line_post) =
+            cursor.line.split_at(cursor.bytes as usize);
+        MatchedRange {
+            row: cursor.row,
+            start,
+            end: cursor.bytes,
+            text: line_pre[start as usize..].to_owned(),
+            post_text: line_post[..(post as usize).min(line_post.len())]
+                .to_owned(),
+        }
+    }
+
+    /// Whether the 0-indexed `(row, col)` position is inside the range or on
+    /// one of its edges.
+    pub fn contains(&self, row: u32, col: u32) -> bool {
+        row == self.row && col >= self.start && col <= self.end
+    }
+
+    /// Whether the `line`, which is the current text of the range's row,
+    /// still has the same text in the range, i.e. it hasn't been edited since
+    /// the completions were computed.
+    pub fn is_intact(&self, line: &str) -> bool {
+        let end_post = self.end as usize + self.post_text.len();
+        line.get(self.start as usize..self.end as usize)
+            == Some(self.text.as_str())
+            && line.get(self.end as usize..end_post)
+                == Some(self.post_text.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MatchedRange;
+    use crate::completion::{CompletionItem, Cursor};
+
+    fn cursor(line: &str, bytes: u32) -> Cursor {
+        Cursor {
+            bytes,
+            keyword_start: None,
+            line: line.into(),
+            row: 3,
+        }
+    }
+
+    fn item(matched_bytes: u32) -> CompletionItem {
+        item_post(matched_bytes, 0)
+    }
+
+    fn item_post(
+        matched_bytes: u32,
+        matched_bytes_post: u32,
+    ) -> CompletionItem {
+        CompletionItem {
+            format: " foobar".into(),
+            matched_bytes,
+            matched_bytes_post,
+            source: "Buffer",
+            text: "foobar".into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn range_of_a_completion() {
+        let range = MatchedRange::new(&item(3), &cursor("let foo.bar", 11));
+        assert_eq!((3, 8, 11), (range.row, range.start, range.end));
+        assert_eq!("bar", range.text);
+    }
+
+    #[test]
+    fn covering_range() {
+        let cursor = cursor("let a = ./foo", 13);
+        let range =
+            MatchedRange::covering(&[item(3), item(5), item(0)], &cursor)
+                .unwrap();
+        assert_eq!((8, 13), (range.start, range.end));
+        assert_eq!("./foo", range.text);
+        assert_eq!(None, MatchedRange::covering(&[], &cursor));
+    }
+
+    #[test]
+    fn moving_inside_the_range() {
+        let range = MatchedRange::new(&item(3), &cursor("let foo.bar", 11));
+        assert!(range.contains(3, 11));
+        assert!(range.contains(3, 9));
+        assert!(range.contains(3, 8));
+    }
+
+    #[test]
+    fn moving_outside_the_range() {
+        let range =
+            MatchedRange::new(&item(3), &cursor("let foo.bar = 1", 11));
+        assert!(!range.contains(3, 7));
+        assert!(!range.contains(3, 12));
+        assert!(!range.contains(4, 11));
+        assert!(!range.contains(2, 11));
+    }
+
+    #[test]
+    fn edits_inside_the_range() {
+        let range = MatchedRange::new(&item(3), &cursor("let foo.bar", 11));
+        assert!(range.is_intact("let foo.bar"));
+        assert!(range.is_intact("let foo.bar = 1"));
+        assert!(range.is_intact("let abc.bar"));
+        assert!(!range.is_intact("let foo.br"));
+        assert!(!range.is_intact("let foo.baz"));
+        assert!(!range.is_intact("let"));
+    }
+
+    #[test]
+    fn range_with_bytes_after_the_cursor() {
+        let cursor = cursor("let fooxr = 1", 7);
+        let range = MatchedRange::new(&item_post(3, 2), &cursor);
+        assert_eq!((4, 7), (range.start, range.end));
+        assert_eq!(("foo", "xr"), (&*range.text, &*range.post_text));
+
+        let range =
+            MatchedRange::covering(&[item(2), item_post(3, 20)], &cursor)
+                .unwrap();
+        assert_eq!(("foo", "xr = 1"), (&*range.text, &*range.post_text));
+    }
+
+    #[test]
+    fn edits_after_the_cursor() {
+        let range =
+            MatchedRange::new(&item_post(3, 2), &cursor("let fooxr", 7));
+        assert!(range.is_intact("let fooxr = 1"));
+        assert!(!range.is_intact("let fooxz"));
+        assert!(!range.is_intact("let foox"));
+    }
+}
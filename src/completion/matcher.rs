@@ -0,0 +1,243 @@
+use std::ops::Range;
+
+use super::unicode;
+
+const SCORE_MATCH: i32 = 16;
+const SCORE_GAP_START: i32 = -3;
+const SCORE_GAP_EXTENSION: i32 = -1;
+
+const BONUS_BOUNDARY: i32 = SCORE_MATCH / 2;
+const BONUS_CAMEL_CASE: i32 = BONUS_BOUNDARY - 1;
+const BONUS_CONSECUTIVE: i32 = -(SCORE_GAP_START + SCORE_GAP_EXTENSION);
+const BONUS_FIRST_CHAR_MULTIPLIER: i32 = 2;
+
+/// The highlight group used for the characters matched by the pattern.
+pub const MATCHING_CHARS_HL: &str = "CompleetMenuMatchingChars";
+
+/// The result of fuzzy matching a pattern against some text.
+#[derive(Debug, PartialEq)]
+pub struct Match {
+    /// How well the pattern matches the text. Higher is better.
+    pub score: i32,
+
+    /// The byte ranges of the text matched by the pattern. Contiguous matched
+    /// characters are merged into a single range, and the ranges always end
+    /// on a grapheme cluster boundary so that a matched character is
+    /// highlighted together with its combining marks.
+    pub ranges: Vec<Range<usize>>,
+}
+
+impl Match {
+    /// Returns the `hl_ranges` of a `CompletionItem` whose `format` contains
+    /// the matched text starting at byte `offset`.
+    pub fn hl_ranges(
+        &self,
+        offset: usize,
+    ) -> Vec<(Range<usize>, &'static str)> {
+        self.ranges
+            .iter()
+            .map(|r| (r.start + offset..r.end + offset, MATCHING_CHARS_HL))
+            .collect()
+    }
+}
+
+/// Fuzzy matches `pattern` against `text`, returning `None` if the characters
+/// of `pattern` don't appear in `text` in the same order.
+///
+/// The matching is case insensitive unless `pattern` contains an uppercase
+/// character. Among all the possible ways the pattern can match, the one with
+/// the highest score is chosen. Characters at the start of words and
+/// consecutive matches are rewarded, gaps between matched characters are
+/// penalized.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<Match> {
+    if pattern.is_empty() {
+        return Some(Match {
+            score: 0,
+            ranges: Vec::new(),
+        });
+    }
+
+    let case_sensitive = pattern.chars().any(char::is_uppercase);
+
+    let eq = |p: char, t: char| match case_sensitive {
+        true => p == t,
+        false => p == t || p.to_lowercase().eq(t.to_lowercase()),
+    };
+
+    let original = text;
+    let pattern = pattern.chars().collect::<Vec<char>>();
+    let text = text.char_indices().collect::<Vec<(usize, char)>>();
+
+    // Bail out early if the pattern isn't a subsequence of the text.
+    let mut chars = text.iter();
+    if !pattern.iter().all(|&p| chars.any(|&(_, t)| eq(p, t))) {
+        return None;
+    }
+
+    let (m, n) = (pattern.len(), text.len());
+
+    let bonuses = (0..n)
+        .map(|j| bonus(j.checked_sub(1).map(|k| text[k].1), text[j].1))
+        .collect::<Vec<i32>>();
+
+    // `scores[i][j]` is the best score of matching the first `i + 1`
+    // characters of the pattern with the `i`-th one matched at the `j`-th
+    // character of the text, and `previous[i][j]` is the position where the
+    // `i - 1`-th pattern character was matched to get that score.
+    let mut scores = vec![vec![None::<i32>; n]; m];
+    let mut previous = vec![vec![0usize; n]; m];
+
+    for j in 0..n {
+        if eq(pattern[0], text[j].1) {
+            // Matches further away from the start of the text are penalized.
+            scores[0][j] = Some(
+                SCORE_MATCH
+                    + bonuses[j] * BONUS_FIRST_CHAR_MULTIPLIER
+                    + SCORE_GAP_EXTENSION * j as i32,
+            );
+        }
+    }
+
+    for i in 1..m {
+        // The best `(score, position)` of the previous pattern character
+        // matched at least two characters before `j`, with the gap penalty
+        // already applied.
+        let mut best_gapped = None::<(i32, usize)>;
+
+        for j in i..n {
+            if j >= 2 {
+                if let Some(score) = scores[i - 1][j - 2] {
+                    let extended = best_gapped
+                        .map(|(s, k)| (s + SCORE_GAP_EXTENSION, k))
+                        .filter(|&(s, _)| s >= score + SCORE_GAP_START);
+
+                    best_gapped =
+                        extended.or(Some((score + SCORE_GAP_START, j - 2)));
+                } else if let Some((s, k)) = best_gapped {
+                    best_gapped = Some((s + SCORE_GAP_EXTENSION, k));
+                }
+            }
+
+            if !eq(pattern[i], text[j].1) {
+                continue;
+            }
+
+            let consecutive =
+                scores[i - 1][j - 1].map(|s| (s + BONUS_CONSECUTIVE, j - 1));
+
+            let best = match (consecutive, best_gapped) {
+                (Some(c), Some(g)) => Some(if c.0 >= g.0 { c } else { g }),
+                (c, g) => c.or(g),
+            };
+
+            if let Some((score, k)) = best {
+                scores[i][j] = Some(score + SCORE_MATCH + bonuses[j]);
+                previous[i][j] = k;
+            }
+        }
+    }
+
+    let (score, mut j) = (0..n)
+        .filter_map(|j| scores[m - 1][j].map(|s| (s, j)))
+        .max_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)))?;
+
+    // Walk back from the last matched character to collect the positions of
+    // all the others.
+    let mut positions = vec![0; m];
+    for i in (0..m).rev() {
+        positions[i] = j;
+        j = previous[i][j];
+    }
+
+    let mut ranges = Vec::<Range<usize>>::new();
+    for j in positions {
+        let (start, c) = text[j];
+        let end = unicode::grapheme_end(original, start + c.len_utf8());
+        match ranges.last_mut() {
+            Some(last) if last.end >= start => last.end = last.end.max(end),
+            _ => ranges.push(start..end),
+        }
+    }
+
+    Some(Match { score, ranges })
+}
+
+/// The bonus given to a character `c` preceded by `prev`, or by nothing if
+/// it's the first character of the text.
+fn bonus(prev: Option<char>, c: char) -> i32 {
+    match prev {
+        None => BONUS_BOUNDARY,
+        Some(p) if !p.is_alphanumeric() && c.is_alphanumeric() => {
+            BONUS_BOUNDARY
+        },
+        Some(p) if p.is_lowercase() && c.is_uppercase() => BONUS_CAMEL_CASE,
+        Some(p) if p.is_alphabetic() && c.is_numeric() => BONUS_CAMEL_CASE,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fuzzy_match;
+
+    fn ranges(pattern: &str, text: &str) -> Option<Vec<(usize, usize)>> {
+        fuzzy_match(pattern, text)
+            .map(|m| m.ranges.iter().map(|r| (r.start, r.end)).collect())
+    }
+
+    fn score(pattern: &str, text: &str) -> i32 {
+        fuzzy_match(pattern, text).unwrap().score
+    }
+
+    #[test]
+    fn not_a_subsequence() {
+        assert_eq!(None, fuzzy_match("abc", "acb"));
+        assert_eq!(None, fuzzy_match("foo", "fo"));
+    }
+
+    #[test]
+    fn prefix_is_a_single_range() {
+        assert_eq!(Some(vec![(0, 3)]), ranges("foo", "foobar"));
+    }
+
+    #[test]
+    fn non_contiguous_matches() {
+        assert_eq!(Some(vec![(0, 1), (3, 4)]), ranges("fb", "foobar"));
+    }
+
+    #[test]
+    fn prefers_word_boundaries() {
+        assert_eq!(Some(vec![(0, 1), (4, 5)]), ranges("fb", "foo_bar_fb"));
+        assert_eq!(Some(vec![(0, 1), (3, 4)]), ranges("gB", "getBar"));
+    }
+
+    #[test]
+    fn smart_case() {
+        assert!(fuzzy_match("foo", "FooBar").is_some());
+        assert!(fuzzy_match("Foo", "foobar").is_none());
+    }
+
+    #[test]
+    fn multibyte_chars() {
+        assert_eq!(Some(vec![(0, 2), (5, 6)]), ranges("öb", "öö_bar"));
+        assert_eq!(Some(vec![(0, 4)]), ranges("ÖÖ", "ÖÖbar"));
+        assert_eq!(Some(vec![(0, 6)]), ranges("日本", "日本語"));
+    }
+
+    #[test]
+    fn combining_chars() {
+        // The combining accent after the `e` is highlighted with it.
+        assert_eq!(Some(vec![(0, 4)]), ranges("ce", "ce\u{301}x"));
+        assert_eq!(Some(vec![(0, 1), (4, 5)]), ranges("cx", "ce\u{301}x"));
+    }
+
+    #[test]
+    fn scores_are_ordered() {
+        // Prefixes beat word boundaries, which beat arbitrary positions.
+        assert!(score("foo", "foobar") > score("foo", "bar_foo"));
+        assert!(score("foo", "bar_foo") > score("foo", "barfoo"));
+
+        // Contiguous matches beat scattered ones.
+        assert!(score("abc", "abcxyz") > score("abc", "axbxcx"));
+    }
+}
@@ -1,10 +1,38 @@
+mod complete;
 mod completion_item;
 mod completion_source;
 mod cursor;
+mod debounce;
+mod dedup;
+mod filter;
+mod history;
+mod keyword_pattern;
+mod matched_range;
+pub mod matcher;
 mod on_bytes;
+mod pipeline;
+mod preview;
+mod ranker;
+mod refresh;
+mod resolve;
 pub mod sources;
+mod trigger;
+mod undo;
+pub mod unicode;
 
-pub use completion_item::CompletionItem;
+pub use complete::{complete_at_cursor, is_autocompleting};
+pub use completion_item::{CompletionItem, Details};
 pub use completion_source::CompletionSource;
-pub use cursor::Cursor;
+pub use cursor::{is_keyword_char, Cursor};
+pub use debounce::{debounce, debounce_with, throttle};
+pub use history::History;
+pub use keyword_pattern::KeywordPattern;
+pub use matched_range::MatchedRange;
+use on_bytes::is_insert_mode;
 pub use on_bytes::on_bytes;
+pub use pipeline::{Cancellation, Job, Pipeline, SourceStats};
+pub use preview::Preview;
+pub use refresh::{refresh, schedule_refresh};
+pub use resolve::resolve_selected;
+pub use trigger::{find_trigger, is_triggered};
+pub use undo::break_undo_sequence;
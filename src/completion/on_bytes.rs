@@ -1,7 +1,10 @@
 use mlua::{prelude::LuaResult, Lua};
 use neovim::{Api, Neovim};
 
+use crate::autocmds;
+use crate::phase::Transition;
 use crate::state::State;
+use crate::ui::menu::native;
 
 /// Executed every time a byte or a group of bytes in an attached buffer is
 /// modified.
@@ -26,13 +29,35 @@ pub fn on_bytes(
         return Ok(Some(true));
     }
 
+    // Buffers edited while another one is current, e.g. by a plugin, don't
+    // get completions, which are only computed for the current buffer.
+    if bufnr != state.buffers.current_nr() {
+        return Ok(None);
+    }
+
+    // The changes made while previewing a completion shouldn't trigger new
+    // completions, while any other change makes the previewed text part of
+    // the buffer.
+    if state.preview.is_own_edit() {
+        return Ok(None);
+    } else if state.preview.is_active() {
+        state.preview.commit();
+        state.ui.completion_menu.selected_index = None;
+        state.transition(Transition::Select(None));
+    }
+
     let api = Neovim::new(lua)?.api;
 
     // We only care about insert mode events.
-    if api.get_mode()?.0 != "i" {
+    let mode = api.get_mode()?.0;
+    if !is_insert_mode(lua, &mode, state)? {
         return Ok(None);
     }
 
+    // The completions are computed again for the cursor of the current
+    // window, so moving the UI to it has nothing to discard.
+    state.follow_current_window(lua, &api)?;
+
     // If we've added or deleted a line we return early. If we've stayed on the
     // same line but we've deleted characters we only continue if the
     // `completion.while_deleting` option is set.
@@ -43,13 +68,26 @@ pub fn on_bytes(
         return Ok(None);
     }
 
+    // If the completions aren't computed while typing they get computed
+    // lazily by `<Plug>(compleet-show-completions)`.
+    if !super::is_autocompleting(state) {
+        state.buffers.current_mut().clear_completions();
+        state.pipeline.stop_loading();
+        return Ok(None);
+    }
+
     // Update the cursor.
-    let cursor = &mut state.cursor;
+    let cursor = &mut state.buffers.current_mut().cursor;
+    let old_keyword_start = (cursor.row, cursor.keyword_start_col());
 
     cursor.row = start_row;
     cursor.line = get_current_line(&api, cursor.row)?;
     cursor.bytes =
         start_col + if bytes_deleted != 0 { 0 } else { bytes_added };
+    state.keyword_pattern.update(lua, cursor)?;
+
+    autocmds::on_keyword_changed(lua, &api, state, old_keyword_start)?;
+    let cursor = &state.buffers.current().cursor;
 
     #[cfg(debug)]
     {
@@ -65,25 +103,90 @@ pub fn on_bytes(
         )?;
     }
 
-    // TODO: if `setting.ui.menu.autoshow` is false we look at
-    // `settings.hint.enable`. If that is also false we can just return early,
-    // otherwise we just need to compute the first completion.
-
-    let completions = &mut state.completions;
-
-    completions.clear();
-    for source in state
+    // The completions computed for the previous character are outdated, the
+    // new ones will be computed by `completion::refresh` once the user stops
+    // typing.
+    let sources = state
         .sources
         .get(&bufnr)
-        .expect("The buffer is attached so it has sources")
-        .iter()
+        .expect("The buffer is attached so it has sources");
+
+    let is_triggered = super::is_triggered(lua, &api, sources, cursor)?;
+
+    // Don't complete keywords shorter than `completion.min_chars`, unless a
+    // trigger character was just typed.
+    if !is_triggered
+        && cursor.keyword_pre().chars().count()
+            < usize::from(state.settings.completion.min_chars)
     {
-        completions.append(&mut source.complete(&api, &cursor)?);
+        state.buffers.current_mut().clear_completions();
+        state.pipeline.stop_loading();
+        return Ok(None);
+    }
+
+    // Typing a trigger character computes the completions right away.
+    let debounce_ms = state.settings.completion.debounce_ms;
+    if debounce_ms > 0 && !is_triggered {
+        state.buffers.current_mut().clear_completions();
+        state.pipeline.stop_loading();
+        super::debounce(lua, debounce_ms)?;
+        return Ok(None);
+    }
+
+    // The completions skipped while throttled are computed at the end of the
+    // window.
+    let throttle_ms = state.settings.completion.throttle_ms;
+    if throttle_ms > 0 && !is_triggered && !super::throttle(lua, throttle_ms)?
+    {
+        state.buffers.current_mut().clear_completions();
+        state.pipeline.stop_loading();
+        return Ok(None);
+    }
+
+    // `CursorMovedI` isn't fired while the native popup menu is visible, so
+    // the completions are computed by `completion::refresh`, which also
+    // updates the menu.
+    if mode == "ic" {
+        super::schedule_refresh(lua)?;
+        return Ok(None);
+    }
+
+    let completions = state.pipeline.run(
+        lua,
+        &api,
+        sources,
+        cursor,
+        &state.settings.completion,
+        &state.history,
+    )?;
+    state.buffers.current_mut().set_completions(completions);
+
+    if state.pipeline.is_computing() {
+        state.transition(Transition::Compute);
     }
 
     Ok(None)
 }
 
+/// Whether the completions are computed in the `mode` returned by
+/// `nvim_get_mode`. Other than insert mode, that's the insert completion
+/// mode entered while the completions are shown in the native popup menu, as
+/// long as none of its items is selected.
+pub(super) fn is_insert_mode(
+    lua: &Lua,
+    mode: &str,
+    state: &State,
+) -> LuaResult<bool> {
+    Ok(match mode {
+        "i" => true,
+        "ic" => {
+            state.ui.is_native_menu_visible()
+                && !native::is_item_selected(lua)?
+        },
+        _ => false,
+    })
+}
+
 fn get_current_line(api: &Api, current_row: u32) -> LuaResult<String> {
     let current_line = api
         .buf_get_lines(
@@ -0,0 +1,352 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use mlua::prelude::{Lua, LuaAnyUserData, LuaResult, LuaValue};
+use neovim::{Api, Neovim};
+
+use super::{complete, CompletionItem, Cursor, History};
+use crate::events::{self, Event};
+use crate::logging;
+use crate::profiling::{self, Stage};
+use crate::settings::completion::CompletionSettings;
+use crate::state::Sources;
+
+/// How often (in milliseconds) to check whether the background jobs have
+/// finished.
+const POLL_INTERVAL_MS: u64 = 10;
+
+/// The key of the Lua registry where the timer used to poll for finished jobs
+/// is stored.
+const POLL_TIMER: &str = "compleet_pipeline_timer";
+
+/// A computation producing completion items that runs on a background thread.
+/// It should check the `Cancellation` it's given every now and then and
+/// return early if it's been cancelled, since its results would be discarded
+/// anyway.
+pub type Job = Box<dyn FnOnce(&Cancellation) -> Vec<CompletionItem> + Send>;
+
+/// Tells a `Job` whether its results are still needed.
+pub struct Cancellation {
+    /// The generation of the pipeline, shared by all the jobs.
+    current: Arc<AtomicU64>,
+
+    /// The generation the job was started at.
+    generation: u64,
+}
+
+impl Cancellation {
+    /// A cancellation that never gets cancelled, used to run a `Job` on the
+    /// main thread.
+    pub fn never() -> Self {
+        Cancellation {
+            current: Arc::new(AtomicU64::new(0)),
+            generation: 0,
+        }
+    }
+
+    /// Whether the job was started at a cursor position that's now outdated.
+    pub fn is_cancelled(&self) -> bool {
+        self.current.load(Ordering::Relaxed) != self.generation
+    }
+}
+
+/// How a source performed in the last completion round, shown by
+/// `:CompleetInfo`.
+#[derive(Debug, Clone, Copy)]
+pub enum SourceStats {
+    /// The source returned `items` completions in `duration`.
+    Done { items: usize, duration: Duration },
+
+    /// The source's job is still running on a background thread.
+    Running,
+}
+
+/// Collects the completions of the sources at the current cursor position,
+/// running the sources that support it on background threads. Every time the
+/// cursor position changes a new generation of jobs is started and the
+/// previous one is cancelled.
+#[derive(Debug, Default)]
+pub struct Pipeline {
+    /// Incremented every time a new generation of jobs is started. It's
+    /// shared with the jobs so that they can tell when they're cancelled.
+    generation: Arc<AtomicU64>,
+
+    /// The `(bufnr, row, bytes, line)` cursor position the current generation
+    /// was started at.
+    position: Option<(u32, u32, u32, String)>,
+
+    /// The indexes of the sources whose completions are computed by the jobs
+    /// of the current generation.
+    background: Vec<usize>,
+
+    /// The `(source index, items, duration)` tuples of the sources that ran
+    /// on the main thread the last time the pipeline was run.
+    sync_stats: Vec<(usize, usize, Duration)>,
+
+    /// Shared with the jobs, which store their results here once they're
+    /// done.
+    results: Arc<Mutex<Results>>,
+
+    /// Whether some jobs were still running or some sources were still
+    /// waiting for their completions the last time the pipeline was run.
+    is_loading: bool,
+}
+
+#[derive(Debug, Default)]
+struct Results {
+    /// The generation the results belong to.
+    generation: u64,
+
+    /// The `(source index, completions, duration)` tuples of the jobs that
+    /// have finished.
+    finished: Vec<(usize, Vec<CompletionItem>, Duration)>,
+
+    /// The number of jobs of the current generation that are still running.
+    pending: usize,
+
+    /// Whether some jobs have finished since the last time we polled.
+    has_news: bool,
+
+    /// Whether the poll timer is running.
+    is_polling: bool,
+}
+
+impl Pipeline {
+    /// Returns the completions available at the current cursor position,
+    /// ranked according to the `completion.sorting` settings.
+    ///
+    /// Jobs are only started if the cursor has moved since the last call, so
+    /// this can be called again when they finish to add their results to the
+    /// ones of the other sources.
+    pub fn run(
+        &mut self,
+        lua: &Lua,
+        api: &Api,
+        sources: &Sources,
+        cursor: &Cursor,
+        settings: &CompletionSettings,
+        history: &History,
+    ) -> LuaResult<Vec<CompletionItem>> {
+        let bufnr = api.get_current_buf()?;
+        let position = (bufnr, cursor.row, cursor.bytes, cursor.line.clone());
+
+        if self.position.as_ref() != Some(&position) {
+            self.restart(lua, api, sources, cursor)?;
+            self.position = Some(position);
+        }
+
+        let mut completions = Vec::new();
+        self.sync_stats.clear();
+
+        for (i, source) in sources.iter().enumerate() {
+            if !self.background.contains(&i) {
+                let start = Instant::now();
+                let mut items = match source.complete(lua, api, cursor) {
+                    Ok(items) => items,
+                    Err(err) => {
+                        logging::error(
+                            "pipeline",
+                            format!(
+                                "source `{}` failed: {err}",
+                                source.name()
+                            ),
+                        );
+                        return Err(err);
+                    },
+                };
+                let duration = start.elapsed();
+                logging::debug(
+                    "pipeline",
+                    format!(
+                        "`{}` returned {} items in {duration:?}",
+                        source.name(),
+                        items.len()
+                    ),
+                );
+                profiling::record(Stage::Source(source.name()), duration);
+                self.sync_stats.push((i, items.len(), duration));
+                completions.append(&mut items);
+            }
+        }
+
+        let results = &mut *self.results.lock().unwrap();
+        results.finished.sort_by_key(|(i, _, _)| *i);
+        for (_, items, _) in &results.finished {
+            completions.extend(items.iter().cloned());
+        }
+
+        self.is_loading = results.pending > 0
+            || sources.iter().any(|source| source.is_loading());
+
+        let start = Instant::now();
+        let completions = complete::process(
+            lua,
+            api,
+            completions,
+            sources,
+            cursor,
+            settings,
+            history,
+        )?;
+        profiling::record(Stage::Ranking, start.elapsed());
+
+        Ok(completions)
+    }
+
+    /// Whether some jobs of the current generation are still running.
+    pub fn is_computing(&self) -> bool {
+        self.results.lock().unwrap().pending > 0
+    }
+
+    /// Whether the completions returned by the last `run` are still
+    /// incomplete because some sources haven't finished computing theirs.
+    pub fn is_loading(&self) -> bool { self.is_loading }
+
+    /// Forgets that the last completions were incomplete, since they're not
+    /// going to be shown, e.g. because the keyword became too short.
+    pub fn stop_loading(&mut self) { self.is_loading = false; }
+
+    /// Returns how the `index`-th source performed in the last completion
+    /// round, or `None` if it hasn't been run yet.
+    pub fn stats(&self, index: usize) -> Option<SourceStats> {
+        if !self.background.contains(&index) {
+            return self.sync_stats.iter().find(|(i, _, _)| *i == index).map(
+                |&(_, items, duration)| SourceStats::Done { items, duration },
+            );
+        }
+
+        let results = &*self.results.lock().unwrap();
+        Some(
+            match results.finished.iter().find(|(i, _, _)| *i == index) {
+                Some((_, items, duration)) => SourceStats::Done {
+                    items: items.len(),
+                    duration: *duration,
+                },
+                None => SourceStats::Running,
+            },
+        )
+    }
+
+    /// Cancels the running jobs and starts new ones for the sources that
+    /// support it.
+    fn restart(
+        &mut self,
+        lua: &Lua,
+        api: &Api,
+        sources: &Sources,
+        cursor: &Cursor,
+    ) -> LuaResult<()> {
+        let generation = self.generation.fetch_add(1, Ordering::Relaxed) + 1;
+
+        let results = &mut *self.results.lock().unwrap();
+        results.generation = generation;
+        results.finished.clear();
+        results.pending = 0;
+        results.has_news = false;
+
+        self.background.clear();
+
+        for (i, source) in sources.iter().enumerate() {
+            let job = match source.complete_in_background(lua, api, cursor)? {
+                Some(job) => job,
+                None => continue,
+            };
+
+            self.background.push(i);
+            results.pending += 1;
+
+            let cancellation = Cancellation {
+                current: self.generation.clone(),
+                generation,
+            };
+
+            let results = self.results.clone();
+            let name = source.name();
+
+            thread::spawn(move || {
+                let start = Instant::now();
+                let completions = job(&cancellation);
+                let duration = start.elapsed();
+
+                profiling::record(Stage::Source(name), duration);
+                logging::debug(
+                    "pipeline",
+                    format!(
+                        "`{name}` returned {} items in {duration:?} on a \
+                         background thread",
+                        completions.len()
+                    ),
+                );
+
+                let results = &mut *results.lock().unwrap();
+                if results.generation == generation {
+                    results.finished.push((i, completions, duration));
+                    results.pending -= 1;
+                    results.has_news = true;
+                }
+            });
+        }
+
+        logging::debug(
+            "pipeline",
+            format!(
+                "generation {generation} started at {}:{}, with {} \
+                 background jobs",
+                cursor.row + 1,
+                cursor.bytes,
+                results.pending
+            ),
+        );
+
+        if results.pending > 0 && !results.is_polling {
+            results.is_polling = true;
+            self.start_polling(lua)?;
+        } else if results.pending == 0 && !results.is_polling {
+            events::fire(lua, Event::SourcesDone)?;
+        }
+
+        Ok(())
+    }
+
+    /// Starts a timer that checks whether the jobs have finished, asking for
+    /// a refresh when they have. The timer stops itself once there are no
+    /// more jobs running.
+    fn start_polling(&self, lua: &Lua) -> LuaResult<()> {
+        let uv = Neovim::new(lua)?.uv()?;
+
+        let timer =
+            match lua.named_registry_value::<_, LuaValue>(POLL_TIMER)? {
+                LuaValue::UserData(timer) => timer,
+                _ => {
+                    let timer = uv.new_timer()?;
+                    lua.set_named_registry_value(POLL_TIMER, timer.clone())?;
+                    timer
+                },
+            };
+
+        let results = self.results.clone();
+
+        let poll = lua.create_function(move |lua, ()| {
+            let results = &mut *results.lock().unwrap();
+
+            if results.has_news {
+                results.has_news = false;
+                super::schedule_refresh(lua)?;
+            }
+
+            if results.pending == 0 {
+                events::fire(lua, Event::SourcesDone)?;
+                results.is_polling = false;
+                let timer =
+                    lua.named_registry_value::<_, LuaAnyUserData>(POLL_TIMER)?;
+                Neovim::new(lua)?.uv()?.timer_stop(timer)?;
+            }
+
+            Ok(())
+        })?;
+
+        uv.timer_start(timer, POLL_INTERVAL_MS, POLL_INTERVAL_MS, poll)
+    }
+}
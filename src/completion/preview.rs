@@ -0,0 +1,284 @@
+use std::slice;
+
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Neovim;
+
+use super::{CompletionItem, Cursor};
+
+/// Keeps track of the completion whose text is being previewed in the buffer
+/// when the `completion.insert_on_select` option is set.
+#[derive(Debug, Default)]
+pub struct Preview {
+    /// The `(row, anchor, start_col, end_col)` of the previewed text, or
+    /// `None` if no completion is being previewed. The `anchor` is where the
+    /// text replaced by the preview starts, which is before the start of the
+    /// previewed completion if it matches fewer bytes than a previous one.
+    range: Option<(u32, u32, u32, u32)>,
+
+    /// The text replaced by the preview, i.e. the bytes between the anchor
+    /// and the cursor that were matched by the completions.
+    original: String,
+
+    /// The text after the cursor when the first completion was previewed,
+    /// whose first `post` bytes are replaced by the previewed completion.
+    after: String,
+
+    /// The `matched_bytes_post` of the previewed completion, i.e. how many
+    /// bytes of `after` it replaced.
+    post: u32,
+
+    /// The number of edits made by the preview whose `on_bytes` callback
+    /// hasn't been executed yet.
+    pending_edits: usize,
+}
+
+/// An edit of the buffer made by the preview, replacing the `start..end`
+/// byte range of the `row`-th line with `text` and moving the cursor to
+/// `cursor_col`.
+#[derive(Debug, PartialEq)]
+struct Edit {
+    row: u32,
+    start: u32,
+    end: u32,
+    text: String,
+    cursor_col: u32,
+}
+
+impl Preview {
+    /// Whether a completion is currently being previewed.
+    pub fn is_active(&self) -> bool { self.range.is_some() }
+
+    /// The `(row, start_col, end_col)` byte range of the previewed text.
+    pub fn range(&self) -> Option<(u32, u32, u32)> {
+        self.range.map(|(row, _, start, end)| (row, start, end))
+    }
+
+    /// Whether the cursor at the `(row, col)` position (with the row
+    /// 0-indexed) is where the preview left it.
+    pub fn is_cursor_at_end(&self, row: u32, col: u32) -> bool {
+        matches!(self.range, Some((r, _, _, end)) if r == row && end == col)
+    }
+
+    /// Called on every change to the buffer. Returns whether the change was
+    /// made by the preview, in which case it should be ignored.
+    pub fn is_own_edit(&mut self) -> bool {
+        if self.pending_edits == 0 {
+            return false;
+        }
+        self.pending_edits -= 1;
+        true
+    }
+
+    /// Replaces either the bytes matched by `completion` around the `cursor`
+    /// or the text of the previously previewed completion with the text of
+    /// `completion`, moving the cursor after it. The `matched_bytes_post`
+    /// after the cursor are replaced too, putting back the ones replaced by
+    /// the previous completion if it matched more of them. If `break_undo`
+    /// is set the first previewed text gets its own undo block.
+    pub fn show(
+        &mut self,
+        lua: &Lua,
+        completion: &CompletionItem,
+        cursor: &Cursor,
+        break_undo: bool,
+    ) -> LuaResult<()> {
+        let join = self.range.is_some() || !break_undo;
+        let edit = self.edit_for(completion, cursor);
+        self.edit(lua, edit, join)
+    }
+
+    /// The edit previewing `completion`. The text from the anchor to the
+    /// previewed completion's start is always written back from `original`,
+    /// so that moving between completions matching different numbers of
+    /// bytes doesn't delete or duplicate any of it.
+    fn edit_for(
+        &mut self,
+        completion: &CompletionItem,
+        cursor: &Cursor,
+    ) -> Edit {
+        let start = cursor.bytes - completion.matched_bytes;
+
+        let (anchor, end) = match self.range {
+            Some((_, anchor, _, end)) => (anchor, end),
+            None => {
+                let (pre, after) = cursor.line.split_at(cursor.bytes as usize);
+                self.original = pre[start as usize..].into();
+                self.after = after.into();
+                self.post = 0;
+                (start, cursor.bytes)
+            },
+        };
+
+        // The text before the anchor hasn't been replaced yet, so it's still
+        // the one of the cursor's line.
+        let anchor = match start < anchor {
+            true => {
+                let before = &cursor.line[start as usize..anchor as usize];
+                self.original.insert_str(0, before);
+                start
+            },
+            false => anchor,
+        };
+
+        let first_line = completion.text.lines().next().unwrap_or("");
+        let new_end = start + first_line.len() as u32;
+
+        let post = completion.matched_bytes_post.min(self.after.len() as u32);
+        let (extra, tail) = replaced_post(&self.after, self.post, post);
+        self.post = post;
+
+        let kept = &self.original[..(start - anchor) as usize];
+        let text = format!("{kept}{first_line}{tail}");
+
+        self.range = Some((cursor.row, anchor, start, new_end));
+
+        Edit {
+            row: cursor.row,
+            start: anchor,
+            end: end + extra,
+            text,
+            cursor_col: new_end,
+        }
+    }
+
+    /// The `matched_bytes_post` of the previewed completion that were
+    /// already replaced in the buffer.
+    pub fn replaced_post(&self) -> u32 { self.post }
+
+    /// Puts back the text that was replaced by the preview.
+    pub fn restore(&mut self, lua: &Lua) -> LuaResult<()> {
+        match self.restoring_edit() {
+            Some(edit) => self.edit(lua, edit, true),
+            None => Ok(()),
+        }
+    }
+
+    /// The edit writing `original` and the replaced bytes after the cursor
+    /// back at the anchor, ending the preview.
+    fn restoring_edit(&mut self) -> Option<Edit> {
+        let (row, anchor, _, end) = self.range.take()?;
+        let mut text = std::mem::take(&mut self.original);
+        let cursor_col = anchor + text.len() as u32;
+        text.push_str(&self.after[..self.post as usize]);
+        self.after.clear();
+        self.post = 0;
+        Some(Edit {
+            row,
+            start: anchor,
+            end,
+            text,
+            cursor_col,
+        })
+    }
+
+    /// Ends the preview, leaving the previewed text in the buffer.
+    pub fn commit(&mut self) {
+        self.range = None;
+        self.original.clear();
+        self.after.clear();
+        self.post = 0;
+    }
+
+    /// Schedules the `edit`, either joining it with the previous change or
+    /// starting a new undo block. The edit can't be made right away because
+    /// it would trigger `completion::on_bytes`, which would deadlock on the
+    /// state's Mutex.
+    fn edit(&mut self, lua: &Lua, edit: Edit, join: bool) -> LuaResult<()> {
+        self.pending_edits += 1;
+
+        let Edit {
+            row,
+            start,
+            end,
+            text,
+            cursor_col,
+        } = edit;
+
+        let edit = lua.create_function(move |lua, ()| {
+            let api = Neovim::new(lua)?.api;
+
+            // Join the edit with the change being inserted, so that a single
+            // undo removes both the typed and the previewed text. Otherwise
+            // undoing only removes the previewed text.
+            match join {
+                true => api.command("silent! undojoin")?,
+                false => super::break_undo_sequence(&api)?,
+            }
+
+            api.buf_set_text(0, row, start, row, end, slice::from_ref(&text))?;
+            api.win_set_cursor(0, row + 1, cursor_col)
+        })?;
+
+        Neovim::new(lua)?.schedule(edit)
+    }
+}
+
+/// Going from a previewed completion replacing the first `old` bytes of the
+/// text `after` the cursor to one replacing the first `new` ones, returns how
+/// many more bytes after the previewed text have to be replaced and the part
+/// of `after` to put back after the new completion.
+fn replaced_post(after: &str, old: u32, new: u32) -> (u32, &str) {
+    match new >= old {
+        true => (new - old, ""),
+        false => (0, &after[new as usize..old as usize]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{replaced_post, Edit, Preview};
+    use crate::completion::{CompletionItem, Cursor};
+
+    fn apply(line: &mut String, edit: Edit) {
+        line.replace_range(edit.start as usize..edit.end as usize, &edit.text);
+    }
+
+    #[test]
+    fn previewing_completions_matching_different_bytes() {
+        let cursor = Cursor {
+            bytes: 8,
+            keyword_start: None,
+            line: "x Foo.ba".into(),
+            row: 0,
+        };
+        let completion = |matched_bytes, text: &str| CompletionItem {
+            matched_bytes,
+            text: text.into(),
+            ..Default::default()
+        };
+
+        let mut preview = Preview::default();
+        let mut line = cursor.line.clone();
+
+        for (matched_bytes, text, expected) in [
+            (2, "bar", "x Foo.bar"),
+            (6, "foo.baz", "x foo.baz"),
+            (2, "bat", "x Foo.bat"),
+            (6, "Foo.bag", "x Foo.bag"),
+        ] {
+            let edit =
+                preview.edit_for(&completion(matched_bytes, text), &cursor);
+            assert_eq!(expected.len() as u32, edit.cursor_col);
+            apply(&mut line, edit);
+            assert_eq!(expected, line);
+        }
+
+        let edit = preview.restoring_edit().unwrap();
+        assert_eq!(8, edit.cursor_col);
+        apply(&mut line, edit);
+        assert_eq!("x Foo.ba", line);
+        assert!(!preview.is_active());
+    }
+
+    #[test]
+    fn replacing_the_bytes_after_the_cursor() {
+        // Nothing was replaced by the previous completion.
+        assert_eq!((3, ""), replaced_post("lo world", 0, 3));
+        assert_eq!((0, ""), replaced_post("lo world", 0, 0));
+
+        // The previous completion replaced more bytes, which are put back.
+        assert_eq!((0, "o"), replaced_post("lo world", 2, 1));
+        assert_eq!((0, "lo"), replaced_post("lo world", 2, 0));
+        assert_eq!((1, ""), replaced_post("lo world", 1, 2));
+    }
+}
@@ -0,0 +1,650 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::mem;
+
+use mlua::prelude::{Lua, LuaFunction, LuaResult, LuaTable, LuaValue};
+use neovim::Api;
+
+use super::{is_keyword_char, CompletionItem, Cursor, History};
+use crate::settings::completion::{Comparator, SortingSettings, COMPARATORS};
+
+/// Only the words within this many rows from the cursor are considered by the
+/// `locality` comparator.
+const LOCALITY_ROWS: u32 = 100;
+
+/// Everything the comparators need to know besides the completions
+/// themselves.
+struct Context<'a> {
+    history: &'a History,
+
+    /// A hashmap where the keys are the words around the cursor and the
+    /// values are their distance in rows from the cursor.
+    distances: HashMap<&'a str, u32>,
+
+    settings: &'a SortingSettings,
+
+    /// The filetype of the current buffer, only set if the `frecency`
+    /// comparator is used.
+    filetype: String,
+
+    /// The text of the line before the cursor.
+    line_pre: &'a str,
+
+    /// Compares two `(index, completion)` pairs using the `n`-th Lua
+    /// comparator. Only set if there are Lua functions in the comparators.
+    lua_compare: Option<&'a LuaCompare<'a>>,
+}
+
+/// A completion together with its index.
+type Indexed<'a> = (usize, &'a CompletionItem);
+
+type LuaCompare<'a> =
+    dyn Fn(usize, Indexed, Indexed) -> LuaResult<Ordering> + 'a;
+
+/// The Lua functions in the `comparators` list. The completions are
+/// converted to Lua tables the first time they're compared, and those are
+/// reused for the rest of the sort.
+struct LuaComparators<'lua> {
+    lua: &'lua Lua,
+
+    functions: Vec<LuaFunction<'lua>>,
+
+    /// The Lua tables of the completions, indexed like the completions.
+    tables: RefCell<Vec<Option<LuaTable<'lua>>>>,
+}
+
+impl<'lua> LuaComparators<'lua> {
+    fn new(lua: &'lua Lua, len: usize) -> LuaResult<Self> {
+        let functions = match lua
+            .named_registry_value::<_, Option<LuaTable>>(COMPARATORS)?
+        {
+            Some(functions) => functions
+                .sequence_values::<LuaFunction>()
+                .collect::<LuaResult<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+
+        Ok(LuaComparators {
+            lua,
+            functions,
+            tables: RefCell::new(vec![None; len]),
+        })
+    }
+
+    /// Compares the `i`-th completion `a` with the `j`-th completion `b`
+    /// using the `n`-th function. Like the comparators of `table.sort`, the
+    /// function returns a truthy value if its first argument should come
+    /// before the second one, so it's called a second time with the
+    /// arguments swapped to tell a tie from `b` coming first.
+    fn compare(
+        &self,
+        n: usize,
+        (i, a): Indexed,
+        (j, b): Indexed,
+    ) -> LuaResult<Ordering> {
+        let function = match self.functions.get(n) {
+            Some(function) => function,
+            None => return Ok(Ordering::Equal),
+        };
+
+        let is_before = |(i, a): Indexed, (j, b): Indexed| {
+            let value = function
+                .call::<_, LuaValue>((self.table(i, a)?, self.table(j, b)?))?;
+            Ok(!matches!(value, LuaValue::Nil | LuaValue::Boolean(false)))
+        };
+
+        ordering(|| is_before((i, a), (j, b)), || is_before((j, b), (i, a)))
+    }
+
+    fn table(
+        &self,
+        index: usize,
+        completion: &CompletionItem,
+    ) -> LuaResult<LuaTable<'lua>> {
+        if let Some(table) = &self.tables.borrow()[index] {
+            return Ok(table.clone());
+        }
+        let table = completion.to_lua(self.lua)?;
+        self.tables.borrow_mut()[index] = Some(table.clone());
+        Ok(table)
+    }
+}
+
+/// Turns the answers of a `table.sort`-style "less than" function into an
+/// ordering that's antisymmetric even when the function returns `false` for
+/// tied items in both directions. `b_is_before` is only called if
+/// `a_is_before` returns `false`.
+fn ordering(
+    a_is_before: impl FnOnce() -> LuaResult<bool>,
+    b_is_before: impl FnOnce() -> LuaResult<bool>,
+) -> LuaResult<Ordering> {
+    Ok(if a_is_before()? {
+        Ordering::Less
+    } else if b_is_before()? {
+        Ordering::Greater
+    } else {
+        Ordering::Equal
+    })
+}
+
+/// Sorts the completions by running the comparators set in `settings` one
+/// after the other, each one breaking the ties left by the previous ones.
+pub fn rank(
+    lua: &Lua,
+    api: &Api,
+    completions: &mut [CompletionItem],
+    cursor: &Cursor,
+    settings: &SortingSettings,
+    history: &History,
+) -> LuaResult<()> {
+    if completions.len() < 2 || settings.comparators.is_empty() {
+        return Ok(());
+    }
+
+    // Getting the lines around the cursor is only worth it if we're going to
+    // use them.
+    let lines = match settings.comparators.contains(&Comparator::Locality) {
+        true => {
+            let first_row = cursor.row.saturating_sub(LOCALITY_ROWS);
+            let last_row = cursor.row + LOCALITY_ROWS + 1;
+            let lines =
+                api.buf_get_lines(0, first_row, last_row as i32, false)?;
+            Some((first_row, lines))
+        },
+        false => None,
+    };
+
+    let distances = match &lines {
+        Some((first_row, lines)) => {
+            word_distances(lines, *first_row, cursor.row)
+        },
+        None => HashMap::new(),
+    };
+
+    let filetype = match settings.comparators.contains(&Comparator::Frecency) {
+        true => api.buf_get_option::<String>(0, "filetype")?,
+        false => String::new(),
+    };
+
+    let lua_comparators = match settings
+        .comparators
+        .iter()
+        .any(|comparator| matches!(comparator, Comparator::Lua(_)))
+    {
+        true => Some(LuaComparators::new(lua, completions.len())?),
+        false => None,
+    };
+
+    let lua_compare = |n, a: Indexed, b: Indexed| {
+        lua_comparators
+            .as_ref()
+            .map_or(Ok(Ordering::Equal), |c| c.compare(n, a, b))
+    };
+
+    let context = Context {
+        history,
+        distances,
+        settings,
+        filetype,
+        line_pre: &cursor.line[..cursor.bytes as usize],
+        lua_compare: lua_comparators
+            .as_ref()
+            .map(|_| &lua_compare as &LuaCompare),
+    };
+
+    sort(completions, &context)
+}
+
+/// Sorts the completions, leaving them in the order of their sources if a Lua
+/// comparator raises an error, which is returned.
+fn sort(
+    completions: &mut [CompletionItem],
+    context: &Context,
+) -> LuaResult<()> {
+    let priority = |item: &CompletionItem| {
+        context
+            .settings
+            .source_priority
+            .iter()
+            .position(|source| source.name() == item.source)
+            .unwrap_or(usize::MAX)
+    };
+
+    let distance = |item: &CompletionItem| {
+        context
+            .distances
+            .get(item.text.as_str())
+            .copied()
+            .unwrap_or(u32::MAX)
+    };
+
+    let last_used =
+        |item: &CompletionItem| context.history.last_used(&item.text);
+
+    // Looking up the frecency goes through the whole history, so it's done
+    // once per completion instead of once per comparison.
+    let frecencies =
+        match context.settings.comparators.contains(&Comparator::Frecency) {
+            true => completions
+                .iter()
+                .map(|item| {
+                    let line_pre = context.line_pre;
+                    let prefix = &line_pre[line_pre
+                        .len()
+                        .saturating_sub(item.matched_bytes as usize)..];
+                    context.history.frecency(
+                        &context.filetype,
+                        prefix,
+                        &item.text,
+                    )
+                })
+                .collect(),
+            false => Vec::new(),
+        };
+
+    // The indices of the completions are sorted instead of the completions
+    // themselves, so that the Lua comparators can cache their tables. The
+    // sort is stable, so completions that compare equal keep the order their
+    // sources returned them in.
+    let mut order = (0..completions.len()).collect::<Vec<usize>>();
+    let compare = |i: usize, j: usize| {
+        let (a, b) = (&completions[i], &completions[j]);
+        for comparator in &context.settings.comparators {
+            let ordering = match comparator {
+                Comparator::Alphabetical => a.text.cmp(&b.text),
+                Comparator::Frecency => frecencies[j].cmp(&frecencies[i]),
+                Comparator::Locality => distance(a).cmp(&distance(b)),
+                Comparator::RecentlyUsed => last_used(b).cmp(&last_used(a)),
+                Comparator::Score => b.score.cmp(&a.score),
+                Comparator::SourcePriority => priority(a).cmp(&priority(b)),
+                Comparator::Lua(n) => match context.lua_compare {
+                    Some(compare) => compare(*n, (i, a), (j, b))?,
+                    None => Ordering::Equal,
+                },
+            };
+            if ordering != Ordering::Equal {
+                return Ok(ordering);
+            }
+        }
+        Ok(Ordering::Equal)
+    };
+
+    // The Lua functions can fail and aren't guaranteed to be a total order,
+    // which `sort_by` may panic on.
+    match context.lua_compare {
+        Some(_) => merge_sort(&mut order, compare)?,
+        None => {
+            order.sort_by(|&i, &j| compare(i, j).unwrap_or(Ordering::Equal))
+        },
+    }
+
+    permute(completions, order);
+
+    Ok(())
+}
+
+/// A stable merge sort of the `order`, which stops at the first error of
+/// `compare`. Unlike `slice::sort_by` the result is a permutation of the
+/// items even if `compare` isn't a total order, and it never panics.
+fn merge_sort(
+    order: &mut Vec<usize>,
+    mut compare: impl FnMut(usize, usize) -> LuaResult<Ordering>,
+) -> LuaResult<()> {
+    let len = order.len();
+    let mut merged = Vec::with_capacity(len);
+    let mut width = 1;
+
+    while width < len {
+        merged.clear();
+        for start in (0..len).step_by(2 * width) {
+            let mid = (start + width).min(len);
+            let end = (start + 2 * width).min(len);
+            let (mut left, mut right) = (start, mid);
+            while left < mid && right < end {
+                // Taking the left one on ties keeps the sort stable.
+                match compare(order[right], order[left])? {
+                    Ordering::Less => {
+                        merged.push(order[right]);
+                        right += 1;
+                    },
+                    _ => {
+                        merged.push(order[left]);
+                        left += 1;
+                    },
+                }
+            }
+            merged.extend_from_slice(&order[left..mid]);
+            merged.extend_from_slice(&order[right..end]);
+        }
+        mem::swap(order, &mut merged);
+        width *= 2;
+    }
+
+    Ok(())
+}
+
+/// Reorders the `items` in place so that the `i`-th one becomes the one that
+/// was at `order[i]`, following the cycles of the permutation.
+fn permute<T>(items: &mut [T], mut order: Vec<usize>) {
+    for start in 0..items.len() {
+        let mut current = start;
+        loop {
+            let next = order[current];
+            order[current] = current;
+            if next == start {
+                break;
+            }
+            items.swap(current, next);
+            current = next;
+        }
+    }
+}
+
+/// Returns a hashmap where the keys are the words contained in `lines`, the
+/// first of which is the `first_row`-th line of the buffer, and the values
+/// are the minimum distance in rows between the word and `cursor_row`.
+fn word_distances(
+    lines: &[String],
+    first_row: u32,
+    cursor_row: u32,
+) -> HashMap<&str, u32> {
+    let mut distances = HashMap::new();
+
+    for (offset, line) in lines.iter().enumerate() {
+        let distance = (first_row + offset as u32).abs_diff(cursor_row);
+
+        for word in line.split(|c| !is_keyword_char(c)) {
+            if word.is_empty() {
+                continue;
+            }
+            distances
+                .entry(word)
+                .and_modify(|d: &mut u32| *d = (*d).min(distance))
+                .or_insert(distance);
+        }
+    }
+
+    distances
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+    use std::collections::HashMap;
+
+    use mlua::prelude::LuaError;
+
+    use super::{
+        merge_sort,
+        ordering,
+        permute,
+        sort,
+        word_distances,
+        Context,
+        History,
+        Indexed,
+        LuaCompare,
+    };
+    use crate::completion::CompletionItem;
+    use crate::settings::completion::{Comparator, SortingSettings};
+    use crate::settings::sources::CompletionSource;
+
+    fn item(text: &str, score: i32, source: &'static str) -> CompletionItem {
+        CompletionItem {
+            format: format!(" {text}"),
+            score,
+            source,
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    fn sorted(
+        mut completions: Vec<CompletionItem>,
+        context: &Context,
+    ) -> Vec<String> {
+        sort(&mut completions, context).unwrap();
+        completions.into_iter().map(|c| c.text).collect()
+    }
+
+    #[test]
+    fn comparators_break_ties_in_order() {
+        let settings = SortingSettings {
+            comparators: vec![Comparator::Score, Comparator::Alphabetical],
+            source_priority: Vec::new(),
+            persist_frecency: false,
+        };
+        let history = History::default();
+        let context = Context {
+            history: &history,
+            distances: HashMap::new(),
+            settings: &settings,
+            filetype: String::new(),
+            line_pre: "",
+            lua_compare: None,
+        };
+
+        assert_eq!(
+            vec!["bar", "baz", "foo"],
+            sorted(
+                vec![
+                    item("foo", 1, "Buffer"),
+                    item("baz", 2, "Buffer"),
+                    item("bar", 2, "Buffer")
+                ],
+                &context
+            )
+        );
+    }
+
+    #[test]
+    fn recently_used_and_source_priority() {
+        let settings = SortingSettings {
+            comparators: vec![
+                Comparator::RecentlyUsed,
+                Comparator::SourcePriority,
+            ],
+            source_priority: vec![
+                CompletionSource::Lsp,
+                CompletionSource::Path,
+            ],
+            persist_frecency: false,
+        };
+        let mut history = History::default();
+        history.record("", "", "foo");
+        history.record("", "", "bar");
+        let context = Context {
+            history: &history,
+            distances: HashMap::new(),
+            settings: &settings,
+            filetype: String::new(),
+            line_pre: "",
+            lua_compare: None,
+        };
+
+        assert_eq!(
+            vec!["bar", "foo", "qux", "baz", "quux"],
+            sorted(
+                vec![
+                    item("quux", 0, "Buffer"),
+                    item("baz", 0, "Path"),
+                    item("qux", 0, "Lsp"),
+                    item("foo", 0, "Buffer"),
+                    item("bar", 0, "Buffer"),
+                ],
+                &context
+            )
+        );
+    }
+
+    #[test]
+    fn frecency() {
+        let settings = SortingSettings {
+            comparators: vec![Comparator::Frecency],
+            source_priority: Vec::new(),
+            persist_frecency: false,
+        };
+        let mut history = History::default();
+        history.record("rust", "fo", "foobar");
+        history.record("rust", "fo", "foobar");
+        history.record("rust", "fo", "foobaz");
+        history.record("lua", "fo", "fooqux");
+        history.record("lua", "fo", "fooqux");
+        history.record("lua", "fo", "fooqux");
+        let context = Context {
+            history: &history,
+            distances: HashMap::new(),
+            settings: &settings,
+            filetype: "rust".into(),
+            line_pre: "let fo",
+            lua_compare: None,
+        };
+
+        let matching = |text| CompletionItem {
+            matched_bytes: 2,
+            ..item(text, 0, "Buffer")
+        };
+
+        assert_eq!(
+            vec!["foobar", "foobaz", "fooqux"],
+            sorted(
+                vec![
+                    matching("fooqux"),
+                    matching("foobaz"),
+                    matching("foobar")
+                ],
+                &context
+            )
+        );
+    }
+
+    #[test]
+    fn locality() {
+        let lines = vec![
+            "let foo = 1;".into(),
+            "".into(),
+            "fooo".into(),
+            "foobar".into(),
+        ];
+        let distances = word_distances(&lines, 10, 12);
+        assert_eq!(Some(&2), distances.get("foo"));
+        assert_eq!(Some(&0), distances.get("fooo"));
+        assert_eq!(Some(&1), distances.get("foobar"));
+
+        let settings = SortingSettings {
+            comparators: vec![Comparator::Locality],
+            source_priority: Vec::new(),
+            persist_frecency: false,
+        };
+        let history = History::default();
+        let context = Context {
+            history: &history,
+            distances,
+            settings: &settings,
+            filetype: String::new(),
+            line_pre: "",
+            lua_compare: None,
+        };
+
+        assert_eq!(
+            vec!["fooo", "foobar", "foo", "foooo"],
+            sorted(
+                vec![
+                    item("foooo", 0, "Buffer"),
+                    item("foo", 0, "Buffer"),
+                    item("foobar", 0, "Buffer"),
+                    item("fooo", 0, "Buffer"),
+                ],
+                &context
+            )
+        );
+    }
+
+    #[test]
+    fn ties_of_less_than_comparators() {
+        // Like `function(a, b) return a.score < b.score end`, which returns
+        // `false` both ways for items with the same score.
+        let less_than = |_: usize, (_, a): Indexed, (_, b): Indexed| {
+            ordering(|| Ok(a.score < b.score), || Ok(b.score < a.score))
+        };
+
+        let (a, b) = (item("a", 1, "Buffer"), item("b", 1, "Buffer"));
+        assert_eq!(Ordering::Equal, less_than(0, (0, &a), (1, &b)).unwrap());
+        assert_eq!(Ordering::Equal, less_than(0, (1, &b), (0, &a)).unwrap());
+
+        let settings = SortingSettings {
+            comparators: vec![Comparator::Lua(0), Comparator::Alphabetical],
+            source_priority: Vec::new(),
+            persist_frecency: false,
+        };
+        let history = History::default();
+        let context = Context {
+            history: &history,
+            distances: HashMap::new(),
+            settings: &settings,
+            filetype: String::new(),
+            line_pre: "",
+            lua_compare: Some(&less_than as &LuaCompare),
+        };
+
+        let mut completions = (0..50)
+            .map(|i| item(&format!("item{:02}", 49 - i), i % 3, "Buffer"))
+            .collect::<Vec<_>>();
+        completions.push(item("item50", 0, "Buffer"));
+
+        let sorted = sorted(completions, &context);
+        assert_eq!(["item01", "item04", "item07"], sorted[..3]);
+        assert_eq!("item50", sorted[17]);
+        assert_eq!(["item44", "item47"], sorted[49..]);
+    }
+
+    #[test]
+    fn inconsistent_and_failing_lua_comparators() {
+        // Not a total order: everything comes before everything else.
+        let mut order = (0..40).collect::<Vec<usize>>();
+        merge_sort(&mut order, |_, _| Ok(Ordering::Less)).unwrap();
+        order.sort_unstable();
+        assert_eq!((0..40).collect::<Vec<usize>>(), order);
+
+        // The first error stops the sort and the sources' order is kept.
+        let failing = |_: usize, _: Indexed, _: Indexed| {
+            Err(LuaError::RuntimeError("oops".into()))
+        };
+        let settings = SortingSettings {
+            comparators: vec![Comparator::Score, Comparator::Lua(0)],
+            source_priority: Vec::new(),
+            persist_frecency: false,
+        };
+        let history = History::default();
+        let context = Context {
+            history: &history,
+            distances: HashMap::new(),
+            settings: &settings,
+            filetype: String::new(),
+            line_pre: "",
+            lua_compare: Some(&failing as &LuaCompare),
+        };
+
+        let mut completions = vec![
+            item("b", 1, "Buffer"),
+            item("a", 2, "Buffer"),
+            item("c", 2, "Buffer"),
+        ];
+        assert!(sort(&mut completions, &context).is_err());
+        assert_eq!(
+            vec!["b", "a", "c"],
+            completions
+                .iter()
+                .map(|c| c.text.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn permutations() {
+        let mut items = vec!['a', 'b', 'c', 'd', 'e'];
+        permute(&mut items, vec![2, 0, 1, 4, 3]);
+        assert_eq!(vec!['c', 'a', 'b', 'e', 'd'], items);
+    }
+}
@@ -0,0 +1,96 @@
+use mlua::{prelude::LuaResult, Lua};
+use neovim::Neovim;
+
+use crate::state::State;
+
+/// Executed on every `User CompleetSourceUpdate` event, which is fired by
+/// asynchronous sources (like the LSP one) when new results become available
+/// for the current cursor position.
+pub fn refresh(lua: &Lua, state: &mut State) -> LuaResult<()> {
+    let api = Neovim::new(lua)?.api;
+
+    // The results may have arrived after the user left insert mode or
+    // switched to a different buffer, in which case there's nothing to do.
+    let bufnr = api.get_current_buf()?;
+    if !state.attached_buffers.contains(&bufnr)
+        || !super::is_insert_mode(lua, &api.get_mode()?.0, state)?
+        || !super::is_autocompleting(state)
+    {
+        return Ok(());
+    }
+
+    state.follow_current_window(lua, &api)?;
+
+    // The new results are merged with the ones already shown, which can
+    // move the selected completion to a different row. The selection follows
+    // it instead of staying on the same row.
+    let selected = state
+        .phase()
+        .selected()
+        .and_then(|i| state.buffers.current().completions.get(i))
+        .map(|c| (c.text.clone(), c.source));
+
+    let cursor = &mut state.buffers.current_mut().cursor;
+
+    let (row, bytes) = api.win_get_cursor(0)?;
+    cursor.row = row - 1;
+    cursor.bytes = bytes;
+    cursor.line = api.get_current_line()?;
+    state.keyword_pattern.update(lua, cursor)?;
+
+    let sources = state
+        .sources
+        .get(&bufnr)
+        .expect("The buffer is attached so it has sources");
+
+    // Like in `completion::on_bytes`, keywords shorter than
+    // `completion.min_chars` are only completed after a trigger character.
+    if cursor.keyword_pre().chars().count()
+        < usize::from(state.settings.completion.min_chars)
+        && !super::is_triggered(lua, &api, sources, cursor)?
+    {
+        return Ok(());
+    }
+
+    let completions = state.pipeline.run(
+        lua,
+        &api,
+        sources,
+        cursor,
+        &state.settings.completion,
+        &state.history,
+    )?;
+    state.buffers.current_mut().set_completions(completions);
+
+    if let Some((text, source)) = selected {
+        if let Some(index) = state
+            .buffers
+            .current()
+            .completions
+            .iter()
+            .position(|c| c.text == text && c.source == source)
+        {
+            state.ui.completion_menu.selected_index = Some(index);
+        }
+    }
+
+    state.update_ui(lua, &api)?;
+
+    super::resolve_selected(lua, &api, state)
+}
+
+/// Schedules a `User CompleetSourceUpdate` event, which will cause `refresh`
+/// to be executed. Used by asynchronous sources to signal that they have new
+/// results. Scheduling makes it safe to call this from fast event contexts
+/// (e.g. libuv callbacks) and from places where the state is locked.
+pub fn schedule_refresh(lua: &Lua) -> LuaResult<()> {
+    let fire_autocmd = lua.create_function(|lua, ()| {
+        let api = Neovim::new(lua)?.api;
+        let opts = lua.create_table_with_capacity(0, 2)?;
+        opts.set("pattern", "CompleetSourceUpdate")?;
+        opts.set("modeline", false)?;
+        api.exec_autocmds(&["User"], opts)
+    })?;
+
+    Neovim::new(lua)?.schedule(fire_autocmd)
+}
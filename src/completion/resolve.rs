@@ -0,0 +1,31 @@
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Api;
+
+use crate::state::State;
+
+/// Asks the source of the selected completion to resolve it, which lets the
+/// source fetch the details it didn't send upfront. Executed every time the
+/// selected completion changes.
+pub fn resolve_selected(lua: &Lua, api: &Api, state: &State) -> LuaResult<()> {
+    let completion = match state
+        .phase()
+        .selected()
+        .and_then(|index| state.buffers.current().completions.get(index))
+    {
+        Some(completion) => completion,
+        None => return Ok(()),
+    };
+
+    let source =
+        state
+            .sources
+            .get(&api.get_current_buf()?)
+            .and_then(|sources| {
+                sources.iter().find(|s| s.name() == completion.source)
+            });
+
+    match source {
+        Some(source) => source.resolve(lua, api, completion),
+        None => Ok(()),
+    }
+}
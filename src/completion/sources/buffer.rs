@@ -0,0 +1,359 @@
+use std::cmp;
+use std::collections::HashMap;
+use std::iter;
+use std::sync::{Arc, Mutex};
+
+use mlua::prelude::{Lua, LuaResult};
+use neovim::{Api, Neovim};
+use serde::Deserialize;
+
+use crate::completion::{
+    is_keyword_char,
+    matcher,
+    Cancellation,
+    CompletionItem,
+    CompletionSource,
+    Cursor,
+    Job,
+};
+use crate::settings::sources::{Contexts, Enable};
+
+/// Words shorter than this many characters are not indexed.
+const MIN_WORD_LENGTH: usize = 3;
+
+/// How many words to match before checking whether the job was cancelled.
+const CANCELLATION_CHECK_INTERVAL: usize = 256;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Buffer {
+    pub enable: Enable,
+
+    #[serde(default)]
+    pub contexts: Contexts,
+
+    /// Whether to also complete words coming from the other buffers the
+    /// source is attached to.
+    #[serde(default)]
+    pub all_buffers: bool,
+
+    /// A hashmap where the keys are the numbers of the buffers the source is
+    /// attached to and the values are the indexes of the words contained in
+    /// those buffers.
+    #[serde(skip)]
+    indexes: Arc<Mutex<HashMap<u32, WordIndex>>>,
+}
+
+#[derive(Debug, Default)]
+struct WordIndex {
+    /// The number of occurrences of every indexed word.
+    counts: HashMap<String, u32>,
+
+    /// Whether at least one line has changed since the last time the buffer
+    /// was indexed.
+    is_dirty: bool,
+
+    /// The words contained in every line of the buffer, or `None` if the line
+    /// has changed since the last time it was indexed.
+    lines: Vec<Option<Vec<String>>>,
+}
+
+impl CompletionSource for Buffer {
+    fn name(&self) -> &'static str { "Buffer" }
+
+    fn is_expensive(&self) -> bool { true }
+
+    fn attach(&self, lua: &Lua, api: &Api, bufnr: u32) -> LuaResult<bool> {
+        let indexes = &mut *self.indexes.lock().unwrap();
+
+        if indexes.contains_key(&bufnr) {
+            return Ok(true);
+        }
+
+        let lines = api.buf_get_lines(bufnr, 0, -1, false)?;
+        indexes.insert(bufnr, WordIndex::new(&lines));
+
+        // Every time some lines are changed we mark them as dirty. This is
+        // cheap, so it's ok to do it synchronously on every change.
+        let _indexes = self.indexes.clone();
+        let on_lines = lua.create_function(
+            move |_,
+                  (_, bufnr, _, first, last, new_last): (
+                String,
+                u32,
+                u32,
+                usize,
+                usize,
+                usize,
+            )| {
+                match _indexes.lock().unwrap().get_mut(&bufnr) {
+                    Some(index) => {
+                        index.splice(first, last, new_last);
+                        Ok(false)
+                    },
+                    // Returning `true` detaches from the buffer.
+                    None => Ok(true),
+                }
+            },
+        )?;
+
+        let _indexes = self.indexes.clone();
+        let on_detach =
+            lua.create_function(move |_, (_, bufnr): (String, u32)| {
+                _indexes.lock().unwrap().remove(&bufnr);
+                Ok(())
+            })?;
+
+        let opts = lua.create_table_with_capacity(0, 2)?;
+        opts.set("on_lines", on_lines)?;
+        opts.set("on_detach", on_detach)?;
+        api.buf_attach(bufnr, false, opts)?;
+
+        // The dirty lines are only re-indexed once Neovim is done changing
+        // the text.
+        let _indexes = self.indexes.clone();
+        let reindex = lua.create_function(move |lua, ()| {
+            match _indexes.lock().unwrap().get_mut(&bufnr) {
+                Some(index) => index.reindex(&Neovim::new(lua)?.api, bufnr),
+                None => Ok(()),
+            }
+        })?;
+
+        let opts = lua.create_table_from([("clear", false)])?;
+        let augroup_id = api.create_augroup("CompleetBufferSource", opts)?;
+
+        // The autocmd of a previous attach would reindex the buffer again on
+        // every change.
+        let opts = lua.create_table_with_capacity(0, 2)?;
+        opts.set("group", augroup_id)?;
+        opts.set("buffer", bufnr)?;
+        api.clear_autocmds(opts)?;
+
+        let opts = lua.create_table_with_capacity(0, 3)?;
+        opts.set("group", augroup_id)?;
+        opts.set("buffer", bufnr)?;
+        opts.set("callback", reindex)?;
+        api.create_autocmd(&["TextChanged", "TextChangedI"], opts)?;
+
+        Ok(true)
+    }
+
+    fn complete(
+        &self,
+        lua: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Vec<CompletionItem>> {
+        Ok(match self.complete_in_background(lua, api, cursor)? {
+            Some(job) => job(&Cancellation::never()),
+            None => Vec::new(),
+        })
+    }
+
+    fn complete_in_background(
+        &self,
+        _: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Option<Job>> {
+        let prefix = cursor.keyword_pre().to_owned();
+
+        if prefix.is_empty() {
+            return Ok(None);
+        }
+
+        let bufnr = api.get_current_buf()?;
+        let all_buffers = self.all_buffers;
+        let indexes = self.indexes.clone();
+
+        Ok(Some(Box::new(move |cancellation| {
+            // The words are copied out of the indexes right away so that the
+            // lock isn't held while matching, which would block the
+            // `on_lines` callbacks running on the main thread.
+            let mut words = indexes
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(&b, _)| all_buffers || b == bufnr)
+                .flat_map(|(_, index)| index.counts.keys())
+                .filter(|&word| word != &prefix)
+                .cloned()
+                .collect::<Vec<String>>();
+
+            words.sort();
+            words.dedup();
+
+            let mut completions = Vec::new();
+
+            for (i, word) in words.into_iter().enumerate() {
+                if i % CANCELLATION_CHECK_INTERVAL == 0
+                    && cancellation.is_cancelled()
+                {
+                    return Vec::new();
+                }
+
+                if let Some(m) = matcher::fuzzy_match(&prefix, &word) {
+                    completions.push(CompletionItem {
+                        details: None,
+                        format: format!(" {}", word),
+                        hl_ranges: m.hl_ranges(1),
+                        kind: Some("Text"),
+                        matched_bytes: prefix.len() as u32,
+                        preselect: false,
+                        score: m.score,
+                        snippet: None,
+                        source: "Buffer",
+                        text: word,
+                        ..Default::default()
+                    });
+                }
+            }
+
+            completions
+        })))
+    }
+}
+
+impl WordIndex {
+    fn new(lines: &[String]) -> Self {
+        let mut index = WordIndex {
+            lines: vec![None; lines.len()],
+            ..Default::default()
+        };
+        for (row, line) in lines.iter().enumerate() {
+            index.set_line(row, line);
+        }
+        index
+    }
+
+    /// Indexes the words contained in the `row`-th line.
+    fn set_line(&mut self, row: usize, line: &str) {
+        if row >= self.lines.len() {
+            return;
+        }
+
+        if let Some(old) = self.lines[row].take() {
+            self.remove_words(&old);
+        }
+
+        let words = words(line).map(String::from).collect::<Vec<String>>();
+
+        for word in &words {
+            *self.counts.entry(word.clone()).or_insert(0) += 1;
+        }
+
+        self.lines[row] = Some(words);
+    }
+
+    /// Decrements the number of occurrences of every word in `words`,
+    /// removing the ones that don't appear in the buffer anymore.
+    fn remove_words(&mut self, words: &[String]) {
+        for word in words {
+            if let Some(count) = self.counts.get_mut(word) {
+                *count -= 1;
+                if *count == 0 {
+                    self.counts.remove(word);
+                }
+            }
+        }
+    }
+
+    /// Replaces the lines in the `first..last` range with `new_last - first`
+    /// dirty lines. The arguments have the same meaning as the ones passed to
+    /// the `on_lines` callback of `nvim_buf_attach`.
+    fn splice(&mut self, first: usize, last: usize, new_last: usize) {
+        let first = cmp::min(first, self.lines.len());
+        let last = cmp::min(cmp::max(first, last), self.lines.len());
+
+        let removed = self
+            .lines
+            .splice(
+                first..last,
+                iter::repeat_with(|| None)
+                    .take(new_last.saturating_sub(first)),
+            )
+            .flatten()
+            .collect::<Vec<Vec<String>>>();
+
+        for words in removed {
+            self.remove_words(&words);
+        }
+
+        self.is_dirty = true;
+    }
+
+    /// Re-indexes all the lines that have changed since the last time the
+    /// buffer was indexed.
+    fn reindex(&mut self, api: &Api, bufnr: u32) -> LuaResult<()> {
+        if !self.is_dirty {
+            return Ok(());
+        }
+
+        let mut row = 0;
+        while row < self.lines.len() {
+            if self.lines[row].is_some() {
+                row += 1;
+                continue;
+            }
+
+            // Get all the contiguous dirty lines with a single API call.
+            let end = (row..self.lines.len())
+                .find(|&r| self.lines[r].is_some())
+                .unwrap_or(self.lines.len());
+
+            let lines =
+                api.buf_get_lines(bufnr, row as u32, end as i32, false)?;
+
+            for (offset, line) in lines.iter().enumerate() {
+                self.set_line(row + offset, line);
+            }
+
+            row = end;
+        }
+
+        self.is_dirty = false;
+
+        Ok(())
+    }
+}
+
+/// Returns an iterator over the words in `line` that are long enough to be
+/// indexed.
+pub(super) fn words(line: &str) -> impl Iterator<Item = &str> {
+    line.split(|c| !is_keyword_char(c))
+        .filter(|word| word.chars().count() >= MIN_WORD_LENGTH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{words, WordIndex};
+
+    #[test]
+    fn words_split_on_punctuation() {
+        assert_eq!(
+            vec!["foo", "bar", "baz_1"],
+            words("foo.bar(baz_1, x)").collect::<Vec<&str>>()
+        );
+    }
+
+    #[test]
+    fn words_multibyte_chars() {
+        assert_eq!(vec!["föö"], words("föö ö").collect::<Vec<&str>>());
+    }
+
+    #[test]
+    fn splice_removes_changed_words() {
+        let mut index = WordIndex::new(&["foo bar".into(), "bar baz".into()]);
+        assert_eq!(Some(&2), index.counts.get("bar"));
+
+        // Change the first line and add a new line after it.
+        index.splice(0, 1, 2);
+        assert_eq!(3, index.lines.len());
+        assert_eq!(None, index.counts.get("foo"));
+        assert_eq!(Some(&1), index.counts.get("bar"));
+
+        index.set_line(0, "foo");
+        index.set_line(1, "qux");
+        assert_eq!(Some(&1), index.counts.get("foo"));
+        assert_eq!(Some(&1), index.counts.get("qux"));
+    }
+}
@@ -0,0 +1,303 @@
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Api;
+use serde::Deserialize;
+
+use crate::completion::{CompletionItem, CompletionSource, Cursor, Details};
+use crate::settings::sources::{Contexts, Enable};
+
+/// The maximum length in bytes of the expressions that are evaluated, since
+/// they're parsed again on every keystroke.
+const MAX_EXPRESSION_LEN: usize = 256;
+
+/// The maximum nesting depth of the parser, which would overflow the stack
+/// on expressions like a long run of `(`.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Calc {
+    pub enable: Enable,
+
+    #[serde(default)]
+    pub contexts: Contexts,
+}
+
+impl CompletionSource for Calc {
+    fn name(&self) -> &'static str { "Calc" }
+
+    fn attach(&self, _: &Lua, _: &Api, _: u32) -> LuaResult<bool> { Ok(true) }
+
+    fn trigger_characters(&self, _: &Lua, _: &Api) -> LuaResult<Vec<String>> {
+        Ok(vec!["=".into()])
+    }
+
+    fn complete(
+        &self,
+        _: &Lua,
+        _: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Vec<CompletionItem>> {
+        let line_pre = &cursor.line[..cursor.bytes as usize];
+
+        let (start, expr, value) = match expression_before(line_pre) {
+            Some(found) => found,
+            None => return Ok(Vec::new()),
+        };
+
+        let result = format_number(value);
+
+        Ok(vec![CompletionItem {
+            details: Some(Details {
+                lines: vec![format!("{expr} = {result}")],
+                is_markdown: false,
+            }),
+            format: format!(" {result}"),
+            hl_ranges: Vec::new(),
+            kind: Some("Value"),
+            // The expression is replaced together with the `=`.
+            matched_bytes: (line_pre.len() - start) as u32,
+            preselect: false,
+            score: 0,
+            snippet: None,
+            source: "Calc",
+            text: result,
+            ..Default::default()
+        }])
+    }
+}
+
+/// Returns the arithmetic expression ending right before the `=` at the end
+/// of `line_pre` together with its byte offset and its value, or `None` if
+/// there isn't one. The longest expression that can be evaluated wins, so
+/// that in `f(1+2=` the unbalanced parenthesis is left out. Expressions longer
+/// than `MAX_EXPRESSION_LEN` bytes aren't evaluated.
+fn expression_before(line_pre: &str) -> Option<(usize, &str, f64)> {
+    let text = line_pre.strip_suffix('=')?;
+
+    let start = text
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| c.is_ascii_digit() || "+-*/%^(). ".contains(c))
+        .last()
+        .map(|(i, _)| i)?;
+
+    if text.len() - start > MAX_EXPRESSION_LEN {
+        return None;
+    }
+
+    text.char_indices()
+        .filter(|&(i, c)| i >= start && c != ' ')
+        .find_map(|(i, _)| {
+            let expr = text[i..].trim_end();
+            Parser::new(expr).parse().map(|value| (i, expr, value))
+        })
+}
+
+/// Formats a number without a fractional part if it's an integer, and
+/// rounded to 10 decimal places otherwise.
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        return format!("{}", value as i64);
+    }
+
+    let rounded = format!("{value:.10}");
+    rounded
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_owned()
+}
+
+/// A recursive descent parser for arithmetic expressions made of numbers,
+/// parentheses, the binary operators `+`, `-`, `*`, `/`, `%`, `^` and the
+/// unary `-`. Expressions nested deeper than `MAX_DEPTH` aren't parsed.
+struct Parser<'a> {
+    text: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+
+    /// Whether an operator has been parsed. A lone number isn't something to
+    /// calculate.
+    has_operators: bool,
+
+    /// How many calls to `unary`, which every nested expression goes
+    /// through, are currently running.
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Parser {
+            text,
+            chars: text.char_indices().peekable(),
+            has_operators: false,
+            depth: 0,
+        }
+    }
+
+    /// Evaluates the whole text, returning `None` if it's not a valid
+    /// expression or if its value is not a finite number.
+    fn parse(mut self) -> Option<f64> {
+        let value = self.expr()?;
+        self.skip_whitespace();
+        (self.chars.peek().is_none()
+            && self.has_operators
+            && value.is_finite())
+        .then_some(value)
+    }
+
+    /// expr := term (('+' | '-') term)*
+    fn expr(&mut self) -> Option<f64> {
+        let mut value = self.term()?;
+        while let Some(op) = self.eat_any("+-") {
+            let rhs = self.term()?;
+            value = if op == '+' { value + rhs } else { value - rhs };
+        }
+        Some(value)
+    }
+
+    /// term := unary (('*' | '/' | '%') unary)*
+    fn term(&mut self) -> Option<f64> {
+        let mut value = self.unary()?;
+        while let Some(op) = self.eat_any("*/%") {
+            let rhs = self.unary()?;
+            if op != '*' && rhs == 0.0 {
+                return None;
+            }
+            value = match op {
+                '*' => value * rhs,
+                '/' => value / rhs,
+                _ => value % rhs,
+            };
+        }
+        Some(value)
+    }
+
+    /// unary := '-' unary | power
+    fn unary(&mut self) -> Option<f64> {
+        if self.depth == MAX_DEPTH {
+            return None;
+        }
+        self.depth += 1;
+        let value = match self.eat_any("-") {
+            Some(_) => self.unary().map(|value| -value),
+            None => self.power(),
+        };
+        self.depth -= 1;
+        value
+    }
+
+    /// power := atom ('^' unary)?
+    fn power(&mut self) -> Option<f64> {
+        let base = self.atom()?;
+        match self.eat_any("^") {
+            Some(_) => self.unary().map(|exp| base.powf(exp)),
+            None => Some(base),
+        }
+    }
+
+    /// atom := number | '(' expr ')'
+    fn atom(&mut self) -> Option<f64> {
+        self.skip_whitespace();
+
+        if self.eat_any("(").is_some() {
+            // Parentheses count as operators since `(2)=` is also worth
+            // completing.
+            self.has_operators = true;
+            let value = self.expr()?;
+            self.eat_any(")")?;
+            return Some(value);
+        }
+
+        let &(start, _) = self.chars.peek()?;
+        let mut end = start;
+        while let Some(&(i, c)) = self.chars.peek() {
+            if !(c.is_ascii_digit() || c == '.') {
+                break;
+            }
+            end = i + 1;
+            self.chars.next();
+        }
+
+        self.text[start..end].parse().ok()
+    }
+
+    /// Consumes the next non-whitespace character if it's one of `ops`.
+    fn eat_any(&mut self, ops: &str) -> Option<char> {
+        self.skip_whitespace();
+        let &(_, c) = self.chars.peek()?;
+        if !ops.contains(c) {
+            return None;
+        }
+        self.chars.next();
+        if c != '(' && c != ')' {
+            self.has_operators = true;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|&(_, c)| c == ' ').is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expression_before, format_number, Parser};
+
+    fn eval(expr: &str) -> Option<f64> { Parser::new(expr).parse() }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(Some(14.0), eval("2*(3+4)"));
+        assert_eq!(Some(10.0), eval("2*3+4"));
+        assert_eq!(Some(-1.0), eval("1 - 2"));
+        assert_eq!(Some(512.0), eval("2^3^2"));
+        assert_eq!(Some(-4.0), eval("-2^2"));
+        assert_eq!(Some(1.0), eval("7 % 3"));
+        assert_eq!(Some(2.5), eval("5 / 2"));
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(None, eval("42"));
+        assert_eq!(None, eval("1 / 0"));
+        assert_eq!(None, eval("(1 + 2"));
+        assert_eq!(None, eval("1 +"));
+        assert_eq!(None, eval("1..2 + 3"));
+    }
+
+    #[test]
+    fn limits() {
+        let nested = format!("{}1{}", "(".repeat(63), ")".repeat(63));
+        assert_eq!(Some(1.0), eval(&nested));
+        let nested = format!("{}1{}", "(".repeat(64), ")".repeat(64));
+        assert_eq!(None, eval(&nested));
+        assert_eq!(None, eval(&"-".repeat(100_000)));
+
+        let long = format!("{}=", "(".repeat(100_000));
+        assert_eq!(None, expression_before(&long));
+        let long = format!("{}1=", "1+".repeat(200));
+        assert_eq!(None, expression_before(&long));
+        let long = format!("{}1=", "1+".repeat(100));
+        assert_eq!(Some(101.0), expression_before(&long).map(|(.., v)| v));
+    }
+
+    #[test]
+    fn expressions() {
+        assert_eq!(Some((0, "2*(3+4)", 14.0)), expression_before("2*(3+4)="));
+        assert_eq!(Some((5, "1 + 2", 3.0)), expression_before("x is 1 + 2 ="));
+        assert_eq!(Some((2, "1+2", 3.0)), expression_before("f(1+2="));
+        assert_eq!(None, expression_before("x = 3="));
+        assert_eq!(None, expression_before("let x ="));
+        assert_eq!(None, expression_before("1 + 2"));
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!("14", format_number(14.0));
+        assert_eq!("-3", format_number(-3.0));
+        assert_eq!("0.3", format_number(0.1 + 0.2));
+        assert_eq!("0.3333333333", format_number(1.0 / 3.0));
+    }
+}
@@ -0,0 +1,250 @@
+use mlua::prelude::{Lua, LuaFunction, LuaResult, LuaTable, LuaValue};
+use neovim::{Api, Neovim};
+
+use crate::completion::{CompletionItem, CompletionSource, Cursor, Job};
+use crate::health::Report;
+use crate::settings::sources::{Condition, Contexts, Enable, PREDICATES};
+
+/// The key of the Lua registry where the function returning the highlight
+/// captures at a position is stored.
+const CAPTURES_AT: &str = "compleet_captures_at";
+
+/// Returns a function returning the names of the captures of the
+/// `highlights` queries matching the 0-indexed `(row, col)` position of a
+/// buffer, or `nil` if the buffer doesn't have a treesitter parser.
+/// `vim.treesitter.get_captures_at_pos` isn't available in Neovim 0.7, so this
+/// does the same thing. The captures of the last position are cached until
+/// the buffer changes, so that they're only computed once for all the
+/// conditional sources.
+const CAPTURES_AT_CHUNK: &str = r#"
+local get_query = vim.treesitter.query.get or vim.treesitter.query.get_query
+local last_key, last_captures
+
+local function captures_at(bufnr, row, col)
+  local ok, parser = pcall(vim.treesitter.get_parser, bufnr)
+  if not ok or not parser then
+    return nil
+  end
+  parser:parse()
+  local captures = {}
+  parser:for_each_tree(function(tree, ltree)
+    local ok, query = pcall(get_query, ltree:lang(), "highlights")
+    if not ok or not query then
+      return
+    end
+    for id, node in query:iter_captures(tree:root(), bufnr, row, row + 1) do
+      local start_row, start_col, end_row, end_col = node:range()
+      if (row > start_row or (row == start_row and col >= start_col))
+        and (row < end_row or (row == end_row and col < end_col))
+      then
+        captures[#captures + 1] = query.captures[id]
+      end
+    end
+  end)
+  return captures
+end
+
+return function(bufnr, row, col)
+  local tick = vim.api.nvim_buf_get_changedtick(bufnr)
+  local key = table.concat({ bufnr, tick, row, col }, ":")
+  if key ~= last_key then
+    last_key, last_captures = key, captures_at(bufnr, row, col)
+  end
+  return last_captures
+end
+"#;
+
+/// Wraps an enabled source, only letting it attach to the buffers satisfying
+/// the conditions of its `enable` option and complete in the syntactic
+/// contexts allowed by its `contexts` option.
+#[derive(Debug)]
+pub struct Conditional<S> {
+    source: S,
+
+    /// The name of the source's section in the config, used to look up the
+    /// function passed as its `enable` option.
+    key: &'static str,
+
+    /// The conditions of the `enable` option, or `None` if it was set to
+    /// `true` or to a function.
+    condition: Option<Condition>,
+
+    /// The `contexts` option of the source.
+    contexts: Contexts,
+}
+
+impl<S> Conditional<S> {
+    pub fn new(
+        source: S,
+        key: &'static str,
+        enable: &Enable,
+        contexts: Contexts,
+    ) -> Self {
+        Conditional {
+            source,
+            key,
+            condition: match enable {
+                Enable::Bool(_) => None,
+                Enable::Condition(condition) => Some(condition.clone()),
+            },
+            contexts,
+        }
+    }
+
+    /// Whether the `contexts` option lets the source complete at the cursor,
+    /// which is checked on the character before it. Buffers without a
+    /// treesitter parser have no contexts, so the option is ignored there.
+    fn is_in_context(
+        &self,
+        lua: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<bool> {
+        if self.contexts.is_empty() {
+            return Ok(true);
+        }
+
+        let captures_at =
+            match lua.named_registry_value::<_, LuaValue>(CAPTURES_AT)? {
+                LuaValue::Function(captures_at) => captures_at,
+                _ => {
+                    let captures_at: LuaFunction =
+                        lua.load(CAPTURES_AT_CHUNK).call(())?;
+                    lua.set_named_registry_value(
+                        CAPTURES_AT,
+                        captures_at.clone(),
+                    )?;
+                    captures_at
+                },
+            };
+
+        let captures = captures_at.call::<_, Option<Vec<String>>>((
+            api.get_current_buf()?,
+            cursor.row,
+            cursor.bytes.saturating_sub(1),
+        ))?;
+
+        Ok(captures.map_or(true, |captures| self.contexts.allows(&captures)))
+    }
+}
+
+impl<S: CompletionSource> CompletionSource for Conditional<S> {
+    fn name(&self) -> &'static str { self.source.name() }
+
+    fn attach(&self, lua: &Lua, api: &Api, bufnr: u32) -> LuaResult<bool> {
+        if let Some(condition) = &self.condition {
+            let filetype = api.buf_get_option::<String>(bufnr, "filetype")?;
+            let buftype = api.buf_get_option::<String>(bufnr, "buftype")?;
+
+            let matches_path = |globs: &[String]| -> LuaResult<bool> {
+                let path = api.buf_get_name(bufnr)?;
+                let nvim = Neovim::new(lua)?;
+                for glob in globs {
+                    let re = api.call_function::<_, String>(
+                        "glob2regpat",
+                        vec![glob.as_str()],
+                    )?;
+                    if nvim.regex(&re)?.match_str(&path)?.is_some() {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            };
+
+            let mut path_error = None;
+            let is_satisfied =
+                condition.is_satisfied(&filetype, &buftype, |globs| {
+                    matches_path(globs).unwrap_or_else(|err| {
+                        path_error = Some(err);
+                        false
+                    })
+                });
+
+            if let Some(err) = path_error {
+                return Err(err);
+            }
+
+            if !is_satisfied {
+                return Ok(false);
+            }
+        }
+
+        let predicate = lua
+            .named_registry_value::<_, Option<LuaTable>>(PREDICATES)?
+            .map(|predicates| {
+                predicates.get::<_, Option<LuaFunction>>(self.key)
+            })
+            .transpose()?
+            .flatten();
+
+        if let Some(predicate) = predicate {
+            if !predicate.call::<_, bool>(bufnr)? {
+                return Ok(false);
+            }
+        }
+
+        self.source.attach(lua, api, bufnr)
+    }
+
+    fn complete(
+        &self,
+        lua: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Vec<CompletionItem>> {
+        if !self.is_in_context(lua, api, cursor)? {
+            return Ok(Vec::new());
+        }
+        self.source.complete(lua, api, cursor)
+    }
+
+    fn confirm(
+        &self,
+        lua: &Lua,
+        api: &Api,
+        completion: &CompletionItem,
+    ) -> LuaResult<()> {
+        self.source.confirm(lua, api, completion)
+    }
+
+    fn check_health(&self, lua: &Lua, api: &Api) -> LuaResult<Vec<Report>> {
+        self.source.check_health(lua, api)
+    }
+
+    fn is_expensive(&self) -> bool { self.source.is_expensive() }
+
+    fn has_callbacks(&self) -> bool { self.source.has_callbacks() }
+
+    fn is_loading(&self) -> bool { self.source.is_loading() }
+
+    fn resolve(
+        &self,
+        lua: &Lua,
+        api: &Api,
+        completion: &CompletionItem,
+    ) -> LuaResult<()> {
+        self.source.resolve(lua, api, completion)
+    }
+
+    fn trigger_characters(
+        &self,
+        lua: &Lua,
+        api: &Api,
+    ) -> LuaResult<Vec<String>> {
+        self.source.trigger_characters(lua, api)
+    }
+
+    fn complete_in_background(
+        &self,
+        lua: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Option<Job>> {
+        // Returning `None` would make the source get queried on the main
+        // thread instead, so an empty job is returned.
+        if !self.is_in_context(lua, api, cursor)? {
+            return Ok(Some(Box::new(|_| Vec::new())));
+        }
+        self.source.complete_in_background(lua, api, cursor)
+    }
+}
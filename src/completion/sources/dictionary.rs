@@ -0,0 +1,340 @@
+use std::fmt;
+use std::fs;
+use std::iter;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Api;
+use serde::Deserialize;
+
+use crate::completion::{
+    matcher,
+    Cancellation,
+    CompletionItem,
+    CompletionSource,
+    Cursor,
+    Job,
+};
+use crate::disk_cache;
+use crate::settings::sources::{Contexts, Enable};
+
+/// The name the word lists are cached to disk under.
+const CACHE_NAME: &str = "dictionary";
+
+/// The maximum number of completions returned for a single prefix. Short
+/// prefixes can match thousands of words in a big dictionary.
+const MAX_COMPLETIONS: usize = 200;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Dictionary {
+    pub enable: Enable,
+
+    #[serde(default)]
+    pub contexts: Contexts,
+
+    /// The paths of the dictionary files, containing one word per line.
+    #[serde(default)]
+    pub files: Vec<String>,
+
+    /// Whether to also complete the words added to the files listed in the
+    /// `spellfile` option (e.g. with `zg`).
+    #[serde(default = "default_spellfile")]
+    pub spellfile: bool,
+
+    /// The words of the files, loaded the first time they're needed.
+    #[serde(skip)]
+    cache: Arc<Mutex<Option<Loaded>>>,
+}
+
+#[derive(Debug)]
+struct Loaded {
+    /// The files the words were loaded from.
+    paths: Vec<PathBuf>,
+
+    words: Arc<WordList>,
+}
+
+/// The words of all the dictionaries, stored in a trie of their lowercase
+/// version so that the ones starting with a given prefix are found without
+/// going through the others.
+struct WordList {
+    words: Vec<String>,
+
+    /// The nodes of the trie, the first one being its root.
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Default)]
+struct Node {
+    /// The `(char, node index)` pairs of the children, sorted by char.
+    children: Vec<(char, u32)>,
+
+    /// The indexes of the words whose lowercase version ends at this node.
+    words: Vec<u32>,
+}
+
+fn default_spellfile() -> bool { true }
+
+impl CompletionSource for Dictionary {
+    fn name(&self) -> &'static str { "Dictionary" }
+
+    fn attach(&self, _: &Lua, _: &Api, _: u32) -> LuaResult<bool> { Ok(true) }
+
+    fn complete(
+        &self,
+        lua: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Vec<CompletionItem>> {
+        Ok(match self.complete_in_background(lua, api, cursor)? {
+            Some(job) => job(&Cancellation::never()),
+            None => Vec::new(),
+        })
+    }
+
+    fn complete_in_background(
+        &self,
+        _: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Option<Job>> {
+        let prefix = cursor.keyword_pre().to_owned();
+
+        if prefix.is_empty() {
+            return Ok(None);
+        }
+
+        // The paths are expanded here since `expand()` has to be called on
+        // the main thread.
+        let mut paths = self.files.clone();
+        if self.spellfile {
+            let spellfile = api.buf_get_option::<String>(0, "spellfile")?;
+            paths.extend(
+                spellfile
+                    .split(',')
+                    .filter(|path| !path.is_empty())
+                    .map(String::from),
+            );
+        }
+
+        let paths = paths
+            .iter()
+            .map(|path| {
+                api.call_function::<_, String>("expand", vec![path.as_str()])
+            })
+            .map(|path| path.map(PathBuf::from))
+            .collect::<LuaResult<Vec<PathBuf>>>()?;
+
+        if paths.is_empty() {
+            return Ok(None);
+        }
+
+        let cache = self.cache.clone();
+
+        Ok(Some(Box::new(move |cancellation| {
+            let words = {
+                let cache = &mut *cache.lock().unwrap();
+                match cache {
+                    Some(loaded) if loaded.paths == paths => {
+                        loaded.words.clone()
+                    },
+                    _ => {
+                        let words = Arc::new(WordList::load(&paths));
+                        *cache = Some(Loaded {
+                            paths,
+                            words: words.clone(),
+                        });
+                        words
+                    },
+                }
+            };
+
+            if cancellation.is_cancelled() {
+                return Vec::new();
+            }
+
+            words
+                .starting_with(&prefix)
+                .filter(|&word| word != prefix)
+                .filter_map(|word| {
+                    matcher::fuzzy_match(&prefix, word).map(|m| {
+                        CompletionItem {
+                            details: None,
+                            format: format!(" {}", word),
+                            hl_ranges: m.hl_ranges(1),
+                            kind: Some("Text"),
+                            matched_bytes: prefix.len() as u32,
+                            preselect: false,
+                            score: m.score,
+                            snippet: None,
+                            source: "Dictionary",
+                            text: word.to_owned(),
+                            ..Default::default()
+                        }
+                    })
+                })
+                .take(MAX_COMPLETIONS)
+                .collect()
+        })))
+    }
+}
+
+impl fmt::Debug for WordList {
+    // Printing hundreds of thousands of words isn't useful to anyone.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "WordList({} words)", self.words.len())
+    }
+}
+
+impl WordList {
+    /// Reads the words of all the files, skipping the ones that can't be
+    /// read. The sorted words are cached to disk until any of the files
+    /// changes, so that big dictionaries are only parsed and sorted once.
+    fn load(paths: &[PathBuf]) -> Self {
+        let key = disk_cache::fingerprint(paths);
+
+        if let Some(words) = disk_cache::read(CACHE_NAME, &key) {
+            return WordList::from_sorted(words);
+        }
+
+        let list = Self::parse(paths);
+        let words = list.starting_with("").collect::<Vec<&str>>();
+        disk_cache::write(CACHE_NAME, &key, &words);
+        list
+    }
+
+    /// Reads the words of all the files.
+    fn parse(paths: &[PathBuf]) -> Self {
+        let mut words = paths
+            .iter()
+            .filter_map(|path| fs::read_to_string(path).ok())
+            .flat_map(|contents| {
+                parse_words(&contents)
+                    .map(|word| (word.to_lowercase(), word.to_owned()))
+                    .collect::<Vec<(String, String)>>()
+            })
+            .collect::<Vec<(String, String)>>();
+
+        words.sort();
+        words.dedup();
+
+        Self::from_sorted(words.into_iter().map(|(_, word)| word).collect())
+    }
+
+    /// Creates a list from words sorted by their lowercase version, which is
+    /// the order they're returned in.
+    fn from_sorted(words: Vec<String>) -> Self {
+        let mut list = WordList {
+            words: Vec::with_capacity(words.len()),
+            nodes: vec![Node::default()],
+        };
+
+        for word in words {
+            let mut node = 0;
+            for char in word.to_lowercase().chars() {
+                let children = &list.nodes[node].children;
+                node = match children.binary_search_by_key(&char, |c| c.0) {
+                    Ok(i) => children[i].1 as usize,
+                    Err(i) => {
+                        let child = list.nodes.len();
+                        list.nodes.push(Node::default());
+                        list.nodes[node]
+                            .children
+                            .insert(i, (char, child as u32));
+                        child
+                    },
+                };
+            }
+            list.nodes[node].words.push(list.words.len() as u32);
+            list.words.push(word);
+        }
+
+        list
+    }
+
+    /// Returns the words starting with `prefix`, ignoring case, sorted by
+    /// their lowercase version.
+    fn starting_with<'a>(
+        &'a self,
+        prefix: &str,
+    ) -> impl Iterator<Item = &'a str> + 'a {
+        let start = prefix.to_lowercase().chars().try_fold(0, |node, char| {
+            let children = &self.nodes[node].children;
+            children
+                .binary_search_by_key(&char, |c| c.0)
+                .ok()
+                .map(|i| children[i].1 as usize)
+        });
+
+        // A depth-first traversal, which visits the words ending at a node
+        // before the longer ones and the children in the order of their
+        // chars.
+        let mut stack = start.into_iter().collect::<Vec<usize>>();
+        let mut words: &[u32] = &[];
+
+        iter::from_fn(move || loop {
+            if let Some((&word, rest)) = words.split_first() {
+                words = rest;
+                return Some(self.words[word as usize].as_str());
+            }
+            let node = &self.nodes[stack.pop()?];
+            words = &node.words;
+            stack.extend(node.children.iter().rev().map(|c| c.1 as usize));
+        })
+    }
+}
+
+/// Returns the words of a dictionary or spell file, which contain one word
+/// per line. Comments starting with `#` are skipped, and so are the words
+/// marked as bad in spell files (e.g. `teh/!`). Other flags after a `/` are
+/// removed.
+fn parse_words(contents: &str) -> impl Iterator<Item = &str> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| match line.split_once('/') {
+            Some((_, flags)) if flags.contains('!') => None,
+            Some((word, _)) => Some(word),
+            None => Some(line),
+        })
+        .filter(|word| !word.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_words, WordList};
+
+    #[test]
+    fn parse_spellfile() {
+        let contents = "# A comment\nfoo\n\n  bar  \nteh/!\nBaz/=\n";
+        assert_eq!(
+            vec!["foo", "bar", "Baz"],
+            parse_words(contents).collect::<Vec<&str>>()
+        );
+    }
+
+    #[test]
+    fn words_starting_with() {
+        let words = WordList::from_sorted(
+            ["bar", "fo", "foo", "fooBar", "Foobaz"]
+                .map(String::from)
+                .to_vec(),
+        );
+
+        assert_eq!(
+            vec!["foo", "fooBar", "Foobaz"],
+            words.starting_with("foo").collect::<Vec<&str>>()
+        );
+        assert_eq!(
+            vec!["fooBar", "Foobaz"],
+            words.starting_with("FOOB").collect::<Vec<&str>>()
+        );
+        assert!(words.starting_with("qux").next().is_none());
+        assert_eq!(
+            vec!["bar", "fo", "foo", "fooBar", "Foobaz"],
+            words.starting_with("").collect::<Vec<&str>>()
+        );
+    }
+}
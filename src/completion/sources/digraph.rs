@@ -0,0 +1,188 @@
+use std::sync::OnceLock;
+
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Api;
+use serde::Deserialize;
+
+use crate::completion::{matcher, CompletionItem, CompletionSource, Cursor};
+use crate::settings::sources::{Contexts, Enable};
+
+/// The `(characters, glyph)` tuples of all the digraphs, both the default
+/// ones and the ones added with `:digraphs`. Loaded once on the main thread
+/// and kept for the lifetime of the process so that the glyphs can be used as
+/// completion kinds.
+static DIGRAPHS: OnceLock<Vec<(String, String)>> = OnceLock::new();
+
+#[derive(Debug, Deserialize)]
+pub struct Digraph {
+    pub enable: Enable,
+
+    #[serde(default)]
+    pub contexts: Contexts,
+
+    /// The text typed before the two characters of a digraph to complete it.
+    #[serde(default = "default_trigger")]
+    pub trigger: String,
+
+    /// Every prefix of every digraph preceded by the `trigger`, which makes
+    /// the menu stay open while the characters of the digraph are typed even
+    /// if they're not keyword characters.
+    #[serde(skip)]
+    triggers: OnceLock<Vec<String>>,
+}
+
+fn default_trigger() -> String { "\\".into() }
+
+impl Default for Digraph {
+    fn default() -> Self {
+        Digraph {
+            enable: Enable::default(),
+            contexts: Contexts::default(),
+            trigger: default_trigger(),
+            triggers: OnceLock::new(),
+        }
+    }
+}
+
+impl CompletionSource for Digraph {
+    fn name(&self) -> &'static str { "Digraph" }
+
+    fn attach(&self, _: &Lua, api: &Api, _: u32) -> LuaResult<bool> {
+        if self.trigger.is_empty() {
+            return Ok(false);
+        }
+
+        // `digraph_getlist()` is only available on recent versions of
+        // Neovim.
+        if DIGRAPHS.get().is_none() {
+            match api.call_function::<_, Vec<Vec<String>>>(
+                "digraph_getlist",
+                vec![true],
+            ) {
+                Ok(digraphs) => {
+                    let _ = DIGRAPHS.set(
+                        digraphs
+                            .into_iter()
+                            .filter_map(|digraph| match &digraph[..] {
+                                [chars, glyph] => {
+                                    Some((chars.clone(), glyph.clone()))
+                                },
+                                _ => None,
+                            })
+                            .collect(),
+                    );
+                },
+                Err(_) => return Ok(false),
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn trigger_characters(&self, _: &Lua, _: &Api) -> LuaResult<Vec<String>> {
+        let digraphs = match DIGRAPHS.get() {
+            Some(digraphs) => digraphs,
+            None => return Ok(Vec::new()),
+        };
+
+        Ok(self
+            .triggers
+            .get_or_init(|| {
+                let mut triggers = vec![self.trigger.clone()];
+                for (chars, _) in digraphs {
+                    for (i, c) in chars.char_indices() {
+                        triggers.push(format!(
+                            "{}{}",
+                            self.trigger,
+                            &chars[..i + c.len_utf8()]
+                        ));
+                    }
+                }
+                triggers.sort();
+                triggers.dedup();
+                triggers
+            })
+            .clone())
+    }
+
+    fn complete(
+        &self,
+        _: &Lua,
+        _: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Vec<CompletionItem>> {
+        let digraphs = match DIGRAPHS.get() {
+            Some(digraphs) => digraphs,
+            None => return Ok(Vec::new()),
+        };
+
+        let line_pre = &cursor.line[..cursor.bytes as usize];
+        let typed = match typed_digraph(line_pre, &self.trigger) {
+            Some(typed) => typed,
+            None => return Ok(Vec::new()),
+        };
+
+        Ok(digraphs
+            .iter()
+            .filter(|(chars, _)| chars.starts_with(typed))
+            .map(|(chars, glyph)| CompletionItem {
+                details: None,
+                format: format!(" {chars}"),
+                hl_ranges: matcher::fuzzy_match(typed, chars)
+                    .map(|m| m.hl_ranges(1))
+                    .unwrap_or_default(),
+                kind: Some(glyph.as_str()),
+                matched_bytes: (self.trigger.len() + typed.len()) as u32,
+                preselect: false,
+                score: 0,
+                snippet: None,
+                source: "Digraph",
+                text: glyph.clone(),
+                ..Default::default()
+            })
+            .collect())
+    }
+}
+
+/// Returns the characters of a digraph typed after the `trigger`, or `None`
+/// if the text before the cursor doesn't end with the trigger followed by at
+/// most two non-whitespace characters.
+fn typed_digraph<'a>(line_pre: &'a str, trigger: &str) -> Option<&'a str> {
+    // The trigger can appear in the typed characters too, e.g. `\\` when the
+    // trigger is `\`, so the earliest candidate that still fits wins.
+    let start = line_pre.len().saturating_sub(trigger.len() + 8);
+
+    line_pre
+        .char_indices()
+        .filter(|&(i, _)| i >= start)
+        .map(|(i, _)| i)
+        .find(|&i| {
+            let rest = &line_pre[i..];
+            rest.strip_prefix(trigger).is_some_and(|typed| {
+                typed.chars().count() <= 2
+                    && !typed.chars().any(char::is_whitespace)
+            })
+        })
+        .map(|i| &line_pre[i + trigger.len()..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::typed_digraph;
+
+    #[test]
+    fn typed_digraphs() {
+        assert_eq!(Some(""), typed_digraph("foo \\", "\\"));
+        assert_eq!(Some("a"), typed_digraph("foo \\a", "\\"));
+        assert_eq!(Some("a:"), typed_digraph("foo \\a:", "\\"));
+        assert_eq!(Some("\\a"), typed_digraph("\\\\a", "\\"));
+        assert_eq!(Some("e'"), typed_digraph("caf<<e'", "<<"));
+    }
+
+    #[test]
+    fn not_digraphs() {
+        assert_eq!(None, typed_digraph("foo", "\\"));
+        assert_eq!(None, typed_digraph("\\abc", "\\"));
+        assert_eq!(None, typed_digraph("\\a b", "\\"));
+    }
+}
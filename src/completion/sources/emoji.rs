@@ -0,0 +1,298 @@
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Api;
+use serde::Deserialize;
+
+use crate::completion::{matcher, CompletionItem, CompletionSource, Cursor};
+use crate::settings::sources::{Contexts, Enable};
+
+/// The `(shortcode, emoji)` tuples of the emojis that can be completed, using
+/// the same shortcodes as GitHub and Slack.
+const EMOJIS: &[(&str, &str)] = &[
+    ("+1", "👍"),
+    ("-1", "👎"),
+    ("100", "💯"),
+    ("alarm_clock", "⏰"),
+    ("alien", "👽"),
+    ("angry", "😠"),
+    ("apple", "🍎"),
+    ("art", "🎨"),
+    ("arrow_down", "⬇️"),
+    ("arrow_left", "⬅️"),
+    ("arrow_right", "➡️"),
+    ("arrow_up", "⬆️"),
+    ("baby", "👶"),
+    ("balloon", "🎈"),
+    ("bang", "💥"),
+    ("beer", "🍺"),
+    ("beers", "🍻"),
+    ("bell", "🔔"),
+    ("blush", "😊"),
+    ("bomb", "💣"),
+    ("book", "📖"),
+    ("books", "📚"),
+    ("boom", "💥"),
+    ("bookmark", "🔖"),
+    ("bow", "🙇"),
+    ("broken_heart", "💔"),
+    ("bug", "🐛"),
+    ("bulb", "💡"),
+    ("cake", "🍰"),
+    ("calendar", "📆"),
+    ("camera", "📷"),
+    ("cat", "🐱"),
+    ("check", "✔️"),
+    ("checkered_flag", "🏁"),
+    ("clap", "👏"),
+    ("clipboard", "📋"),
+    ("cloud", "☁️"),
+    ("coffee", "☕"),
+    ("cold_sweat", "😰"),
+    ("computer", "💻"),
+    ("confused", "😕"),
+    ("construction", "🚧"),
+    ("cool", "🆒"),
+    ("crown", "👑"),
+    ("cry", "😢"),
+    ("dart", "🎯"),
+    ("dash", "💨"),
+    ("disappointed", "😞"),
+    ("dizzy", "💫"),
+    ("dog", "🐶"),
+    ("dragon", "🐉"),
+    ("eyes", "👀"),
+    ("facepalm", "🤦"),
+    ("fire", "🔥"),
+    ("fist", "✊"),
+    ("flushed", "😳"),
+    ("gear", "⚙️"),
+    ("ghost", "👻"),
+    ("gift", "🎁"),
+    ("globe_with_meridians", "🌐"),
+    ("green_heart", "💚"),
+    ("grimacing", "😬"),
+    ("grin", "😁"),
+    ("grinning", "😀"),
+    ("hammer", "🔨"),
+    ("hankey", "💩"),
+    ("heart", "❤️"),
+    ("heart_eyes", "😍"),
+    ("heavy_check_mark", "✔️"),
+    ("heavy_minus_sign", "➖"),
+    ("heavy_plus_sign", "➕"),
+    ("hourglass", "⌛"),
+    ("hugs", "🤗"),
+    ("hushed", "😯"),
+    ("innocent", "😇"),
+    ("joy", "😂"),
+    ("key", "🔑"),
+    ("kiss", "💋"),
+    ("laughing", "😆"),
+    ("link", "🔗"),
+    ("lipstick", "💄"),
+    ("lock", "🔒"),
+    ("loudspeaker", "📢"),
+    ("mag", "🔍"),
+    ("mailbox", "📫"),
+    ("memo", "📝"),
+    ("moneybag", "💰"),
+    ("monkey", "🐒"),
+    ("moon", "🌙"),
+    ("muscle", "💪"),
+    ("mute", "🔇"),
+    ("nerd_face", "🤓"),
+    ("neutral_face", "😐"),
+    ("no_entry", "⛔"),
+    ("no_entry_sign", "🚫"),
+    ("notes", "🎶"),
+    ("ok", "🆗"),
+    ("ok_hand", "👌"),
+    ("open_mouth", "😮"),
+    ("package", "📦"),
+    ("page_facing_up", "📄"),
+    ("paperclip", "📎"),
+    ("partying_face", "🥳"),
+    ("pencil2", "✏️"),
+    ("penguin", "🐧"),
+    ("pensive", "😔"),
+    ("point_down", "👇"),
+    ("point_left", "👈"),
+    ("point_right", "👉"),
+    ("point_up", "☝️"),
+    ("poop", "💩"),
+    ("pray", "🙏"),
+    ("pushpin", "📌"),
+    ("question", "❓"),
+    ("rage", "😡"),
+    ("rainbow", "🌈"),
+    ("raised_hands", "🙌"),
+    ("recycle", "♻️"),
+    ("red_circle", "🔴"),
+    ("relaxed", "☺️"),
+    ("relieved", "😌"),
+    ("rewind", "⏪"),
+    ("robot", "🤖"),
+    ("rocket", "🚀"),
+    ("rofl", "🤣"),
+    ("rose", "🌹"),
+    ("rotating_light", "🚨"),
+    ("scream", "😱"),
+    ("see_no_evil", "🙈"),
+    ("shrug", "🤷"),
+    ("skull", "💀"),
+    ("sleeping", "😴"),
+    ("slightly_smiling_face", "🙂"),
+    ("smile", "😄"),
+    ("smiley", "😃"),
+    ("smirk", "😏"),
+    ("snail", "🐌"),
+    ("snake", "🐍"),
+    ("snowflake", "❄️"),
+    ("sob", "😭"),
+    ("sparkles", "✨"),
+    ("speech_balloon", "💬"),
+    ("star", "⭐"),
+    ("star2", "🌟"),
+    ("stuck_out_tongue", "😛"),
+    ("sun_with_face", "🌞"),
+    ("sunglasses", "😎"),
+    ("sunny", "☀️"),
+    ("sweat", "😓"),
+    ("sweat_smile", "😅"),
+    ("tada", "🎉"),
+    ("thinking", "🤔"),
+    ("thumbsdown", "👎"),
+    ("thumbsup", "👍"),
+    ("tired_face", "😫"),
+    ("trophy", "🏆"),
+    ("truck", "🚚"),
+    ("turtle", "🐢"),
+    ("umbrella", "☔"),
+    ("unamused", "😒"),
+    ("unicorn", "🦄"),
+    ("upside_down_face", "🙃"),
+    ("v", "✌️"),
+    ("warning", "⚠️"),
+    ("wave", "👋"),
+    ("white_check_mark", "✅"),
+    ("wink", "😉"),
+    ("wrench", "🔧"),
+    ("x", "❌"),
+    ("yellow_heart", "💛"),
+    ("yum", "😋"),
+    ("zap", "⚡"),
+    ("zipper_mouth_face", "🤐"),
+    ("zzz", "💤"),
+];
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Emoji {
+    pub enable: Enable,
+
+    #[serde(default)]
+    pub contexts: Contexts,
+}
+
+impl CompletionSource for Emoji {
+    fn name(&self) -> &'static str { "Emoji" }
+
+    fn attach(&self, _: &Lua, _: &Api, _: u32) -> LuaResult<bool> { Ok(true) }
+
+    fn trigger_characters(&self, _: &Lua, _: &Api) -> LuaResult<Vec<String>> {
+        Ok(vec![":".into()])
+    }
+
+    fn complete(
+        &self,
+        _: &Lua,
+        _: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Vec<CompletionItem>> {
+        let shortcode =
+            match shortcode_before(&cursor.line[..cursor.bytes as usize]) {
+                Some(shortcode) => shortcode,
+                None => return Ok(Vec::new()),
+            };
+
+        Ok(EMOJIS
+            .iter()
+            .filter_map(|&(code, emoji)| {
+                // Right after the colon there's nothing to match yet.
+                let (score, hl_ranges) = match shortcode.is_empty() {
+                    true => (0, Vec::new()),
+                    false => {
+                        let m = matcher::fuzzy_match(shortcode, code)?;
+                        // The shortcode is displayed after a space and a
+                        // colon.
+                        (m.score, m.hl_ranges(2))
+                    },
+                };
+
+                Some(CompletionItem {
+                    details: None,
+                    format: format!(" :{code}:"),
+                    hl_ranges,
+                    kind: Some(emoji),
+                    // The colon is replaced together with the shortcode.
+                    matched_bytes: shortcode.len() as u32 + 1,
+                    preselect: false,
+                    score,
+                    snippet: None,
+                    source: "Emoji",
+                    text: emoji.to_owned(),
+                    ..Default::default()
+                })
+            })
+            .collect())
+    }
+}
+
+/// Returns the part of a shortcode typed before the cursor, i.e. the text
+/// after the opening colon, or `None` if the cursor isn't after one. The
+/// colon has to be at the start of a word so that things like `std::` or
+/// `key:value` don't get completed, and the full list of emojis is only
+/// offered after a colon preceded by whitespace.
+fn shortcode_before(line_pre: &str) -> Option<&str> {
+    let start = line_pre
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| {
+            c.is_ascii_lowercase()
+                || c.is_ascii_digit()
+                || matches!(c, '_' | '+' | '-')
+        })
+        .last()
+        .map(|(i, _)| i)
+        .unwrap_or(line_pre.len());
+
+    let before = line_pre[..start].strip_suffix(':')?;
+
+    match before.chars().last() {
+        Some(c) if c.is_alphanumeric() || c == ':' => None,
+        Some(c) if !c.is_whitespace() && start == line_pre.len() => None,
+        _ => Some(&line_pre[start..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::shortcode_before;
+
+    #[test]
+    fn shortcodes() {
+        assert_eq!(Some("ta"), shortcode_before("Done :ta"));
+        assert_eq!(Some("+1"), shortcode_before(":+1"));
+        assert_eq!(Some("heart_e"), shortcode_before("(:heart_e"));
+        assert_eq!(Some(""), shortcode_before("Done :"));
+        assert_eq!(Some(""), shortcode_before(":"));
+    }
+
+    #[test]
+    fn not_shortcodes() {
+        assert_eq!(None, shortcode_before("tada"));
+        assert_eq!(None, shortcode_before("key:val"));
+        assert_eq!(None, shortcode_before("std::co"));
+        assert_eq!(None, shortcode_before("std::"));
+        assert_eq!(None, shortcode_before("(:"));
+        assert_eq!(None, shortcode_before(":Ta"));
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::{env, fs};
+
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Api;
+use serde::Deserialize;
+
+use crate::completion::{
+    matcher,
+    CompletionItem,
+    CompletionSource,
+    Cursor,
+    Details,
+};
+use crate::settings::sources::{Contexts, Enable};
+
+#[derive(Debug, Deserialize)]
+pub struct Env {
+    pub enable: Enable,
+
+    #[serde(default)]
+    pub contexts: Contexts,
+
+    /// Whether to also complete the variables defined in the `.env` file of
+    /// Neovim's current working directory.
+    #[serde(default)]
+    pub dotenv: bool,
+
+    /// Whether to show the values of the variables in the details window.
+    #[serde(default = "default_show_values")]
+    pub show_values: bool,
+}
+
+fn default_show_values() -> bool { true }
+
+impl Default for Env {
+    fn default() -> Self {
+        Env {
+            enable: Enable::default(),
+            contexts: Contexts::default(),
+            dotenv: false,
+            show_values: default_show_values(),
+        }
+    }
+}
+
+impl CompletionSource for Env {
+    fn name(&self) -> &'static str { "Env" }
+
+    fn attach(&self, _: &Lua, _: &Api, _: u32) -> LuaResult<bool> { Ok(true) }
+
+    fn trigger_characters(&self, _: &Lua, _: &Api) -> LuaResult<Vec<String>> {
+        Ok(vec!["$".into(), "${".into()])
+    }
+
+    fn complete(
+        &self,
+        _: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Vec<CompletionItem>> {
+        let prefix = match name_before(&cursor.line[..cursor.bytes as usize]) {
+            Some(prefix) => prefix,
+            None => return Ok(Vec::new()),
+        };
+
+        // The variables of the `.env` file take precedence over the ones
+        // Neovim was started with. The variables that aren't valid UTF-8 are
+        // skipped.
+        let mut variables = env::vars_os()
+            .filter_map(|(name, value)| {
+                Some((name.into_string().ok()?, value.into_string().ok()?))
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        if self.dotenv {
+            let cwd =
+                api.call_function::<_, String>("getcwd", Vec::<u8>::new())?;
+            if let Ok(contents) =
+                fs::read_to_string(PathBuf::from(cwd).join(".env"))
+            {
+                variables.extend(parse_dotenv(&contents));
+            }
+        }
+
+        Ok(variables
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let m = matcher::fuzzy_match(prefix, &name)?;
+
+                Some(CompletionItem {
+                    details: self.show_values.then(|| Details {
+                        lines: value.lines().map(String::from).collect(),
+                        is_markdown: false,
+                    }),
+                    // The name is displayed after a space and a `$`.
+                    format: format!(" ${name}"),
+                    hl_ranges: m.hl_ranges(2),
+                    kind: Some("Variable"),
+                    matched_bytes: prefix.len() as u32,
+                    preselect: false,
+                    score: m.score,
+                    snippet: None,
+                    source: "Env",
+                    text: name,
+                    ..Default::default()
+                })
+            })
+            .collect())
+    }
+}
+
+/// Returns the part of a variable name typed before the cursor after a `$`
+/// or a `${`, or `None` if the cursor isn't after one. Names can't start with
+/// a digit, so that things like `$1` aren't completed.
+fn name_before(line_pre: &str) -> Option<&str> {
+    let start = line_pre
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| c.is_ascii_alphanumeric() || c == '_')
+        .last()
+        .map(|(i, _)| i)
+        .unwrap_or(line_pre.len());
+
+    let before = &line_pre[..start];
+    let before = before.strip_suffix('{').unwrap_or(before);
+    before.strip_suffix('$')?;
+
+    let name = &line_pre[start..];
+    match name.starts_with(|c: char| c.is_ascii_digit()) {
+        true => None,
+        false => Some(name),
+    }
+}
+
+/// Parses the contents of a `.env` file into `(name, value)` tuples. Every
+/// line is a `NAME=value` assignment, optionally preceded by `export`, and
+/// the quotes around a value are removed. Blank lines and comments starting
+/// with `#` are skipped.
+fn parse_dotenv(contents: &str) -> Vec<(String, String)> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (name, value) = line.split_once('=')?;
+            let name = name.trim();
+
+            if name.is_empty() || name.starts_with('#') {
+                return None;
+            }
+
+            let value = value.trim();
+            let value = ['"', '\'']
+                .iter()
+                .find_map(|&quote| {
+                    value.strip_prefix(quote)?.strip_suffix(quote)
+                })
+                .unwrap_or(value);
+
+            Some((name.to_owned(), value.to_owned()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{name_before, parse_dotenv};
+
+    #[test]
+    fn names() {
+        assert_eq!(Some("HO"), name_before("echo $HO"));
+        assert_eq!(Some("PA"), name_before("cd ${PA"));
+        assert_eq!(Some(""), name_before("echo \"$"));
+        assert_eq!(Some(""), name_before("${"));
+    }
+
+    #[test]
+    fn not_names() {
+        assert_eq!(None, name_before("HOME"));
+        assert_eq!(None, name_before("echo {HO"));
+        assert_eq!(None, name_before("echo $1"));
+    }
+
+    #[test]
+    fn dotenv_files() {
+        let contents = "\
+# The database
+DATABASE_URL=\"postgres://localhost/db\"
+export API_KEY='secret'
+
+PORT = 8080
+#COMMENTED=1
+not an assignment
+";
+        assert_eq!(
+            vec![
+                ("DATABASE_URL".into(), "postgres://localhost/db".into()),
+                ("API_KEY".into(), "secret".into()),
+                ("PORT".into(), "8080".into()),
+            ],
+            parse_dotenv(contents)
+        );
+    }
+}
@@ -0,0 +1,340 @@
+use std::fmt::{self, Display, Write};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// How deeply arrays and objects can be nested. Documents nested deeper are
+/// rejected instead of overflowing the stack of the thread parsing them.
+const MAX_DEPTH: usize = 64;
+
+/// A JSON value, just enough of it to speak JSON-RPC with the external
+/// providers. Objects keep their keys in order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Parses a JSON document, returning `None` if it's not valid JSON.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parser = Parser {
+            chars: text.chars().peekable(),
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        parser.chars.peek().is_none().then_some(value)
+    }
+
+    /// Returns the value of the `key` field if this is an object that has
+    /// it.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => {
+                fields.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+            },
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self { Json::String(s.to_owned()) }
+}
+
+impl From<u32> for Json {
+    fn from(n: u32) -> Self { Json::Number(n.into()) }
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => write!(f, "{b}"),
+            Json::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => {
+                write!(f, "{}", *n as i64)
+            },
+            Json::Number(n) if n.is_finite() => write!(f, "{n}"),
+            Json::Number(_) => f.write_str("null"),
+            Json::String(s) => write_string(f, s),
+            Json::Array(values) => {
+                f.write_char('[')?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                f.write_char(']')
+            },
+            Json::Object(fields) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_char('}')
+            },
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+/// A recursive descent parser for JSON documents.
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+
+    /// How many arrays and objects the parser is in.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn value(&mut self) -> Option<Json> {
+        self.skip_whitespace();
+        match *self.chars.peek()? {
+            'n' => self.keyword("null", Json::Null),
+            't' => self.keyword("true", Json::Bool(true)),
+            'f' => self.keyword("false", Json::Bool(false)),
+            '"' => self.string().map(Json::String),
+            '[' => self.nested(Self::array),
+            '{' => self.nested(Self::object),
+            _ => self.number(),
+        }
+    }
+
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Option<Json>,
+    ) -> Option<Json> {
+        if self.depth == MAX_DEPTH {
+            return None;
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Json) -> Option<Json> {
+        for expected in keyword.chars() {
+            if self.chars.next()? != expected {
+                return None;
+            }
+        }
+        Some(value)
+    }
+
+    fn number(&mut self) -> Option<Json> {
+        let mut number = String::new();
+        while let Some(c) = self
+            .chars
+            .next_if(|c| c.is_ascii_digit() || "+-.eE".contains(*c))
+        {
+            number.push(c);
+        }
+        number.parse().ok().map(Json::Number)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.chars.next();
+        let mut string = String::new();
+        loop {
+            match self.chars.next()? {
+                '"' => return Some(string),
+                '\\' => match self.chars.next()? {
+                    'n' => string.push('\n'),
+                    'r' => string.push('\r'),
+                    't' => string.push('\t'),
+                    'b' => string.push('\u{8}'),
+                    'f' => string.push('\u{c}'),
+                    'u' => string.push(self.unicode_escape()?),
+                    c @ ('"' | '\\' | '/') => string.push(c),
+                    _ => return None,
+                },
+                c => string.push(c),
+            }
+        }
+    }
+
+    /// Parses the 4 hex digits after a `\u`, and the low surrogate following
+    /// a high one.
+    fn unicode_escape(&mut self) -> Option<char> {
+        let high = self.hex4()?;
+        if !(0xd800..0xdc00).contains(&high) {
+            return char::from_u32(high);
+        }
+        if self.chars.next()? != '\\' || self.chars.next()? != 'u' {
+            return None;
+        }
+        let low = self.hex4().filter(|low| (0xdc00..0xe000).contains(low))?;
+        char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00))
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        (0..4).try_fold(0, |acc, _| {
+            Some(acc * 16 + self.chars.next()?.to_digit(16)?)
+        })
+    }
+
+    fn array(&mut self) -> Option<Json> {
+        self.chars.next();
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.chars.next_if_eq(&']').is_some() {
+            return Some(Json::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            match self.chars.next()? {
+                ',' => continue,
+                ']' => return Some(Json::Array(values)),
+                _ => return None,
+            }
+        }
+    }
+
+    fn object(&mut self) -> Option<Json> {
+        self.chars.next();
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.chars.next_if_eq(&'}').is_some() {
+            return Some(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.chars.peek() != Some(&'"') {
+                return None;
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            if self.chars.next()? != ':' {
+                return None;
+            }
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.chars.next()? {
+                ',' => continue,
+                '}' => return Some(Json::Object(fields)),
+                _ => return None,
+            }
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_ascii_whitespace()).is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Json;
+
+    #[test]
+    fn parsing() {
+        let json = Json::parse(
+            r#" {"id": 3, "result": [{"text": "users", "kind": null}],
+                "ok": true, "pi": -3.5e0} "#,
+        )
+        .unwrap();
+
+        assert_eq!(Some(3.0), json.get("id").and_then(Json::as_f64));
+        assert_eq!(Some(&Json::Bool(true)), json.get("ok"));
+        assert_eq!(Some(-3.5), json.get("pi").and_then(Json::as_f64));
+
+        let result = json.get("result").and_then(Json::as_array).unwrap();
+        assert_eq!(
+            Some("users"),
+            result[0].get("text").and_then(Json::as_str)
+        );
+        assert_eq!(Some(&Json::Null), result[0].get("kind"));
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            Some(Json::String("a\"b\\c\nd/é😀".into())),
+            Json::parse(r#""a\"b\\c\nd\/é😀""#)
+        );
+    }
+
+    #[test]
+    fn invalid_documents() {
+        assert_eq!(None, Json::parse(""));
+        assert_eq!(None, Json::parse("[1, 2"));
+        assert_eq!(None, Json::parse(r#"{"a" 1}"#));
+        assert_eq!(None, Json::parse(r#"{a: 1}"#));
+        assert_eq!(None, Json::parse("[1] 2"));
+        assert_eq!(None, Json::parse("nul"));
+        assert_eq!(None, Json::parse(r#""\x""#));
+    }
+
+    #[test]
+    fn nesting() {
+        let nested = |depth| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(Json::parse(&nested(64)).is_some());
+        assert_eq!(None, Json::parse(&nested(65)));
+        assert_eq!(None, Json::parse(&"[{\"a\":".repeat(100_000)));
+    }
+
+    #[test]
+    fn encoding() {
+        let json = Json::Object(vec![
+            ("id".into(), 7.into()),
+            ("line".into(), "say \"hi\"\n".into()),
+            (
+                "list".into(),
+                Json::Array(vec![Json::Null, Json::Bool(false)]),
+            ),
+            ("x".into(), Json::Number(0.5)),
+        ]);
+        assert_eq!(
+            r#"{"id":7,"line":"say \"hi\"\n","list":[null,false],"x":0.5}"#,
+            json.to_string()
+        );
+        assert_eq!(Some(json.clone()), Json::parse(&json.to_string()));
+    }
+}
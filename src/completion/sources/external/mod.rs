@@ -0,0 +1,364 @@
+mod json;
+mod rpc;
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use json::Json;
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Api;
+use rpc::{Connection, RequestError};
+
+use crate::completion::{
+    matcher,
+    Cancellation,
+    CompletionItem,
+    CompletionSource,
+    Cursor,
+    Details,
+    Job,
+};
+use crate::health::Report;
+use crate::logging;
+use crate::ui::menu::kinds::KINDS;
+
+/// How long to wait before spawning a process that failed again, so that a
+/// broken command isn't run on every keystroke.
+const RESPAWN_DELAY: Duration = Duration::from_secs(5);
+
+/// A source whose completions come from an external process registered with
+/// `require("compleet").register_external_source`, e.g. one reading the
+/// columns of a database. The process is spawned the first time it's needed
+/// and is sent a JSON-RPC `complete` request every time the completions are
+/// computed. Requests always run on background threads, since the process
+/// can take up to the whole timeout to answer.
+#[derive(Debug)]
+pub struct External {
+    name: &'static str,
+
+    /// The executable of the process followed by its arguments.
+    cmd: Vec<String>,
+
+    /// The filetypes of the buffers the source attaches to, or all of them
+    /// if empty.
+    filetypes: Vec<String>,
+
+    /// How long to wait for the completions before giving up on them.
+    timeout: Duration,
+
+    trigger_characters: Vec<String>,
+
+    process: Arc<Mutex<Process>>,
+}
+
+#[derive(Debug)]
+enum Process {
+    NotStarted,
+    Running(Arc<Connection>),
+
+    /// The process couldn't be spawned or it exited `at` some point. It's
+    /// spawned again by the first request made `RESPAWN_DELAY` after that.
+    Failed {
+        error: String,
+        at: Instant,
+    },
+}
+
+impl Process {
+    /// Returns the connection to the running process, spawning it if it's
+    /// not running. Returns `None` if the process can't be spawned or failed
+    /// too recently.
+    fn connection(
+        &mut self,
+        name: &'static str,
+        cmd: &[String],
+    ) -> Option<Arc<Connection>> {
+        match self {
+            Process::Running(connection) if !connection.has_exited() => {
+                return Some(connection.clone())
+            },
+            Process::Running(_) => {
+                self.fail(name, "the process exited");
+                return None;
+            },
+            Process::Failed { at, .. } if at.elapsed() < RESPAWN_DELAY => {
+                return None
+            },
+            _ => {},
+        }
+
+        match Connection::spawn(name, cmd) {
+            Ok(connection) => {
+                let connection = Arc::new(connection);
+                *self = Process::Running(connection.clone());
+                Some(connection)
+            },
+            Err(err) => {
+                self.fail(name, &format!("couldn't spawn: {err}"));
+                None
+            },
+        }
+    }
+
+    fn fail(&mut self, name: &'static str, error: &str) {
+        logging::error(name, error);
+        *self = Process::Failed {
+            error: error.to_owned(),
+            at: Instant::now(),
+        };
+    }
+}
+
+impl External {
+    pub fn new(
+        name: &'static str,
+        cmd: Vec<String>,
+        filetypes: Vec<String>,
+        timeout: Duration,
+        trigger_characters: Vec<String>,
+    ) -> Self {
+        External {
+            name,
+            cmd,
+            filetypes,
+            timeout,
+            trigger_characters,
+            process: Arc::new(Mutex::new(Process::NotStarted)),
+        }
+    }
+}
+
+impl CompletionSource for External {
+    fn name(&self) -> &'static str { self.name }
+
+    fn attach(&self, _: &Lua, api: &Api, bufnr: u32) -> LuaResult<bool> {
+        if self.filetypes.is_empty() {
+            return Ok(true);
+        }
+        let filetype = api.buf_get_option::<String>(bufnr, "filetype")?;
+        Ok(self.filetypes.contains(&filetype))
+    }
+
+    fn trigger_characters(&self, _: &Lua, _: &Api) -> LuaResult<Vec<String>> {
+        Ok(self.trigger_characters.clone())
+    }
+
+    fn check_health(&self, _: &Lua, api: &Api) -> LuaResult<Vec<Report>> {
+        let mut reports = vec![Report::executable(
+            api,
+            &self.cmd[0],
+            self.name,
+            "Check the `cmd` passed to `register_external_source`",
+        )?];
+
+        if let Process::Failed { error, .. } = &*self.process.lock().unwrap() {
+            reports.push(Report::error(
+                format!("The {} provider stopped: {error}", self.name),
+                &["Check the provider and its messages in `:Compleet log`"],
+            ));
+        }
+
+        Ok(reports)
+    }
+
+    /// Waiting for the process would block the UI, so the completions are
+    /// only computed by the jobs of `complete_in_background`.
+    fn complete(
+        &self,
+        _: &Lua,
+        _: &Api,
+        _: &Cursor,
+    ) -> LuaResult<Vec<CompletionItem>> {
+        Ok(Vec::new())
+    }
+
+    fn complete_in_background(
+        &self,
+        _: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Option<Job>> {
+        let prefix = cursor.keyword_pre().to_owned();
+        let line_pre = &cursor.line[..cursor.bytes as usize];
+
+        // Returning `None` would make the source get queried on the main
+        // thread instead, so an empty job is returned.
+        if prefix.is_empty()
+            && !self
+                .trigger_characters
+                .iter()
+                .any(|t| line_pre.ends_with(t))
+        {
+            return Ok(Some(Box::new(|_| Vec::new())));
+        }
+
+        let params = Json::Object(vec![
+            (
+                "filetype".into(),
+                api.buf_get_option::<String>(0, "filetype")?.as_str().into(),
+            ),
+            (
+                "path".into(),
+                api.call_function::<_, String>("expand", vec!["%:p"])?
+                    .as_str()
+                    .into(),
+            ),
+            ("line".into(), cursor.line.as_str().into()),
+            ("row".into(), cursor.row.into()),
+            ("col".into(), cursor.bytes.into()),
+            ("keyword".into(), prefix.as_str().into()),
+        ]);
+
+        let (name, cmd, timeout) = (self.name, self.cmd.clone(), self.timeout);
+        let process = self.process.clone();
+
+        Ok(Some(Box::new(move |cancellation: &Cancellation| {
+            // The lock is only held while getting the connection, so that
+            // the requests of different generations can overlap.
+            let connection =
+                match process.lock().unwrap().connection(name, &cmd) {
+                    Some(connection) => connection,
+                    None => return Vec::new(),
+                };
+
+            let result = match connection.request(
+                "complete",
+                params,
+                timeout,
+                cancellation,
+            ) {
+                Ok(result) => result,
+                Err(RequestError::TimedOut) => return Vec::new(),
+                Err(RequestError::Response(err)) => {
+                    logging::warn(name, err);
+                    return Vec::new();
+                },
+                Err(RequestError::Disconnected) => {
+                    let process = &mut *process.lock().unwrap();
+                    // A newer job could have respawned the process already.
+                    if matches!(
+                        process,
+                        Process::Running(c) if Arc::ptr_eq(c, &connection)
+                    ) {
+                        process.fail(name, "the process exited");
+                    }
+                    return Vec::new();
+                },
+            };
+
+            result
+                .as_array()
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|item| to_completion(item, &prefix, name))
+                .collect()
+        })))
+    }
+}
+
+/// Converts an item of the result of a `complete` request, which is either a
+/// string or an object with a `text` field and optional `label` (shown in
+/// the menu instead of the text), `kind` (the name of one of the LSP
+/// completion kinds) and `details` fields, into a completion matching the
+/// `prefix`.
+fn to_completion(
+    item: &Json,
+    prefix: &str,
+    source: &'static str,
+) -> Option<CompletionItem> {
+    let text = item.as_str().or_else(|| item.get("text")?.as_str())?;
+
+    if text == prefix {
+        return None;
+    }
+
+    let label = item.get("label").and_then(Json::as_str);
+
+    let kind = item.get("kind").and_then(Json::as_str).and_then(|kind| {
+        KINDS
+            .iter()
+            .find(|(k, _, _)| *k == kind)
+            .map(|(k, _, _)| *k)
+    });
+
+    let details =
+        item.get("details")
+            .and_then(Json::as_str)
+            .map(|details| Details {
+                lines: details.lines().map(String::from).collect(),
+                is_markdown: false,
+            });
+
+    // After a trigger character there's no keyword to match, and the
+    // provider decides what to return. The matched characters are only
+    // highlighted if the text is what's shown in the menu.
+    let (score, hl_ranges) = match prefix.is_empty() {
+        true => (0, Vec::new()),
+        false => {
+            let m = matcher::fuzzy_match(prefix, text)?;
+            let hl_ranges = match label {
+                Some(_) => Vec::new(),
+                None => m.hl_ranges(1),
+            };
+            (m.score, hl_ranges)
+        },
+    };
+
+    Some(CompletionItem {
+        details,
+        format: format!(" {}", label.unwrap_or(text)),
+        hl_ranges,
+        kind,
+        matched_bytes: prefix.len() as u32,
+        preselect: false,
+        score,
+        snippet: None,
+        source,
+        text: text.to_owned(),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_completion, Json};
+
+    #[test]
+    fn completions() {
+        let item = Json::parse(
+            r#"{"text": "user_id", "kind": "Field", "details": "int\npk"}"#,
+        )
+        .unwrap();
+        let completion = to_completion(&item, "uid", "Sql").unwrap();
+        assert_eq!("user_id", completion.text);
+        assert_eq!(" user_id", completion.format);
+        assert_eq!(Some("Field"), completion.kind);
+        assert_eq!(3, completion.matched_bytes);
+        assert!(!completion.hl_ranges.is_empty());
+        assert_eq!(vec!["int", "pk"], completion.details.unwrap().lines);
+    }
+
+    #[test]
+    fn labels_and_unknown_kinds() {
+        let item = Json::parse(
+            r#"{"text": "users", "label": "users (table)", "kind": "Table"}"#,
+        )
+        .unwrap();
+        let completion = to_completion(&item, "us", "Sql").unwrap();
+        assert_eq!(" users (table)", completion.format);
+        assert_eq!(None, completion.kind);
+        assert!(completion.hl_ranges.is_empty());
+    }
+
+    #[test]
+    fn strings_and_invalid_items() {
+        let item = Json::String("orders".into());
+        assert!(to_completion(&item, "", "Sql").is_some());
+        assert!(to_completion(&item, "xyz", "Sql").is_none());
+        assert!(to_completion(&item, "orders", "Sql").is_none());
+        assert!(to_completion(&Json::Null, "", "Sql").is_none());
+        assert!(
+            to_completion(&Json::parse("{}").unwrap(), "", "Sql").is_none()
+        );
+    }
+}
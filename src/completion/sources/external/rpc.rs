@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::json::Json;
+use crate::completion::Cancellation;
+use crate::logging;
+
+/// How often a pending request checks whether it's been cancelled.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The requests waiting for a response, indexed by their ids.
+type Pending = Arc<Mutex<HashMap<u32, Sender<Json>>>>;
+
+/// A running provider process, spoken to with JSON-RPC messages framed like
+/// the ones of the language servers, i.e. preceded by a `Content-Length`
+/// header. Requests can be sent from multiple threads at once, and every
+/// response is handed to the request with the same id by a background thread
+/// reading the process' stdout.
+#[derive(Debug)]
+pub struct Connection {
+    child: Mutex<Child>,
+    stdin: Mutex<ChildStdin>,
+    pending: Pending,
+
+    /// Set by the reader thread once the process' stdout is closed.
+    has_exited: Arc<AtomicBool>,
+
+    /// The id of the next request.
+    next_id: AtomicU32,
+}
+
+/// Why a request didn't get a result.
+#[derive(Debug)]
+pub enum RequestError {
+    /// The process didn't answer within the timeout, or the request was
+    /// cancelled. The connection can still be used.
+    TimedOut,
+
+    /// The process answered with an error.
+    Response(String),
+
+    /// The process exited or its stdin was closed, and the connection is
+    /// unusable.
+    Disconnected,
+}
+
+impl Connection {
+    /// Spawns the `cmd`, whose first element is the executable and the rest
+    /// its arguments. The lines the process writes to its stderr are logged
+    /// as warnings with the `name` of the source as target.
+    pub fn spawn(name: &'static str, cmd: &[String]) -> io::Result<Self> {
+        let (program, args) = cmd.split_first().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "empty command")
+        })?;
+
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+
+        let pending = Pending::default();
+        let has_exited = Arc::new(AtomicBool::new(false));
+
+        let (_pending, _has_exited) = (pending.clone(), has_exited.clone());
+        thread::spawn(move || {
+            let mut stdout = BufReader::new(stdout);
+            while let Ok(Some(body)) = read_message(&mut stdout) {
+                // Messages that aren't valid JSON are skipped, and so are
+                // notifications and the responses to requests that timed out.
+                let message = match Json::parse(&body) {
+                    Some(message) => message,
+                    None => continue,
+                };
+                let id = match message.get("id").and_then(Json::as_f64) {
+                    Some(id) => id as u32,
+                    None => continue,
+                };
+                if let Some(sender) = _pending.lock().unwrap().remove(&id) {
+                    let _ = sender.send(message);
+                }
+            }
+            _has_exited.store(true, Ordering::Relaxed);
+            // Dropping the senders wakes up the requests still waiting.
+            _pending.lock().unwrap().clear();
+        });
+
+        thread::spawn(move || {
+            for line in BufReader::new(stderr).lines() {
+                match line {
+                    Ok(line) => logging::warn(name, line),
+                    Err(_) => break,
+                }
+            }
+        });
+
+        Ok(Connection {
+            child: Mutex::new(child),
+            stdin: Mutex::new(stdin),
+            pending,
+            has_exited,
+            next_id: AtomicU32::new(1),
+        })
+    }
+
+    /// Whether the process has exited, in which case every request fails
+    /// with `RequestError::Disconnected`.
+    pub fn has_exited(&self) -> bool {
+        self.has_exited.load(Ordering::Relaxed)
+    }
+
+    /// Sends a `method` request with the given `params`, waiting at most
+    /// `timeout` for its result. No lock is held while waiting.
+    pub fn request(
+        &self,
+        method: &str,
+        params: Json,
+        timeout: Duration,
+        cancellation: &Cancellation,
+    ) -> Result<Json, RequestError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+
+        let (sender, response) = mpsc::channel();
+        self.pending.lock().unwrap().insert(id, sender);
+
+        // Checked after registering the request, since the reader thread
+        // sets the flag before dropping the pending requests.
+        if self.has_exited() {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(RequestError::Disconnected);
+        }
+
+        let request = Json::Object(vec![
+            ("jsonrpc".into(), "2.0".into()),
+            ("id".into(), id.into()),
+            ("method".into(), method.into()),
+            ("params".into(), params),
+        ]);
+
+        let written = write_message(
+            &mut *self.stdin.lock().unwrap(),
+            &request.to_string(),
+        );
+
+        let result = match written {
+            Ok(()) => self.wait(&response, timeout, cancellation),
+            Err(_) => Err(RequestError::Disconnected),
+        };
+
+        self.pending.lock().unwrap().remove(&id);
+
+        let message = result?;
+
+        if let Some(error) = message.get("error") {
+            let msg = error.get("message").and_then(Json::as_str);
+            return Err(RequestError::Response(
+                msg.unwrap_or("unknown error").to_owned(),
+            ));
+        }
+
+        Ok(message.get("result").cloned().unwrap_or(Json::Null))
+    }
+
+    fn wait(
+        &self,
+        response: &mpsc::Receiver<Json>,
+        timeout: Duration,
+        cancellation: &Cancellation,
+    ) -> Result<Json, RequestError> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() || cancellation.is_cancelled() {
+                return Err(RequestError::TimedOut);
+            }
+
+            match response.recv_timeout(left.min(POLL_INTERVAL)) {
+                Ok(message) => return Ok(message),
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(RequestError::Disconnected)
+                },
+            }
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let child = &mut *self.child.lock().unwrap();
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+/// Writes a message `body` preceded by its `Content-Length` header.
+pub fn write_message(writer: &mut impl Write, body: &str) -> io::Result<()> {
+    write!(writer, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    writer.flush()
+}
+
+/// Reads the body of the next message, returning `None` once the stream is
+/// closed. Headers other than `Content-Length` are ignored.
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    let mut header = String::new();
+
+    loop {
+        header.clear();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            // Stray blank lines before the headers are skipped.
+            match length {
+                Some(_) => break,
+                None => continue,
+            }
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let mut body = vec![0; length.expect("the loop only breaks with it")];
+    reader.read_exact(&mut body)?;
+
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{read_message, write_message};
+
+    #[test]
+    fn framing() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, r#"{"id":1}"#).unwrap();
+        write_message(&mut buffer, "{\"text\":\"é\"}").unwrap();
+        assert!(buffer.starts_with(b"Content-Length: 8\r\n\r\n{\"id\":1}"));
+
+        let mut reader = Cursor::new(buffer);
+        assert_eq!(
+            Some(r#"{"id":1}"#.to_owned()),
+            read_message(&mut reader).unwrap()
+        );
+        assert_eq!(
+            Some("{\"text\":\"é\"}".to_owned()),
+            read_message(&mut reader).unwrap()
+        );
+        assert_eq!(None, read_message(&mut reader).unwrap());
+    }
+
+    #[test]
+    fn other_headers() {
+        let message =
+            "Content-Type: application/json\r\ncontent-length: 2\r\n\r\n{}";
+        assert_eq!(
+            Some("{}".to_owned()),
+            read_message(&mut Cursor::new(message)).unwrap()
+        );
+    }
+}
@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path as StdPath, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Api;
+use serde::Deserialize;
+
+use crate::completion::{
+    matcher,
+    Cancellation,
+    CompletionItem,
+    CompletionSource,
+    Cursor,
+    Details,
+    Job,
+};
+use crate::health::Report;
+use crate::settings::sources::{Contexts, Enable};
+
+/// The trailer used to credit the other authors of a commit.
+const CO_AUTHOR_TRAILER: &str = "Co-authored-by:";
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Git {
+    pub enable: Enable,
+
+    #[serde(default)]
+    pub contexts: Contexts,
+
+    /// How many of the most recent commits to complete the hashes of.
+    #[serde(default = "default_max_commits")]
+    pub max_commits: u32,
+
+    /// A hashmap where the keys are the directories `git` was run in and the
+    /// values are the data read from their repository. It's never
+    /// invalidated, so new branches and commits are only picked up after
+    /// restarting Neovim.
+    #[serde(skip)]
+    repos: Arc<Mutex<HashMap<PathBuf, Arc<Repo>>>>,
+}
+
+#[derive(Debug)]
+struct Repo {
+    /// The `(abbreviated hash, subject)` tuples of the most recent commits.
+    commits: Vec<(String, String)>,
+
+    /// The names of the local and remote branches.
+    branches: Vec<String>,
+
+    /// The `Name <email>` of everyone who authored a commit.
+    authors: Vec<String>,
+}
+
+fn default_max_commits() -> u32 { 100 }
+
+impl CompletionSource for Git {
+    fn name(&self) -> &'static str { "Git" }
+
+    fn attach(&self, _: &Lua, api: &Api, bufnr: u32) -> LuaResult<bool> {
+        Ok(api.buf_get_option::<String>(bufnr, "filetype")? == "gitcommit")
+    }
+
+    fn check_health(&self, _: &Lua, api: &Api) -> LuaResult<Vec<Report>> {
+        Ok(vec![Report::executable(
+            api,
+            "git",
+            self.name(),
+            "Install git from https://git-scm.com",
+        )?])
+    }
+
+    fn complete(
+        &self,
+        lua: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Vec<CompletionItem>> {
+        Ok(match self.complete_in_background(lua, api, cursor)? {
+            Some(job) => job(&Cancellation::never()),
+            None => Vec::new(),
+        })
+    }
+
+    fn complete_in_background(
+        &self,
+        _: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Option<Job>> {
+        let line_pre = cursor.line[..cursor.bytes as usize].to_owned();
+        let prefix = cursor.keyword_pre().to_owned();
+
+        let is_trailer = co_author_query(&line_pre).is_some();
+        if prefix.is_empty() && !is_trailer {
+            return Ok(None);
+        }
+
+        // The commit message is edited in a file inside the `.git`
+        // directory, which `git` is happy to be run from.
+        let dir = PathBuf::from(
+            api.call_function::<_, String>("expand", vec!["%:p:h"])?,
+        );
+
+        let repos = self.repos.clone();
+        let max_commits = self.max_commits;
+
+        Ok(Some(Box::new(move |cancellation| {
+            let repo = {
+                let repos = &mut *repos.lock().unwrap();
+                repos
+                    .entry(dir)
+                    .or_insert_with_key(|dir| {
+                        Arc::new(Repo::read(dir, max_commits))
+                    })
+                    .clone()
+            };
+
+            if cancellation.is_cancelled() {
+                return Vec::new();
+            }
+
+            match co_author_query(&line_pre) {
+                Some(query) => repo.complete_co_authors(query),
+                None => repo.complete_refs(&prefix, line_pre == prefix),
+            }
+        })))
+    }
+}
+
+impl Repo {
+    /// Reads the commits, branches and authors of the repository containing
+    /// `dir`. Whatever can't be read is left empty, e.g. everything if `dir`
+    /// isn't in a repository or if `git` isn't installed.
+    fn read(dir: &StdPath, max_commits: u32) -> Self {
+        let max_commits = format!("--max-count={max_commits}");
+
+        let commits = git(dir, &["log", &max_commits, "--format=%h%x09%s"])
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(hash, subject)| (hash.to_owned(), subject.to_owned()))
+            .collect();
+
+        let branches = git(
+            dir,
+            &[
+                "for-each-ref",
+                "--format=%(refname:short)",
+                "refs/heads",
+                "refs/remotes",
+            ],
+        )
+        .lines()
+        .filter(|branch| !branch.ends_with("/HEAD"))
+        .map(String::from)
+        .collect();
+
+        let mut authors = git(dir, &["log", "--format=%aN <%aE>"])
+            .lines()
+            .map(String::from)
+            .collect::<Vec<String>>();
+        authors.sort();
+        authors.dedup();
+
+        Repo {
+            commits,
+            branches,
+            authors,
+        }
+    }
+
+    /// Completes the name and email of an author after a `Co-authored-by:`
+    /// trailer.
+    fn complete_co_authors(&self, query: &str) -> Vec<CompletionItem> {
+        self.authors
+            .iter()
+            .filter(|&author| author != query)
+            .filter_map(|author| {
+                let (score, hl_ranges) = match query.is_empty() {
+                    true => (0, Vec::new()),
+                    false => {
+                        let m = matcher::fuzzy_match(query, author)?;
+                        (m.score, m.hl_ranges(1))
+                    },
+                };
+                Some(item(
+                    author.clone(),
+                    None,
+                    "Text",
+                    query,
+                    score,
+                    hl_ranges,
+                ))
+            })
+            .collect()
+    }
+
+    /// Completes commit hashes, branch names and, at the start of a line,
+    /// whole `Co-authored-by:` trailers.
+    fn complete_refs(
+        &self,
+        prefix: &str,
+        is_at_sol: bool,
+    ) -> Vec<CompletionItem> {
+        // Hashes are only completed by their prefix, fuzzy matching them
+        // would match almost every commit.
+        let commits = self
+            .commits
+            .iter()
+            .filter(|(hash, _)| hash.starts_with(prefix) && hash != prefix)
+            .map(|(hash, subject)| {
+                let details = Details {
+                    lines: vec![subject.clone()],
+                    is_markdown: false,
+                };
+                let hl_ranges =
+                    vec![(1..1 + prefix.len(), "CompleetMenuMatchingChars")];
+                item(
+                    hash.clone(),
+                    Some(details),
+                    "Reference",
+                    prefix,
+                    0,
+                    hl_ranges,
+                )
+            });
+
+        let branches = self
+            .branches
+            .iter()
+            .filter(|&branch| branch != prefix)
+            .filter_map(|branch| {
+                matcher::fuzzy_match(prefix, branch).map(|m| {
+                    item(
+                        branch.clone(),
+                        None,
+                        "Reference",
+                        prefix,
+                        m.score,
+                        m.hl_ranges(1),
+                    )
+                })
+            });
+
+        let authors = if is_at_sol { &self.authors[..] } else { &[] };
+        let trailers = authors
+            .iter()
+            .map(|author| format!("{CO_AUTHOR_TRAILER} {author}"))
+            .filter_map(|trailer| {
+                matcher::fuzzy_match(prefix, &trailer).map(|m| {
+                    let hl_ranges = m.hl_ranges(1);
+                    item(trailer, None, "Text", prefix, m.score, hl_ranges)
+                })
+            });
+
+        commits.chain(branches).chain(trailers).collect()
+    }
+}
+
+fn item(
+    text: String,
+    details: Option<Details>,
+    kind: &'static str,
+    matched: &str,
+    score: i32,
+    hl_ranges: Vec<(Range<usize>, &'static str)>,
+) -> CompletionItem {
+    CompletionItem {
+        details,
+        format: format!(" {text}"),
+        hl_ranges,
+        kind: Some(kind),
+        matched_bytes: matched.len() as u32,
+        preselect: false,
+        score,
+        snippet: None,
+        source: "Git",
+        text,
+        ..Default::default()
+    }
+}
+
+/// Runs `git` in `dir`, returning its output or an empty string if it failed.
+fn git(dir: &StdPath, args: &[&str]) -> String {
+    Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .unwrap_or_default()
+}
+
+/// Returns the text typed after a `Co-authored-by:` trailer at the start of
+/// the line, or `None` if the line isn't a trailer.
+fn co_author_query(line_pre: &str) -> Option<&str> {
+    let (trailer, query) =
+        line_pre.split_at_checked(CO_AUTHOR_TRAILER.len())?;
+    trailer
+        .eq_ignore_ascii_case(CO_AUTHOR_TRAILER)
+        .then(|| query.trim_start())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::co_author_query;
+
+    #[test]
+    fn co_author_trailers() {
+        assert_eq!(Some(""), co_author_query("Co-authored-by:"));
+        assert_eq!(Some("Jo"), co_author_query("Co-authored-by: Jo"));
+        assert_eq!(Some("Jo"), co_author_query("co-authored-by: Jo"));
+        assert_eq!(None, co_author_query("Co-author"));
+        assert_eq!(None, co_author_query("Fix the thing"));
+        assert_eq!(None, co_author_query("See Co-authored-by: Jo"));
+    }
+}
@@ -1,8 +1,15 @@
-use mlua::prelude::LuaResult;
+use mlua::prelude::{Lua, LuaResult};
 use neovim::Api;
 use serde::Deserialize;
 
-use crate::completion::{CompletionItem, CompletionSource, Cursor};
+use crate::completion::{
+    matcher,
+    CompletionItem,
+    CompletionSource,
+    Cursor,
+    Details,
+};
+use crate::settings::sources::{Contexts, Enable};
 
 const LOREM_IPSUM: [&'static str; 12] = [
     "Lorem ipsum dolor sit amet, consectetuer adipiscing elit. Ut purus elit,",
@@ -158,20 +165,22 @@ const LOREMS: [&'static str; 128] = [
     "dignissim",
 ];
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct Lipsum {
-    pub enable: bool,
-}
+    pub enable: Enable,
 
-impl Default for Lipsum {
-    fn default() -> Self { Lipsum { enable: false } }
+    #[serde(default)]
+    pub contexts: Contexts,
 }
 
 impl CompletionSource for Lipsum {
-    fn attach(&self, _: &Api, _: u32) -> LuaResult<bool> { Ok(true) }
+    fn name(&self) -> &'static str { "Lipsum" }
+
+    fn attach(&self, _: &Lua, _: &Api, _: u32) -> LuaResult<bool> { Ok(true) }
 
     fn complete(
         &self,
+        _: &Lua,
         _: &Api,
         cursor: &Cursor,
     ) -> LuaResult<Vec<CompletionItem>> {
@@ -186,19 +195,25 @@ impl CompletionSource for Lipsum {
 
         Ok(LOREMS
             .iter()
-            .filter(|&&word| word.starts_with(word_pre) && word != word_pre)
-            .map(|&word| CompletionItem {
-                details: Some(
-                    LOREM_IPSUM.map(|word| word.to_string()).to_vec(),
-                ),
+            .filter(|&&word| word != word_pre)
+            .filter_map(|&word| {
+                matcher::fuzzy_match(word_pre, word).map(|m| (word, m))
+            })
+            .map(|(word, m)| CompletionItem {
+                details: Some(Details {
+                    lines: LOREM_IPSUM.map(|word| word.to_string()).to_vec(),
+                    is_markdown: false,
+                }),
                 format: format!(" {}", word),
-                hl_ranges: vec![(
-                    1..word_pre.len() + 1,
-                    "CompleetMenuMatchingChars",
-                )],
+                hl_ranges: m.hl_ranges(1),
+                kind: Some("Text"),
                 matched_bytes: word_pre.len() as u32,
+                preselect: false,
+                score: m.score,
+                snippet: None,
                 source: "Lipsum",
                 text: word.to_string(),
+                ..Default::default()
             })
             .collect())
     }
@@ -0,0 +1,833 @@
+use std::sync::{Arc, Mutex};
+
+use mlua::prelude::{
+    Lua,
+    LuaFunction,
+    LuaRegistryKey,
+    LuaResult,
+    LuaTable,
+    LuaValue,
+};
+use neovim::{Api, Neovim};
+use serde::Deserialize;
+
+use super::text_edits::{self, TextEdit};
+use crate::completion::{
+    self,
+    matcher,
+    CompletionItem,
+    CompletionSource,
+    Cursor,
+    Details,
+};
+use crate::health::Report;
+use crate::settings::sources::{Contexts, Enable};
+use crate::snippets::Snippet;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Lsp {
+    pub enable: Enable,
+
+    #[serde(default)]
+    pub contexts: Contexts,
+
+    /// Holds the results of the last `textDocument/completion` request. It's
+    /// shared with the response handlers, which are executed asynchronously
+    /// by Neovim's event loop.
+    #[serde(skip)]
+    cache: Arc<Mutex<LspCache>>,
+}
+
+#[derive(Debug, Default)]
+struct LspCache {
+    /// A registry key pointing to the function used to cancel the last
+    /// request, or `None` if no request has been sent yet.
+    cancel: Option<LuaRegistryKey>,
+
+    /// Incremented every time a new request is sent. Used to discard the
+    /// responses to stale requests.
+    generation: u64,
+
+    /// Whether at least one of the servers flagged its results as
+    /// incomplete, meaning further typing should trigger a new request.
+    is_incomplete: bool,
+
+    /// The id given to the next item added to the cache.
+    next_id: u64,
+
+    /// Whether no server has responded to the last request yet.
+    is_waiting: bool,
+
+    /// The completion items returned by the servers, converted to an
+    /// intermediate representation that can be filtered as the user keeps
+    /// typing.
+    items: Vec<LspItem>,
+
+    /// Whether the items should be cleared when the next response arrives.
+    /// Set after a request is sent so that the old results can be displayed
+    /// until the new ones are available.
+    replace_on_response: bool,
+
+    /// The cursor column (in bytes) where the last request was sent.
+    request_col: u32,
+
+    /// The `(row, col)` position (with the column in bytes) of the start of
+    /// the word where the last request was sent, or `None` if no request has
+    /// been sent yet.
+    word_start: Option<(u32, u32)>,
+}
+
+#[derive(Debug)]
+struct LspItem {
+    /// The edits to apply along with the item when it's confirmed, e.g. to
+    /// import the symbol being completed.
+    additional_edits: Vec<TextEdit>,
+
+    /// The text to display in the details window.
+    details: Option<Details>,
+
+    /// The id of the client that sent the item.
+    client_id: u32,
+
+    /// The start column (in UTF-16 code units) of the range replaced by the
+    /// item's `textEdit`, if it has one.
+    edit_start: Option<u32>,
+
+    /// Unique among all the items cached since the source was created, and
+    /// copied into the `id` of the completions created from the item.
+    id: u64,
+
+    /// The text used to filter the item against the completion prefix.
+    filter_text: Option<String>,
+
+    /// The name of the item's `CompletionItemKind`.
+    kind: Option<&'static str>,
+
+    /// The text shown in the completion menu.
+    label: String,
+
+    /// Whether the server wants the item to be selected when the menu is
+    /// opened.
+    preselect: bool,
+
+    /// A registry key pointing to the item as it was sent by the server if
+    /// its documentation can be fetched with a `completionItem/resolve`
+    /// request, or `None` if it can't or if the request was already sent.
+    resolvable: Option<LuaRegistryKey>,
+
+    /// The body of the snippet to expand, if the item's `insertTextFormat`
+    /// is `Snippet`.
+    snippet: Option<String>,
+
+    /// The text used to sort the item relative to the other items.
+    sort_text: Option<String>,
+
+    /// The text that will be inserted in the buffer.
+    text: String,
+}
+
+/*
+TODOs:
+
+1. remove source on `LspStop` or if the server quits;
+
+2. add the source on `LspStart`.
+*/
+
+impl CompletionSource for Lsp {
+    fn name(&self) -> &'static str { "Lsp" }
+
+    fn attach(&self, _: &Lua, _: &Api, _bufnr: u32) -> LuaResult<bool> {
+        // Language servers usually attach to a buffer some time after the
+        // `BufEnter` event we get called on, so we always attach and check
+        // for clients capable of completion in `complete`.
+        Ok(true)
+    }
+
+    fn check_health(&self, lua: &Lua, _: &Api) -> LuaResult<Vec<Report>> {
+        let clients = Neovim::new(lua)?.lsp()?.get_active_clients()?;
+        Ok(vec![match has_completion_clients(&clients)? {
+            true => Report::ok("A language server can complete"),
+            false => Report::warn(
+                "No running language server can complete",
+                &["Check that a server is configured for the filetypes you \
+                   edit, e.g. with `:LspInfo`"],
+            ),
+        }])
+    }
+
+    fn trigger_characters(
+        &self,
+        lua: &Lua,
+        _: &Api,
+    ) -> LuaResult<Vec<String>> {
+        trigger_characters(&Neovim::new(lua)?.lsp()?.buf_get_clients(0)?)
+    }
+
+    fn complete(
+        &self,
+        lua: &Lua,
+        _: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Vec<CompletionItem>> {
+        let nvim = Neovim::new(lua)?;
+
+        let clients = nvim.lsp()?.buf_get_clients(0)?;
+        if !has_completion_clients(&clients)? {
+            return Ok(Vec::new());
+        }
+
+        // Without a keyword before the cursor a request is only sent right
+        // after one of the servers' trigger characters.
+        let prefix = cursor.keyword_pre();
+        let triggers = trigger_characters(&clients)?;
+        let trigger = match prefix.is_empty() {
+            true => match completion::find_trigger(
+                &cursor.line[..cursor.bytes as usize],
+                &triggers,
+            ) {
+                Some(trigger) => Some(trigger),
+                None => return Ok(Vec::new()),
+            },
+            false => None,
+        };
+
+        let word_start = (cursor.row, cursor.bytes - prefix.len() as u32);
+
+        let mut cache = self.cache.lock().unwrap();
+
+        let is_new_word = cache.word_start != Some(word_start);
+        let is_stale =
+            cache.is_incomplete && cache.request_col != cursor.bytes;
+
+        if is_new_word || is_stale {
+            if is_new_word {
+                cache.items.clear();
+            }
+
+            if let Some(key) = cache.cancel.take() {
+                lua.registry_value::<mlua::Function>(&key)?
+                    .call::<_, ()>(())?;
+                lua.remove_registry_value(key)?;
+            }
+
+            cache.generation += 1;
+            cache.is_waiting = true;
+            cache.replace_on_response = true;
+            cache.request_col = cursor.bytes;
+            cache.word_start = Some(word_start);
+
+            let generation = cache.generation;
+
+            // The lock has to be released before sending the request since
+            // the response handler needs to acquire it.
+            drop(cache);
+
+            let cancel = self
+                .request(lua, &nvim, cursor, is_stale, trigger, generation)?;
+            let key = lua.create_registry_value(cancel)?;
+
+            let mut cache = self.cache.lock().unwrap();
+            cache.cancel = Some(key);
+
+            return Ok(cache.to_completions(cursor, prefix));
+        }
+
+        Ok(cache.to_completions(cursor, prefix))
+    }
+
+    fn is_loading(&self) -> bool { self.cache.lock().unwrap().is_waiting }
+
+    fn has_callbacks(&self) -> bool { true }
+
+    fn resolve(
+        &self,
+        lua: &Lua,
+        api: &Api,
+        completion: &CompletionItem,
+    ) -> LuaResult<()> {
+        let (client_id, key, generation) = {
+            let cache = &mut *self.cache.lock().unwrap();
+            match cache.find(completion).and_then(|item| {
+                Some((item.client_id, item.resolvable.take()?))
+            }) {
+                Some((client_id, key)) => (client_id, key, cache.generation),
+                None => return Ok(()),
+            }
+        };
+
+        let item = lua.registry_value::<LuaTable>(&key)?;
+        lua.remove_registry_value(key)?;
+
+        let client =
+            match Neovim::new(lua)?.lsp()?.get_client_by_id(client_id)? {
+                Some(client) => client,
+                None => return Ok(()),
+            };
+
+        let filetype = api.buf_get_option::<String>(0, "filetype")?;
+        let (label, text) =
+            (item.get::<_, String>("label")?, completion.text.clone());
+
+        let cache = self.cache.clone();
+        let handler = lua.create_function(
+            move |lua, (err, result): (LuaValue, LuaValue)| {
+                let resolved = match (err, result) {
+                    (LuaValue::Nil, LuaValue::Table(result)) => {
+                        parse_item(result, client_id, &filetype)?
+                    },
+                    _ => return Ok(()),
+                };
+
+                let cache = &mut *cache.lock().unwrap();
+
+                if cache.generation != generation {
+                    return Ok(());
+                }
+
+                if let Some(item) = cache
+                    .items
+                    .iter_mut()
+                    .find(|item| item.label == label && item.text == text)
+                {
+                    // Some servers only send the edits of the resolved items.
+                    if !resolved.additional_edits.is_empty() {
+                        item.additional_edits = resolved.additional_edits;
+                    }
+                    if resolved.details.is_some() {
+                        item.details = resolved.details;
+                        completion::schedule_refresh(lua)?;
+                    }
+                }
+
+                Ok(())
+            },
+        )?;
+
+        client.get::<_, LuaFunction>("request")?.call::<_, ()>((
+            "completionItem/resolve",
+            item,
+            handler,
+            0,
+        ))
+    }
+
+    fn confirm(
+        &self,
+        lua: &Lua,
+        api: &Api,
+        completion: &CompletionItem,
+    ) -> LuaResult<()> {
+        let edits = match self.cache.lock().unwrap().find(completion) {
+            Some(item) => item.additional_edits.clone(),
+            None => return Ok(()),
+        };
+
+        text_edits::apply(lua, api, &edits)
+    }
+}
+
+impl Lsp {
+    /// Sends a `textDocument/completion` request to all the clients attached
+    /// to the current buffer, returning the function used to cancel it.
+    fn request<'lua>(
+        &self,
+        lua: &'lua Lua,
+        nvim: &Neovim<'lua>,
+        cursor: &Cursor,
+        is_incomplete: bool,
+        trigger: Option<&str>,
+        generation: u64,
+    ) -> LuaResult<mlua::Function<'lua>> {
+        let text_document =
+            lua.create_table_from([("uri", nvim.uri_from_bufnr(0)?)])?;
+
+        let position = lua.create_table_from([
+            ("line", cursor.row),
+            (
+                "character",
+                byte_to_utf16(&cursor.line, cursor.bytes as usize),
+            ),
+        ])?;
+
+        // A `triggerKind` of 1 means the completion was triggered by typing
+        // an identifier, 2 by typing one of the server's trigger characters
+        // and 3 that it was re-triggered because the current completion list
+        // is incomplete.
+        let context = lua.create_table_with_capacity(0, 2)?;
+        match (is_incomplete, trigger) {
+            (true, _) => context.set("triggerKind", 3)?,
+            (false, Some(trigger)) => {
+                context.set("triggerKind", 2)?;
+                context.set("triggerCharacter", trigger)?;
+            },
+            (false, None) => context.set("triggerKind", 1)?,
+        }
+
+        let params = lua.create_table_with_capacity(0, 3)?;
+        params.set("textDocument", text_document)?;
+        params.set("position", position)?;
+        params.set("context", context)?;
+
+        // Used to highlight the signatures in the details window.
+        let filetype = nvim.api.buf_get_option::<String>(0, "filetype")?;
+
+        let cache = self.cache.clone();
+        let handler = lua.create_function(
+            move |lua,
+                  (err, result, ctx, _config): (
+                LuaValue,
+                LuaValue,
+                LuaTable,
+                LuaValue,
+            )| {
+                let client_id = ctx.get::<_, u32>("client_id")?;
+                let response = match (err, result) {
+                    (LuaValue::Nil, LuaValue::Table(result)) => Some(
+                        parse_response(lua, result, client_id, &filetype)?,
+                    ),
+                    _ => None,
+                };
+
+                let cache = &mut *cache.lock().unwrap();
+
+                if cache.generation != generation {
+                    return Ok(());
+                }
+
+                // A failed request still stops the loading indicator.
+                let was_waiting = std::mem::take(&mut cache.is_waiting);
+
+                let (items, is_incomplete) = match response {
+                    Some(response) => response,
+                    None if was_waiting => {
+                        return completion::schedule_refresh(lua)
+                    },
+                    None => return Ok(()),
+                };
+
+                if cache.replace_on_response {
+                    cache.items.clear();
+                    cache.is_incomplete = false;
+                    cache.replace_on_response = false;
+                    // Frees the raw items of the old results.
+                    lua.expire_registry_values();
+                }
+
+                for mut item in items {
+                    item.id = cache.next_id;
+                    cache.next_id += 1;
+                    cache.items.push(item);
+                }
+                cache.is_incomplete |= is_incomplete;
+
+                // Let the rest of the plugin know there are new completions
+                // available.
+                completion::schedule_refresh(lua)
+            },
+        )?;
+
+        nvim.lsp()?
+            .buf_request(0, "textDocument/completion", params, handler)
+    }
+}
+
+impl LspCache {
+    /// Returns the cached item a completion was created from.
+    fn find(&mut self, completion: &CompletionItem) -> Option<&mut LspItem> {
+        self.items.iter_mut().find(|item| item.id == completion.id)
+    }
+
+    /// Filters the cached items against the text before the cursor,
+    /// converting the ones that match into `CompletionItem`s.
+    fn to_completions(
+        &self,
+        cursor: &Cursor,
+        prefix: &str,
+    ) -> Vec<CompletionItem> {
+        let line_pre = &cursor.line[..cursor.bytes as usize];
+
+        let mut matches = self
+            .items
+            .iter()
+            .filter_map(|item| {
+                // The completion prefix either starts where the item's
+                // `textEdit` says it does or at the start of the current
+                // keyword.
+                let prefix = match item.edit_start {
+                    Some(col) => match utf16_to_byte(line_pre, col) {
+                        start if start <= line_pre.len() => &line_pre[start..],
+                        _ => return None,
+                    },
+                    None => prefix,
+                };
+
+                if item.text == prefix {
+                    return None;
+                }
+
+                let filter_text =
+                    item.filter_text.as_ref().unwrap_or(&item.label);
+
+                let score = matcher::fuzzy_match(prefix, filter_text)?.score;
+
+                Some((item, prefix, score))
+            })
+            .collect::<Vec<(&LspItem, &str, i32)>>();
+
+        // Sort by score first, using the order suggested by the server to
+        // break ties.
+        matches.sort_by(|(a, _, a_score), (b, _, b_score)| {
+            b_score.cmp(a_score).then_with(|| {
+                a.sort_text
+                    .as_ref()
+                    .unwrap_or(&a.label)
+                    .cmp(b.sort_text.as_ref().unwrap_or(&b.label))
+            })
+        });
+
+        matches
+            .into_iter()
+            .map(|(item, prefix, score)| CompletionItem {
+                details: item.details.clone(),
+                format: format!(" {}", item.label),
+                // The matched characters are highlighted in the label, which
+                // can be different from the filter text.
+                hl_ranges: matcher::fuzzy_match(prefix, &item.label)
+                    .map(|m| m.hl_ranges(1))
+                    .unwrap_or_default(),
+                id: item.id,
+                kind: item.kind,
+                matched_bytes: prefix.len() as u32,
+                preselect: item.preselect,
+                score,
+                snippet: item.snippet.clone(),
+                source: "Lsp",
+                text: item.text.clone(),
+                ..Default::default()
+            })
+            .collect()
+    }
+}
+
+/// Whether the client with the given id can resolve the completion items it
+/// sends, i.e. if its `completionProvider` has the `resolveProvider` set.
+fn can_resolve(lua: &Lua, client_id: u32) -> LuaResult<bool> {
+    let client = match Neovim::new(lua)?.lsp()?.get_client_by_id(client_id)? {
+        Some(client) => client,
+        None => return Ok(false),
+    };
+
+    let provider = match client.get::<_, LuaValue>("server_capabilities")? {
+        LuaValue::Table(capabilities) => {
+            capabilities.get::<_, LuaValue>("completionProvider")?
+        },
+        _ => return Ok(false),
+    };
+
+    match provider {
+        LuaValue::Table(provider) => Ok(provider
+            .get::<_, Option<bool>>("resolveProvider")?
+            .unwrap_or(false)),
+        _ => Ok(false),
+    }
+}
+
+/// Whether at least one of the `clients` attached to a buffer is capable of
+/// providing completions.
+fn has_completion_clients(clients: &LuaTable) -> LuaResult<bool> {
+    for pair in clients.clone().pairs::<LuaValue, LuaTable>() {
+        let (_, client) = pair?;
+        let capabilities = client.get::<_, LuaValue>("server_capabilities")?;
+        if let LuaValue::Table(capabilities) = capabilities {
+            if capabilities.contains_key("completionProvider")? {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Returns the trigger characters of all the `clients` attached to a buffer.
+fn trigger_characters(clients: &LuaTable) -> LuaResult<Vec<String>> {
+    let mut triggers = Vec::new();
+    for pair in clients.clone().pairs::<LuaValue, LuaTable>() {
+        let (_, client) = pair?;
+        let provider =
+            match client.get::<_, LuaValue>("server_capabilities")? {
+                LuaValue::Table(capabilities) => {
+                    capabilities.get::<_, LuaValue>("completionProvider")?
+                },
+                _ => continue,
+            };
+        if let LuaValue::Table(provider) = provider {
+            if let Some(chars) =
+                provider.get::<_, Option<Vec<String>>>("triggerCharacters")?
+            {
+                triggers.extend(chars);
+            }
+        }
+    }
+    Ok(triggers)
+}
+
+/// Parses the result of a `textDocument/completion` request, which can either
+/// be a `CompletionList` or an array of `CompletionItem`s. Returns the parsed
+/// items together with the value of the list's `isIncomplete` field.
+fn parse_response<'lua>(
+    lua: &'lua Lua,
+    result: LuaTable<'lua>,
+    client_id: u32,
+    filetype: &str,
+) -> LuaResult<(Vec<LspItem>, bool)> {
+    let (items, is_incomplete) = match result.get::<_, LuaValue>("items")? {
+        LuaValue::Table(items) => (
+            items,
+            result
+                .get::<_, Option<bool>>("isIncomplete")?
+                .unwrap_or(false),
+        ),
+        _ => (result, false),
+    };
+
+    let can_resolve = can_resolve(lua, client_id)?;
+
+    let items = items
+        .sequence_values::<LuaTable>()
+        .map(|item| {
+            let item = item?;
+            let mut parsed = parse_item(item.clone(), client_id, filetype)?;
+            // Only the items missing their documentation need resolving.
+            if can_resolve && !item.contains_key("documentation")? {
+                parsed.resolvable = Some(lua.create_registry_value(item)?);
+            }
+            Ok(parsed)
+        })
+        .collect::<LuaResult<Vec<LspItem>>>()?;
+
+    Ok((items, is_incomplete))
+}
+
+/// Converts an LSP `CompletionItem` into an `LspItem`, where `filetype` is
+/// the filetype of the buffer the completion was requested in.
+fn parse_item(
+    item: LuaTable,
+    client_id: u32,
+    filetype: &str,
+) -> LuaResult<LspItem> {
+    let label = item.get::<_, String>("label")?;
+
+    let (edit_text, edit_start) = match item.get::<_, LuaValue>("textEdit")? {
+        LuaValue::Table(edit) => {
+            // A `textEdit` is either a `TextEdit` with a `range` or an
+            // `InsertReplaceEdit` with an `insert` and a `replace` range.
+            let range = match edit.get::<_, LuaValue>("range")? {
+                LuaValue::Table(range) => range,
+                _ => edit.get::<_, LuaTable>("insert")?,
+            };
+            let start = range.get::<_, LuaTable>("start")?;
+            (
+                Some(edit.get::<_, String>("newText")?),
+                Some(start.get::<_, u32>("character")?),
+            )
+        },
+        _ => (None, None),
+    };
+
+    let text = edit_text
+        .or(item.get::<_, Option<String>>("insertText")?)
+        .unwrap_or_else(|| label.clone());
+
+    // An `insertTextFormat` of 2 means the text is a snippet, in which case
+    // we filter and hint the text of the expanded snippet.
+    let (text, snippet) =
+        match item.get::<_, Option<u8>>("insertTextFormat")? {
+            Some(2) => (Snippet::parse(&text).text, Some(text)),
+            _ => (text, None),
+        };
+
+    let documentation = match item.get::<_, LuaValue>("documentation")? {
+        LuaValue::String(s) => Some((s.to_str()?.to_owned(), false)),
+        // `MarkupContent`.
+        LuaValue::Table(t) => {
+            t.get::<_, Option<String>>("value")?.map(|value| {
+                (
+                    value,
+                    t.get::<_, String>("kind").unwrap_or_default()
+                        == "markdown",
+                )
+            })
+        },
+        _ => None,
+    };
+
+    Ok(LspItem {
+        additional_edits: text_edits::parse(&item)?,
+        details: get_details(
+            item.get::<_, Option<String>>("detail")?,
+            documentation,
+            filetype,
+        ),
+        client_id,
+        edit_start,
+        id: 0,
+        filter_text: item.get("filterText")?,
+        kind: item.get::<_, Option<u8>>("kind")?.and_then(kind_name),
+        label,
+        preselect: item.get::<_, Option<bool>>("preselect")?.unwrap_or(false),
+        resolvable: None,
+        snippet,
+        sort_text: item.get("sortText")?,
+        text,
+    })
+}
+
+/// Returns the details of an item given its `detail` and its `documentation`,
+/// together with whether the documentation is written in markdown. If the
+/// details are rendered as markdown the `detail`, which is usually a type
+/// signature, is put in a code block highlighted as `filetype`.
+fn get_details(
+    detail: Option<String>,
+    documentation: Option<(String, bool)>,
+    filetype: &str,
+) -> Option<Details> {
+    let is_markdown = match &documentation {
+        Some((_, is_markdown)) => *is_markdown,
+        None => detail.is_some(),
+    };
+
+    let mut lines = Vec::new();
+
+    if let Some(detail) = detail {
+        if is_markdown {
+            lines.push(format!("```{filetype}"));
+        }
+        lines.extend(detail.lines().map(String::from));
+        if is_markdown {
+            lines.push("```".into());
+        }
+    }
+
+    if let Some((documentation, _)) = documentation {
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.extend(documentation.lines().map(String::from));
+    }
+
+    (!lines.is_empty()).then_some(Details { lines, is_markdown })
+}
+
+/// Returns the name of an LSP `CompletionItemKind`.
+fn kind_name(kind: u8) -> Option<&'static str> {
+    Some(match kind {
+        1 => "Text",
+        2 => "Method",
+        3 => "Function",
+        4 => "Constructor",
+        5 => "Field",
+        6 => "Variable",
+        7 => "Class",
+        8 => "Interface",
+        9 => "Module",
+        10 => "Property",
+        11 => "Unit",
+        12 => "Value",
+        13 => "Enum",
+        14 => "Keyword",
+        15 => "Snippet",
+        16 => "Color",
+        17 => "File",
+        18 => "Reference",
+        19 => "Folder",
+        20 => "EnumMember",
+        21 => "Constant",
+        22 => "Struct",
+        23 => "Event",
+        24 => "Operator",
+        25 => "TypeParameter",
+        _ => return None,
+    })
+}
+
+/// Converts a byte offset in `line` into an offset in UTF-16 code units,
+/// which is how the LSP spec measures columns.
+fn byte_to_utf16(line: &str, byte: usize) -> u32 {
+    line[..byte].encode_utf16().count() as u32
+}
+
+/// Converts an offset in UTF-16 code units into a byte offset in `line`.
+pub(super) fn utf16_to_byte(line: &str, col: u32) -> usize {
+    let mut units = 0;
+    for (byte, char) in line.char_indices() {
+        if units >= col as usize {
+            return byte;
+        }
+        units += char.len_utf16();
+    }
+    line.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{byte_to_utf16, get_details, utf16_to_byte};
+    use crate::completion::Details;
+
+    #[test]
+    fn plaintext_details() {
+        assert_eq!(
+            Some(Details {
+                lines: vec!["fn foo()".into(), "".into(), "Does foo.".into()],
+                is_markdown: false,
+            }),
+            get_details(
+                Some("fn foo()".into()),
+                Some(("Does foo.".into(), false)),
+                "rust"
+            )
+        );
+    }
+
+    #[test]
+    fn markdown_details() {
+        assert_eq!(
+            Some(Details {
+                lines: vec![
+                    "```rust".into(),
+                    "fn foo()".into(),
+                    "```".into(),
+                    "".into(),
+                    "Does *foo*.".into(),
+                ],
+                is_markdown: true,
+            }),
+            get_details(
+                Some("fn foo()".into()),
+                Some(("Does *foo*.".into(), true)),
+                "rust"
+            )
+        );
+        assert_eq!(None, get_details(None, None, "rust"));
+    }
+
+    #[test]
+    fn utf16_roundtrip_ascii() {
+        assert_eq!(3, byte_to_utf16("foobar", 3));
+        assert_eq!(3, utf16_to_byte("foobar", 3));
+    }
+
+    #[test]
+    // Every `ö` is 2 bytes long and 1 UTF-16 code unit, the `🎉` is 4 bytes
+    // long and 2 UTF-16 code units.
+    fn utf16_roundtrip_multibyte() {
+        let line = "föö🎉bar";
+        assert_eq!(3, byte_to_utf16(line, "föö".len()));
+        assert_eq!(5, byte_to_utf16(line, "föö🎉".len()));
+        assert_eq!("föö🎉".len(), utf16_to_byte(line, 5));
+    }
+
+    #[test]
+    fn utf16_past_end_of_line() {
+        assert_eq!("foo".len(), utf16_to_byte("foo", 10))
+    }
+}
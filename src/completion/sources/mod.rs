@@ -1,5 +1,44 @@
+mod buffer;
+mod calc;
+mod conditional;
+mod dictionary;
+mod digraph;
+mod emoji;
+mod env;
+mod external;
+mod git;
 mod lipsum;
 mod lsp;
+mod nvim_lua;
+mod path;
+mod postfix;
+mod ripgrep;
+mod snippets;
+mod spell;
+mod tags;
+mod text_edits;
+mod tmux;
+mod treesitter;
+mod user_source;
 
+pub use buffer::Buffer;
+pub use calc::Calc;
+pub use conditional::Conditional;
+pub use dictionary::Dictionary;
+pub use digraph::Digraph;
+pub use emoji::Emoji;
+pub use env::Env;
+pub use external::External;
+pub use git::Git;
 pub use lipsum::Lipsum;
 pub use lsp::Lsp;
+pub use nvim_lua::NvimLua;
+pub use path::Path;
+pub use postfix::Postfix;
+pub use ripgrep::Ripgrep;
+pub use snippets::Snippets;
+pub use spell::Spell;
+pub use tags::Tags;
+pub use tmux::Tmux;
+pub use treesitter::Treesitter;
+pub use user_source::UserSource;
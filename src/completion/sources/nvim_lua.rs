@@ -0,0 +1,218 @@
+use mlua::prelude::{Lua, LuaResult, LuaTable, LuaValue};
+use neovim::Api;
+use serde::Deserialize;
+
+use crate::completion::{matcher, CompletionItem, CompletionSource, Cursor};
+use crate::settings::sources::{Contexts, Enable};
+
+/// The tables of option accessors, whose fields are the options.
+const OPTION_TABLES: &[&str] = &["o", "go", "bo", "wo", "opt", "opt_local"];
+
+#[derive(Debug, Default, Deserialize)]
+pub struct NvimLua {
+    pub enable: Enable,
+
+    #[serde(default)]
+    pub contexts: Contexts,
+}
+
+impl CompletionSource for NvimLua {
+    fn name(&self) -> &'static str { "NvimLua" }
+
+    fn attach(&self, _: &Lua, api: &Api, bufnr: u32) -> LuaResult<bool> {
+        Ok(api.buf_get_option::<String>(bufnr, "filetype")? == "lua")
+    }
+
+    fn trigger_characters(&self, _: &Lua, _: &Api) -> LuaResult<Vec<String>> {
+        Ok(vec![".".into()])
+    }
+
+    fn complete(
+        &self,
+        lua: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Vec<CompletionItem>> {
+        let (path, query) =
+            match dotted_path(&cursor.line[..cursor.bytes as usize]) {
+                Some(parsed) => parsed,
+                None => return Ok(Vec::new()),
+            };
+
+        let fields = match path[..] {
+            // The fields of `vim.fn` are only created when they're accessed.
+            ["vim", "fn"] => api
+                .call_function::<_, Vec<String>>(
+                    "getcompletion",
+                    vec!["", "function"],
+                )?
+                .into_iter()
+                .map(|name| name.trim_end_matches(['(', ')']).to_owned())
+                // Script-local and autoload functions can't be called with
+                // `vim.fn.name`.
+                .filter(|name| !name.contains(['#', '<', ':']))
+                .map(|name| (name, "Function"))
+                .collect(),
+
+            ["vim", table] if OPTION_TABLES.contains(&table) => {
+                let info = api.get_all_options_info()?;
+                info.pairs::<String, LuaValue>()
+                    .map(|pair| pair.map(|(name, _)| (name, "Property")))
+                    .collect::<LuaResult<Vec<(String, &'static str)>>>()?
+            },
+
+            _ => match resolve(lua, &path)? {
+                Some(table) => fields(table)?,
+                None => return Ok(Vec::new()),
+            },
+        };
+
+        Ok(fields
+            .into_iter()
+            // Private fields are only completed when asked for.
+            .filter(|(name, _)| {
+                !name.starts_with('_') || query.starts_with('_')
+            })
+            .filter(|(name, _)| name != query)
+            .filter_map(|(name, kind)| {
+                let (score, hl_ranges) = match query.is_empty() {
+                    true => (0, Vec::new()),
+                    false => {
+                        let m = matcher::fuzzy_match(query, &name)?;
+                        (m.score, m.hl_ranges(1))
+                    },
+                };
+
+                Some(CompletionItem {
+                    details: None,
+                    format: format!(" {name}"),
+                    hl_ranges,
+                    kind: Some(kind),
+                    matched_bytes: query.len() as u32,
+                    preselect: false,
+                    score,
+                    snippet: None,
+                    source: "NvimLua",
+                    text: name,
+                    ..Default::default()
+                })
+            })
+            .collect())
+    }
+}
+
+/// Looks up the table at the given path of fields in the live Lua state,
+/// starting either from a global or from a module loaded with `require`.
+fn resolve<'lua>(
+    lua: &'lua Lua,
+    path: &[&str],
+) -> LuaResult<Option<LuaTable<'lua>>> {
+    let (root, fields) = match path.split_first() {
+        Some(split) => split,
+        None => return Ok(None),
+    };
+
+    let mut value = match lua.globals().get::<_, LuaValue>(*root)? {
+        LuaValue::Nil => lua
+            .globals()
+            .get::<_, LuaTable>("package")?
+            .get::<_, LuaTable>("loaded")?
+            .get::<_, LuaValue>(*root)?,
+        value => value,
+    };
+
+    for &field in fields {
+        value = match value {
+            LuaValue::Table(table) => table.get(field)?,
+            _ => return Ok(None),
+        };
+    }
+
+    Ok(match value {
+        LuaValue::Table(table) => Some(table),
+        _ => None,
+    })
+}
+
+/// Returns the `(name, kind)` tuples of the string keys of a table.
+fn fields(table: LuaTable) -> LuaResult<Vec<(String, &'static str)>> {
+    let mut fields = Vec::new();
+
+    for pair in table.pairs::<LuaValue, LuaValue>() {
+        let (key, value) = pair?;
+
+        let name = match key {
+            LuaValue::String(name) => match name.to_str() {
+                Ok(name) => name.to_owned(),
+                Err(_) => continue,
+            },
+            _ => continue,
+        };
+
+        let kind = match value {
+            LuaValue::Function(_) => "Function",
+            LuaValue::Table(_) => "Module",
+            _ => "Field",
+        };
+
+        fields.push((name, kind));
+    }
+
+    Ok(fields)
+}
+
+/// Splits the dotted expression before the cursor into the path of the table
+/// being indexed and the name of the field being typed, e.g. `vim.api.nvim_`
+/// into `(["vim", "api"], "nvim_")`. Returns `None` if the cursor isn't after
+/// a dotted expression.
+fn dotted_path(line_pre: &str) -> Option<(Vec<&str>, &str)> {
+    let start = line_pre
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| {
+            c.is_ascii_alphanumeric() || c == '_' || c == '.'
+        })
+        .last()
+        .map(|(i, _)| i)?;
+
+    // Method calls and string concatenations aren't fields.
+    if line_pre[..start].ends_with([':', '.']) {
+        return None;
+    }
+
+    let (path, query) = line_pre[start..].rsplit_once('.')?;
+    let path = path.split('.').collect::<Vec<&str>>();
+
+    let is_identifier = |segment: &&str| {
+        segment
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+    };
+
+    path.iter().all(is_identifier).then_some((path, query))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::dotted_path;
+
+    #[test]
+    fn dotted_paths() {
+        assert_eq!(
+            Some((vec!["vim", "api"], "nvim_buf")),
+            dotted_path("local x = vim.api.nvim_buf")
+        );
+        assert_eq!(Some((vec!["vim"], "")), dotted_path("vim."));
+        assert_eq!(Some((vec!["string"], "fo")), dotted_path("(string.fo"));
+    }
+
+    #[test]
+    fn not_dotted_paths() {
+        assert_eq!(None, dotted_path("foo"));
+        assert_eq!(None, dotted_path("x = 1.5"));
+        assert_eq!(None, dotted_path("vim..api"));
+        assert_eq!(None, dotted_path("a ..b.c"));
+        assert_eq!(None, dotted_path("x = ."));
+    }
+}
@@ -0,0 +1,289 @@
+use std::env;
+use std::path::{Path as StdPath, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use mlua::prelude::{Lua, LuaResult, LuaValue};
+use neovim::{Api, Neovim};
+use serde::Deserialize;
+
+use crate::completion::{
+    self,
+    matcher,
+    CompletionItem,
+    CompletionSource,
+    Cursor,
+};
+use crate::settings::sources::{Contexts, Enable};
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Path {
+    pub enable: Enable,
+
+    #[serde(default)]
+    pub contexts: Contexts,
+
+    /// Holds the entries of the last directory that was read.
+    #[serde(skip)]
+    cache: Arc<Mutex<DirCache>>,
+}
+
+#[derive(Debug, Default)]
+struct DirCache {
+    /// The directory whose entries are cached, or `None` if no directory has
+    /// been read yet.
+    dir: Option<PathBuf>,
+
+    /// The `(name, is_directory)` tuples of all the entries of the directory.
+    entries: Vec<(String, bool)>,
+
+    /// Whether the directory is still being read.
+    is_pending: bool,
+}
+
+impl CompletionSource for Path {
+    fn name(&self) -> &'static str { "Path" }
+
+    fn attach(&self, _: &Lua, _: &Api, _: u32) -> LuaResult<bool> { Ok(true) }
+
+    fn trigger_characters(&self, _: &Lua, _: &Api) -> LuaResult<Vec<String>> {
+        Ok(vec!["/".into()])
+    }
+
+    fn complete(
+        &self,
+        lua: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Vec<CompletionItem>> {
+        let (dir, prefix) =
+            match parse_path(&cursor.line[..cursor.bytes as usize]) {
+                Some(parsed) => parsed,
+                None => return Ok(Vec::new()),
+            };
+
+        // Relative paths are resolved starting from the directory of the file
+        // in the current buffer, falling back to Neovim's current working
+        // directory for buffers that aren't associated to a file.
+        let buf_dir = match api
+            .call_function::<_, String>("expand", vec!["%:p:h"])?
+            .as_str()
+        {
+            "" => env::current_dir().unwrap_or_default(),
+            dir => PathBuf::from(dir),
+        };
+
+        let home = env::var_os("HOME").map(PathBuf::from);
+
+        let dir = match resolve_dir(dir, &buf_dir, home.as_deref()) {
+            Some(dir) => dir,
+            None => return Ok(Vec::new()),
+        };
+
+        let cache = &mut *self.cache.lock().unwrap();
+
+        if cache.dir.as_ref() != Some(&dir) {
+            cache.dir = Some(dir.clone());
+            cache.entries.clear();
+            cache.is_pending = true;
+            self.read_dir(lua, dir)?;
+            return Ok(Vec::new());
+        }
+
+        if cache.is_pending {
+            return Ok(Vec::new());
+        }
+
+        // Hidden files are only shown if the user has started typing their
+        // name.
+        let show_hidden = prefix.starts_with('.');
+
+        Ok(cache
+            .entries
+            .iter()
+            .filter(|(name, _)| show_hidden || !name.starts_with('.'))
+            .filter_map(|(name, is_dir)| {
+                let m = matcher::fuzzy_match(prefix, name)?;
+
+                // Directories are completed with a trailing slash so that
+                // their entries are completed right away.
+                let text = match is_dir {
+                    true => format!("{name}/"),
+                    false => name.clone(),
+                };
+
+                (text != prefix).then(|| CompletionItem {
+                    details: None,
+                    format: format!(" {}", text),
+                    hl_ranges: m.hl_ranges(1),
+                    kind: Some(match is_dir {
+                        true => "Folder",
+                        false => "File",
+                    }),
+                    matched_bytes: prefix.len() as u32,
+                    preselect: false,
+                    score: m.score,
+                    snippet: None,
+                    source: "Path",
+                    text,
+                    ..Default::default()
+                })
+            })
+            .collect())
+    }
+}
+
+impl Path {
+    /// Asynchronously reads the entries of `dir`, storing them in the cache
+    /// and asking for a refresh once it's done.
+    fn read_dir(&self, lua: &Lua, dir: PathBuf) -> LuaResult<()> {
+        let path = dir.to_string_lossy().into_owned();
+        let cache = self.cache.clone();
+
+        let callback = lua.create_function(
+            move |lua, (err, handle): (LuaValue, LuaValue)| {
+                let cache = &mut *cache.lock().unwrap();
+
+                // Another directory was requested while this one was being
+                // read.
+                if cache.dir.as_ref() != Some(&dir) {
+                    return Ok(());
+                }
+
+                cache.is_pending = false;
+
+                if !matches!(err, LuaValue::Nil) {
+                    return Ok(());
+                }
+
+                let uv = Neovim::new(lua)?.uv()?;
+                while let Some((name, r#type)) =
+                    uv.fs_scandir_next(handle.clone())?
+                {
+                    let is_dir = r#type.as_deref() == Some("directory");
+                    cache.entries.push((name, is_dir));
+                }
+
+                // List directories before files, then sort alphabetically.
+                cache.entries.sort_by(|(a, a_is_dir), (b, b_is_dir)| {
+                    b_is_dir.cmp(a_is_dir).then_with(|| a.cmp(b))
+                });
+
+                completion::schedule_refresh(lua)
+            },
+        )?;
+
+        Neovim::new(lua)?.uv()?.fs_scandir(&path, callback)
+    }
+}
+
+/// If the text before the cursor ends with something that looks like a path
+/// returns a `(dir, prefix)` tuple, where `dir` is the part of the path up to
+/// and including the last slash and `prefix` is the partial file name after
+/// it.
+fn parse_path(line_pre: &str) -> Option<(&str, &str)> {
+    let start = line_pre
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| !is_path_delimiter(c))
+        .last()
+        .map(|(i, _)| i)?;
+
+    let path = &line_pre[start..];
+
+    if !["/", "./", "../", "~/"].iter().any(|p| path.starts_with(p))
+        // A lone slash is more likely to be a division, a double slash a
+        // comment.
+        || path == "/"
+        || path.contains("//")
+    {
+        return None;
+    }
+
+    let slash = path.rfind('/').expect("the path contains a slash");
+
+    Some((&path[..=slash], &path[slash + 1..]))
+}
+
+/// Whether `c` can't be part of a path we want to complete.
+fn is_path_delimiter(c: char) -> bool {
+    const DELIMITERS: &[char] = &[
+        '"', '\'', '`', '(', ')', '[', ']', '{', '}', '<', '>', ',', ';', '=',
+    ];
+
+    c.is_whitespace() || DELIMITERS.contains(&c)
+}
+
+/// Turns the `dir` part of a parsed path into an absolute path, expanding `~`
+/// to the `home` directory and resolving relative paths starting from
+/// `base_dir`.
+fn resolve_dir(
+    dir: &str,
+    base_dir: &StdPath,
+    home: Option<&StdPath>,
+) -> Option<PathBuf> {
+    if let Some(rest) = dir.strip_prefix("~/") {
+        return home.map(|home| home.join(rest));
+    }
+
+    match dir.starts_with('/') {
+        true => Some(PathBuf::from(dir)),
+        false => Some(base_dir.join(dir)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{parse_path, resolve_dir};
+
+    // NOTE: the `|` in the following comments indicates the cursor position.
+
+    #[test]
+    // `foo(./src/co|`
+    fn relative_path_inside_parens() {
+        assert_eq!(Some(("./src/", "co")), parse_path("foo(./src/co"))
+    }
+
+    #[test]
+    // `cd ../|`
+    fn parent_dir_without_prefix() {
+        assert_eq!(Some(("../", "")), parse_path("cd ../"))
+    }
+
+    #[test]
+    // `let p = "/usr/lo|`
+    fn absolute_path_inside_string() {
+        assert_eq!(Some(("/usr/", "lo")), parse_path("let p = \"/usr/lo"))
+    }
+
+    #[test]
+    // `src/main|`
+    fn not_a_path() {
+        assert_eq!(None, parse_path("src/main"));
+        assert_eq!(None, parse_path("https://foo"));
+        assert_eq!(None, parse_path("foo "));
+        assert_eq!(None, parse_path("a / b"));
+        assert_eq!(None, parse_path("// comment"));
+    }
+
+    #[test]
+    fn resolve_home_and_relative_dirs() {
+        let base = Path::new("/foo/bar");
+        let home = Path::new("/home/user");
+
+        assert_eq!(
+            Some(PathBuf::from("/home/user/baz/")),
+            resolve_dir("~/baz/", base, Some(home))
+        );
+        assert_eq!(None, resolve_dir("~/baz/", base, None));
+        assert_eq!(
+            Some(PathBuf::from("/foo/bar/../")),
+            resolve_dir("../", base, Some(home))
+        );
+        assert_eq!(
+            Some(PathBuf::from("/etc/")),
+            resolve_dir("/etc/", base, Some(home))
+        );
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::{BTreeMap, HashMap};
+
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Api;
+use serde::{Deserialize, Deserializer};
+
+use crate::completion::{
+    matcher,
+    CompletionItem,
+    CompletionSource,
+    Cursor,
+    Details,
+};
+use crate::settings::sources::{Contexts, Enable};
+use crate::snippets::Snippet;
+
+/// The placeholder of a template replaced by the expression before the `.`.
+const EXPR: &str = "{expr}";
+
+/// The filetype whose templates are offered in every buffer.
+const ALL_FILETYPES: &str = "*";
+
+/// A hashmap where the keys are filetypes and the values map the triggers
+/// typed after the `.` to the bodies of the snippets they expand to.
+type Templates = HashMap<String, HashMap<String, String>>;
+
+#[derive(Debug, Deserialize)]
+pub struct Postfix {
+    pub enable: Enable,
+
+    #[serde(default)]
+    pub contexts: Contexts,
+
+    /// The templates of every filetype, with the user's ones added on top of
+    /// the default ones.
+    #[serde(
+        default = "default_templates",
+        deserialize_with = "deserialize_templates"
+    )]
+    pub templates: Templates,
+}
+
+fn default_templates() -> Templates {
+    let templates: &[(&str, &[(&str, &str)])] = &[
+        (ALL_FILETYPES, &[("not", "!{expr}"), ("par", "({expr})")]),
+        (
+            "rust",
+            &[
+                ("dbg", "dbg!({expr})"),
+                ("let", "let ${1:name} = {expr};"),
+                ("ok", "Ok({expr})"),
+                ("ref", "&{expr}"),
+                ("ret", "return {expr};"),
+                ("some", "Some({expr})"),
+            ],
+        ),
+        (
+            "lua",
+            &[
+                ("local", "local ${1:name} = {expr}"),
+                ("not", "not {expr}"),
+                ("print", "print({expr})"),
+                ("ret", "return {expr}"),
+            ],
+        ),
+        (
+            "python",
+            &[
+                ("len", "len({expr})"),
+                ("not", "not {expr}"),
+                ("print", "print({expr})"),
+                ("ret", "return {expr}"),
+            ],
+        ),
+    ];
+
+    templates
+        .iter()
+        .map(|&(filetype, triggers)| {
+            let triggers = triggers
+                .iter()
+                .map(|&(trigger, body)| (trigger.to_owned(), body.to_owned()))
+                .collect();
+            (filetype.to_owned(), triggers)
+        })
+        .collect()
+}
+
+/// Adds the templates of the config to the default ones, replacing the
+/// default templates with the same triggers.
+fn deserialize_templates<'de, D>(
+    deserializer: D,
+) -> Result<Templates, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut templates = default_templates();
+    for (filetype, triggers) in Templates::deserialize(deserializer)? {
+        templates.entry(filetype).or_default().extend(triggers);
+    }
+    Ok(templates)
+}
+
+impl Default for Postfix {
+    fn default() -> Self {
+        Postfix {
+            enable: Enable::default(),
+            contexts: Contexts::default(),
+            templates: default_templates(),
+        }
+    }
+}
+
+impl CompletionSource for Postfix {
+    fn name(&self) -> &'static str { "Postfix" }
+
+    fn attach(&self, _: &Lua, _: &Api, _: u32) -> LuaResult<bool> { Ok(true) }
+
+    fn trigger_characters(&self, _: &Lua, _: &Api) -> LuaResult<Vec<String>> {
+        Ok(vec![".".into()])
+    }
+
+    fn complete(
+        &self,
+        _: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Vec<CompletionItem>> {
+        let line_pre = &cursor.line[..cursor.bytes as usize];
+
+        let (start, expr, typed) = match expression_before(line_pre) {
+            Some(found) => found,
+            None => return Ok(Vec::new()),
+        };
+
+        let filetype = api.buf_get_option::<String>(0, "filetype")?;
+
+        Ok(self
+            .templates_of(&filetype)
+            .into_iter()
+            .filter_map(|(trigger, template)| {
+                let m = matcher::fuzzy_match(typed, trigger)?;
+                let body = render(template, expr);
+                let text = Snippet::parse(&body).text;
+
+                Some(CompletionItem {
+                    details: Some(Details {
+                        lines: text.lines().map(String::from).collect(),
+                        is_markdown: false,
+                    }),
+                    // The trigger is displayed after a space and a dot.
+                    format: format!(" .{trigger}"),
+                    hl_ranges: m.hl_ranges(2),
+                    kind: Some("Snippet"),
+                    // The expression is replaced together with the trigger.
+                    matched_bytes: (line_pre.len() - start) as u32,
+                    preselect: false,
+                    score: m.score,
+                    snippet: Some(body),
+                    source: "Postfix",
+                    text,
+                    ..Default::default()
+                })
+            })
+            .collect())
+    }
+}
+
+impl Postfix {
+    /// The `(trigger, template)` tuples offered in buffers of the given
+    /// `filetype`, where the filetype's own templates take precedence over
+    /// the ones of every filetype. Templates set to an empty string are left
+    /// out.
+    fn templates_of(&self, filetype: &str) -> BTreeMap<&str, &str> {
+        [ALL_FILETYPES, filetype]
+            .iter()
+            .filter_map(|filetype| self.templates.get(*filetype))
+            .flatten()
+            .map(|(trigger, template)| (trigger.as_str(), template.as_str()))
+            .collect::<BTreeMap<_, _>>()
+            .into_iter()
+            .filter(|(_, template)| !template.is_empty())
+            .collect()
+    }
+}
+
+/// Returns the expression before the last `.` of `line_pre` together with
+/// its byte offset and the trigger typed after the dot, or `None` if there
+/// isn't one. The expression is made of identifiers, field accesses, paths
+/// and balanced brackets or quotes, e.g. `foo.bar(1, 2)[0]`.
+fn expression_before(line_pre: &str) -> Option<(usize, &str, &str)> {
+    let trigger_start = line_pre
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| c.is_alphanumeric() || c == '_')
+        .last()
+        .map(|(i, _)| i)
+        .unwrap_or(line_pre.len());
+
+    let text = line_pre[..trigger_start].strip_suffix('.')?;
+
+    let mut closers = Vec::new();
+    let mut start = text.len();
+
+    for (i, c) in text.char_indices().rev() {
+        match closers.last() {
+            // Inside a string everything up to the opening quote is skipped.
+            Some(&quote @ ('"' | '\'' | '`')) => {
+                if c == quote {
+                    closers.pop();
+                }
+            },
+
+            Some(&closer) => match c {
+                ')' | ']' | '}' | '"' | '\'' | '`' => closers.push(c),
+                '(' | '[' | '{' => {
+                    if closer != matching(c) {
+                        return None;
+                    }
+                    closers.pop();
+                },
+                _ => {},
+            },
+
+            None => match c {
+                ')' | ']' | '}' | '"' | '\'' | '`' => closers.push(c),
+                c if c.is_alphanumeric() || "_.:?".contains(c) => {},
+                _ => break,
+            },
+        }
+
+        start = i;
+    }
+
+    let expr = &text[start..];
+
+    let is_expr = closers.is_empty()
+        && !expr.starts_with(['.', ':'])
+        && !expr.chars().all(|c| c.is_ascii_digit());
+
+    is_expr.then_some((start, expr, &line_pre[trigger_start..]))
+}
+
+/// The closing bracket matching an opening one.
+fn matching(opener: char) -> char {
+    match opener {
+        '(' => ')',
+        '[' => ']',
+        _ => '}',
+    }
+}
+
+/// Replaces the placeholders of a `template` with the `expr`, which is
+/// escaped so that it's inserted as it is by the snippet.
+fn render(template: &str, expr: &str) -> String {
+    let mut escaped = String::with_capacity(expr.len());
+    for c in expr.chars() {
+        if matches!(c, '$' | '}' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    template.replace(EXPR, &escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expression_before, render, Postfix};
+
+    #[test]
+    fn expressions() {
+        assert_eq!(Some((4, "foo", "no")), expression_before("let foo.no"));
+        assert_eq!(
+            Some((0, "self.items.len()", "")),
+            expression_before("self.items.len().")
+        );
+        assert_eq!(
+            Some((2, "f(a, b[1])?", "ok")),
+            expression_before("x(f(a, b[1])?.ok")
+        );
+        assert_eq!(
+            Some((0, "\"a (b\".len()", "r")),
+            expression_before("\"a (b\".len().r")
+        );
+        assert_eq!(
+            Some((0, "Vec::new()", "")),
+            expression_before("Vec::new().")
+        );
+    }
+
+    #[test]
+    fn not_expressions() {
+        assert_eq!(None, expression_before("foo"));
+        assert_eq!(None, expression_before("foo ."));
+        assert_eq!(None, expression_before(".."));
+        assert_eq!(None, expression_before("1."));
+        assert_eq!(None, expression_before("f(a)].ok"));
+        assert_eq!(None, expression_before("a, b).ok"));
+    }
+
+    #[test]
+    fn escaped_expressions() {
+        assert_eq!("Some(foo)", render("Some({expr})", "foo"));
+        assert_eq!("!\\$x", render("!{expr}", "$x"));
+        assert_eq!("({ a = 1 \\})", render("({expr})", "{ a = 1 }"));
+    }
+
+    #[test]
+    fn filetype_templates() {
+        let mut postfix = Postfix::default();
+        postfix
+            .templates
+            .entry("rust".into())
+            .or_default()
+            .insert("par".into(), "".into());
+
+        let rust = postfix.templates_of("rust");
+        assert_eq!(Some(&"!{expr}"), rust.get("not"));
+        assert_eq!(Some(&"Ok({expr})"), rust.get("ok"));
+        assert_eq!(None, rust.get("par"));
+
+        let lua = postfix.templates_of("lua");
+        assert_eq!(Some(&"not {expr}"), lua.get("not"));
+        assert_eq!(Some(&"({expr})"), lua.get("par"));
+        assert_eq!(None, lua.get("ok"));
+    }
+}
@@ -0,0 +1,320 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader};
+use std::path::{Path as StdPath, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Api;
+use serde::Deserialize;
+
+use crate::completion::{
+    matcher,
+    Cancellation,
+    CompletionItem,
+    CompletionSource,
+    Cursor,
+    Job,
+};
+use crate::disk_cache;
+use crate::health::Report;
+use crate::settings::sources::{Contexts, Enable};
+
+/// The name the indexes are cached to disk under.
+const CACHE_NAME: &str = "ripgrep";
+
+/// The regex passed to `rg` to find identifiers at least 3 characters long.
+const WORD_REGEX: &str = r"[\p{L}_][\p{L}\p{N}_]{2,}";
+
+/// How many words to match before checking whether the job was cancelled.
+const CANCELLATION_CHECK_INTERVAL: usize = 256;
+
+/// A hashmap where the keys are the project roots and the values are the
+/// words found in them.
+type Indexes = Arc<Mutex<HashMap<PathBuf, Index>>>;
+
+#[derive(Debug, Deserialize)]
+pub struct Ripgrep {
+    pub enable: Enable,
+
+    #[serde(default)]
+    pub contexts: Contexts,
+
+    /// Files bigger than this are skipped, using the same format as `rg`'s
+    /// `--max-filesize` option, e.g. `1M` or `512K`.
+    #[serde(default = "default_max_filesize")]
+    pub max_filesize: String,
+
+    /// Stop indexing a project after finding this many different words.
+    #[serde(default = "default_max_words")]
+    pub max_words: usize,
+
+    #[serde(skip)]
+    indexes: Indexes,
+}
+
+#[derive(Debug, Default)]
+struct Index {
+    /// The words found in the project, sorted and without duplicates.
+    words: Arc<Vec<String>>,
+
+    /// Whether a file has been written since the project was indexed.
+    is_stale: bool,
+
+    /// Whether `rg` is currently running on the project.
+    is_indexing: bool,
+}
+
+fn default_max_filesize() -> String { "1M".into() }
+
+fn default_max_words() -> usize { 100_000 }
+
+impl Default for Ripgrep {
+    fn default() -> Self {
+        Ripgrep {
+            enable: Enable::default(),
+            contexts: Contexts::default(),
+            max_filesize: default_max_filesize(),
+            max_words: default_max_words(),
+            indexes: Arc::default(),
+        }
+    }
+}
+
+impl CompletionSource for Ripgrep {
+    fn name(&self) -> &'static str { "Ripgrep" }
+
+    fn attach(&self, lua: &Lua, api: &Api, _: u32) -> LuaResult<bool> {
+        if api.call_function::<_, u8>("executable", vec!["rg"])? != 1 {
+            return Ok(false);
+        }
+
+        // Writing any file marks all the indexes as stale, and the projects
+        // are indexed again the next time they're completed.
+        let indexes = self.indexes.clone();
+        let on_write = lua.create_function(move |_, ()| {
+            for index in indexes.lock().unwrap().values_mut() {
+                index.is_stale = true;
+            }
+            Ok(())
+        })?;
+
+        let opts = lua.create_table_from([("clear", true)])?;
+        let augroup_id = api.create_augroup("CompleetRipgrepSource", opts)?;
+
+        let opts = lua.create_table_with_capacity(0, 2)?;
+        opts.set("group", augroup_id)?;
+        opts.set("callback", on_write)?;
+        api.create_autocmd(&["BufWritePost"], opts)?;
+
+        Ok(true)
+    }
+
+    fn check_health(&self, _: &Lua, api: &Api) -> LuaResult<Vec<Report>> {
+        Ok(vec![Report::executable(
+            api,
+            "rg",
+            self.name(),
+            "Install ripgrep from https://github.com/BurntSushi/ripgrep",
+        )?])
+    }
+
+    fn complete(
+        &self,
+        lua: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Vec<CompletionItem>> {
+        Ok(match self.complete_in_background(lua, api, cursor)? {
+            Some(job) => job(&Cancellation::never()),
+            None => Vec::new(),
+        })
+    }
+
+    fn complete_in_background(
+        &self,
+        _: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Option<Job>> {
+        let prefix = cursor.keyword_pre().to_owned();
+
+        if prefix.is_empty() {
+            return Ok(None);
+        }
+
+        let root = PathBuf::from(
+            api.call_function::<_, String>("getcwd", Vec::<u8>::new())?,
+        );
+
+        let indexes = self.indexes.clone();
+        let max_filesize = self.max_filesize.clone();
+        let max_words = self.max_words;
+
+        Ok(Some(Box::new(move |cancellation| {
+            // The first time a project is completed its words are read from
+            // the disk cache, if it was indexed in a previous session. They
+            // could be outdated, so the project is still indexed again.
+            let (words, should_index) = {
+                let indexes = &mut *indexes.lock().unwrap();
+                let index = indexes.entry(root.clone()).or_insert_with(|| {
+                    let key = cache_key(&root, &max_filesize, max_words);
+                    Index {
+                        words: Arc::new(
+                            disk_cache::read(&cache_name(&root), &key)
+                                .unwrap_or_default(),
+                        ),
+                        is_stale: true,
+                        is_indexing: false,
+                    }
+                });
+                let should_index = index.is_stale && !index.is_indexing;
+                if should_index {
+                    index.is_stale = false;
+                    index.is_indexing = true;
+                }
+                (index.words.clone(), should_index)
+            };
+
+            // The lock isn't held while `rg` runs. If the project already has
+            // some words it's indexed in the background, and the jobs keep
+            // using the stale words in the meantime.
+            let words = match (should_index, words.is_empty()) {
+                (true, true) => {
+                    reindex(&indexes, &root, &max_filesize, max_words)
+                },
+                (true, false) => {
+                    thread::spawn(move || {
+                        reindex(&indexes, &root, &max_filesize, max_words)
+                    });
+                    words
+                },
+                (false, _) => words,
+            };
+
+            let mut completions = Vec::new();
+
+            for (i, word) in words.iter().enumerate() {
+                if i % CANCELLATION_CHECK_INTERVAL == 0
+                    && cancellation.is_cancelled()
+                {
+                    return Vec::new();
+                }
+
+                if word == &prefix {
+                    continue;
+                }
+
+                if let Some(m) = matcher::fuzzy_match(&prefix, word) {
+                    completions.push(CompletionItem {
+                        details: None,
+                        format: format!(" {word}"),
+                        hl_ranges: m.hl_ranges(1),
+                        kind: Some("Text"),
+                        matched_bytes: prefix.len() as u32,
+                        preselect: false,
+                        score: m.score,
+                        snippet: None,
+                        source: "Ripgrep",
+                        text: word.clone(),
+                        ..Default::default()
+                    });
+                }
+            }
+
+            completions
+        })))
+    }
+}
+
+/// Indexes the project at `root` again, updating its index and its copy in
+/// the disk cache.
+fn reindex(
+    indexes: &Indexes,
+    root: &StdPath,
+    max_filesize: &str,
+    max_words: usize,
+) -> Arc<Vec<String>> {
+    let words = Arc::new(index_project(root, max_filesize, max_words));
+
+    if let Some(index) = indexes.lock().unwrap().get_mut(root) {
+        index.words = words.clone();
+        index.is_indexing = false;
+    }
+
+    let key = cache_key(root, max_filesize, max_words);
+    disk_cache::write(&cache_name(root), &key, &words);
+
+    words
+}
+
+/// The name of the cache file of the project at `root`, one per project.
+/// Roots are paths, so the file is named after their hash.
+fn cache_name(root: &StdPath) -> String {
+    let root = root.display().to_string();
+    format!("{CACHE_NAME}-{:016x}", disk_cache::fnv1a(&root))
+}
+
+/// The key the words of the project at `root` are cached under, which also
+/// depends on the options limiting how many of them are found.
+fn cache_key(root: &StdPath, max_filesize: &str, max_words: usize) -> String {
+    format!("{}\t{max_filesize}\t{max_words}", root.display())
+}
+
+/// Runs `rg` over the project at `root`, returning the words found in its
+/// files sorted and without duplicates. `rg` respects the `.gitignore`s of
+/// the project, and it's killed once `max_words` different words have been
+/// found.
+fn index_project(
+    root: &StdPath,
+    max_filesize: &str,
+    max_words: usize,
+) -> Vec<String> {
+    let child = Command::new("rg")
+        .args([
+            "--only-matching",
+            "--no-filename",
+            "--no-line-number",
+            "--no-heading",
+            "--no-messages",
+            "--max-filesize",
+            max_filesize,
+            "--",
+            WORD_REGEX,
+        ])
+        .arg(root)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+
+    let mut child = match child {
+        Ok(child) => child,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut words = HashSet::new();
+
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines() {
+            let line = match line {
+                Ok(line) => line,
+                // Files that aren't valid UTF-8.
+                Err(_) => continue,
+            };
+            if !words.contains(&line) {
+                words.insert(line);
+                if words.len() >= max_words {
+                    break;
+                }
+            }
+        }
+    }
+
+    let _ = child.kill();
+    let _ = child.wait();
+
+    let mut words = words.into_iter().collect::<Vec<String>>();
+    words.sort();
+    words
+}
@@ -0,0 +1,48 @@
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Api;
+use serde::Deserialize;
+
+use super::{luasnip::LuaSnip, ultisnips::UltiSnips};
+
+/// A snippet defined in a snippet engine.
+#[derive(Debug, Clone)]
+pub struct EngineSnippet {
+    /// The text that expands the snippet.
+    pub trigger: String,
+
+    /// A description of what the snippet expands to, if the engine has one.
+    pub description: Option<String>,
+}
+
+/// An adapter over a third-party snippet engine.
+pub trait Engine {
+    /// The name of the engine, shown in the details of its snippets.
+    fn name(&self) -> &'static str;
+
+    /// Whether the engine is installed.
+    fn is_available(&self, lua: &Lua, api: &Api) -> LuaResult<bool>;
+
+    /// Returns the snippets available in the current buffer.
+    fn snippets(&self, lua: &Lua, api: &Api) -> LuaResult<Vec<EngineSnippet>>;
+
+    /// Expands the snippet whose trigger is right before the cursor,
+    /// returning whether there was one.
+    fn expand(&self, lua: &Lua, api: &Api) -> LuaResult<bool>;
+}
+
+/// The snippet engines that can be used by the `snippets` source.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EngineKind {
+    Luasnip,
+    Ultisnips,
+}
+
+impl EngineKind {
+    pub fn engine(&self) -> &'static dyn Engine {
+        match self {
+            EngineKind::Luasnip => &LuaSnip,
+            EngineKind::Ultisnips => &UltiSnips,
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use mlua::prelude::{Lua, LuaFunction, LuaResult, LuaTable, LuaValue};
+use neovim::Api;
+
+use super::engine::{Engine, EngineSnippet};
+
+/// An adapter over [LuaSnip](https://github.com/L3MON4D3/LuaSnip).
+pub struct LuaSnip;
+
+impl LuaSnip {
+    /// Returns the `luasnip` module, or `None` if it can't be required.
+    fn module<'lua>(
+        &self,
+        lua: &'lua Lua,
+    ) -> LuaResult<Option<LuaTable<'lua>>> {
+        let pcall = lua.globals().get::<_, LuaFunction>("pcall")?;
+        let require = lua.globals().get::<_, LuaFunction>("require")?;
+
+        Ok(
+            match pcall.call::<_, (bool, LuaValue)>((require, "luasnip"))? {
+                (true, LuaValue::Table(luasnip)) => Some(luasnip),
+                _ => None,
+            },
+        )
+    }
+}
+
+impl Engine for LuaSnip {
+    fn name(&self) -> &'static str { "LuaSnip" }
+
+    fn is_available(&self, lua: &Lua, _: &Api) -> LuaResult<bool> {
+        Ok(self.module(lua)?.is_some())
+    }
+
+    fn snippets(&self, lua: &Lua, _: &Api) -> LuaResult<Vec<EngineSnippet>> {
+        let luasnip = match self.module(lua)? {
+            Some(luasnip) => luasnip,
+            None => return Ok(Vec::new()),
+        };
+
+        // A `{ [filetype] = { snippet, .. } }` table with the snippets of all
+        // the filetypes of the current buffer.
+        let available = luasnip
+            .get::<_, LuaFunction>("available")?
+            .call::<_, LuaTable>(())?;
+
+        let mut snippets = Vec::new();
+
+        for pair in available.pairs::<LuaValue, LuaTable>() {
+            let (_, filetype_snippets) = pair?;
+
+            for snippet in filetype_snippets.sequence_values::<LuaTable>() {
+                let snippet = snippet?;
+
+                // Regex triggers can't be completed.
+                if snippet.get::<_, Option<bool>>("regTrig")?.unwrap_or(false)
+                {
+                    continue;
+                }
+
+                let description =
+                    match snippet.get::<_, LuaValue>("description")? {
+                        LuaValue::String(s) => Some(s.to_str()?.to_owned()),
+                        LuaValue::Table(lines) => Some(
+                            lines
+                                .sequence_values::<String>()
+                                .collect::<LuaResult<Vec<String>>>()?
+                                .join("\n"),
+                        ),
+                        _ => None,
+                    };
+
+                snippets.push(EngineSnippet {
+                    trigger: snippet.get("trigger")?,
+                    description,
+                });
+            }
+        }
+
+        Ok(snippets)
+    }
+
+    fn expand(&self, lua: &Lua, _: &Api) -> LuaResult<bool> {
+        let luasnip = match self.module(lua)? {
+            Some(luasnip) => luasnip,
+            None => return Ok(false),
+        };
+
+        if !luasnip
+            .get::<_, LuaFunction>("expandable")?
+            .call::<_, bool>(())?
+        {
+            return Ok(false);
+        }
+
+        luasnip.get::<_, LuaFunction>("expand")?.call::<_, ()>(())?;
+
+        Ok(true)
+    }
+}
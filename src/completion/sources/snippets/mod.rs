@@ -0,0 +1,155 @@
+mod engine;
+mod luasnip;
+mod ultisnips;
+
+use engine::EngineKind;
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Api;
+use serde::Deserialize;
+
+use crate::completion::{
+    matcher,
+    CompletionItem,
+    CompletionSource,
+    Cursor,
+    Details,
+};
+use crate::health::Report;
+use crate::settings::sources::{Contexts, Enable};
+
+#[derive(Debug, Deserialize)]
+pub struct Snippets {
+    pub enable: Enable,
+
+    #[serde(default)]
+    pub contexts: Contexts,
+
+    /// The snippet engines to list the snippets of. Confirming a snippet
+    /// expands it through the first engine that can expand its trigger.
+    #[serde(default = "default_engines")]
+    pub engines: Vec<EngineKind>,
+}
+
+fn default_engines() -> Vec<EngineKind> {
+    vec![EngineKind::Luasnip, EngineKind::Ultisnips]
+}
+
+impl Default for Snippets {
+    fn default() -> Self {
+        Snippets {
+            enable: Enable::default(),
+            contexts: Contexts::default(),
+            engines: default_engines(),
+        }
+    }
+}
+
+impl CompletionSource for Snippets {
+    fn name(&self) -> &'static str { "Snippets" }
+
+    fn attach(&self, lua: &Lua, api: &Api, _: u32) -> LuaResult<bool> {
+        for kind in &self.engines {
+            if kind.engine().is_available(lua, api)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn check_health(&self, lua: &Lua, api: &Api) -> LuaResult<Vec<Report>> {
+        let mut reports = Vec::new();
+        for kind in &self.engines {
+            let engine = kind.engine();
+            if engine.is_available(lua, api)? {
+                reports.push(Report::ok(format!(
+                    "{} is installed",
+                    engine.name()
+                )));
+            }
+        }
+
+        if reports.is_empty() {
+            reports.push(Report::error(
+                "None of the snippet engines in `sources.snippets.engines` \
+                 is installed",
+                &["Install LuaSnip or UltiSnips, or disable the source"],
+            ));
+        }
+
+        Ok(reports)
+    }
+
+    fn complete(
+        &self,
+        lua: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Vec<CompletionItem>> {
+        let prefix = cursor.keyword_pre();
+
+        if prefix.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut completions = Vec::new();
+
+        for kind in &self.engines {
+            let engine = kind.engine();
+
+            if !engine.is_available(lua, api)? {
+                continue;
+            }
+
+            for snippet in engine.snippets(lua, api)? {
+                let m = match matcher::fuzzy_match(prefix, &snippet.trigger) {
+                    Some(m) => m,
+                    None => continue,
+                };
+
+                let mut lines = snippet
+                    .description
+                    .map(|d| d.lines().map(String::from).collect())
+                    .unwrap_or_else(Vec::new);
+                lines.push(format!("({})", engine.name()));
+
+                completions.push(CompletionItem {
+                    details: Some(Details {
+                        lines,
+                        is_markdown: false,
+                    }),
+                    format: format!(" {}", snippet.trigger),
+                    hl_ranges: m.hl_ranges(1),
+                    kind: Some("Snippet"),
+                    matched_bytes: prefix.len() as u32,
+                    preselect: false,
+                    score: m.score,
+                    snippet: None,
+                    source: "Snippets",
+                    text: snippet.trigger,
+                    ..Default::default()
+                });
+            }
+        }
+
+        Ok(completions)
+    }
+
+    fn has_callbacks(&self) -> bool { true }
+
+    fn confirm(
+        &self,
+        lua: &Lua,
+        api: &Api,
+        _: &CompletionItem,
+    ) -> LuaResult<()> {
+        // The trigger is now before the cursor, so it's up to the engines to
+        // expand it.
+        for kind in &self.engines {
+            let engine = kind.engine();
+            if engine.is_available(lua, api)? && engine.expand(lua, api)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
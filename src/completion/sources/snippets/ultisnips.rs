@@ -0,0 +1,49 @@
+use mlua::prelude::{Lua, LuaResult, LuaTable};
+use neovim::Api;
+
+use super::engine::{Engine, EngineSnippet};
+
+/// An adapter over [UltiSnips](https://github.com/SirVer/ultisnips).
+pub struct UltiSnips;
+
+impl Engine for UltiSnips {
+    fn name(&self) -> &'static str { "UltiSnips" }
+
+    fn is_available(&self, _: &Lua, api: &Api) -> LuaResult<bool> {
+        Ok(api.call_function::<_, u8>(
+            "exists",
+            vec!["g:did_plugin_ultisnips"],
+        )? == 1)
+    }
+
+    fn snippets(&self, _: &Lua, api: &Api) -> LuaResult<Vec<EngineSnippet>> {
+        // A `{ [trigger] = description }` dictionary.
+        let snippets = api.call_function::<_, LuaTable>(
+            "UltiSnips#SnippetsInCurrentScope",
+            vec![0],
+        )?;
+
+        snippets
+            .pairs::<String, String>()
+            .map(|pair| {
+                pair.map(|(trigger, description)| EngineSnippet {
+                    trigger,
+                    description: (!description.is_empty())
+                        .then_some(description),
+                })
+            })
+            .collect()
+    }
+
+    fn expand(&self, _: &Lua, api: &Api) -> LuaResult<bool> {
+        api.call_function::<_, ()>(
+            "UltiSnips#ExpandSnippet",
+            Vec::<u8>::new(),
+        )?;
+
+        Ok(api.call_function::<_, i64>(
+            "eval",
+            vec!["get(g:, 'ulti_expand_res', 0)"],
+        )? == 1)
+    }
+}
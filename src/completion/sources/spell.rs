@@ -0,0 +1,118 @@
+use mlua::prelude::{Lua, LuaResult, LuaValue};
+use neovim::Api;
+use serde::Deserialize;
+
+use crate::completion::{CompletionItem, CompletionSource, Cursor};
+use crate::settings::sources::{Contexts, Enable};
+
+#[derive(Debug, Deserialize)]
+pub struct Spell {
+    pub enable: Enable,
+
+    #[serde(default)]
+    pub contexts: Contexts,
+
+    /// The maximum number of corrections offered for a misspelled word.
+    #[serde(default = "default_max_suggestions")]
+    pub max_suggestions: u32,
+}
+
+fn default_max_suggestions() -> u32 { 10 }
+
+impl Default for Spell {
+    fn default() -> Self {
+        Spell {
+            enable: Enable::default(),
+            contexts: Contexts::default(),
+            max_suggestions: default_max_suggestions(),
+        }
+    }
+}
+
+impl CompletionSource for Spell {
+    fn name(&self) -> &'static str { "Spell" }
+
+    fn attach(&self, _: &Lua, _: &Api, _: u32) -> LuaResult<bool> { Ok(true) }
+
+    fn complete(
+        &self,
+        lua: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Vec<CompletionItem>> {
+        let (pre, post) = (cursor.keyword_pre(), cursor.keyword_post());
+        let word = format!("{pre}{post}");
+
+        // The `spell` option is local to the window, and `spellbadword()`
+        // doesn't find anything without it.
+        if !is_spellable(&word) || !api.win_get_option::<bool>(0, "spell")? {
+            return Ok(Vec::new());
+        }
+
+        let word = LuaValue::String(lua.create_string(&word)?);
+
+        let (bad, _) = api.call_function::<_, (String, String)>(
+            "spellbadword",
+            vec![word.clone()],
+        )?;
+
+        if bad.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let suggestions = api.call_function::<_, Vec<String>>(
+            "spellsuggest",
+            vec![word, LuaValue::Integer(self.max_suggestions.into())],
+        )?;
+
+        let len = suggestions.len();
+
+        Ok(suggestions
+            .into_iter()
+            .enumerate()
+            .map(|(i, suggestion)| CompletionItem {
+                details: None,
+                format: format!(" {suggestion}"),
+                hl_ranges: Vec::new(),
+                id: 0,
+                kind: Some("SpellFix"),
+                // The whole misspelled word is replaced, including the part
+                // after the cursor.
+                matched_bytes: pre.len() as u32,
+                matched_bytes_post: post.len() as u32,
+                preselect: false,
+                // Keep the order of `spellsuggest()`, which puts the most
+                // likely corrections first.
+                score: (len - i) as i32,
+                snippet: None,
+                source: "Spell",
+                text: suggestion,
+            })
+            .collect())
+    }
+}
+
+/// Whether the `word` is worth spell checking, i.e. it's made of at least two
+/// letters and nothing else. Identifiers like `foo_bar` or `x86` aren't.
+fn is_spellable(word: &str) -> bool {
+    word.chars().count() > 1 && word.chars().all(char::is_alphabetic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_spellable;
+
+    #[test]
+    fn words() {
+        assert!(is_spellable("helo"));
+        assert!(is_spellable("façade"));
+    }
+
+    #[test]
+    fn not_words() {
+        assert!(!is_spellable(""));
+        assert!(!is_spellable("a"));
+        assert!(!is_spellable("foo_bar"));
+        assert!(!is_spellable("x86"));
+    }
+}
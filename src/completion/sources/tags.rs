@@ -0,0 +1,417 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Api;
+use serde::Deserialize;
+
+use crate::completion::{
+    matcher,
+    Cancellation,
+    CompletionItem,
+    CompletionSource,
+    Cursor,
+    Details,
+    Job,
+};
+use crate::settings::sources::{Contexts, Enable};
+
+/// The maximum number of completions returned for a single prefix.
+const MAX_COMPLETIONS: usize = 200;
+
+/// A hashmap where the keys are the paths of the tag files and the values are
+/// their contents together with the time they were last modified, so that
+/// they're only read again after they change.
+type Cache = Mutex<HashMap<PathBuf, (SystemTime, Arc<TagFile>)>>;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Tags {
+    pub enable: Enable,
+
+    #[serde(default)]
+    pub contexts: Contexts,
+
+    #[serde(skip)]
+    cache: Arc<Cache>,
+}
+
+/// The contents of a tag file.
+struct TagFile {
+    contents: Vec<u8>,
+    sorting: Sorting,
+}
+
+/// How the lines of a tag file are sorted, as recorded in its
+/// `!_TAG_FILE_SORTED` header.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum Sorting {
+    Unsorted,
+    #[default]
+    Sorted,
+    FoldCase,
+}
+
+/// A line of a tag file.
+#[derive(Debug, PartialEq)]
+struct Tag<'a> {
+    name: &'a str,
+    file: &'a str,
+    kind: Option<&'a str>,
+}
+
+impl CompletionSource for Tags {
+    fn name(&self) -> &'static str { "Tags" }
+
+    fn attach(&self, _: &Lua, _: &Api, _: u32) -> LuaResult<bool> { Ok(true) }
+
+    fn complete(
+        &self,
+        lua: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Vec<CompletionItem>> {
+        Ok(match self.complete_in_background(lua, api, cursor)? {
+            Some(job) => job(&Cancellation::never()),
+            None => Vec::new(),
+        })
+    }
+
+    fn complete_in_background(
+        &self,
+        _: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Option<Job>> {
+        let prefix = cursor.keyword_pre().to_owned();
+
+        if prefix.is_empty() {
+            return Ok(None);
+        }
+
+        // `tagfiles()` depends on the `tags` option of the current buffer and
+        // returns paths relative to the current working directory, so they're
+        // made absolute here on the main thread.
+        let paths = api
+            .call_function::<_, Vec<String>>("tagfiles", Vec::<String>::new())?
+            .iter()
+            .map(|path| {
+                api.call_function::<_, String>(
+                    "fnamemodify",
+                    vec![path.as_str(), ":p"],
+                )
+            })
+            .map(|path| path.map(PathBuf::from))
+            .collect::<LuaResult<Vec<PathBuf>>>()?;
+
+        if paths.is_empty() {
+            return Ok(None);
+        }
+
+        let cache = self.cache.clone();
+
+        Ok(Some(Box::new(move |cancellation| {
+            let files = paths
+                .into_iter()
+                .filter_map(|path| load(&cache, path))
+                .collect::<Vec<Arc<TagFile>>>();
+
+            if cancellation.is_cancelled() {
+                return Vec::new();
+            }
+
+            let mut seen = Vec::<&str>::new();
+            let mut completions = Vec::new();
+
+            for tag in
+                files.iter().flat_map(|file| file.starting_with(&prefix))
+            {
+                if tag.name == prefix || seen.contains(&tag.name) {
+                    continue;
+                }
+                seen.push(tag.name);
+
+                let m = match matcher::fuzzy_match(&prefix, tag.name) {
+                    Some(m) => m,
+                    None => continue,
+                };
+
+                completions.push(CompletionItem {
+                    details: Some(Details {
+                        lines: vec![tag.file.to_owned()],
+                        is_markdown: false,
+                    }),
+                    format: format!(" {}", tag.name),
+                    hl_ranges: m.hl_ranges(1),
+                    kind: Some(tag.kind.map_or("Text", completion_kind)),
+                    matched_bytes: prefix.len() as u32,
+                    preselect: false,
+                    score: m.score,
+                    snippet: None,
+                    source: "Tags",
+                    text: tag.name.to_owned(),
+                    ..Default::default()
+                });
+
+                if completions.len() == MAX_COMPLETIONS {
+                    break;
+                }
+            }
+
+            completions
+        })))
+    }
+}
+
+/// Returns the contents of the tag file at `path`, reading it again if it's
+/// been modified since the last time it was read. Returns `None` if the file
+/// can't be read.
+fn load(cache: &Cache, path: PathBuf) -> Option<Arc<TagFile>> {
+    let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+
+    let cache = &mut *cache.lock().unwrap();
+    if let Some((last_modified, file)) = cache.get(&path) {
+        if *last_modified == modified {
+            return Some(file.clone());
+        }
+    }
+
+    let file = Arc::new(TagFile::new(fs::read(&path).ok()?));
+    cache.insert(path, (modified, file.clone()));
+    Some(file)
+}
+
+impl fmt::Debug for TagFile {
+    // Tag files can be hundreds of megabytes.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "TagFile({} bytes, {:?})",
+            self.contents.len(),
+            self.sorting
+        )
+    }
+}
+
+impl TagFile {
+    fn new(contents: Vec<u8>) -> Self {
+        let sorting = contents
+            .split(|&b| b == b'\n')
+            .take_while(|line| line.starts_with(b"!_TAG_"))
+            .find_map(|line| line.strip_prefix(b"!_TAG_FILE_SORTED\t"))
+            .map(|value| match value.first() {
+                Some(b'0') => Sorting::Unsorted,
+                Some(b'2') => Sorting::FoldCase,
+                _ => Sorting::Sorted,
+            })
+            .unwrap_or_default();
+
+        TagFile { contents, sorting }
+    }
+
+    /// Returns the tags whose name starts with `prefix`, found with a binary
+    /// search over the lines of the file unless it isn't sorted. The search
+    /// ignores case if the file is sorted ignoring case.
+    fn starting_with<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = Tag<'a>> + 'a {
+        let fold_case = self.sorting == Sorting::FoldCase;
+        let contents = &self.contents[..];
+
+        let start = match self.sorting {
+            Sorting::Unsorted => 0,
+            _ => self.lower_bound(prefix.as_bytes(), fold_case),
+        };
+
+        let is_sorted = self.sorting != Sorting::Unsorted;
+
+        contents[start..]
+            .split(|&b| b == b'\n')
+            .map(move |line| (line, starts_with(line, prefix, fold_case)))
+            .take_while(move |&(_, matches)| matches || !is_sorted)
+            .filter(|&(_, matches)| matches)
+            .filter_map(|(line, _)| std::str::from_utf8(line).ok())
+            .filter_map(parse_line)
+    }
+
+    /// Returns the byte offset of the first line whose name isn't smaller
+    /// than `prefix`.
+    fn lower_bound(&self, prefix: &[u8], fold_case: bool) -> usize {
+        let contents = &self.contents[..];
+        let (mut lo, mut hi) = (0, contents.len());
+
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+
+            let line_start = contents[..mid]
+                .iter()
+                .rposition(|&b| b == b'\n')
+                .map_or(0, |i| i + 1)
+                .max(lo);
+
+            let line_end = contents[line_start..]
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(contents.len(), |i| line_start + i);
+
+            let line = &contents[line_start..line_end];
+            let name = line.split(|&b| b == b'\t').next().unwrap_or(line);
+
+            // Like Vim, files sorted ignoring case are expected to be sorted
+            // by the uppercase version of the names.
+            let is_smaller = match fold_case {
+                true => name
+                    .iter()
+                    .map(u8::to_ascii_uppercase)
+                    .lt(prefix.iter().map(u8::to_ascii_uppercase)),
+                false => name < prefix,
+            };
+
+            if is_smaller {
+                lo = line_end + 1;
+            } else {
+                hi = line_start;
+            }
+        }
+
+        lo.min(contents.len())
+    }
+}
+
+/// Whether the name of the tag on `line` starts with `prefix`.
+fn starts_with(line: &[u8], prefix: &str, fold_case: bool) -> bool {
+    let prefix = prefix.as_bytes();
+    match line.get(..prefix.len()) {
+        Some(start) if fold_case => start.eq_ignore_ascii_case(prefix),
+        Some(start) => start == prefix,
+        None => false,
+    }
+}
+
+/// Parses a line of a tag file, i.e. `{name}\t{file}\t{address};"\t{fields}`
+/// where the kind is either the first field without a `:` or the value of
+/// the `kind:` field. Returns `None` for the header lines.
+fn parse_line(line: &str) -> Option<Tag<'_>> {
+    if line.starts_with("!_TAG_") {
+        return None;
+    }
+
+    let mut parts = line.splitn(3, '\t');
+    let name = parts.next().filter(|name| !name.is_empty())?;
+    let file = parts.next()?;
+
+    let kind = parts
+        .next()
+        .and_then(|rest| rest.split_once(";\"\t"))
+        .and_then(|(_, fields)| {
+            fields
+                .split('\t')
+                .find_map(|field| match field.split_once(':') {
+                    Some(("kind", kind)) => Some(kind),
+                    Some(_) => None,
+                    None => Some(field),
+                })
+        })
+        .filter(|kind| !kind.is_empty());
+
+    Some(Tag { name, file, kind })
+}
+
+/// Maps the kinds of the tags generated by ctags, either as their one letter
+/// abbreviation or by their full name, to completion kinds.
+fn completion_kind(kind: &str) -> &'static str {
+    match kind {
+        "f" | "function" | "p" | "prototype" => "Function",
+        "method" => "Method",
+        "m" | "member" | "field" => "Field",
+        "c" | "class" => "Class",
+        "s" | "struct" | "u" | "union" => "Struct",
+        "g" | "enum" => "Enum",
+        "e" | "enumerator" => "EnumMember",
+        "i" | "interface" => "Interface",
+        "n" | "namespace" | "module" | "package" => "Module",
+        "d" | "macro" | "constant" => "Constant",
+        "t" | "typedef" | "type" => "TypeParameter",
+        "v" | "variable" | "l" | "local" => "Variable",
+        _ => "Text",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_line, Sorting, Tag, TagFile};
+
+    const TAGS: &str = "!_TAG_FILE_FORMAT\t2\t/extended \
+                        format/\n!_TAG_FILE_SORTED\t1\t/0=unsorted, \
+                        1=sorted/\nFoo\tsrc/foo.rs\t/^struct \
+                        Foo;$/;\"\ts\nbar\tsrc/bar.rs\t/^fn bar() \
+                        {$/;\"\tf\nbaz\tsrc/bar.rs\t4;\"\tkind:variable\\
+                        tline:4\nqux\tsrc/qux.rs\t/^mod qux;$/;\"\tn\n";
+
+    fn names<'a>(file: &'a TagFile, prefix: &'a str) -> Vec<&'a str> {
+        file.starting_with(prefix).map(|tag| tag.name).collect()
+    }
+
+    #[test]
+    fn binary_search() {
+        let file = TagFile::new(TAGS.as_bytes().to_vec());
+        assert_eq!(Sorting::Sorted, file.sorting);
+        assert_eq!(vec!["bar", "baz"], names(&file, "ba"));
+        assert_eq!(vec!["Foo"], names(&file, "F"));
+        assert_eq!(vec!["qux"], names(&file, "q"));
+        assert!(names(&file, "foo").is_empty());
+        assert!(names(&file, "zzz").is_empty());
+    }
+
+    #[test]
+    fn fold_case() {
+        let tags = TAGS
+            .replace("SORTED\t1", "SORTED\t2")
+            .replace("Foo\tsrc/foo.rs\t/^struct Foo;$/;\"\ts\n", "");
+        let tags = tags.replace("baz\t", "Bay\tsrc/bay.rs\t1;\"\tf\nbaz\t");
+        let file = TagFile::new(tags.into_bytes());
+        assert_eq!(Sorting::FoldCase, file.sorting);
+        assert_eq!(vec!["bar", "Bay", "baz"], names(&file, "BA"));
+    }
+
+    #[test]
+    fn unsorted() {
+        let tags =
+            TAGS.replace("SORTED\t1", "SORTED\t0") + "abc\tx\t1;\"\tf\n";
+        let file = TagFile::new(tags.into_bytes());
+        assert_eq!(vec!["abc"], names(&file, "a"));
+    }
+
+    #[test]
+    fn parse_lines() {
+        assert_eq!(
+            Some(Tag {
+                name: "bar",
+                file: "src/bar.rs",
+                kind: Some("f")
+            }),
+            parse_line("bar\tsrc/bar.rs\t/^fn bar() {$/;\"\tf")
+        );
+        assert_eq!(
+            Some(Tag {
+                name: "baz",
+                file: "src/bar.rs",
+                kind: Some("variable")
+            }),
+            parse_line("baz\tsrc/bar.rs\t4;\"\tline:4\tkind:variable")
+        );
+        assert_eq!(
+            Some(Tag {
+                name: "old",
+                file: "a.c",
+                kind: None
+            }),
+            parse_line("old\ta.c\t12")
+        );
+        assert_eq!(None, parse_line("!_TAG_FILE_SORTED\t1\t/0=unsorted/"));
+    }
+}
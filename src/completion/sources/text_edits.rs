@@ -0,0 +1,252 @@
+use std::cmp::Reverse;
+
+use mlua::prelude::{Lua, LuaResult, LuaTable, LuaValue};
+use neovim::{Api, Neovim};
+
+use super::lsp::utf16_to_byte;
+
+/// The name of the namespace of the notice shown after applying the edits.
+const NOTICE_NAMESPACE: &str = "compleet_text_edits";
+
+/// The key of the Lua registry where the timer used to clear the notice is
+/// stored.
+const NOTICE_TIMER: &str = "compleet_text_edits_timer";
+
+/// How many milliseconds the notice stays visible.
+const NOTICE_TIMEOUT_MS: u64 = 3000;
+
+/// An LSP `TextEdit`, with the columns of its range in UTF-16 code units.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEdit {
+    /// The 0-indexed `(line, character)` position where the range starts.
+    pub start: (u32, u32),
+
+    /// The 0-indexed `(line, character)` position where the range ends.
+    pub end: (u32, u32),
+
+    /// The text replacing the range.
+    pub new_text: String,
+}
+
+/// Parses the `additionalTextEdits` of an LSP `CompletionItem`, e.g. the
+/// imports needed by the completion.
+pub fn parse(item: &LuaTable) -> LuaResult<Vec<TextEdit>> {
+    let edits = match item.get::<_, LuaValue>("additionalTextEdits")? {
+        LuaValue::Table(edits) => edits,
+        _ => return Ok(Vec::new()),
+    };
+
+    edits
+        .sequence_values::<LuaTable>()
+        .map(|edit| {
+            let edit = edit?;
+            let range = edit.get::<_, LuaTable>("range")?;
+            let position = |key| -> LuaResult<(u32, u32)> {
+                let position = range.get::<_, LuaTable>(key)?;
+                Ok((position.get("line")?, position.get("character")?))
+            };
+
+            Ok(TextEdit {
+                start: position("start")?,
+                end: position("end")?,
+                new_text: edit.get("newText")?,
+            })
+        })
+        .collect()
+}
+
+/// Applies the edits to the current buffer, joining them with the undo block
+/// of the completion they came with. They're usually far from the cursor
+/// (e.g. an import at the top of the file), so the cursor is moved along
+/// with the text before it. A notice with the added text is shown at the end
+/// of the first edited line for a few seconds.
+pub fn apply(lua: &Lua, api: &Api, edits: &[TextEdit]) -> LuaResult<()> {
+    if edits.is_empty() {
+        return Ok(());
+    }
+
+    api.command("silent! undojoin")?;
+
+    // Applying the edits from the last one to the first one keeps the ranges
+    // of the following ones valid.
+    let mut edits = edits.to_vec();
+    edits.sort_by_key(|edit| Reverse(edit.start));
+
+    let (row, col) = api.win_get_cursor(0)?;
+    let mut cursor = (row - 1, col);
+
+    for edit in &edits {
+        let lines =
+            api.buf_get_lines(0, edit.start.0, edit.end.0 as i32 + 1, false)?;
+
+        // The range is past the end of the buffer.
+        if lines.len() != (edit.end.0 - edit.start.0 + 1) as usize {
+            continue;
+        }
+
+        let start_col = utf16_to_byte(&lines[0], edit.start.1) as u32;
+        let end_col =
+            utf16_to_byte(&lines[lines.len() - 1], edit.end.1) as u32;
+        let (start, end) = ((edit.start.0, start_col), (edit.end.0, end_col));
+
+        let replacement = edit.new_text.split('\n').collect::<Vec<&str>>();
+        api.buf_set_text(0, start.0, start.1, end.0, end.1, &replacement)?;
+        cursor = shift_cursor(cursor, start, end, &replacement);
+    }
+
+    api.win_set_cursor(0, cursor.0 + 1, cursor.1)?;
+
+    match notice(&edits) {
+        Some(text) => {
+            show_notice(lua, api, edits[edits.len() - 1].start.0, &text)
+        },
+        None => Ok(()),
+    }
+}
+
+/// Returns where the `cursor` ends up after replacing the `start..end` range
+/// with the lines of the `replacement`. Edits after the cursor don't move it.
+fn shift_cursor(
+    cursor: (u32, u32),
+    start: (u32, u32),
+    end: (u32, u32),
+    replacement: &[&str],
+) -> (u32, u32) {
+    if end > cursor {
+        return cursor;
+    }
+
+    let added = replacement.len().saturating_sub(1) as u32;
+    let row = cursor.0 + added - (end.0 - start.0);
+
+    if end.0 != cursor.0 {
+        return (row, cursor.1);
+    }
+
+    // The text between the end of the range and the cursor now follows the
+    // last line of the replacement.
+    let last_len = replacement.last().map_or(0, |line| line.len()) as u32;
+    let line_start = if added == 0 { start.1 } else { 0 };
+    (row, line_start + last_len + cursor.1 - end.1)
+}
+
+/// Returns the notice shown after applying the edits, i.e. the first line
+/// added by the first one, or `None` if the edits only delete text.
+fn notice(edits: &[TextEdit]) -> Option<String> {
+    let mut added = edits
+        .iter()
+        .rev()
+        .filter_map(|edit| {
+            edit.new_text
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+        })
+        .collect::<Vec<&str>>();
+
+    if added.is_empty() {
+        return None;
+    }
+
+    let first = added.remove(0);
+    Some(match added.len() {
+        0 => format!("added `{first}`"),
+        more => format!("added `{first}` (+{more} more)"),
+    })
+}
+
+/// Shows the `text` as virtual text at the end of the `row`-th line of the
+/// current buffer, clearing it after `NOTICE_TIMEOUT_MS` milliseconds.
+fn show_notice(lua: &Lua, api: &Api, row: u32, text: &str) -> LuaResult<()> {
+    let bufnr = api.get_current_buf()?;
+    let nsid = api.create_namespace(NOTICE_NAMESPACE)?;
+
+    api.buf_clear_namespace(bufnr, nsid as i32, 0, -1)?;
+
+    let opts = lua.create_table_with_capacity(0, 2)?;
+    opts.set("virt_text", [[format!("  {text}"), "CompleetHint".into()]])?;
+    opts.set("virt_text_pos", "eol")?;
+    api.buf_set_extmark(bufnr, nsid, row, 0, opts)?;
+
+    let uv = Neovim::new(lua)?.uv()?;
+
+    let timer = match lua.named_registry_value::<_, LuaValue>(NOTICE_TIMER)? {
+        LuaValue::UserData(timer) => timer,
+        _ => {
+            let timer = uv.new_timer()?;
+            lua.set_named_registry_value(NOTICE_TIMER, timer.clone())?;
+            timer
+        },
+    };
+
+    // The API can't be called from the timer's callback, so clearing the
+    // notice is scheduled.
+    let schedule_clear = lua.create_function(move |lua, ()| {
+        let clear = lua.create_function(move |lua, ()| {
+            let api = Neovim::new(lua)?.api;
+            if api.buf_is_valid(bufnr)? {
+                api.buf_clear_namespace(bufnr, nsid as i32, 0, -1)?;
+            }
+            Ok(())
+        })?;
+        Neovim::new(lua)?.schedule(clear)
+    })?;
+
+    // Starting a timer that's already running resets its timeout.
+    uv.timer_start(timer, NOTICE_TIMEOUT_MS, 0, schedule_clear)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{notice, shift_cursor, TextEdit};
+
+    fn edit(start: (u32, u32), end: (u32, u32), new_text: &str) -> TextEdit {
+        TextEdit {
+            start,
+            end,
+            new_text: new_text.into(),
+        }
+    }
+
+    #[test]
+    fn imports_move_the_cursor_down() {
+        assert_eq!(
+            (12, 7),
+            shift_cursor((10, 7), (1, 0), (1, 0), &["use a;", "use b;", ""])
+        );
+        // Replacing two lines with one.
+        assert_eq!((9, 7), shift_cursor((10, 7), (1, 0), (2, 3), &["foo"]));
+    }
+
+    #[test]
+    fn edits_on_the_cursor_line() {
+        assert_eq!((3, 10), shift_cursor((3, 7), (3, 0), (3, 2), &["fooba"]));
+        assert_eq!(
+            (4, 8),
+            shift_cursor((3, 7), (3, 0), (3, 2), &["a", "bar"])
+        );
+    }
+
+    #[test]
+    fn edits_after_the_cursor() {
+        assert_eq!((3, 7), shift_cursor((3, 7), (3, 8), (3, 9), &["a", "b"]));
+        assert_eq!((3, 7), shift_cursor((3, 7), (5, 0), (5, 0), &["a", ""]));
+    }
+
+    #[test]
+    fn notices() {
+        assert_eq!(
+            Some("added `use std::fmt;`".into()),
+            notice(&[edit((1, 0), (1, 0), "use std::fmt;\n")])
+        );
+        // The edits are sorted from the last one to the first one.
+        assert_eq!(
+            Some("added `use a;` (+1 more)".into()),
+            notice(&[
+                edit((3, 0), (3, 0), "use b;\n"),
+                edit((1, 0), (1, 0), "\nuse a;\n"),
+            ])
+        );
+        assert_eq!(None, notice(&[edit((1, 0), (2, 0), "")]));
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::HashSet;
+use std::env;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Api;
+use serde::Deserialize;
+
+use super::buffer::words;
+use crate::completion::{
+    matcher,
+    Cancellation,
+    CompletionItem,
+    CompletionSource,
+    Cursor,
+    Job,
+};
+use crate::health::Report;
+use crate::settings::sources::{Contexts, Enable};
+
+/// How long the words captured from the panes are reused before capturing
+/// them again.
+const CAPTURE_TTL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Tmux {
+    pub enable: Enable,
+
+    #[serde(default)]
+    pub contexts: Contexts,
+
+    /// Whether to complete the words visible in the panes of every tmux
+    /// window and session, instead of only the ones of the current window.
+    #[serde(default)]
+    pub all_panes: bool,
+
+    /// The words captured the last time the panes were captured.
+    #[serde(skip)]
+    cache: Arc<Mutex<Option<Capture>>>,
+}
+
+#[derive(Debug)]
+struct Capture {
+    /// When the panes were captured.
+    time: Instant,
+
+    /// The words visible in the panes, sorted and without duplicates.
+    words: Arc<Vec<String>>,
+}
+
+impl CompletionSource for Tmux {
+    fn name(&self) -> &'static str { "Tmux" }
+
+    fn attach(&self, _: &Lua, _: &Api, _: u32) -> LuaResult<bool> {
+        Ok(env::var_os("TMUX").is_some())
+    }
+
+    fn check_health(&self, _: &Lua, api: &Api) -> LuaResult<Vec<Report>> {
+        let mut reports = vec![Report::executable(
+            api,
+            "tmux",
+            self.name(),
+            "Install tmux from https://github.com/tmux/tmux",
+        )?];
+
+        if env::var_os("TMUX").is_none() {
+            reports.push(Report::warn(
+                "Neovim isn't running inside tmux, the Tmux source isn't \
+                 attached to any buffer",
+                &["Start Neovim from a tmux session"],
+            ));
+        }
+
+        Ok(reports)
+    }
+
+    fn complete(
+        &self,
+        lua: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Vec<CompletionItem>> {
+        Ok(match self.complete_in_background(lua, api, cursor)? {
+            Some(job) => job(&Cancellation::never()),
+            None => Vec::new(),
+        })
+    }
+
+    fn complete_in_background(
+        &self,
+        _: &Lua,
+        _: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Option<Job>> {
+        let prefix = cursor.keyword_pre().to_owned();
+
+        if prefix.is_empty() {
+            return Ok(None);
+        }
+
+        let cache = self.cache.clone();
+        let all_panes = self.all_panes;
+
+        Ok(Some(Box::new(move |cancellation| {
+            let words = {
+                let cache = &mut *cache.lock().unwrap();
+                match cache {
+                    Some(capture) if capture.time.elapsed() < CAPTURE_TTL => {
+                        capture.words.clone()
+                    },
+                    _ => {
+                        let words = Arc::new(capture(all_panes));
+                        *cache = Some(Capture {
+                            time: Instant::now(),
+                            words: words.clone(),
+                        });
+                        words
+                    },
+                }
+            };
+
+            if cancellation.is_cancelled() {
+                return Vec::new();
+            }
+
+            words
+                .iter()
+                .filter(|&word| word != &prefix)
+                .filter_map(|word| {
+                    matcher::fuzzy_match(&prefix, word).map(|m| {
+                        CompletionItem {
+                            details: None,
+                            format: format!(" {word}"),
+                            hl_ranges: m.hl_ranges(1),
+                            kind: Some("Text"),
+                            matched_bytes: prefix.len() as u32,
+                            preselect: false,
+                            score: m.score,
+                            snippet: None,
+                            source: "Tmux",
+                            text: word.clone(),
+                            ..Default::default()
+                        }
+                    })
+                })
+                .collect()
+        })))
+    }
+}
+
+/// Returns the words visible in the other tmux panes, sorted and without
+/// duplicates. The pane Neovim is running in is skipped since its words are
+/// better completed by the `buffer` source.
+fn capture(all_panes: bool) -> Vec<String> {
+    let current = env::var("TMUX_PANE").unwrap_or_default();
+
+    let mut args = vec!["list-panes", "-F", "#{pane_id}"];
+    if all_panes {
+        args.push("-a");
+    }
+
+    let mut captured = HashSet::new();
+
+    for pane in tmux(&args).lines().filter(|&pane| pane != current) {
+        let contents = tmux(&["capture-pane", "-p", "-J", "-t", pane]);
+        captured.extend(contents.lines().flat_map(words).map(String::from));
+    }
+
+    let mut words = captured.into_iter().collect::<Vec<String>>();
+    words.sort();
+    words
+}
+
+/// Runs `tmux`, returning its output or an empty string if it failed.
+fn tmux(args: &[&str]) -> String {
+    Command::new("tmux")
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .unwrap_or_default()
+}
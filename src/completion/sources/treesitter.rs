@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use mlua::prelude::{Lua, LuaResult};
+use neovim::treesitter::TSNode;
+use neovim::{Api, Neovim};
+use serde::Deserialize;
+
+use crate::completion::{
+    matcher,
+    Cancellation,
+    CompletionItem,
+    CompletionSource,
+    Cursor,
+    Job,
+};
+use crate::settings::sources::{Contexts, Enable};
+
+/// Symbols shorter than this many bytes are not indexed.
+const MIN_SYMBOL_LENGTH: usize = 3;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Treesitter {
+    pub enable: Enable,
+
+    #[serde(default)]
+    pub contexts: Contexts,
+
+    /// Whether to skip the identifiers found inside comments, e.g. in doc
+    /// comments parsed by an injected language.
+    #[serde(default = "default_true")]
+    pub skip_comments: bool,
+
+    /// Whether to skip the identifiers found inside strings, e.g. in
+    /// interpolated strings.
+    #[serde(default = "default_true")]
+    pub skip_strings: bool,
+
+    /// A hashmap where the keys are the numbers of the buffers the source is
+    /// attached to and the values are the symbols found in their syntax
+    /// trees.
+    #[serde(skip)]
+    symbols: Arc<Mutex<HashMap<u32, Vec<Symbol>>>>,
+}
+
+#[derive(Debug, Clone)]
+struct Symbol {
+    text: String,
+
+    /// The kind of the symbol, e.g. `Function`, `Field` or `Variable`.
+    kind: &'static str,
+}
+
+fn default_true() -> bool { true }
+
+impl CompletionSource for Treesitter {
+    fn name(&self) -> &'static str { "Treesitter" }
+
+    fn is_expensive(&self) -> bool { true }
+
+    fn attach(&self, lua: &Lua, api: &Api, bufnr: u32) -> LuaResult<bool> {
+        if self.symbols.lock().unwrap().contains_key(&bufnr) {
+            return Ok(true);
+        }
+
+        // `get_parser` fails if there's no parser for the buffer's language.
+        let symbols = match index(lua, api, bufnr, self.skip()) {
+            Ok(symbols) => symbols,
+            Err(_) => return Ok(false),
+        };
+        self.symbols.lock().unwrap().insert(bufnr, symbols);
+
+        // The syntax tree is only walked again once Neovim is done changing
+        // the text. The symbols missed while typing in insert mode are
+        // picked up when leaving it.
+        let _symbols = self.symbols.clone();
+        let skip = self.skip();
+        let reindex = lua.create_function(move |lua, ()| {
+            let api = Neovim::new(lua)?.api;
+            let symbols = index(lua, &api, bufnr, skip)?;
+            _symbols.lock().unwrap().insert(bufnr, symbols);
+            Ok(())
+        })?;
+
+        let _symbols = self.symbols.clone();
+        let on_unload = lua.create_function(move |_, ()| {
+            _symbols.lock().unwrap().remove(&bufnr);
+            Ok(())
+        })?;
+
+        let opts = lua.create_table_from([("clear", false)])?;
+        let augroup_id =
+            api.create_augroup("CompleetTreesitterSource", opts)?;
+
+        // Unloading the buffer keeps its autocmds, which would be created
+        // again when it's loaded and attached to again.
+        let opts = lua.create_table_with_capacity(0, 2)?;
+        opts.set("group", augroup_id)?;
+        opts.set("buffer", bufnr)?;
+        api.clear_autocmds(opts)?;
+
+        let opts = lua.create_table_with_capacity(0, 3)?;
+        opts.set("group", augroup_id)?;
+        opts.set("buffer", bufnr)?;
+        opts.set("callback", reindex)?;
+        api.create_autocmd(&["TextChanged", "InsertLeave"], opts)?;
+
+        let opts = lua.create_table_with_capacity(0, 4)?;
+        opts.set("group", augroup_id)?;
+        opts.set("buffer", bufnr)?;
+        opts.set("callback", on_unload)?;
+        opts.set("once", true)?;
+        api.create_autocmd(&["BufUnload"], opts)?;
+
+        Ok(true)
+    }
+
+    fn complete(
+        &self,
+        lua: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Vec<CompletionItem>> {
+        Ok(match self.complete_in_background(lua, api, cursor)? {
+            Some(job) => job(&Cancellation::never()),
+            None => Vec::new(),
+        })
+    }
+
+    fn complete_in_background(
+        &self,
+        _: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Option<Job>> {
+        let prefix = cursor.keyword_pre().to_owned();
+
+        if prefix.is_empty() {
+            return Ok(None);
+        }
+
+        let bufnr = api.get_current_buf()?;
+        let symbols = match self.symbols.lock().unwrap().get(&bufnr) {
+            Some(symbols) => symbols.clone(),
+            None => return Ok(None),
+        };
+
+        Ok(Some(Box::new(move |cancellation| {
+            if cancellation.is_cancelled() {
+                return Vec::new();
+            }
+
+            symbols
+                .into_iter()
+                .filter(|symbol| symbol.text != prefix)
+                .filter_map(|symbol| {
+                    matcher::fuzzy_match(&prefix, &symbol.text).map(|m| {
+                        CompletionItem {
+                            details: None,
+                            format: format!(" {}", symbol.text),
+                            hl_ranges: m.hl_ranges(1),
+                            kind: Some(symbol.kind),
+                            matched_bytes: prefix.len() as u32,
+                            preselect: false,
+                            score: m.score,
+                            snippet: None,
+                            source: "Treesitter",
+                            text: symbol.text,
+                            ..Default::default()
+                        }
+                    })
+                })
+                .collect()
+        })))
+    }
+}
+
+impl Treesitter {
+    /// The `(skip_comments, skip_strings)` settings, copied into the
+    /// callbacks that re-index the buffers.
+    fn skip(&self) -> (bool, bool) { (self.skip_comments, self.skip_strings) }
+}
+
+/// Walks the syntax trees of a buffer, returning the symbols found in their
+/// leaves.
+fn index(
+    lua: &Lua,
+    api: &Api,
+    bufnr: u32,
+    (skip_comments, skip_strings): (bool, bool),
+) -> LuaResult<Vec<Symbol>> {
+    let parser = Neovim::new(lua)?.treesitter()?.get_parser(bufnr)?;
+    let lines = api.buf_get_lines(bufnr, 0, -1, false)?;
+
+    let mut symbols = HashMap::<String, &'static str>::new();
+
+    // The `(node, parent type)` tuples of the nodes left to visit.
+    let mut stack = parser
+        .parse()?
+        .into_iter()
+        .map(|root| (root, String::new()))
+        .collect::<Vec<(TSNode, String)>>();
+
+    while let Some((node, parent_type)) = stack.pop() {
+        let node_type = node.r#type()?;
+
+        if (skip_comments && node_type.contains("comment"))
+            || (skip_strings && node_type.contains("string"))
+        {
+            continue;
+        }
+
+        let children = node.named_child_count()?;
+
+        if children > 0 {
+            for i in 0..children {
+                stack.push((node.named_child(i)?, node_type.clone()));
+            }
+            continue;
+        }
+
+        let kind = match symbol_kind(&node_type, &parent_type) {
+            Some(kind) => kind,
+            None => continue,
+        };
+
+        let (start_row, start_col, end_row, end_col) = node.range()?;
+
+        // Identifiers never span multiple lines.
+        let text = match (start_row == end_row)
+            .then(|| lines.get(start_row as usize))
+            .flatten()
+            .and_then(|line| line.get(start_col as usize..end_col as usize))
+        {
+            Some(text) if text.len() >= MIN_SYMBOL_LENGTH => text,
+            _ => continue,
+        };
+
+        // Symbols used in different ways keep the most specific kind, e.g. a
+        // function name is a `Function` even where it's passed around as a
+        // variable.
+        symbols
+            .entry(text.to_owned())
+            .and_modify(|k| {
+                if *k == "Variable" {
+                    *k = kind
+                }
+            })
+            .or_insert(kind);
+    }
+
+    Ok(symbols
+        .into_iter()
+        .map(|(text, kind)| Symbol { text, kind })
+        .collect())
+}
+
+/// Returns the completion kind of a leaf node of the syntax tree given its
+/// type and the type of its parent, or `None` if the node isn't a symbol.
+/// Grammars name their node types differently, so this only looks for the
+/// conventions most of them share.
+fn symbol_kind(node_type: &str, parent_type: &str) -> Option<&'static str> {
+    if !node_type.ends_with("identifier") && node_type != "name" {
+        return None;
+    }
+
+    let kind = if node_type.contains("type") {
+        "Class"
+    } else if node_type.contains("field") || node_type.contains("property") {
+        "Field"
+    } else if node_type.contains("package") || node_type.contains("module") {
+        "Module"
+    } else if ["function", "method", "call"]
+        .iter()
+        .any(|ty| parent_type.contains(ty))
+    {
+        "Function"
+    } else {
+        "Variable"
+    };
+
+    Some(kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::symbol_kind;
+
+    #[test]
+    fn kinds() {
+        assert_eq!(
+            Some("Function"),
+            symbol_kind("identifier", "function_item")
+        );
+        assert_eq!(
+            Some("Function"),
+            symbol_kind("identifier", "call_expression")
+        );
+        assert_eq!(Some("Variable"), symbol_kind("identifier", "parameter"));
+        assert_eq!(
+            Some("Class"),
+            symbol_kind("type_identifier", "struct_item")
+        );
+        assert_eq!(
+            Some("Field"),
+            symbol_kind("field_identifier", "field_expression")
+        );
+        assert_eq!(
+            Some("Field"),
+            symbol_kind("property_identifier", "member_expression")
+        );
+        assert_eq!(Some("Variable"), symbol_kind("name", "variable_name"));
+        assert_eq!(None, symbol_kind("integer_literal", "let_declaration"));
+    }
+}
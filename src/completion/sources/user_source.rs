@@ -0,0 +1,159 @@
+use mlua::prelude::{
+    Lua,
+    LuaFunction,
+    LuaRegistryKey,
+    LuaResult,
+    LuaTable,
+    LuaValue,
+};
+use neovim::Api;
+
+use crate::completion::{
+    matcher,
+    CompletionItem,
+    CompletionSource,
+    Cursor,
+    Details,
+};
+use crate::ui::menu::kinds::KINDS;
+
+/// A source defined in Lua and registered with
+/// `require("compleet").register_source`.
+#[derive(Debug)]
+pub struct UserSource {
+    name: &'static str,
+
+    /// A registry key pointing to the Lua function returning the
+    /// completions.
+    complete: LuaRegistryKey,
+
+    trigger_characters: Vec<String>,
+}
+
+impl UserSource {
+    pub fn new(
+        name: &'static str,
+        complete: LuaRegistryKey,
+        trigger_characters: Vec<String>,
+    ) -> Self {
+        UserSource {
+            name,
+            complete,
+            trigger_characters,
+        }
+    }
+}
+
+impl CompletionSource for UserSource {
+    fn name(&self) -> &'static str { self.name }
+
+    fn attach(&self, _: &Lua, _: &Api, _: u32) -> LuaResult<bool> { Ok(true) }
+
+    fn trigger_characters(&self, _: &Lua, _: &Api) -> LuaResult<Vec<String>> {
+        Ok(self.trigger_characters.clone())
+    }
+
+    fn complete(
+        &self,
+        lua: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<Vec<CompletionItem>> {
+        let prefix = cursor.keyword_pre();
+
+        let ctx = lua.create_table_with_capacity(0, 5)?;
+        ctx.set("bufnr", api.get_current_buf()?)?;
+        ctx.set("row", cursor.row)?;
+        ctx.set("col", cursor.bytes)?;
+        ctx.set("line", cursor.line.as_str())?;
+        ctx.set("keyword", prefix)?;
+
+        let complete = lua.registry_value::<LuaFunction>(&self.complete)?;
+        let items = match complete.call::<_, Option<LuaTable>>(ctx)? {
+            Some(items) => items,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut completions = Vec::new();
+
+        for item in items.sequence_values::<LuaValue>() {
+            let (text, kind, details) = parse_item(item?)?;
+
+            if text == prefix {
+                continue;
+            }
+
+            // After a trigger character there's no keyword to match, and the
+            // source decides what to return.
+            let (score, hl_ranges) = match prefix.is_empty() {
+                true => (0, Vec::new()),
+                false => match matcher::fuzzy_match(prefix, &text) {
+                    Some(m) => (m.score, m.hl_ranges(1)),
+                    None => continue,
+                },
+            };
+
+            completions.push(CompletionItem {
+                details,
+                format: format!(" {text}"),
+                hl_ranges,
+                kind,
+                matched_bytes: prefix.len() as u32,
+                preselect: false,
+                score,
+                snippet: None,
+                source: self.name,
+                text,
+                ..Default::default()
+            });
+        }
+
+        Ok(completions)
+    }
+}
+
+/// Parses a completion returned by a Lua source, which is either a string or
+/// a `{ text, kind, details }` table where `kind` is the name of one of the
+/// LSP completion kinds and `details` is either a string or a list of lines.
+fn parse_item(
+    item: LuaValue,
+) -> LuaResult<(String, Option<&'static str>, Option<Details>)> {
+    let table = match item {
+        LuaValue::String(text) => {
+            return Ok((text.to_str()?.to_owned(), None, None));
+        },
+        LuaValue::Table(table) => table,
+        _ => {
+            return Err(mlua::Error::RuntimeError(
+                "completions must be strings or tables".into(),
+            ))
+        },
+    };
+
+    let text = table.get::<_, String>("text")?;
+
+    let kind = table.get::<_, Option<String>>("kind")?.and_then(|kind| {
+        KINDS
+            .iter()
+            .find(|(k, _, _)| *k == kind)
+            .map(|(k, _, _)| *k)
+    });
+
+    let details = match table.get::<_, LuaValue>("details")? {
+        LuaValue::String(details) => {
+            Some(details.to_str()?.lines().map(String::from).collect())
+        },
+        LuaValue::Table(lines) => Some(
+            lines
+                .sequence_values()
+                .collect::<LuaResult<Vec<String>>>()?,
+        ),
+        _ => None,
+    }
+    .map(|lines| Details {
+        lines,
+        is_markdown: false,
+    });
+
+    Ok((text, kind, details))
+}
@@ -0,0 +1,62 @@
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Api;
+
+use super::Cursor;
+use crate::state::Sources;
+
+/// Returns the longest of the `triggers` the text before the cursor ends
+/// with, or `None` if it doesn't end with any of them.
+pub fn find_trigger<'a>(
+    line_pre: &str,
+    triggers: &'a [String],
+) -> Option<&'a str> {
+    triggers
+        .iter()
+        .filter(|trigger| !trigger.is_empty() && line_pre.ends_with(*trigger))
+        .max_by_key(|trigger| trigger.len())
+        .map(String::as_str)
+}
+
+/// Whether the text before the cursor ends with one of the trigger characters
+/// of the `sources`, in which case the completions should be computed right
+/// away.
+pub fn is_triggered(
+    lua: &Lua,
+    api: &Api,
+    sources: &Sources,
+    cursor: &Cursor,
+) -> LuaResult<bool> {
+    let line_pre = &cursor.line[..cursor.bytes as usize];
+
+    for source in sources {
+        let triggers = source.trigger_characters(lua, api)?;
+        if find_trigger(line_pre, &triggers).is_some() {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_trigger;
+
+    fn triggers() -> Vec<String> { vec![".".into(), ":".into(), "::".into()] }
+
+    #[test]
+    fn no_trigger() {
+        // foo|
+        assert_eq!(None, find_trigger("foo", &triggers()));
+        // foo.b|
+        assert_eq!(None, find_trigger("foo.b", &triggers()));
+    }
+
+    #[test]
+    fn longest_trigger_wins() {
+        // foo.|
+        assert_eq!(Some("."), find_trigger("foo.", &triggers()));
+        // std::|
+        assert_eq!(Some("::"), find_trigger("std::", &triggers()));
+    }
+}
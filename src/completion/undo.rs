@@ -0,0 +1,10 @@
+use mlua::prelude::LuaResult;
+use neovim::Api;
+
+/// Closes the current undo block, so that the next change made to the buffer
+/// is undone separately from the text inserted so far. Setting
+/// `'undolevels'` to its own value is the documented way of doing it from a
+/// script (see `:h undo-break`).
+pub fn break_undo_sequence(api: &Api) -> LuaResult<()> {
+    api.command("let &g:undolevels = &g:undolevels")
+}
@@ -0,0 +1,103 @@
+//! Approximations of the Unicode grapheme cluster boundaries and display
+//! widths, good enough to lay out and highlight the completions as Neovim
+//! renders them.
+
+/// The ranges of the characters that don't start a new grapheme cluster but
+/// are composed with the previous character, i.e. combining marks, zero width
+/// joiners, variation selectors and emoji modifiers.
+const COMPOSING: [(char, char); 14] = [
+    ('\u{0300}', '\u{036f}'),
+    ('\u{0483}', '\u{0489}'),
+    ('\u{0591}', '\u{05bd}'),
+    ('\u{0610}', '\u{061a}'),
+    ('\u{064b}', '\u{065f}'),
+    ('\u{1ab0}', '\u{1aff}'),
+    ('\u{1dc0}', '\u{1dff}'),
+    ('\u{200c}', '\u{200d}'),
+    ('\u{20d0}', '\u{20ff}'),
+    ('\u{3099}', '\u{309a}'),
+    ('\u{fe00}', '\u{fe0f}'),
+    ('\u{fe20}', '\u{fe2f}'),
+    ('\u{1f3fb}', '\u{1f3ff}'),
+    ('\u{e0100}', '\u{e01ef}'),
+];
+
+/// The ranges of the characters taking up two cells, i.e. the East Asian wide
+/// and fullwidth characters and most emojis.
+const WIDE: [(char, char); 14] = [
+    ('\u{1100}', '\u{115f}'),
+    ('\u{2e80}', '\u{303e}'),
+    ('\u{3041}', '\u{33ff}'),
+    ('\u{3400}', '\u{4dbf}'),
+    ('\u{4e00}', '\u{9fff}'),
+    ('\u{a000}', '\u{a4cf}'),
+    ('\u{ac00}', '\u{d7a3}'),
+    ('\u{f900}', '\u{faff}'),
+    ('\u{fe30}', '\u{fe4f}'),
+    ('\u{ff00}', '\u{ff60}'),
+    ('\u{ffe0}', '\u{ffe6}'),
+    ('\u{1f300}', '\u{1f64f}'),
+    ('\u{1f900}', '\u{1f9ff}'),
+    ('\u{20000}', '\u{3fffd}'),
+];
+
+fn in_table(table: &[(char, char)], c: char) -> bool {
+    table.iter().any(|&(start, end)| start <= c && c <= end)
+}
+
+/// Whether `c` is composed with the character before it instead of starting
+/// a new grapheme cluster.
+pub fn is_composing(c: char) -> bool { in_table(&COMPOSING, c) }
+
+/// The number of cells taken up by `c`.
+pub fn char_width(c: char) -> usize {
+    match c {
+        _ if is_composing(c) => 0,
+        _ if in_table(&WIDE, c) => 2,
+        _ => 1,
+    }
+}
+
+/// The number of cells taken up by `str`.
+pub fn display_width(str: &str) -> usize { str.chars().map(char_width).sum() }
+
+/// Returns the end of the grapheme cluster containing the character ending at
+/// byte `end` of `text`, i.e. `end` moved past the composing characters
+/// following it. Characters joined by a zero width joiner are part of the
+/// same cluster.
+pub fn grapheme_end(text: &str, mut end: usize) -> usize {
+    let mut joined = false;
+    for c in text[end..].chars() {
+        if !(joined || is_composing(c)) {
+            break;
+        }
+        joined = c == '\u{200d}';
+        end += c.len_utf8();
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn widths() {
+        assert_eq!(3, display_width("foo"));
+        assert_eq!(4, display_width("日本"));
+        assert_eq!(2, display_width("e\u{301}e"));
+        assert_eq!(2, display_width("🦀"));
+    }
+
+    #[test]
+    fn grapheme_ends() {
+        // `e` followed by a combining acute accent.
+        assert_eq!(3, grapheme_end("e\u{301}x", 1));
+        assert_eq!(4, grapheme_end("e\u{301}x", 4));
+
+        // Woman + ZWJ + laptop.
+        let technologist = "\u{1f469}\u{200d}\u{1f4bb}!";
+        assert_eq!(11, grapheme_end(technologist, 4));
+        assert_eq!(3, grapheme_end("日本", 3));
+    }
+}
@@ -0,0 +1,171 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use mlua::prelude::LuaResult;
+use neovim::Api;
+
+/// The first line of every cache file, changed whenever their format does so
+/// that files written by older versions are ignored.
+const HEADER: &str = "compleet-cache v1";
+
+/// The directory of the cache files, global so that the sources building
+/// their indexes on background threads can read and write them. `None` if
+/// caching to disk is disabled.
+static DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Sets where the indexes of the sources are cached, i.e. the `compleet`
+/// directory under Neovim's cache directory, or disables caching them if
+/// `enable` is `false`.
+pub fn init(api: &Api, enable: bool) -> LuaResult<()> {
+    let dir = match enable {
+        true => {
+            let cache =
+                api.call_function::<_, String>("stdpath", vec!["cache"])?;
+            Some(PathBuf::from(cache).join("compleet"))
+        },
+        false => None,
+    };
+    *DIR.lock().unwrap() = dir;
+    Ok(())
+}
+
+/// The directory of the cache files, or `None` if caching is disabled.
+pub fn dir() -> Option<PathBuf> { DIR.lock().unwrap().clone() }
+
+/// Returns the lines cached under `name`, or `None` if they weren't cached
+/// or if they were cached under a different `key`.
+pub fn read(name: &str, key: &str) -> Option<Vec<String>> {
+    let contents = fs::read_to_string(dir()?.join(name)).ok()?;
+    let mut lines = contents.lines();
+
+    if lines.next() != Some(HEADER) || lines.next() != Some(key) {
+        return None;
+    }
+
+    Some(lines.map(String::from).collect())
+}
+
+/// Caches the `lines` under `name`, overwriting the ones cached under any
+/// other `key`. Every `name` has a single file, with the `key` stored in its
+/// header. None of the lines can contain a newline. Errors are ignored,
+/// since the lines can always be computed again.
+pub fn write<L: AsRef<str>>(name: &str, key: &str, lines: &[L]) {
+    let dir = match dir() {
+        Some(dir) if !key.contains('\n') => dir,
+        _ => return,
+    };
+
+    let mut contents = format!("{HEADER}\n{key}\n");
+    for line in lines {
+        contents.push_str(line.as_ref());
+        contents.push('\n');
+    }
+
+    // The file is written under a temporary name and then renamed, so that
+    // another Neovim instance never reads it half-written.
+    let path = dir.join(name);
+    let tmp = path.with_extension("tmp");
+    let _ = fs::create_dir_all(&dir)
+        .and_then(|_| fs::write(&tmp, contents))
+        .and_then(|_| fs::rename(&tmp, &path));
+}
+
+/// Deletes all the cache files, returning how many were deleted.
+pub fn clear() -> io::Result<usize> {
+    let dir = match dir() {
+        Some(dir) => dir,
+        None => return Ok(0),
+    };
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err),
+    };
+
+    let mut deleted = 0;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_file() {
+            fs::remove_file(path)?;
+            deleted += 1;
+        }
+    }
+    Ok(deleted)
+}
+
+/// Returns a key that changes when any of the files at `paths` is modified,
+/// made of their paths, modification times and sizes. Files that can't be
+/// read are included without them.
+pub fn fingerprint(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| {
+            let (mtime, len) = fs::metadata(path)
+                .ok()
+                .and_then(|m| {
+                    let mtime = m.modified().ok()?.duration_since(UNIX_EPOCH);
+                    Some((mtime.ok()?.as_nanos(), m.len()))
+                })
+                .unwrap_or_default();
+            format!("{}:{mtime}:{len}", path.display())
+        })
+        .collect::<Vec<String>>()
+        .join("\t")
+}
+
+/// The 64-bit FNV-1a hash of `s`, which unlike the hasher of the standard
+/// library is guaranteed to be the same across versions of Rust.
+pub fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{fingerprint, fnv1a, read, write, DIR};
+
+    #[test]
+    fn fnv1a_hashes() {
+        assert_eq!(0xcbf29ce484222325, fnv1a(""));
+        assert_eq!(0xaf63dc4c8601ec8c, fnv1a("a"));
+        assert_eq!(0x85944171f73967e8, fnv1a("foobar"));
+    }
+
+    #[test]
+    fn changing_the_key_overwrites_the_file() {
+        let dir = std::env::temp_dir()
+            .join(format!("compleet-cache-{}", std::process::id()));
+        *DIR.lock().unwrap() = Some(dir.clone());
+
+        write("words", "a", &["foo", "bar"]);
+        assert_eq!(Some(vec!["foo".into(), "bar".into()]), read("words", "a"));
+
+        write("words", "b", &["baz"]);
+        assert_eq!(None, read("words", "a"));
+        assert_eq!(Some(vec!["baz".into()]), read("words", "b"));
+        assert_eq!(1, fs::read_dir(&dir).unwrap().count());
+
+        *DIR.lock().unwrap() = None;
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn missing_files_are_fingerprinted() {
+        let paths = vec![
+            PathBuf::from("/does/not/exist"),
+            PathBuf::from("/neither/does/this"),
+        ];
+        assert_eq!(
+            "/does/not/exist:0:0\t/neither/does/this:0:0",
+            fingerprint(&paths)
+        );
+    }
+}
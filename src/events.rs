@@ -0,0 +1,78 @@
+use mlua::prelude::{Lua, LuaResult, LuaTable};
+use neovim::Neovim;
+
+use crate::completion::CompletionItem;
+
+/// The `User` autocommands fired during the completion lifecycle, which other
+/// plugins and user configs can hook into. The data of an event is passed in
+/// the `data` field of the table given to the autocmd's callback.
+#[derive(Debug)]
+pub enum Event<'a> {
+    /// `User CompleetMenuOpened`, with the number of `items` in the menu.
+    MenuOpened { items: usize },
+
+    /// `User CompleetMenuClosed`.
+    MenuClosed,
+
+    /// `User CompleetItemConfirmed`, with the `text`, `kind`, `source` and
+    /// `details` of the confirmed completion. Fired once the completion has
+    /// been inserted in the buffer.
+    ItemConfirmed(&'a CompletionItem),
+
+    /// `User CompleetSourcesDone`, fired once the sources running on
+    /// background threads have returned their completions for the current
+    /// cursor position.
+    SourcesDone,
+}
+
+impl Event<'_> {
+    fn pattern(&self) -> &'static str {
+        match self {
+            Event::MenuOpened { .. } => "CompleetMenuOpened",
+            Event::MenuClosed => "CompleetMenuClosed",
+            Event::ItemConfirmed(_) => "CompleetItemConfirmed",
+            Event::SourcesDone => "CompleetSourcesDone",
+        }
+    }
+
+    fn data<'lua>(&self, lua: &'lua Lua) -> LuaResult<LuaTable<'lua>> {
+        let data = lua.create_table()?;
+
+        match self {
+            Event::MenuOpened { items } => data.set("items", *items)?,
+
+            Event::ItemConfirmed(completion) => {
+                data.set("text", completion.text.as_str())?;
+                data.set("kind", completion.kind)?;
+                data.set("source", completion.source)?;
+                if let Some(details) = &completion.details {
+                    data.set("details", details.lines.clone())?;
+                }
+            },
+
+            Event::MenuClosed | Event::SourcesDone => {},
+        }
+
+        Ok(data)
+    }
+}
+
+/// Schedules the `User` autocommand of an event. Like with
+/// `completion::schedule_refresh`, scheduling makes it safe to call this while
+/// the state is locked, since the autocmds' callbacks can call back into the
+/// plugin.
+pub fn fire(lua: &Lua, event: Event) -> LuaResult<()> {
+    let pattern = event.pattern();
+    let data = lua.create_registry_value(event.data(lua)?)?;
+
+    let fire_autocmd = lua.create_function(move |lua, ()| {
+        let api = Neovim::new(lua)?.api;
+        let opts = lua.create_table_with_capacity(0, 3)?;
+        opts.set("pattern", pattern)?;
+        opts.set("modeline", false)?;
+        opts.set("data", lua.registry_value::<LuaTable>(&data)?)?;
+        api.exec_autocmds(&["User"], opts)
+    })?;
+
+    Neovim::new(lua)?.schedule(fire_autocmd)
+}
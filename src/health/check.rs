@@ -0,0 +1,99 @@
+use mlua::prelude::{Lua, LuaResult};
+use neovim::{Api, Neovim};
+
+use super::report::{self, Report};
+use crate::state::State;
+
+/// Executed by `:checkhealth compleet`, through the `compleet.health` Lua
+/// module.
+pub fn check(lua: &Lua, state: &State) -> LuaResult<()> {
+    let api = Neovim::new(lua)?.api;
+
+    report::start(lua, "Neovim")?;
+    for report in check_neovim(&api, state)? {
+        report.send(lua)?;
+    }
+
+    report::start(lua, "Setup")?;
+    check_setup(state).send(lua)?;
+
+    report::start(lua, "Channel")?;
+    check_channel(lua)?.send(lua)?;
+
+    report::start(lua, "Sources")?;
+    if state.all_sources().next().is_none() {
+        Report::warn(
+            "No sources are enabled",
+            &["Enable some in the `sources` section of the config"],
+        )
+        .send(lua)?;
+    }
+
+    for source in state.all_sources() {
+        let reports = source.check_health(lua, &api)?;
+        if reports.is_empty() {
+            Report::ok(format!("{} is enabled", source.name())).send(lua)?;
+        }
+        for report in reports {
+            report.send(lua)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn check_neovim(api: &Api, state: &State) -> LuaResult<Vec<Report>> {
+    let has = |version: &str| -> LuaResult<bool> {
+        Ok(api.call_function::<_, u8>("has", vec![version])? == 1)
+    };
+
+    if !has("nvim-0.7")? {
+        return Ok(vec![Report::error(
+            "nvim-compleet needs Neovim 0.7+",
+            &["Update Neovim"],
+        )]);
+    }
+
+    let mut reports = vec![Report::ok("Neovim 0.7+")];
+
+    if state.settings.ui.menu.title.is_some() && !has("nvim-0.9")? {
+        reports.push(Report::warn(
+            "`ui.menu.title` needs Neovim 0.9+, the menu has no title",
+            &["Update Neovim or unset the option"],
+        ));
+    }
+
+    Ok(reports)
+}
+
+/// Checks that the completions computed on background threads can be sent
+/// back to the main thread, which polls them with a `vim.loop` timer and then
+/// schedules a refresh of the UI.
+fn check_channel(lua: &Lua) -> LuaResult<Report> {
+    let is_available = lua
+        .load(
+            r#"return type(vim.loop) == "table"
+              and type(vim.loop.new_timer) == "function"
+              and type(vim.schedule) == "function""#,
+        )
+        .eval::<bool>()?;
+
+    Ok(match is_available {
+        true => Report::ok("The background jobs can send their completions"),
+        false => Report::error(
+            "`vim.loop` timers or `vim.schedule` are missing, the \
+             completions of the background jobs are never shown",
+            &["Use a Neovim build with the `luv` bindings"],
+        ),
+    })
+}
+
+fn check_setup(state: &State) -> Report {
+    match state.augroup_id {
+        Some(_) => Report::ok("`setup` was called"),
+        None => Report::error(
+            "`setup` wasn't called, nothing is completed",
+            &["Call `require(\"compleet\").setup()` in your config"],
+        ),
+    }
+}
@@ -0,0 +1,5 @@
+mod check;
+mod report;
+
+pub use check::check;
+pub use report::Report;
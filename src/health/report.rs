@@ -0,0 +1,94 @@
+use mlua::prelude::{Lua, LuaFunction, LuaResult, LuaValue};
+use neovim::Api;
+
+/// The key of the Lua registry where the function sending the reports is
+/// stored.
+const SEND: &str = "compleet_health_send";
+
+/// Sends a report to the buffer of `:checkhealth`. Neovim 0.7 only has the
+/// `health#report_*` Vimscript functions, 0.8 added `vim.health.report_*`
+/// and 0.10 deprecated them in favour of `vim.health.{start,ok,...}`.
+const SEND_CHUNK: &str = r#"
+local kind, msg, advice = ...
+local health = vim.health or {}
+local report = health.start and health[kind]
+  or health["report_" .. kind]
+  or vim.fn["health#report_" .. kind]
+if advice and #advice > 0 then
+  report(msg, advice)
+else
+  report(msg)
+end
+"#;
+
+/// The outcome of one of the checks of `:checkhealth compleet`. Warnings and
+/// errors come with suggestions on how to fix them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Report {
+    Ok(String),
+    Warn(String, Vec<String>),
+    Error(String, Vec<String>),
+}
+
+impl Report {
+    pub fn ok(msg: impl Into<String>) -> Self { Report::Ok(msg.into()) }
+
+    pub fn warn(msg: impl Into<String>, advice: &[&str]) -> Self {
+        Report::Warn(msg.into(), to_owned(advice))
+    }
+
+    pub fn error(msg: impl Into<String>, advice: &[&str]) -> Self {
+        Report::Error(msg.into(), to_owned(advice))
+    }
+
+    /// Checks whether the `executable` needed by the `source` is installed,
+    /// suggesting to `install` it otherwise.
+    pub fn executable(
+        api: &Api,
+        executable: &str,
+        source: &str,
+        install: &str,
+    ) -> LuaResult<Self> {
+        Ok(
+            match api.call_function::<_, u8>("executable", vec![executable])? {
+                1 => Report::ok(format!("`{executable}` is installed")),
+                _ => Report::error(
+                    format!(
+                        "`{executable}` isn't installed, the {source} source \
+                         won't complete anything"
+                    ),
+                    &[install],
+                ),
+            },
+        )
+    }
+
+    pub fn send(&self, lua: &Lua) -> LuaResult<()> {
+        let (kind, msg, advice) = match self {
+            Report::Ok(msg) => ("ok", msg, None),
+            Report::Warn(msg, advice) => ("warn", msg, Some(advice)),
+            Report::Error(msg, advice) => ("error", msg, Some(advice)),
+        };
+        send(lua)?.call((kind, msg.as_str(), advice.cloned()))
+    }
+}
+
+/// Starts a new section of the report.
+pub fn start(lua: &Lua, name: &str) -> LuaResult<()> {
+    send(lua)?.call(("start", name))
+}
+
+fn send(lua: &Lua) -> LuaResult<LuaFunction<'_>> {
+    match lua.named_registry_value::<_, LuaValue>(SEND)? {
+        LuaValue::Function(send) => Ok(send),
+        _ => {
+            let send: LuaFunction = lua.load(SEND_CHUNK).into_function()?;
+            lua.set_named_registry_value(SEND, send.clone())?;
+            Ok(send)
+        },
+    }
+}
+
+fn to_owned(advice: &[&str]) -> Vec<String> {
+    advice.iter().map(|&a| a.to_owned()).collect()
+}
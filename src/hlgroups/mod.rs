@@ -1,5 +0,0 @@
-mod setup;
-mod setup_error_msg;
-
-pub use setup::setup;
-pub use setup_error_msg::setup_error_msg;
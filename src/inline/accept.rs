@@ -0,0 +1,98 @@
+use crate::completion::is_keyword_char;
+
+/// How much of an inline suggestion is accepted at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    /// The next word, together with the whitespace before it.
+    Word,
+
+    /// The rest of the current line, or the line break ending it.
+    Line,
+
+    /// The whole suggestion.
+    All,
+}
+
+/// Returns the start of the suggested `text` accepted with the given
+/// `granularity`.
+pub fn accepted(text: &str, granularity: Granularity) -> &str {
+    // A suggestion starting on a new line first accepts the line break.
+    if granularity != Granularity::All && text.starts_with('\n') {
+        return &text[..1];
+    }
+
+    let len = match granularity {
+        Granularity::All => text.len(),
+
+        Granularity::Line => text.find('\n').unwrap_or(text.len()),
+
+        Granularity::Word => {
+            let start = text
+                .find(|c: char| c == '\n' || !c.is_whitespace())
+                .unwrap_or(text.len());
+            let rest = &text[start..];
+            // A character that isn't part of a keyword (e.g. a parenthesis)
+            // is accepted on its own.
+            let word = match rest.find(|c| !is_keyword_char(c)) {
+                Some(0) => rest
+                    .chars()
+                    .next()
+                    .filter(|&c| c != '\n')
+                    .map_or(0, char::len_utf8),
+                Some(end) => end,
+                None => rest.len(),
+            };
+            start + word
+        },
+    };
+
+    &text[..len]
+}
+
+/// Returns the 0-indexed `(row, col)` position right after the `text` if
+/// it's inserted at `(row, col)`.
+pub fn end_position(row: u32, col: u32, text: &str) -> (u32, u32) {
+    match text.rsplit_once('\n') {
+        Some((before, last)) => (
+            row + before.matches('\n').count() as u32 + 1,
+            last.len() as u32,
+        ),
+        None => (row, col + text.len() as u32),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{accepted, end_position, Granularity};
+
+    #[test]
+    fn accept_word() {
+        let word = |text| accepted(text, Granularity::Word);
+        assert_eq!("foo", word("foo(bar)"));
+        assert_eq!("  bar", word("  bar baz"));
+        assert_eq!("(", word("(bar)"));
+        assert_eq!(" ", word(" \nfoo"));
+        assert_eq!("\n", word("\nfoo"));
+        assert_eq!("baz", word("baz"));
+    }
+
+    #[test]
+    fn accept_line() {
+        let line = |text| accepted(text, Granularity::Line);
+        assert_eq!("foo bar", line("foo bar\nbaz"));
+        assert_eq!("\n", line("\nbaz"));
+        assert_eq!("foo", line("foo"));
+    }
+
+    #[test]
+    fn accept_all() {
+        assert_eq!("foo\nbar", accepted("foo\nbar", Granularity::All));
+    }
+
+    #[test]
+    fn end_positions() {
+        assert_eq!((3, 10), end_position(3, 7, "foo"));
+        assert_eq!((4, 0), end_position(3, 7, "\n"));
+        assert_eq!((5, 3), end_position(3, 7, "a\nbc\ndef"));
+    }
+}
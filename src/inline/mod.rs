@@ -0,0 +1,7 @@
+mod accept;
+mod setup;
+mod state;
+
+pub use accept::Granularity;
+pub use setup::setup;
+pub use state::Inline;
@@ -0,0 +1,133 @@
+use std::sync::{Arc, Mutex};
+
+use mlua::prelude::{Lua, LuaRegistryKey, LuaResult};
+use neovim::{Api, Neovim};
+
+use super::Granularity;
+use crate::boundary;
+use crate::state::State;
+
+/// Creates the autocommands used to show the inline suggestions, together
+/// with the `<Plug>` mappings accepting them. Returns registry keys pointing
+/// to the function the providers call with their suggestions and to the one
+/// called when the requests to the providers are debounced.
+pub fn setup(
+    lua: &Lua,
+    api: &Api,
+    state: &Arc<Mutex<State>>,
+) -> LuaResult<(LuaRegistryKey, LuaRegistryKey)> {
+    // Called by the providers with their suggestion, possibly from a fast
+    // event context and while the state is locked, so showing it is
+    // scheduled.
+    let _state = state.clone();
+    let on_suggestion = lua.create_function(
+        move |lua, args: (u64, u32, u32, u32, Option<String>)| {
+            let _state = _state.clone();
+            let show = lua.create_function(
+                move |lua,
+                      (request, bufnr, row, col, text): (
+                    u64,
+                    u32,
+                    u32,
+                    u32,
+                    Option<String>,
+                )| {
+                    let text = match text {
+                        Some(text) => text,
+                        None => return Ok(()),
+                    };
+                    boundary::guard(lua, &_state, "inline_show", |state| {
+                        let api = Neovim::new(lua)?.api;
+                        state.inline.show(
+                            lua,
+                            &api,
+                            (request, bufnr, row, col),
+                            text,
+                        )
+                    })
+                },
+            )?;
+            Neovim::new(lua)?.schedule(show.bind(args)?)
+        },
+    )?;
+
+    let _state = state.clone();
+    let on_changed = lua.create_function(move |lua, ()| {
+        boundary::guard(lua, &_state, "inline_on_changed", |state| {
+            let api = Neovim::new(lua)?.api;
+            if !state.attached_buffers.contains(&api.get_current_buf()?) {
+                return Ok(());
+            }
+            let debounce_ms = state.settings.completion.debounce_ms;
+            state.inline.on_changed(lua, &api, debounce_ms)
+        })
+    })?;
+
+    // Called by the debounce timer in a fast event context, so asking the
+    // providers is scheduled.
+    let _state = state.clone();
+    let on_debounced = lua.create_function(move |lua, request: u64| {
+        let _state = _state.clone();
+        let suggest = lua.create_function(move |lua, request: u64| {
+            boundary::guard(lua, &_state, "inline_on_debounced", |state| {
+                let api = Neovim::new(lua)?.api;
+                state.inline.on_debounced(lua, &api, request)
+            })
+        })?;
+        Neovim::new(lua)?.schedule(suggest.bind(request)?)
+    })?;
+
+    let _state = state.clone();
+    let on_cursor_moved = lua.create_function(move |lua, ()| {
+        boundary::guard(lua, &_state, "inline_on_cursor_moved", |state| {
+            let api = Neovim::new(lua)?.api;
+            state.inline.on_cursor_moved(&api)
+        })
+    })?;
+
+    let _state = state.clone();
+    let on_leave = lua.create_function(move |lua, ()| {
+        boundary::guard(lua, &_state, "inline_on_leave", |state| {
+            let api = Neovim::new(lua)?.api;
+            state.inline.clear(&api)
+        })
+    })?;
+
+    let opts = lua.create_table_from([("clear", true)])?;
+    let augroup_id = api.create_augroup("CompleetInline", opts)?;
+
+    let opts = lua.create_table_with_capacity(0, 2)?;
+    opts.set("group", augroup_id)?;
+
+    opts.set("callback", on_changed)?;
+    api.create_autocmd(&["TextChangedI"], opts.clone())?;
+
+    opts.set("callback", on_cursor_moved)?;
+    api.create_autocmd(&["CursorMovedI"], opts.clone())?;
+
+    opts.set("callback", on_leave)?;
+    api.create_autocmd(&["InsertLeave", "BufLeave"], opts)?;
+
+    for (granularity, plug) in [
+        (Granularity::Word, "<Plug>(compleet-accept-inline-word)"),
+        (Granularity::Line, "<Plug>(compleet-accept-inline-line)"),
+        (Granularity::All, "<Plug>(compleet-accept-inline)"),
+    ] {
+        let _state = state.clone();
+        let accept = lua.create_function(move |lua, ()| {
+            boundary::guard(lua, &_state, "inline_accept", |state| {
+                let api = Neovim::new(lua)?.api;
+                state.inline.accept(lua, &api, granularity)
+            })
+        })?;
+
+        let opts = lua.create_table_from([("silent", true)])?;
+        opts.set("callback", accept)?;
+        api.set_keymap("i", plug, "", opts)?;
+    }
+
+    Ok((
+        lua.create_registry_value(on_suggestion)?,
+        lua.create_registry_value(on_debounced)?,
+    ))
+}
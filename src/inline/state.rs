@@ -0,0 +1,327 @@
+use mlua::prelude::{Lua, LuaFunction, LuaRegistryKey, LuaResult, LuaTable};
+use neovim::{Api, Neovim};
+
+use super::accept::{self, Granularity};
+use crate::completion;
+
+/// The key of the Lua registry where the timer used to debounce the requests
+/// to the providers is stored.
+const DEBOUNCE_TIMER: &str = "compleet_inline_debounce_timer";
+
+/// Holds the inline suggestions shown as ghost text, which come from the
+/// providers registered with `require("compleet").register_inline_provider`
+/// and are independent from the completion menu.
+#[derive(Debug)]
+pub struct Inline {
+    /// The namespace id of the ghost text.
+    nsid: u32,
+
+    /// The `(name, suggest)` pairs of the registered providers, where
+    /// `suggest` is a registry key pointing to the Lua function asked for a
+    /// suggestion.
+    providers: Vec<(String, LuaRegistryKey)>,
+
+    /// Incremented every time the providers are asked for a suggestion, used
+    /// to discard the suggestions for an outdated request.
+    request: u64,
+
+    /// The suggestion currently shown, if any.
+    suggestion: Option<Suggestion>,
+
+    /// A registry key pointing to the Lua function that shows a suggestion
+    /// delivered by a provider, set by `inline::setup`.
+    pub on_suggestion: Option<LuaRegistryKey>,
+
+    /// A registry key pointing to the Lua function asking the providers for
+    /// a suggestion once the debounce timeout has passed, set by
+    /// `inline::setup`.
+    pub on_debounced: Option<LuaRegistryKey>,
+}
+
+#[derive(Debug)]
+struct Suggestion {
+    bufnr: u32,
+
+    /// The 0-indexed position the suggestion starts at.
+    row: u32,
+    col: u32,
+
+    /// The part of the suggestion that hasn't been typed or accepted yet.
+    text: String,
+}
+
+impl Inline {
+    pub fn new(api: &Api) -> LuaResult<Self> {
+        Ok(Inline {
+            nsid: api.create_namespace("compleet_inline_suggestion")?,
+            providers: Vec::new(),
+            request: 0,
+            suggestion: None,
+            on_suggestion: None,
+            on_debounced: None,
+        })
+    }
+}
+
+impl Inline {
+    /// Registers a provider, replacing the one with the same name if there's
+    /// one.
+    pub fn register(&mut self, name: String, suggest: LuaRegistryKey) {
+        self.providers.retain(|(n, _)| n != &name);
+        self.providers.push((name, suggest));
+    }
+
+    /// Whether a suggestion is currently shown.
+    pub fn is_visible(&self) -> bool { self.suggestion.is_some() }
+
+    /// Erases the ghost text and discards the suggestions that are still
+    /// being computed.
+    pub fn clear(&mut self, api: &Api) -> LuaResult<()> {
+        self.request += 1;
+        if self.suggestion.take().is_some() {
+            api.buf_clear_namespace(0, self.nsid.try_into().unwrap(), 0, -1)?;
+        }
+        Ok(())
+    }
+
+    /// Executed on every `TextChangedI` event in attached buffers. If the
+    /// text typed since the suggestion was shown matches its start the rest
+    /// of it stays visible, otherwise the providers are asked for a new one
+    /// once `debounce_ms` milliseconds have passed without typing.
+    pub fn on_changed(
+        &mut self,
+        lua: &Lua,
+        api: &Api,
+        debounce_ms: u32,
+    ) -> LuaResult<()> {
+        let bufnr = api.get_current_buf()?;
+        let (row, col) = api.win_get_cursor(0)?;
+        let row = row - 1;
+        let line = api.get_current_line()?;
+
+        if let Some(suggestion) = &mut self.suggestion {
+            // The typed text can't be sliced if the line was also edited
+            // before the cursor, in which case the suggestion is dropped.
+            let typed = (suggestion.bufnr == bufnr && suggestion.row == row)
+                .then(|| line.get(suggestion.col as usize..col as usize))
+                .flatten();
+
+            if let Some(typed) = typed {
+                if let Some(rest) = suggestion
+                    .text
+                    .strip_prefix(typed)
+                    .filter(|rest| !rest.is_empty())
+                {
+                    suggestion.text = rest.to_owned();
+                    suggestion.col = col;
+                    return self.draw(lua, api);
+                }
+            }
+        }
+
+        self.clear(api)?;
+
+        if self.providers.is_empty() {
+            return Ok(());
+        }
+
+        if debounce_ms > 0 {
+            let on_debounced = lua.registry_value::<LuaFunction>(
+                self.on_debounced
+                    .as_ref()
+                    .expect("`on_debounced` has already been created"),
+            )?;
+            return completion::debounce_with(
+                lua,
+                DEBOUNCE_TIMER,
+                debounce_ms,
+                on_debounced.bind(self.request)?,
+            );
+        }
+
+        self.suggest(lua, api)
+    }
+
+    /// Executed once the debounce timeout of the `request`-th request has
+    /// passed, asking the providers for a suggestion unless the text has
+    /// changed or insert mode was left since.
+    pub fn on_debounced(
+        &mut self,
+        lua: &Lua,
+        api: &Api,
+        request: u64,
+    ) -> LuaResult<()> {
+        if request != self.request || !api.get_mode()?.0.starts_with('i') {
+            return Ok(());
+        }
+        self.suggest(lua, api)
+    }
+
+    /// Asks the providers for a suggestion at the cursor. Providers are
+    /// called while the state is locked, so they shouldn't call the functions
+    /// of the `compleet` module.
+    fn suggest(&self, lua: &Lua, api: &Api) -> LuaResult<()> {
+        let bufnr = api.get_current_buf()?;
+        let (row, col) = api.win_get_cursor(0)?;
+        let row = row - 1;
+        let line = api.get_current_line()?;
+
+        let on_suggestion = lua.registry_value::<LuaFunction>(
+            self.on_suggestion
+                .as_ref()
+                .expect("`on_suggestion` has already been created"),
+        )?;
+
+        let ctx = lua.create_table_with_capacity(0, 4)?;
+        ctx.set("bufnr", bufnr)?;
+        ctx.set("row", row)?;
+        ctx.set("col", col)?;
+        ctx.set("line", line)?;
+
+        for (_, suggest) in &self.providers {
+            let suggest = lua.registry_value::<LuaFunction>(suggest)?;
+            let done = on_suggestion.bind((self.request, bufnr, row, col))?;
+            suggest.call::<_, ()>((ctx.clone(), done))?;
+        }
+
+        Ok(())
+    }
+
+    /// Executed on every `CursorMovedI` event, erasing the ghost text if the
+    /// cursor was moved away from the position the suggestion starts at.
+    /// Typing moves the cursor forward, which is handled by `on_changed`.
+    pub fn on_cursor_moved(&mut self, api: &Api) -> LuaResult<()> {
+        let suggestion = match &self.suggestion {
+            Some(suggestion) => suggestion,
+            None => return Ok(()),
+        };
+
+        let (row, col) = api.win_get_cursor(0)?;
+        if api.get_current_buf()? != suggestion.bufnr
+            || row - 1 != suggestion.row
+            || col < suggestion.col
+        {
+            self.clear(api)?;
+        }
+
+        Ok(())
+    }
+
+    /// Shows the `text` suggested by a provider for the `request`-th request,
+    /// made with the cursor at `(row, col)` in the buffer `bufnr`. The first
+    /// provider to answer wins, and the suggestion is discarded if the cursor
+    /// has moved since the request or if there's text after the cursor, which
+    /// the ghost text would cover.
+    pub fn show(
+        &mut self,
+        lua: &Lua,
+        api: &Api,
+        (request, bufnr, row, col): (u64, u32, u32, u32),
+        text: String,
+    ) -> LuaResult<()> {
+        if request != self.request
+            || self.suggestion.is_some()
+            || text.is_empty()
+            || !api.get_mode()?.0.starts_with('i')
+            || api.get_current_buf()? != bufnr
+            || api.win_get_cursor(0)? != (row + 1, col)
+            || api.get_current_line()?.len() != col as usize
+        {
+            return Ok(());
+        }
+
+        self.suggestion = Some(Suggestion {
+            bufnr,
+            row,
+            col,
+            text,
+        });
+
+        self.draw(lua, api)
+    }
+
+    /// Inserts the start of the suggestion accepted with the given
+    /// `granularity`, keeping the rest of it visible.
+    pub fn accept(
+        &mut self,
+        lua: &Lua,
+        api: &Api,
+        granularity: Granularity,
+    ) -> LuaResult<()> {
+        let suggestion = match &mut self.suggestion {
+            Some(suggestion) => suggestion,
+            None => return Ok(()),
+        };
+
+        let text = accept::accepted(&suggestion.text, granularity).to_owned();
+        let (row, col) = (suggestion.row, suggestion.col);
+        let end = accept::end_position(row, col, &text);
+
+        // The ghost text is drawn again at the new position by `on_changed`,
+        // once the text has been inserted.
+        api.buf_clear_namespace(0, self.nsid.try_into().unwrap(), 0, -1)?;
+
+        match suggestion.text.len() == text.len() {
+            true => self.clear(api)?,
+            false => {
+                suggestion.text.drain(..text.len());
+                (suggestion.row, suggestion.col) = end;
+            },
+        }
+
+        // Inserting the text while the state is locked would deadlock in
+        // `completion::on_bytes`, so it's scheduled.
+        let insert = lua.create_function(
+            |lua,
+             (row, col, text, end_row, end_col): (
+                u32,
+                u32,
+                String,
+                u32,
+                u32,
+            )| {
+                let api = Neovim::new(lua)?.api;
+                let lines = text.split('\n').collect::<Vec<&str>>();
+                api.buf_set_text(0, row, col, row, col, &lines)?;
+                api.win_set_cursor(0, end_row + 1, end_col)
+            },
+        )?;
+
+        Neovim::new(lua)?
+            .schedule(insert.bind((row, col, text, end.0, end.1))?)
+    }
+
+    /// Draws the suggestion as ghost text, with its first line right after
+    /// the cursor and the other ones as virtual lines below it.
+    fn draw(&self, lua: &Lua, api: &Api) -> LuaResult<()> {
+        let suggestion =
+            self.suggestion.as_ref().expect("There's a suggestion");
+
+        let mut lines = suggestion.text.split('\n');
+        let first = lines.next().unwrap_or_default();
+
+        let opts = lua.create_table_with_capacity(0, 4)?;
+        opts.set("id", 1)?;
+        opts.set("virt_text", [[first, "CompleetGhostText"]])?;
+        opts.set("virt_text_pos", "overlay")?;
+
+        let virt_lines = lines
+            .map(|line| {
+                lua.create_sequence_from([[line, "CompleetGhostText"]])
+            })
+            .collect::<LuaResult<Vec<LuaTable>>>()?;
+        if !virt_lines.is_empty() {
+            opts.set("virt_lines", virt_lines)?;
+        }
+
+        api.buf_set_extmark(
+            0,
+            self.nsid,
+            suggestion.row,
+            suggestion.col,
+            opts,
+        )?;
+
+        Ok(())
+    }
+}
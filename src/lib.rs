@@ -6,12 +6,23 @@ use neovim::Neovim;
 
 mod api;
 mod autocmds;
+mod boundary;
+mod cmdline;
 mod commands;
 mod completion;
-mod hlgroups;
+mod disk_cache;
+mod events;
+mod health;
+mod inline;
+mod logging;
 mod mappings;
+mod phase;
+mod profiling;
+mod session;
 mod settings;
+mod snippets;
 mod state;
+mod terminal;
 mod ui;
 
 use state::State;
@@ -23,37 +34,42 @@ BUGs:
 
 TODOs: On Hold
 
-1. Show scroll indicator if number of completions is bigger than the completion
-   menu's max height. This needs floating windows to support scrollbars. See
-   `:h api-floatwin`.
-
-2. Add option `ui.details.add_menu_spacing` to add 1 column of horizontal
+1. Add option `ui.details.add_menu_spacing` to add 1 column of horizontal
    spacing between the completion menu and the details window.
-
-TODOs
-
-1. Right now everything is sync. This will be a problem when we start dealing
-   with possibly thousands of completion results from LSPs. Can we leverage
-   async on the Rust end w/ Tokyo? Also look into `:h vim.loop` and `:h
-   lua-loop-threading`.
 */
 
 #[mlua::lua_module]
 fn compleet(lua: &Lua) -> LuaResult<Table> {
-    // The plugin runs in the main thread, so panics will take down the whole
-    // Neovim process. We can't do a lot except relaying the panic infos.
+    // The panics of the autocmds and mappings are caught by
+    // `boundary::guard`, which stops the plugin in the current buffer, so
+    // the hook only logs where they happened. Any other panic would take
+    // down the whole Neovim process, and we can't do a lot except relaying
+    // the panic infos.
     panic::set_hook(Box::new(|infos| {
-        eprintln!(
-            "[nvim-compleet] {infos}. \
-             Please open a new issue at \
-             'https://github.com/noib3/nvim-compleet/issues'."
-        );
-        std::process::exit(1);
+        logging::error("panic", infos);
+        if !boundary::is_guarded() {
+            eprintln!(
+                "[nvim-compleet] {infos}. \
+                 Please open a new issue at \
+                 'https://github.com/noib3/nvim-compleet/issues'."
+            );
+            std::process::exit(1);
+        }
     }));
 
     let api = Neovim::new(lua)?.api;
     let state = Arc::new(Mutex::new(State::new(&api)?));
 
+    let _state = state.clone();
+    let confirm = lua.create_function(move |lua, index| {
+        api::confirm(lua, &mut _state.lock().unwrap(), index)
+    })?;
+
+    let _state = state.clone();
+    let check_health = lua.create_function(move |lua, ()| {
+        health::check(lua, &_state.lock().unwrap())
+    })?;
+
     let _state = state.clone();
     let has_completions = lua.create_function(move |lua, ()| {
         api::has_completions(lua, &mut _state.lock().unwrap())
@@ -61,7 +77,15 @@ fn compleet(lua: &Lua) -> LuaResult<Table> {
 
     let _state = state.clone();
     let is_completion_selected = lua.create_function(move |_, ()| {
-        Ok(_state.lock().unwrap().ui.completion_menu.is_item_selected())
+        let state = _state.lock().unwrap();
+        Ok(state.phase().selected().is_some()
+            || state.cmdline.is_item_selected()
+            || state.terminal.is_item_selected())
+    })?;
+
+    let _state = state.clone();
+    let is_details_visible = lua.create_function(move |_, ()| {
+        Ok(_state.lock().unwrap().ui.completion_details.is_visible())
     })?;
 
     let _state = state.clone();
@@ -69,20 +93,105 @@ fn compleet(lua: &Lua) -> LuaResult<Table> {
         Ok(_state.lock().unwrap().ui.completion_hint.is_visible())
     })?;
 
+    let _state = state.clone();
+    let is_inline_visible = lua.create_function(move |_, ()| {
+        Ok(_state.lock().unwrap().inline.is_visible())
+    })?;
+
     let _state = state.clone();
     let is_menu_visible = lua.create_function(move |_, ()| {
-        Ok(_state.lock().unwrap().ui.completion_menu.is_visible())
+        let state = _state.lock().unwrap();
+        Ok(state.phase().is_menu_visible()
+            || state.cmdline.is_menu_visible()
+            || state.terminal.is_menu_visible())
     })?;
 
+    let _state = state.clone();
+    let is_snippet_active = lua.create_function(move |_, ()| {
+        Ok(_state.lock().unwrap().snippet.is_active())
+    })?;
+
+    let _state = state.clone();
+    let lualine_component = lua.create_function(move |_, ()| {
+        // Statuslines can be redrawn while the state is locked, e.g. while
+        // the menu is being updated.
+        Ok(match _state.try_lock() {
+            Ok(state) => api::lualine_component(&state),
+            Err(_) => String::new(),
+        })
+    })?;
+
+    let _state = state.clone();
+    let register_external_source = lua.create_function(move |lua, spec| {
+        api::register_external_source(lua, &mut _state.lock().unwrap(), spec)
+    })?;
+
+    let _state = state.clone();
+    let register_inline_provider = lua.create_function(move |lua, spec| {
+        api::register_inline_provider(lua, &mut _state.lock().unwrap(), spec)
+    })?;
+
+    let _state = state.clone();
+    let register_source = lua.create_function(move |lua, spec| {
+        api::register_source(lua, &mut _state.lock().unwrap(), spec)
+    })?;
+
+    let _state = state.clone();
+    let select = lua.create_function(move |lua, index| {
+        api::select(lua, &mut _state.lock().unwrap(), index)
+    })?;
+
+    let set_multicursor_hook =
+        lua.create_function(api::set_multicursor_hook)?;
+
+    let _state = state.clone();
+    let status =
+        lua.create_function(move |lua, ()| match _state.try_lock() {
+            Ok(state) => api::status(lua, &state),
+            Err(_) => Ok(None),
+        })?;
+
+    let _state = state.clone();
+    let tab = lua.create_function(move |lua, ()| {
+        boundary::guard(lua, &_state, "tab", |state| {
+            mappings::tab(lua, state, 1)
+        })
+    })?;
+
+    let _state = state.clone();
+    let shift_tab = lua.create_function(move |lua, ()| {
+        boundary::guard(lua, &_state, "shift_tab", |state| {
+            mappings::tab(lua, state, -1)
+        })
+    })?;
+
+    let validate = lua
+        .create_function(|lua, preferences| api::validate(lua, preferences))?;
+
     let setup = lua.create_function(move |lua, preferences| {
         api::setup(lua, &state, preferences)
     })?;
 
     Ok(lua.create_table_from([
+        ("check_health", check_health),
+        ("confirm", confirm),
         ("has_completions", has_completions),
         ("is_completion_selected", is_completion_selected),
+        ("is_details_visible", is_details_visible),
         ("is_hint_visible", is_hint_visible),
+        ("is_inline_visible", is_inline_visible),
         ("is_menu_visible", is_menu_visible),
+        ("is_snippet_active", is_snippet_active),
+        ("lualine_component", lualine_component),
+        ("register_external_source", register_external_source),
+        ("register_inline_provider", register_inline_provider),
+        ("register_source", register_source),
+        ("select", select),
+        ("set_multicursor_hook", set_multicursor_hook),
         ("setup", setup),
+        ("shift_tab", shift_tab),
+        ("status", status),
+        ("tab", tab),
+        ("validate", validate),
     ])?)
 }
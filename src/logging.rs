@@ -0,0 +1,145 @@
+use std::fmt::Display;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use mlua::prelude::LuaResult;
+use neovim::Api;
+
+use crate::settings::logging::{Level, LoggingSettings};
+
+/// The logger is global so that the sources running on background threads
+/// can also log.
+static LOGGER: Mutex<Logger> = Mutex::new(Logger {
+    path: None,
+    level: Level::Off,
+});
+
+#[derive(Debug)]
+struct Logger {
+    /// The path of the log file, or `None` if `init` hasn't been called yet.
+    path: Option<PathBuf>,
+
+    level: Level,
+}
+
+/// Sets the level of the logger from the `logging` settings. Logs are written
+/// to `compleet.log` in Neovim's cache directory.
+pub fn init(api: &Api, settings: &LoggingSettings) -> LuaResult<()> {
+    let cache = api.call_function::<_, String>("stdpath", vec!["cache"])?;
+    let logger = &mut *LOGGER.lock().unwrap();
+    logger.path = Some(PathBuf::from(cache).join("compleet.log"));
+    logger.level = settings.level;
+    Ok(())
+}
+
+/// The path of the log file, or `None` if the logger hasn't been set up.
+pub fn path() -> Option<PathBuf> { LOGGER.lock().unwrap().path.clone() }
+
+/// Appends a message to the log file if the logger's level allows it.
+/// `target` is the part of the plugin the message comes from, e.g.
+/// `pipeline`. Errors writing to the file are ignored, since there's nowhere
+/// to report them.
+pub fn log(level: Level, target: &str, msg: impl Display) {
+    let logger = &*LOGGER.lock().unwrap();
+
+    let path = match &logger.path {
+        Some(path) if level != Level::Off && level <= logger.level => path,
+        _ => return,
+    };
+
+    let line = format_line(SystemTime::now(), level, target, &msg.to_string());
+
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+
+    if let Ok(mut file) =
+        OpenOptions::new().create(true).append(true).open(path)
+    {
+        let _ = writeln!(file, "{line}");
+    }
+}
+
+pub fn error(target: &str, msg: impl Display) {
+    log(Level::Error, target, msg)
+}
+
+pub fn warn(target: &str, msg: impl Display) { log(Level::Warn, target, msg) }
+
+pub fn info(target: &str, msg: impl Display) { log(Level::Info, target, msg) }
+
+pub fn debug(target: &str, msg: impl Display) {
+    log(Level::Debug, target, msg)
+}
+
+fn format_line(
+    time: SystemTime,
+    level: Level,
+    target: &str,
+    msg: &str,
+) -> String {
+    format!(
+        "{} {:<5} {target}: {msg}",
+        format_timestamp(time),
+        level.name()
+    )
+}
+
+/// Formats a time as `YYYY-MM-DD hh:mm:ss.mmm` in UTC.
+fn format_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let secs_of_day = secs % 86400;
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}.{:03}",
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis(),
+    )
+}
+
+/// Converts a number of days since 1970-01-01 to a `(year, month, day)`
+/// date, using Howard Hinnant's `civil_from_days` algorithm.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{civil_from_days, format_line, format_timestamp};
+    use crate::settings::logging::Level;
+
+    #[test]
+    fn dates() {
+        assert_eq!((1970, 1, 1), civil_from_days(0));
+        assert_eq!((2000, 2, 29), civil_from_days(11016));
+        assert_eq!((2024, 12, 31), civil_from_days(20088));
+    }
+
+    #[test]
+    fn lines() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        assert_eq!("2023-11-14 22:13:20.123", format_timestamp(time));
+        assert_eq!(
+            "2023-11-14 22:13:20.123 WARN  pipeline: foo",
+            format_line(time, Level::Warn, "pipeline", "foo")
+        );
+    }
+}
@@ -0,0 +1,25 @@
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Neovim;
+
+use crate::phase::Action;
+use crate::state::State;
+
+/// Executed on `<Plug>(compleet-abort)`.
+pub fn abort(lua: &Lua, state: &mut State) -> LuaResult<()> {
+    if !state.phase().allows(Action::Abort) {
+        return Ok(());
+    }
+
+    let api = Neovim::new(lua)?.api;
+
+    // Put back the text that was typed before a completion was previewed.
+    state.preview.restore(lua)?;
+
+    state.close_ui(lua, &api)?;
+
+    // The completions are cleared so that the UI isn't shown again until
+    // something new is typed.
+    state.buffers.current_mut().clear_completions();
+
+    Ok(())
+}
@@ -1,26 +1,81 @@
-use mlua::prelude::{Lua, LuaResult};
-use neovim::Neovim;
+use std::cmp;
+use std::collections::HashMap;
+use std::slice;
 
-use crate::state::State;
+use mlua::prelude::{Lua, LuaFunction, LuaRegistryKey, LuaResult, LuaValue};
+use neovim::{Api, Neovim};
 
-/// Executed on both `<Plug>(compleet-insert-hinted-completion)` and
-/// `<Plug>(compleet-insert-selected-completion)`.
+use crate::api;
+use crate::completion::{self, CompletionItem, MatchedRange};
+use crate::events::{self, Event};
+use crate::phase::Transition;
+use crate::settings::completion::ConfirmBehavior;
+use crate::snippets::Snippet;
+use crate::state::{Sources, State};
+
+/// The internal mapping fed after the keys of a typed completion, whose
+/// callback runs the hooks that need the completion to be in the buffer.
+pub const AFTER_TYPED_PLUG: &str = "<Plug>(compleet-after-typed)";
+
+/// The key of the Lua registry where the hooks of a typed completion are
+/// stored until `AFTER_TYPED_PLUG` is executed.
+const AFTER_TYPED: &str = "compleet_after_typed";
+
+/// Executed on `<Plug>(compleet-insert-hinted-completion)`,
+/// `<Plug>(compleet-insert-selected-completion)` and
+/// `<Plug>(compleet-confirm-completion)`. With the `Replace` behavior the
+/// keyword after the cursor is replaced by the completion. With the
+/// `completion.dot_repeat` option set the completion is typed instead of
+/// being set in the buffer, so that it's repeated by `.`. Completions that
+/// aren't snippets are also inserted at the cursors returned by the hook set
+/// with `set_multicursor_hook`. Completions whose text spans multiple lines
+/// replace the whole match and leave the cursor at the end of their last
+/// line.
 pub fn insert_completion(
     lua: &Lua,
     state: &mut State,
     index: usize,
+    behavior: ConfirmBehavior,
 ) -> LuaResult<()> {
-    let completion = &state.completions[index];
-    let cursor = &state.cursor;
+    // Another completion may already be being inserted.
+    if !state.transition(Transition::Insert) {
+        return Ok(());
+    }
+
+    let nvim = Neovim::new(lua)?;
+
+    // The text matched by the completions may have been edited since they
+    // were computed, in which case their ranges don't point to it anymore
+    // and the menu is closed instead. The previewed text is part of the
+    // buffer but not of the matched range, so it's not checked then.
+    if !state.preview.is_active() {
+        let row = nvim.api.win_get_cursor(0)?.0 - 1;
+        if !state
+            .buffers
+            .current()
+            .is_matched_range_intact(row, &nvim.api.get_current_line()?)
+        {
+            state.buffers.current_mut().clear_completions();
+            state.pipeline.stop_loading();
+            return state.close_ui(lua, &nvim.api);
+        }
+    }
 
-    let text_to_insert = get_text_to_insert(
-        completion.matched_bytes as usize,
-        &cursor.line[cursor.bytes as usize..],
-        &completion.text,
-    );
+    let completion = &state.buffers.current().completions[index];
+    let cursor = &state.buffers.current().cursor;
+    let range = MatchedRange::new(completion, cursor);
 
-    let end_column = (cursor.bytes - completion.matched_bytes) as usize
-        + completion.text.len();
+    let filetype = nvim.api.buf_get_option::<String>(0, "filetype")?;
+    state
+        .history
+        .record(&filetype, &range.text, &completion.text);
+
+    // The number of bytes after the cursor to be replaced by the completion.
+    let bytes_post = match behavior {
+        ConfirmBehavior::Insert => 0,
+        ConfirmBehavior::Replace => cursor.keyword_post().len() as u32,
+    }
+    .max(completion.matched_bytes_post);
 
     // NOTE: Inserting the completion in the buffer right at this point
     // triggers `completion::bytes_changed`, which causes the Mutex wrapping
@@ -30,22 +85,209 @@ pub fn insert_completion(
     // pass it to `nvim.schedule` to be executed at a later time in Neovim's
     // event loop.
 
+    let is_multiline = completion.text.contains('\n');
+
+    let add_parens = match state.settings.completion.auto_parens
+        && wants_parens(completion)
+        && !is_multiline
+    {
+        true => Some(lua.create_function(after_confirm)?),
+        false => None,
+    };
+
     let insert_completion = lua.create_function(
-        move |lua, (row, col, text): (u32, u32, String)| {
+        move |lua,
+              (row, start, end, text, cursor_col): (
+            u32,
+            u32,
+            u32,
+            String,
+            u32,
+        )| {
             let api = Neovim::new(lua)?.api;
-            api.buf_set_text(0, row, col, row, col, &[text])?;
-            api.win_set_cursor(0, row + 1, end_column as u32)?;
+            let lines = split_lines(&text);
+            api.buf_set_text(0, row, start, row, end, &lines)?;
+            let (row, col) = end_position(row, cursor_col, &lines);
+            api.win_set_cursor(0, row + 1, col)?;
             Ok(())
         },
     )?;
 
-    let nvim = Neovim::new(lua)?;
+    // If the completion is being previewed its text is already in the buffer,
+    // and snippets are expanded in place of the previewed text. The preview
+    // also replaced the `matched_bytes_post`, so only the rest of the bytes
+    // after the cursor are left.
+    let previewed = state.preview.range();
+    let bytes_post_left =
+        bytes_post.saturating_sub(state.preview.replaced_post());
+    state.preview.commit();
 
-    nvim.schedule(insert_completion.bind((
-        cursor.row,
-        cursor.bytes,
-        text_to_insert.to_string(),
-    ))?)?;
+    // Completions that are typed don't start a new undo block, so that `.`
+    // repeats the whole insertion.
+    let is_typed = state.settings.completion.dot_repeat
+        && completion.snippet.is_none()
+        && !is_multiline;
+
+    // The previewed text already started a new undo block.
+    if state.settings.completion.break_undo && previewed.is_none() && !is_typed
+    {
+        let break_undo = lua.create_function(|lua, ()| {
+            completion::break_undo_sequence(&Neovim::new(lua)?.api)
+        })?;
+        nvim.schedule(break_undo)?;
+    }
+
+    if let (Some((row, start, end)), None) = (previewed, &completion.snippet) {
+        // Only the first line of a multi-line completion is previewed.
+        if is_multiline {
+            nvim.schedule(insert_completion.bind((
+                row,
+                start,
+                end + bytes_post_left,
+                completion.text.clone(),
+                end,
+            ))?)?;
+        } else if bytes_post_left > 0 {
+            nvim.schedule(insert_completion.bind((
+                row,
+                end,
+                end + bytes_post_left,
+                "",
+                end,
+            ))?)?;
+        }
+        if let Some(add_parens) = add_parens {
+            nvim.schedule(add_parens.bind((row, end))?)?;
+        }
+        nvim.schedule(after_insert(
+            lua,
+            &nvim,
+            &state.sources,
+            state.on_inserted.as_ref(),
+            completion,
+            (!is_multiline).then_some(bytes_post),
+        )?)?;
+        return state.ui.cleanup(lua, &nvim.api);
+    }
+
+    // Snippets replace the matched bytes before the cursor with the text of
+    // the expanded snippet. The same deadlock described above applies here.
+    if let Some(body) = &completion.snippet {
+        let indent = cursor
+            .line
+            .find(|c: char| !c.is_whitespace())
+            .map_or(&*cursor.line, |i| &cursor.line[..i]);
+
+        let (start, end) = previewed
+            .map_or((range.start, range.end), |(_, start, end)| (start, end));
+
+        let expand_snippet = state.snippet.expand(
+            lua,
+            &nvim.api,
+            &Snippet::parse(body),
+            cursor.row,
+            (start, end + bytes_post_left),
+            indent,
+        )?;
+
+        nvim.schedule(expand_snippet)?;
+        nvim.schedule(after_insert(
+            lua,
+            &nvim,
+            &state.sources,
+            state.on_inserted.as_ref(),
+            completion,
+            None,
+        )?)?;
+
+        state.ui.completion_menu.selected_index = None;
+
+        return Ok(());
+    }
+
+    // If the completion starts with the text before the cursor we only need
+    // to insert what's missing, otherwise (e.g. for fuzzy matches) the matched
+    // bytes are replaced by the whole completion. When replacing the keyword
+    // (or the `matched_bytes_post`) after the cursor its text doesn't matter.
+    let (start_column, text_to_insert) =
+        match (behavior, completion.text_after_match(cursor)) {
+            (ConfirmBehavior::Insert, Some(_))
+                if !is_multiline && completion.matched_bytes_post == 0 =>
+            {
+                (
+                    cursor.bytes,
+                    get_text_to_insert(
+                        completion.matched_bytes as usize,
+                        &cursor.line[cursor.bytes as usize..],
+                        &completion.text,
+                    ),
+                )
+            },
+            _ => (range.start, completion.text.as_str()),
+        };
+
+    let end_column = range.start + completion.text.len() as u32;
+
+    if is_typed {
+        // The bytes after the cursor that are already part of the
+        // completion, which the cursor is moved over.
+        let skipped = end_column - start_column - text_to_insert.len() as u32;
+        let post = (cursor.bytes + bytes_post) as usize;
+        let line = &cursor.line;
+
+        let keys = typed_keys(
+            line[start_column as usize..cursor.bytes as usize]
+                .chars()
+                .count(),
+            line[cursor.bytes as usize..post].chars().count(),
+            text_to_insert,
+            line[post..post + skipped as usize].chars().count(),
+            add_parens.is_some()
+                && !line[post + skipped as usize..].starts_with('('),
+        );
+
+        // The keys are typed once the state has been unlocked, for the same
+        // reason explained above. Scheduled callbacks can run before the
+        // keys are consumed, so the hooks are run by a mapping fed after
+        // them instead.
+        let after_insert = after_insert(
+            lua,
+            &nvim,
+            &state.sources,
+            state.on_inserted.as_ref(),
+            completion,
+            (!is_multiline).then_some(bytes_post),
+        )?;
+        lua.set_named_registry_value(AFTER_TYPED, after_insert)?;
+
+        let keys = nvim.api.replace_termcodes(&keys, true, true, true)?;
+        nvim.api.feedkeys(&keys, "n", false)?;
+        let plug =
+            nvim.api
+                .replace_termcodes(AFTER_TYPED_PLUG, true, true, true)?;
+        nvim.api.feedkeys(&plug, "m", false)?;
+    } else {
+        nvim.schedule(insert_completion.bind((
+            cursor.row,
+            start_column,
+            cursor.bytes + bytes_post,
+            text_to_insert.to_string(),
+            end_column,
+        ))?)?;
+
+        if let Some(add_parens) = add_parens {
+            nvim.schedule(add_parens.bind((cursor.row, end_column))?)?;
+        }
+
+        nvim.schedule(after_insert(
+            lua,
+            &nvim,
+            &state.sources,
+            state.on_inserted.as_ref(),
+            completion,
+            (!is_multiline).then_some(bytes_post),
+        )?)?;
+    }
 
     // Reset the selected completion.
     state.ui.completion_menu.selected_index = None;
@@ -53,6 +295,259 @@ pub fn insert_completion(
     Ok(())
 }
 
+/// Splits the text of a completion into the lines passed to
+/// `nvim_buf_set_text`, dropping the carriage returns of CRLF line endings.
+fn split_lines(text: &str) -> Vec<&str> {
+    text.split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .collect()
+}
+
+/// Returns the 0-indexed `(row, col)` position of the cursor after the
+/// `lines` have been inserted at the `row`-th line. Single lines leave the
+/// cursor at `cursor_col`, while multiple lines leave it at the end of the
+/// last one.
+fn end_position(row: u32, cursor_col: u32, lines: &[&str]) -> (u32, u32) {
+    match lines {
+        [_] | [] => (row, cursor_col),
+        [.., last] => (row + lines.len() as u32 - 1, last.len() as u32),
+    }
+}
+
+/// Returns a function running the hooks of a completion once it's been
+/// inserted at the main cursor: its insertion at the other cursors of a
+/// multicursor plugin if `extra_bytes_post` is set, the `confirm` method of
+/// its source and the `ItemConfirmed` event.
+fn after_insert<'lua>(
+    lua: &'lua Lua,
+    nvim: &Neovim,
+    sources: &HashMap<u32, Sources>,
+    on_inserted: Option<&LuaRegistryKey>,
+    completion: &CompletionItem,
+    extra_bytes_post: Option<u32>,
+) -> LuaResult<LuaFunction<'lua>> {
+    let extra_insertions = match extra_bytes_post {
+        Some(bytes_post) => {
+            extra_insertions(lua, nvim, completion, bytes_post)?
+        },
+        None => None,
+    };
+    let confirm = confirm(lua, nvim, sources, completion)?;
+    let on_inserted = on_inserted
+        .map(|key| lua.registry_value::<LuaFunction>(key))
+        .transpose()?;
+
+    // The hooks are stored in the registry, since Lua functions can't be
+    // moved into the closure.
+    let hooks = [extra_insertions, confirm, on_inserted]
+        .into_iter()
+        .flatten()
+        .map(|hook| lua.create_registry_value(hook))
+        .collect::<LuaResult<Vec<_>>>()?;
+
+    let completion = completion.clone();
+
+    lua.create_function(move |lua, ()| {
+        for hook in &hooks {
+            lua.registry_value::<LuaFunction>(hook)?.call::<_, ()>(())?;
+        }
+        // The event is fired after the insertion, so the completion is
+        // already in the buffer when the autocmds run.
+        events::fire(lua, Event::ItemConfirmed(&completion))
+    })
+}
+
+/// Executed on `AFTER_TYPED_PLUG`. Runs the hooks of the last typed
+/// completion, once its keys have been consumed.
+pub fn after_typed(lua: &Lua) -> LuaResult<()> {
+    let hooks = lua.named_registry_value::<_, LuaValue>(AFTER_TYPED)?;
+    lua.set_named_registry_value(AFTER_TYPED, LuaValue::Nil)?;
+    match hooks {
+        LuaValue::Function(hooks) => hooks.call(()),
+        _ => Ok(()),
+    }
+}
+
+/// Returns a function inserting the completion at the other cursors of a
+/// multicursor plugin, to be run after it's been inserted at the main one,
+/// or `None` if there are no other cursors. At every cursor the
+/// `matched_bytes` before it and the `bytes_post` after it are replaced by
+/// the text of the completion.
+fn extra_insertions<'lua>(
+    lua: &'lua Lua,
+    nvim: &Neovim,
+    completion: &CompletionItem,
+    bytes_post: u32,
+) -> LuaResult<Option<LuaFunction<'lua>>> {
+    let cursors = api::extra_cursors(lua)?;
+    if cursors.is_empty() {
+        return Ok(None);
+    }
+
+    // The positions returned by the hook refer to the buffer as it is now,
+    // before the completion is inserted at the main cursor.
+    let (row, col) = nvim.api.win_get_cursor(0)?;
+    let main = (row - 1, col);
+    let line_len = current_line_len(&nvim.api, main.0)?;
+
+    let matched_bytes = completion.matched_bytes;
+    let text = completion.text.clone();
+
+    let insert = lua.create_function(move |lua, ()| {
+        let api = Neovim::new(lua)?.api;
+
+        // The cursors after the main one on the same line have been shifted
+        // by the insertion.
+        let shift = current_line_len(&api, main.0)? as i64 - line_len as i64;
+
+        let (row, mut col) = api.win_get_cursor(0)?;
+
+        for (r, c) in extra_positions(main, shift, &cursors) {
+            let len = current_line_len(&api, r)?;
+            if c > len {
+                continue;
+            }
+            let start = c.saturating_sub(matched_bytes);
+            let end = cmp::min(c + bytes_post, len);
+            api.buf_set_text(0, r, start, r, end, slice::from_ref(&text))?;
+
+            if r == main.0 && c <= main.1 {
+                col = (col as i64 + text.len() as i64 - (end - start) as i64)
+                    as u32;
+            }
+        }
+
+        api.win_set_cursor(0, row, col)
+    })?;
+
+    Ok(Some(insert))
+}
+
+fn current_line_len(api: &Api, row: u32) -> LuaResult<u32> {
+    Ok(api
+        .buf_get_lines(0, row, row as i32 + 1, false)?
+        .first()
+        .map_or(0, |line| line.len() as u32))
+}
+
+/// Returns the positions of the other cursors where the completion should be
+/// inserted, from the last one to the first one so that inserting at one
+/// doesn't move the following ones. The cursors on the `main` line after the
+/// main cursor are moved by `shift` bytes, which is how much the line grew
+/// when the completion was inserted.
+fn extra_positions(
+    main: (u32, u32),
+    shift: i64,
+    cursors: &[(u32, u32)],
+) -> Vec<(u32, u32)> {
+    let mut positions = cursors
+        .iter()
+        .filter(|&&cursor| cursor != main)
+        .map(|&(row, col)| match row == main.0 && col > main.1 {
+            true => (row, (col as i64 + shift).max(0) as u32),
+            false => (row, col),
+        })
+        .collect::<Vec<(u32, u32)>>();
+
+    positions.sort_unstable_by(|a, b| b.cmp(a));
+    positions.dedup();
+    positions
+}
+
+/// Schedules a call to the `confirm` method of the source of the completion,
+/// which runs after the completion has been inserted.
+pub fn schedule_confirm(
+    lua: &Lua,
+    nvim: &Neovim,
+    sources: &HashMap<u32, Sources>,
+    completion: &CompletionItem,
+) -> LuaResult<()> {
+    match confirm(lua, nvim, sources, completion)? {
+        Some(confirm) => nvim.schedule(confirm),
+        None => Ok(()),
+    }
+}
+
+/// Returns a function calling the `confirm` method of the source of the
+/// completion, or `None` if the source isn't attached to the buffer anymore.
+fn confirm<'lua>(
+    lua: &'lua Lua,
+    nvim: &Neovim,
+    sources: &HashMap<u32, Sources>,
+    completion: &CompletionItem,
+) -> LuaResult<Option<LuaFunction<'lua>>> {
+    let source =
+        match sources
+            .get(&nvim.api.get_current_buf()?)
+            .and_then(|sources| {
+                sources.iter().find(|s| s.name() == completion.source)
+            }) {
+            Some(source) => source.clone(),
+            None => return Ok(None),
+        };
+
+    let completion = completion.clone();
+    lua.create_function(move |lua, ()| {
+        source.confirm(lua, &Neovim::new(lua)?.api, &completion)
+    })
+    .map(Some)
+}
+
+/// Executed after a function-like completion is confirmed with the
+/// `completion.auto_parens` option set. Inserts `()` at the `(row, col)`
+/// position where the completion ends and moves the cursor between them,
+/// unless the completion is already followed by an opening parenthesis.
+fn after_confirm(lua: &Lua, (row, col): (u32, u32)) -> LuaResult<()> {
+    let api = Neovim::new(lua)?.api;
+
+    let line = api.buf_get_lines(0, row, row as i32 + 1, false)?;
+    let is_followed_by_paren = line
+        .first()
+        .and_then(|line| line.get(col as usize..))
+        .is_some_and(|rest| rest.starts_with('('));
+
+    if !is_followed_by_paren {
+        api.buf_set_text(0, row, col, row, col, &["()"])?;
+    }
+
+    api.win_set_cursor(0, row + 1, col + 1)
+}
+
+/// Returns the keys typing a completion in a way that can be repeated with
+/// `.`: `backspaces` characters before the cursor and `deletes` after it are
+/// deleted, then the `text` is typed and the cursor is moved over the
+/// `skipped` characters already in the buffer. `<C-g>U` keeps the cursor
+/// movements from starting a new change. The undo block isn't broken with
+/// `<C-g>u`, since `.` would then only repeat the completion.
+fn typed_keys(
+    backspaces: usize,
+    deletes: usize,
+    text: &str,
+    skipped: usize,
+    add_parens: bool,
+) -> String {
+    let mut keys = "<BS>".repeat(backspaces);
+    keys.push_str(&"<Del>".repeat(deletes));
+    keys.push_str(&text.replace('<', "<lt>"));
+    keys.push_str(&"<C-g>U<Right>".repeat(skipped));
+    if add_parens {
+        keys.push_str("()<C-g>U<Left>");
+    }
+    keys
+}
+
+/// Whether a pair of parentheses should be added after confirming a
+/// completion with the `completion.auto_parens` option set. Snippets usually
+/// come with their own parentheses and tabstops for the arguments.
+fn wants_parens(completion: &CompletionItem) -> bool {
+    completion.snippet.is_none()
+        && !completion.text.ends_with(')')
+        && matches!(
+            completion.kind,
+            Some("Function" | "Method" | "Constructor")
+        )
+}
+
 /// Returns the text that should be inserted into the buffer, taking into
 /// account what comes after the cursor. For example, if we have `f|o` and
 /// we're completing `foo` we only need to insert the first `o`, since the
@@ -101,7 +596,63 @@ fn get_text_to_insert<'a>(
 
 #[cfg(test)]
 mod tests {
-    use super::get_text_to_insert;
+    use super::{
+        end_position,
+        extra_positions,
+        get_text_to_insert,
+        split_lines,
+        typed_keys,
+        wants_parens,
+    };
+    use crate::completion::CompletionItem;
+
+    #[test]
+    fn parens_after_functions() {
+        let item = |text: &str, kind, snippet: Option<&str>| CompletionItem {
+            details: None,
+            format: format!(" {text}"),
+            hl_ranges: Vec::new(),
+            kind,
+            matched_bytes: 0,
+            preselect: false,
+            score: 0,
+            snippet: snippet.map(String::from),
+            source: "Lsp",
+            text: text.into(),
+            ..Default::default()
+        };
+
+        assert!(wants_parens(&item("foo", Some("Function"), None)));
+        assert!(wants_parens(&item("new", Some("Constructor"), None)));
+        assert!(!wants_parens(&item("foo", Some("Variable"), None)));
+        assert!(!wants_parens(&item("foo", None, None)));
+        assert!(!wants_parens(&item("foo()", Some("Method"), None)));
+        assert!(!wants_parens(&item(
+            "foo(bar)",
+            Some("Function"),
+            Some("foo(${1:bar})")
+        )));
+    }
+
+    #[test]
+    fn typed_completions() {
+        assert_eq!("<BS><BS>foo<lt>T>", typed_keys(2, 0, "foo<T>", 0, false));
+        assert_eq!(
+            "<Del>oob<C-g>U<Right><C-g>U<Right>()<C-g>U<Left>",
+            typed_keys(0, 1, "oob", 2, true)
+        );
+    }
+
+    #[test]
+    fn extra_cursors() {
+        // The main cursor is on the third row, and the line grew by 3 bytes.
+        let cursors = [(0, 4), (2, 10), (2, 1), (2, 6), (5, 0), (0, 4)];
+
+        assert_eq!(
+            vec![(5, 0), (2, 13), (2, 1), (0, 4)],
+            extra_positions((2, 6), 3, &cursors)
+        );
+    }
 
     // NOTE: the `|` in the following comments indicates the cursor position.
 
@@ -204,4 +755,12 @@ mod tests {
     fn foo10() {
         assert_eq!("ooba", get_text_to_insert("f".len(), "rbaz", "foobar"));
     }
+
+    #[test]
+    fn multiline_text() {
+        let lines = split_lines("if x {\r\n\ty\r\n}");
+        assert_eq!(vec!["if x {", "\ty", "}"], lines);
+        assert_eq!((5, 1), end_position(3, 8, &lines));
+        assert_eq!((3, 8), end_position(3, 8, &split_lines("foo")));
+    }
 }
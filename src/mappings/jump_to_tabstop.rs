@@ -0,0 +1,23 @@
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Neovim;
+
+use crate::state::State;
+
+/// Executed on `<Plug>(compleet-next-tabstop)` and
+/// `<Plug>(compleet-prev-tabstop)`.
+pub fn jump_to_tabstop(
+    lua: &Lua,
+    state: &mut State,
+    step: i8, // either 1 or -1
+) -> LuaResult<()> {
+    if !state.snippet.is_active() {
+        return Ok(());
+    }
+
+    let api = Neovim::new(lua)?.api;
+
+    // Any completion visible at the old tabstop is no longer relevant.
+    state.close_ui(lua, &api)?;
+
+    state.snippet.jump(lua, &api, step)
+}
@@ -1,9 +1,23 @@
+mod abort;
 mod insert_completion;
+mod jump_to_tabstop;
+mod mouse;
+mod scroll_details;
 mod select_completion;
+mod select_page;
 mod setup;
 mod show_completions;
+mod tab;
 
-use insert_completion::insert_completion;
+use abort::abort;
+use insert_completion::{after_typed, AFTER_TYPED_PLUG};
+pub use insert_completion::{insert_completion, schedule_confirm};
+use jump_to_tabstop::jump_to_tabstop;
+pub use mouse::setup as setup_mouse;
+use scroll_details::scroll_details;
 use select_completion::select_completion;
+pub use select_completion::select_index;
+use select_page::{select_edge, select_page};
 pub use setup::setup;
 use show_completions::show_completions;
+pub use tab::tab;
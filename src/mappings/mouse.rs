@@ -0,0 +1,92 @@
+use std::sync::{Arc, Mutex};
+
+use mlua::prelude::{Lua, LuaResult};
+use neovim::{Api, Neovim};
+
+use crate::boundary;
+use crate::state::State;
+
+/// The mouse keys handled over the completion menu.
+#[derive(Debug, Clone, Copy)]
+enum MouseKey {
+    Move,
+    Click,
+}
+
+impl MouseKey {
+    fn lhs(&self) -> &'static str {
+        match self {
+            MouseKey::Move => "<MouseMove>",
+            MouseKey::Click => "<LeftMouse>",
+        }
+    }
+}
+
+const MOUSE_KEYS: [MouseKey; 2] = [MouseKey::Move, MouseKey::Click];
+
+/// Maps the mouse keys in insert mode if `enable` is set, or unmaps them if
+/// it isn't. Called by `setup` every time the `ui.menu.mouse` option changes.
+pub fn setup(
+    lua: &Lua,
+    api: &Api,
+    state: &Arc<Mutex<State>>,
+    enable: bool,
+) -> LuaResult<()> {
+    if !enable {
+        for key in MOUSE_KEYS {
+            api.del_keymap("i", key.lhs())?;
+        }
+        return Ok(());
+    }
+
+    // The `mousemoveevent` option needed to get `<MouseMove>` is set by the
+    // menu while it's open.
+    for key in MOUSE_KEYS {
+        let _state = state.clone();
+        let callback = lua.create_function(move |lua, ()| {
+            boundary::guard(lua, &_state, "on_mouse", |state| {
+                on_mouse(lua, state, key)
+            })
+        })?;
+
+        let opts = lua.create_table_from([("silent", true)])?;
+        opts.set("callback", callback)?;
+        api.set_keymap("i", key.lhs(), "", opts)?;
+    }
+
+    Ok(())
+}
+
+/// Executed on `<MouseMove>` and `<LeftMouse>` in insert mode with the
+/// `ui.menu.mouse` option set. Hovering over a completion selects it and
+/// clicking on it confirms it. Clicking anywhere else does what it would do
+/// without the mapping.
+fn on_mouse(lua: &Lua, state: &mut State, key: MouseKey) -> LuaResult<()> {
+    let api = Neovim::new(lua)?.api;
+
+    let hovered = match state.phase().is_menu_visible() {
+        true => state.ui.completion_menu.hovered_index(lua, &api)?,
+        false => None,
+    };
+
+    match (key, hovered) {
+        (MouseKey::Move, Some(index))
+            if state.phase().selected() != Some(index) =>
+        {
+            super::select_index(lua, state, Some(index))
+        },
+
+        (MouseKey::Move, _) => Ok(()),
+
+        (MouseKey::Click, Some(index)) => {
+            let behavior = state.settings.completion.behavior;
+            super::insert_completion(lua, state, index, behavior)
+        },
+
+        // Typed with `noremap` so that it doesn't trigger this mapping again.
+        (MouseKey::Click, None) => {
+            let keys = api.replace_termcodes(key.lhs(), true, true, true)?;
+            api.feedkeys(&keys, "n", false)
+        },
+    }
+}
@@ -0,0 +1,18 @@
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Neovim;
+
+use crate::state::State;
+
+/// Executed on `<Plug>(compleet-scroll-details-down)` and
+/// `<Plug>(compleet-scroll-details-up)`. It's a no-op if the details window
+/// isn't visible, in which case `compleet.is_details_visible` can be used to
+/// fall back to another key.
+pub fn scroll_details(
+    lua: &Lua,
+    state: &mut State,
+    step: i8, // either 1 or -1
+) -> LuaResult<()> {
+    let api = Neovim::new(lua)?.api;
+    state.ui.completion_details.scroll(lua, &api, step)?;
+    Ok(())
+}
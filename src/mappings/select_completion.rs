@@ -1,7 +1,10 @@
 use mlua::prelude::{Lua, LuaResult};
 use neovim::Neovim;
 
+use crate::completion;
+use crate::phase::{Action, Transition};
 use crate::state::State;
+use crate::ui::menu;
 
 /// Executed on `<Plug>(compleet-next-completion)` and
 /// `<Plug>(compleet-prev-completion)`.
@@ -10,40 +13,68 @@ pub fn select_completion(
     state: &mut State,
     step: i8, // either 1 or -1
 ) -> LuaResult<()> {
-    if !state.ui.completion_menu.is_visible() {
+    if !state.phase().allows(Action::Select) {
         return Ok(());
     }
 
+    let menu = &state.ui.completion_menu;
+
+    let cycle = state.settings.ui.menu.cycle;
+    let new_index = menu.next_index(
+        state.buffers.current().completions.len(),
+        step,
+        cycle,
+    );
+
+    // Nothing to do if the selection is clamped at either end of the menu.
+    if new_index.is_some() && new_index == state.phase().selected() {
+        return Ok(());
+    }
+
+    select_index(lua, state, new_index)
+}
+
+/// Selects the completion at `new_index` in the visible menu, or goes back to
+/// no selection if it's `None`, updating the preview, the details and the
+/// hint.
+pub fn select_index(
+    lua: &Lua,
+    state: &mut State,
+    new_index: Option<usize>,
+) -> LuaResult<()> {
     let menu = &mut state.ui.completion_menu;
-    let completions = &state.completions;
-
-    let last_index = completions.len() - 1;
-    let new_index = match step {
-        // Selecting the next completion
-        1 => match menu.selected_index {
-            Some(index) if index == last_index => None,
-            Some(index) => Some(index + 1),
-            None => Some(0),
-        },
-
-        // Selecting the previous completion
-        -1 => match menu.selected_index {
-            Some(index) if index == 0 => None,
-            Some(index) => Some(index - 1),
-            None => Some(last_index),
-        },
-
-        _ => unreachable!(),
-    };
+    let completions = &state.buffers.current().completions;
 
     let api = Neovim::new(lua)?.api;
 
     let hint = &mut state.ui.completion_hint;
     let details = &mut state.ui.completion_details;
-    let cursor = &state.cursor;
+    let cursor = &state.buffers.current().cursor;
 
     // Select the new completion.
-    menu.select(&api, new_index)?;
+    menu.select(lua, &api, new_index)?;
+
+    let insert_on_select = state.settings.completion.insert_on_select;
+    let break_undo = state.settings.completion.break_undo;
+
+    // Preview the selected completion, or put back the original text if
+    // going back to no selection.
+    if insert_on_select {
+        match new_index {
+            Some(index) => state.preview.show(
+                lua,
+                &completions[index],
+                cursor,
+                break_undo,
+            )?,
+            None => state.preview.restore(lua)?,
+        }
+    }
+
+    // Selecting a completion can scroll the menu.
+    if state.settings.ui.menu.scrollbar {
+        menu.update_scrollbar(lua, &api, completions.len())?;
+    }
 
     // Update the completion details.
     let menu_winid = menu
@@ -53,13 +84,14 @@ pub fn select_completion(
     let menu_width =
         menu.width.expect("The menu is visible so it has a width");
 
-    let lines = new_index.and_then(|i| completions[i].details.as_ref());
+    let lines = new_index
+        .and_then(|i| menu::details(&completions[i], &state.settings.ui.menu));
 
     details.update(
         lua,
         &api,
-        lines,
-        &state.settings.ui.details.border,
+        lines.as_ref(),
+        &state.settings.ui.details,
         menu_width,
         menu_winid,
         &state.settings.ui.menu.border,
@@ -67,15 +99,16 @@ pub fn select_completion(
     )?;
 
     // Update the completion hint.
-    if state.settings.ui.hint.enable && cursor.is_at_eol() {
-        if let Some(index) = new_index {
-            let completion = &completions[index];
-            let text = &completion.text[(completion.matched_bytes as usize)..];
-            hint.set(lua, &api, text, cursor, index)?;
-        } else {
-            hint.erase(&api)?;
-        }
+    // Like in `Ui::update`, going back to no selection hints the first
+    // completion. The previewed text would overlap with the hint, so it's
+    // erased while previewing.
+    if insert_on_select && hint.is_visible() {
+        hint.erase(&api)?;
+    } else if state.settings.ui.hint.enable && !insert_on_select {
+        hint.update(lua, &api, completions, new_index.unwrap_or(0), cursor)?;
     }
 
-    Ok(())
+    state.transition(Transition::Select(new_index));
+
+    completion::resolve_selected(lua, &api, state)
 }
@@ -0,0 +1,50 @@
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Neovim;
+
+use super::select_index;
+use crate::phase::Action;
+use crate::state::State;
+
+/// Executed on `<Plug>(compleet-page-down)` and `<Plug>(compleet-page-up)`,
+/// moving the selection by the number of completions visible in the menu.
+pub fn select_page(
+    lua: &Lua,
+    state: &mut State,
+    step: i8, // either 1 or -1
+) -> LuaResult<()> {
+    if !state.phase().allows(Action::Select) {
+        return Ok(());
+    }
+
+    let api = Neovim::new(lua)?.api;
+    let menu = &state.ui.completion_menu;
+    let new_index = menu.next_page_index(
+        &api,
+        state.buffers.current().completions.len(),
+        step,
+    )?;
+
+    if Some(new_index) == state.phase().selected() {
+        return Ok(());
+    }
+
+    select_index(lua, state, Some(new_index))
+}
+
+/// Executed on `<Plug>(compleet-first)` and `<Plug>(compleet-last)`.
+pub fn select_edge(lua: &Lua, state: &mut State, last: bool) -> LuaResult<()> {
+    if !state.phase().allows(Action::Select) {
+        return Ok(());
+    }
+
+    let new_index = match last {
+        true => state.buffers.current().completions.len() - 1,
+        false => 0,
+    };
+
+    if Some(new_index) == state.phase().selected() {
+        return Ok(());
+    }
+
+    select_index(lua, state, Some(new_index))
+}
@@ -1,47 +1,155 @@
 use std::sync::{Arc, Mutex};
 
-use mlua::prelude::{Lua, LuaResult};
+use mlua::prelude::{Lua, LuaRegistryKey, LuaResult};
 use neovim::Api;
 
+use crate::boundary;
+use crate::phase::{Phase, Transition};
+use crate::settings::completion::ConfirmBehavior;
 use crate::state::State;
+use crate::ui::menu::INDEX_LABELS;
 
+/// Defines the `<Plug>` mappings. Returns a registry key pointing to the
+/// function run once a confirmed completion has been inserted.
 pub fn setup(
     lua: &Lua,
     api: &Api,
     state: &Arc<Mutex<State>>,
-) -> LuaResult<()> {
+) -> LuaResult<LuaRegistryKey> {
     // Insert the currently hinted completion.
     let _state = state.clone();
     let insert_hinted_completion = lua.create_function(move |lua, ()| {
-        let _state = &mut _state.lock().unwrap();
-        if let Some(index) = _state.ui.completion_hint.hinted_index {
-            super::insert_completion(lua, _state, index)?;
-        }
-        Ok(())
+        boundary::guard(lua, &_state, "insert_hinted_completion", |state| {
+            if let Some(index) = state.ui.completion_hint.hinted_index {
+                super::insert_completion(
+                    lua,
+                    state,
+                    index,
+                    ConfirmBehavior::Insert,
+                )?;
+            }
+            Ok(())
+        })
     })?;
 
     // Insert the currently selected completion.
     let _state = state.clone();
     let insert_selected_completion = lua.create_function(move |lua, ()| {
-        let _state = &mut _state.lock().unwrap();
-        if let Some(index) = _state.ui.completion_menu.selected_index {
-            super::insert_completion(lua, _state, index)?;
-        }
-        Ok(())
+        boundary::guard(lua, &_state, "insert_selected_completion", |state| {
+            if let Phase::Selected(index) = state.phase() {
+                super::insert_completion(
+                    lua,
+                    state,
+                    index,
+                    ConfirmBehavior::Insert,
+                )?;
+            }
+            Ok(())
+        })
+    })?;
+
+    // Insert the currently selected completion, using the
+    // `completion.behavior` option to decide whether to replace the keyword
+    // after the cursor.
+    let _state = state.clone();
+    let confirm_completion = lua.create_function(move |lua, ()| {
+        boundary::guard(lua, &_state, "confirm_completion", |state| {
+            if let Phase::Selected(index) = state.phase() {
+                let behavior = state.settings.completion.behavior;
+                super::insert_completion(lua, state, index, behavior)?;
+            }
+            Ok(())
+        })
+    })?;
+
+    // Insert the `n`-th completion in the menu, using the
+    // `completion.behavior` option like `confirm_completion`.
+    let _state = state.clone();
+    let confirm_nth = lua.create_function(move |lua, n: usize| {
+        boundary::guard(lua, &_state, "confirm_nth", |state| {
+            if state.phase().is_menu_visible()
+                && n <= state.buffers.current().completions.len()
+            {
+                let behavior = state.settings.completion.behavior;
+                super::insert_completion(lua, state, n - 1, behavior)?;
+            }
+            Ok(())
+        })
+    })?;
+
+    // Close the UI, restoring the text of the previewed completion.
+    let _state = state.clone();
+    let abort = lua.create_function(move |lua, ()| {
+        boundary::guard(lua, &_state, "abort", |state| {
+            super::abort(lua, state)
+        })
     })?;
 
     // Select either the previous or next completion in the completion menu
     // based on the value of `step`.
     let _state = state.clone();
     let select_completion = lua.create_function(move |lua, step| {
-        super::select_completion(lua, &mut _state.lock().unwrap(), step)
+        boundary::guard(lua, &_state, "select_completion", |state| {
+            super::select_completion(lua, state, step)
+        })
+    })?;
+
+    // Move the selection by one page of completions, forward or backward
+    // based on the value of `step`.
+    let _state = state.clone();
+    let select_page = lua.create_function(move |lua, step| {
+        boundary::guard(lua, &_state, "select_page", |state| {
+            super::select_page(lua, state, step)
+        })
+    })?;
+
+    // Select either the first or the last completion in the menu.
+    let _state = state.clone();
+    let select_edge = lua.create_function(move |lua, last| {
+        boundary::guard(lua, &_state, "select_edge", |state| {
+            super::select_edge(lua, state, last)
+        })
+    })?;
+
+    // Scroll the details window either up or down based on the value of
+    // `step`.
+    let _state = state.clone();
+    let scroll_details = lua.create_function(move |lua, step| {
+        boundary::guard(lua, &_state, "scroll_details", |state| {
+            super::scroll_details(lua, state, step)
+        })
     })?;
 
     // Show the completion menu with all the currently available completion
     // candidates.
     let _state = state.clone();
     let show_completions = lua.create_function(move |lua, ()| {
-        super::show_completions(lua, &mut _state.lock().unwrap())
+        boundary::guard(lua, &_state, "show_completions", |state| {
+            super::show_completions(lua, state)
+        })
+    })?;
+
+    // Jump to either the previous or next tabstop of the active snippet based
+    // on the value of `step`.
+    let _state = state.clone();
+    let jump_to_tabstop = lua.create_function(move |lua, step| {
+        boundary::guard(lua, &_state, "jump_to_tabstop", |state| {
+            super::jump_to_tabstop(lua, state, step)
+        })
+    })?;
+
+    // Run the hooks of a typed completion once its keys have been consumed.
+    // It doesn't need the state, so it's not guarded.
+    let after_typed =
+        lua.create_function(|lua, ()| super::after_typed(lua))?;
+
+    // Run as the last hook of a confirmed completion, ending its insertion.
+    let _state = state.clone();
+    let on_inserted = lua.create_function(move |lua, ()| {
+        boundary::guard(lua, &_state, "on_inserted", |state| {
+            state.transition(Transition::Inserted);
+            Ok(())
+        })
     })?;
 
     let opts = lua.create_table_from([("silent", true)])?;
@@ -62,14 +170,77 @@ pub fn setup(
         opts.clone(),
     )?;
 
+    opts.set("callback", abort)?;
+    api.set_keymap("i", "<Plug>(compleet-abort)", "", opts.clone())?;
+
+    opts.set("callback", confirm_completion)?;
+    api.set_keymap(
+        "i",
+        "<Plug>(compleet-confirm-completion)",
+        "",
+        opts.clone(),
+    )?;
+
+    for n in 1..=INDEX_LABELS {
+        opts.set("callback", confirm_nth.bind(n)?)?;
+        api.set_keymap(
+            "i",
+            &format!("<Plug>(compleet-confirm-{n})"),
+            "",
+            opts.clone(),
+        )?;
+    }
+
     opts.set("callback", select_completion.bind(1)?)?;
     api.set_keymap("i", "<Plug>(compleet-next-completion)", "", opts.clone())?;
 
     opts.set("callback", select_completion.bind(-1)?)?;
     api.set_keymap("i", "<Plug>(compleet-prev-completion)", "", opts.clone())?;
 
+    opts.set("callback", select_page.bind(1)?)?;
+    api.set_keymap("i", "<Plug>(compleet-page-down)", "", opts.clone())?;
+
+    opts.set("callback", select_page.bind(-1)?)?;
+    api.set_keymap("i", "<Plug>(compleet-page-up)", "", opts.clone())?;
+
+    opts.set("callback", select_edge.bind(false)?)?;
+    api.set_keymap("i", "<Plug>(compleet-first)", "", opts.clone())?;
+
+    opts.set("callback", select_edge.bind(true)?)?;
+    api.set_keymap("i", "<Plug>(compleet-last)", "", opts.clone())?;
+
+    opts.set("callback", scroll_details.bind(1)?)?;
+    api.set_keymap(
+        "i",
+        "<Plug>(compleet-scroll-details-down)",
+        "",
+        opts.clone(),
+    )?;
+
+    opts.set("callback", scroll_details.bind(-1)?)?;
+    api.set_keymap(
+        "i",
+        "<Plug>(compleet-scroll-details-up)",
+        "",
+        opts.clone(),
+    )?;
+
+    opts.set("callback", jump_to_tabstop.bind(1)?)?;
+    api.set_keymap("i", "<Plug>(compleet-next-tabstop)", "", opts.clone())?;
+
+    opts.set("callback", jump_to_tabstop.bind(-1)?)?;
+    api.set_keymap("i", "<Plug>(compleet-prev-tabstop)", "", opts.clone())?;
+
     opts.set("callback", show_completions)?;
-    api.set_keymap("i", "<Plug>(compleet-show-completions)", "", opts)?;
+    api.set_keymap(
+        "i",
+        "<Plug>(compleet-show-completions)",
+        "",
+        opts.clone(),
+    )?;
+
+    opts.set("callback", after_typed)?;
+    api.set_keymap("i", super::AFTER_TYPED_PLUG, "", opts)?;
 
-    Ok(())
+    lua.create_registry_value(on_inserted)
 }
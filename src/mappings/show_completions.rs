@@ -1,28 +1,59 @@
 use mlua::prelude::{Lua, LuaResult};
 use neovim::Neovim;
 
+use crate::completion;
+use crate::phase::Action;
 use crate::state::State;
 use crate::ui::menu;
 
 /// Executed on `<Plug>(compleet-show-completions)`.
 pub fn show_completions(lua: &Lua, state: &mut State) -> LuaResult<()> {
-    let menu = &mut state.ui.completion_menu;
-    let completions = &state.completions;
+    let api = Neovim::new(lua)?.api;
 
-    if !menu.is_visible() && !completions.is_empty() {
-        let api = Neovim::new(lua)?.api;
+    // The completions shown in another window are computed again for the
+    // cursor of the current one.
+    state.follow_current_window(lua, &api)?;
 
-        let maybe_position = menu::positioning::get_position(
+    if !state.phase().allows(Action::Show) {
+        return Ok(());
+    }
+
+    // The completions might not have been computed yet if they're not
+    // computed while typing, or if they're being debounced.
+    if state.buffers.current().completions.is_empty()
+        && state.attached_buffers.contains(&api.get_current_buf()?)
+    {
+        completion::complete_at_cursor(lua, &api, state)?;
+    }
+
+    let completions = &state.buffers.current().completions;
+
+    if completions.is_empty() {
+        return Ok(());
+    }
+
+    let lines = menu::render(lua, completions, &state.settings.ui.menu)?;
+
+    let maybe_position = menu::positioning::get_position(
+        &api,
+        completions,
+        &state.buffers.current().cursor,
+        &lines,
+        &state.settings.ui.menu,
+    )?;
+
+    if let Some(position) = maybe_position {
+        state.ui.spawn_menu(
+            lua,
             &api,
+            &position,
             completions,
-            &state.settings.ui.menu,
+            &lines,
+            &state.buffers.current().cursor,
+            &state.settings,
         )?;
-
-        if let Some(position) = maybe_position {
-            menu.spawn(lua, &api, &position, &state.settings.ui.menu.border)?;
-            menu.fill(lua, &api, completions)?;
-        }
+        state.transition(state.ui.displayed(state.pipeline.is_loading()));
     }
 
-    Ok(())
+    completion::resolve_selected(lua, &api, state)
 }
@@ -0,0 +1,55 @@
+use mlua::prelude::{Lua, LuaResult};
+use neovim::{Api, Neovim};
+
+use crate::api;
+use crate::phase::Action;
+use crate::state::State;
+
+/// Executed by the `require("compleet").tab` and
+/// `require("compleet").shift_tab` Lua functions. In order, it:
+///
+/// 1. selects the next (or previous) completion if the menu is visible;
+///
+/// 2. jumps to the next (or previous) tabstop if a snippet is active;
+///
+/// 3. opens the menu if there are completions at the cursor (only when going
+///    forward);
+///
+/// 4. inserts a literal `<Tab>` (or `<S-Tab>`) otherwise.
+pub fn tab(
+    lua: &Lua,
+    state: &mut State,
+    step: i8, // either 1 or -1
+) -> LuaResult<()> {
+    let api = Neovim::new(lua)?.api;
+
+    if state.cmdline.is_menu_visible() {
+        return state.cmdline.select(lua, &api, step, &state.settings);
+    }
+
+    // In the command line there's nothing else to do.
+    if api.get_mode()?.0 == "c" {
+        return feed_literal(&api, step);
+    }
+
+    if state.phase().allows(Action::Select) {
+        return super::select_completion(lua, state, step);
+    }
+
+    if state.snippet.is_active() {
+        return super::jump_to_tabstop(lua, state, step);
+    }
+
+    if step == 1 && api::has_completions(lua, state)? {
+        return super::show_completions(lua, state);
+    }
+
+    feed_literal(&api, step)
+}
+
+/// Inserts a literal `<Tab>` or `<S-Tab>`, bypassing the user's mappings.
+fn feed_literal(api: &Api, step: i8) -> LuaResult<()> {
+    let key = if step == 1 { "<Tab>" } else { "<S-Tab>" };
+    let keys = api.replace_termcodes(key, true, false, true)?;
+    api.feedkeys(&keys, "n", false)
+}
@@ -0,0 +1,269 @@
+/// The phase of the completion lifecycle the plugin is in. It's stored in the
+/// `State` and only changed by `Transition`s, which are applied by the code
+/// computing, displaying, selecting and inserting the completions. The
+/// mappings check it to decide what they're allowed to do, instead of looking
+/// at the individual windows.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// There are no completions, and none are being computed.
+    #[default]
+    Idle,
+
+    /// Some sources are still computing the completions on background
+    /// threads, and none are displayed yet.
+    Computing,
+
+    /// The completions are only displayed by the hint, e.g. because the menu
+    /// isn't shown automatically.
+    Hinted,
+
+    /// The completion menu is open but none of its items are selected.
+    Shown,
+
+    /// The completion at the given index is selected in the menu.
+    Selected(usize),
+
+    /// A completion was confirmed, and its insertion is scheduled or its
+    /// keys are being typed. Nothing else can be selected or confirmed until
+    /// it's been inserted.
+    Inserting,
+}
+
+/// Something that happened to the completions, moving them to a new phase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    /// The sources started computing the completions at a new cursor
+    /// position.
+    Compute,
+
+    /// The UI was updated: `menu` is whether the completion menu is open,
+    /// with the `selected` completion, `hint` whether the hint is shown and
+    /// `is_loading` whether some sources are still computing.
+    Display {
+        menu: bool,
+        selected: Option<usize>,
+        hint: bool,
+        is_loading: bool,
+    },
+
+    /// The completion at the given index was selected in the menu, or the
+    /// selection was cleared if it's `None`.
+    Select(Option<usize>),
+
+    /// A completion was confirmed.
+    Insert,
+
+    /// The confirmed completion is in the buffer, and the hooks that run
+    /// after its insertion are done.
+    Inserted,
+
+    /// The UI was closed and the completions discarded.
+    Close,
+}
+
+/// Something a mapping can do to the completions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Open the menu with `<Plug>(compleet-show-completions)`.
+    Show,
+
+    /// Select the next or previous completion in the menu.
+    Select,
+
+    /// Close the UI, restoring the previewed text.
+    Abort,
+}
+
+impl Phase {
+    /// The phase the `transition` moves to, or `None` if it's not allowed
+    /// from this phase.
+    pub fn next(self, transition: Transition) -> Option<Self> {
+        use Phase::*;
+        use Transition::*;
+
+        match (self, transition) {
+            (Inserting, Compute | Select(_) | Insert) => None,
+            (Idle, Compute) => Some(Computing),
+            (_, Compute) => Some(self),
+
+            // The UI can be redrawn for the new text before the hooks of the
+            // insertion have run, which ends the insertion early.
+            (
+                _,
+                Display {
+                    menu: true,
+                    selected,
+                    ..
+                },
+            ) => Some(selected.map_or(Shown, Selected)),
+            (_, Display { hint: true, .. }) => Some(Hinted),
+            (
+                _,
+                Display {
+                    is_loading: true, ..
+                },
+            ) => Some(Computing),
+            (_, Display { .. }) => Some(Idle),
+
+            (Shown | Selected(_), Select(index)) => {
+                Some(index.map_or(Shown, Selected))
+            },
+            (_, Select(_)) => None,
+
+            (Hinted | Shown | Selected(_), Insert) => Some(Inserting),
+            (_, Insert) => None,
+
+            (Inserting, Inserted) => Some(Idle),
+            (_, Inserted) => Some(self),
+
+            (_, Close) => Some(Idle),
+        }
+    }
+
+    /// Whether the `action` can be taken in this phase.
+    pub fn allows(&self, action: Action) -> bool {
+        match action {
+            Action::Show => {
+                matches!(self, Phase::Idle | Phase::Computing | Phase::Hinted)
+            },
+            Action::Select => self.is_menu_visible(),
+            Action::Abort => !matches!(self, Phase::Idle | Phase::Inserting),
+        }
+    }
+
+    pub fn is_menu_visible(&self) -> bool {
+        matches!(self, Phase::Shown | Phase::Selected(_))
+    }
+
+    /// The index of the selected completion, if there's one.
+    pub fn selected(&self) -> Option<usize> {
+        match self {
+            Phase::Selected(index) => Some(*index),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, Phase, Transition};
+
+    fn display(
+        menu: bool,
+        selected: Option<usize>,
+        hint: bool,
+        is_loading: bool,
+    ) -> Transition {
+        Transition::Display {
+            menu,
+            selected,
+            hint,
+            is_loading,
+        }
+    }
+
+    #[test]
+    fn displaying() {
+        let idle = Phase::Idle;
+        assert_eq!(
+            Some(Phase::Selected(2)),
+            idle.next(display(true, Some(2), true, true))
+        );
+        assert_eq!(
+            Some(Phase::Shown),
+            idle.next(display(true, None, true, false))
+        );
+        assert_eq!(
+            Some(Phase::Hinted),
+            idle.next(display(false, None, true, true))
+        );
+        assert_eq!(
+            Some(Phase::Computing),
+            idle.next(display(false, None, false, true))
+        );
+        assert_eq!(
+            Some(Phase::Idle),
+            Phase::Selected(1).next(display(false, None, false, false))
+        );
+    }
+
+    #[test]
+    fn a_full_round() {
+        let transitions = [
+            (Transition::Compute, Phase::Computing),
+            (display(true, None, false, false), Phase::Shown),
+            (Transition::Select(Some(0)), Phase::Selected(0)),
+            (Transition::Select(Some(3)), Phase::Selected(3)),
+            (Transition::Insert, Phase::Inserting),
+            (Transition::Inserted, Phase::Idle),
+        ];
+
+        let mut phase = Phase::Idle;
+        for (transition, expected) in transitions {
+            phase = phase.next(transition).unwrap();
+            assert_eq!(expected, phase);
+        }
+    }
+
+    #[test]
+    fn computing_keeps_the_displayed_completions() {
+        assert_eq!(
+            Some(Phase::Computing),
+            Phase::Idle.next(Transition::Compute)
+        );
+        assert_eq!(
+            Some(Phase::Hinted),
+            Phase::Hinted.next(Transition::Compute)
+        );
+        assert_eq!(
+            Some(Phase::Selected(1)),
+            Phase::Selected(1).next(Transition::Compute)
+        );
+    }
+
+    #[test]
+    fn selecting_needs_the_menu() {
+        assert_eq!(None, Phase::Idle.next(Transition::Select(Some(0))));
+        assert_eq!(None, Phase::Hinted.next(Transition::Select(Some(0))));
+        assert_eq!(
+            Some(Phase::Shown),
+            Phase::Selected(4).next(Transition::Select(None))
+        );
+    }
+
+    #[test]
+    fn inserting_is_exclusive() {
+        assert_eq!(None, Phase::Idle.next(Transition::Insert));
+        assert_eq!(None, Phase::Computing.next(Transition::Insert));
+        assert_eq!(
+            Some(Phase::Inserting),
+            Phase::Hinted.next(Transition::Insert)
+        );
+
+        let inserting = Phase::Inserting;
+        assert_eq!(None, inserting.next(Transition::Insert));
+        assert_eq!(None, inserting.next(Transition::Select(Some(0))));
+        assert_eq!(None, inserting.next(Transition::Compute));
+        assert_eq!(Some(Phase::Idle), inserting.next(Transition::Close));
+
+        // The menu reopened for the inserted text before the hooks ran.
+        let shown = inserting.next(display(true, None, false, false)).unwrap();
+        assert_eq!(Some(Phase::Shown), shown.next(Transition::Inserted));
+    }
+
+    #[test]
+    fn guards() {
+        assert!(Phase::Idle.allows(Action::Show));
+        assert!(Phase::Hinted.allows(Action::Show));
+        assert!(!Phase::Shown.allows(Action::Show));
+        assert!(!Phase::Inserting.allows(Action::Show));
+
+        assert!(Phase::Shown.allows(Action::Select));
+        assert!(!Phase::Hinted.allows(Action::Select));
+        assert!(!Phase::Inserting.allows(Action::Select));
+
+        assert!(Phase::Computing.allows(Action::Abort));
+        assert!(!Phase::Idle.allows(Action::Abort));
+        assert!(!Phase::Inserting.allows(Action::Abort));
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The maximum number of samples kept for every stage. The oldest ones are
+/// dropped first.
+const MAX_SAMPLES: usize = 1000;
+
+/// The profiler is global so that the sources running on background threads
+/// can also record their timings.
+static PROFILER: Mutex<Profiler> = Mutex::new(Profiler {
+    enabled: false,
+    samples: BTreeMap::new(),
+});
+
+#[derive(Debug)]
+struct Profiler {
+    /// Set by the `debug.profile` option.
+    enabled: bool,
+
+    samples: BTreeMap<Stage, VecDeque<Duration>>,
+}
+
+/// A stage of a completion round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    /// A source computing its completions.
+    Source(&'static str),
+
+    /// Merging, matching and sorting the completions of all the sources.
+    Ranking,
+
+    /// Updating the menu, the hint and the details window.
+    Rendering,
+}
+
+impl Stage {
+    fn name(&self) -> String {
+        match self {
+            Stage::Source(name) => format!("source {name}"),
+            Stage::Ranking => "ranking".into(),
+            Stage::Rendering => "rendering".into(),
+        }
+    }
+}
+
+/// Enables or disables the profiler. Disabling it discards the samples.
+pub fn enable(enabled: bool) {
+    let profiler = &mut *PROFILER.lock().unwrap();
+    profiler.enabled = enabled;
+    if !enabled {
+        profiler.samples.clear();
+    }
+}
+
+/// Records how long a stage took, if the profiler is enabled.
+pub fn record(stage: Stage, duration: Duration) {
+    let profiler = &mut *PROFILER.lock().unwrap();
+    if !profiler.enabled {
+        return;
+    }
+    let samples = profiler.samples.entry(stage).or_default();
+    if samples.len() == MAX_SAMPLES {
+        samples.pop_front();
+    }
+    samples.push_back(duration);
+}
+
+/// Returns the lines of the report shown by `CompleetProfile`, with the
+/// median and 95th percentile durations of every stage.
+pub fn report() -> Vec<String> {
+    let profiler = &*PROFILER.lock().unwrap();
+
+    if !profiler.enabled {
+        return vec!["Profiling is disabled, set `debug.profile` to enable \
+                     it."
+        .into()];
+    }
+
+    let mut lines = vec![format!(
+        "{:<24} {:>7} {:>10} {:>10}",
+        "stage", "samples", "p50", "p95"
+    )];
+
+    for (stage, samples) in &profiler.samples {
+        let mut sorted = samples.iter().copied().collect::<Vec<Duration>>();
+        sorted.sort_unstable();
+        lines.push(format_row(&stage.name(), &sorted));
+    }
+
+    if profiler.samples.is_empty() {
+        lines.push("no samples yet".into());
+    }
+
+    lines
+}
+
+fn format_row(name: &str, sorted: &[Duration]) -> String {
+    let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+    format!(
+        "{name:<24} {:>7} {:>8.2}ms {:>8.2}ms",
+        sorted.len(),
+        ms(percentile(sorted, 50)),
+        ms(percentile(sorted, 95)),
+    )
+}
+
+/// Returns the `p`-th percentile of the sorted durations, using the
+/// nearest-rank method.
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (p * sorted.len()).div_ceil(100);
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{format_row, percentile};
+
+    #[test]
+    fn percentiles() {
+        let samples = (1..=20)
+            .map(Duration::from_millis)
+            .collect::<Vec<Duration>>();
+
+        assert_eq!(Duration::from_millis(10), percentile(&samples, 50));
+        assert_eq!(Duration::from_millis(19), percentile(&samples, 95));
+        assert_eq!(Duration::from_millis(1), percentile(&samples[..1], 95));
+        assert_eq!(Duration::ZERO, percentile(&[], 50));
+    }
+
+    #[test]
+    fn rows() {
+        let samples = [Duration::from_micros(1500), Duration::from_millis(3)];
+        assert_eq!(
+            "source Buffer                  2     1.50ms     3.00ms",
+            format_row("source Buffer", &samples)
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use std::fs;
+use std::path::PathBuf;
+
+use mlua::prelude::LuaResult;
+use neovim::Api;
+
+use crate::disk_cache::fnv1a;
+use crate::state::State;
+
+/// The first line of every session file, changed whenever their format does
+/// so that files written by older versions are ignored.
+const HEADER: &str = "compleet-session v1";
+
+/// The tag of the lines holding the paths of the buffers completion was
+/// stopped in.
+const STOPPED: &str = "stopped";
+
+/// Executed on every `VimLeavePre` event. If a session was written with
+/// `:mksession` or loaded, saves the buffers completion was stopped in and
+/// the history of the inserted completions next to it, so that they can be
+/// restored the next time the session is loaded.
+pub fn save(api: &Api, state: &State) -> LuaResult<()> {
+    let session = match this_session(api)? {
+        Some(session) => session,
+        None => return Ok(()),
+    };
+
+    let mut lines = vec![HEADER.to_owned(), session.clone()];
+
+    for &bufnr in &state.stopped_buffers {
+        if !api.buf_is_valid(bufnr)? {
+            continue;
+        }
+        let name = api.buf_get_name(bufnr)?;
+        if !name.is_empty() && !name.contains('\n') {
+            lines.push(format!("{STOPPED}\t{name}"));
+        }
+    }
+
+    lines.extend(state.history.to_session());
+
+    // Errors are ignored, the worst that can happen is that the state isn't
+    // restored.
+    let path = path(api, &session)?;
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let _ = fs::write(path, lines.join("\n") + "\n");
+
+    Ok(())
+}
+
+/// Executed on every `SessionLoadPost` event. Stops completion in the
+/// buffers it was stopped in when the session was saved, and restores the
+/// history of the inserted completions.
+pub fn restore(api: &Api, state: &mut State) -> LuaResult<()> {
+    let session = match this_session(api)? {
+        Some(session) => session,
+        None => return Ok(()),
+    };
+
+    let contents = match fs::read_to_string(path(api, &session)?) {
+        Ok(contents) => contents,
+        Err(_) => return Ok(()),
+    };
+
+    let mut lines = contents.lines();
+    if lines.next() != Some(HEADER) || lines.next() != Some(&session) {
+        return Ok(());
+    }
+
+    let (stopped, history) = parse(lines);
+
+    for bufnr in api.list_bufs()? {
+        if !stopped.contains(&api.buf_get_name(bufnr)?.as_str())
+            || state.stopped_buffers.contains(&bufnr)
+        {
+            continue;
+        }
+        if state.attached_buffers.contains(&bufnr) {
+            state.detach(api, bufnr)?;
+        }
+        state.stopped_buffers.push(bufnr);
+    }
+
+    state.history.restore_session(history);
+
+    Ok(())
+}
+
+/// Returns the path of the current session file, i.e. `v:this_session`, or
+/// `None` if no session was written or loaded.
+fn this_session(api: &Api) -> LuaResult<Option<String>> {
+    let session = api.get_vvar::<String>("this_session")?;
+    Ok((!session.is_empty()).then_some(session))
+}
+
+/// Returns the path of the file the state of the `session` is saved to,
+/// under Neovim's data directory. Session files can be anywhere, so it's
+/// named after the hash of their path.
+fn path(api: &Api, session: &str) -> LuaResult<PathBuf> {
+    let data = api.call_function::<_, String>("stdpath", vec!["data"])?;
+    Ok(PathBuf::from(data)
+        .join("compleet")
+        .join("sessions")
+        .join(format!("{:016x}", fnv1a(session))))
+}
+
+/// Splits the lines of a session file following its header into the paths
+/// of the buffers completion was stopped in and the lines holding the
+/// history.
+fn parse<'a, L>(lines: L) -> (Vec<&'a str>, Vec<&'a str>)
+where
+    L: IntoIterator<Item = &'a str>,
+{
+    let mut stopped = Vec::new();
+    let mut history = Vec::new();
+
+    for line in lines {
+        match line.split_once('\t') {
+            Some((STOPPED, path)) => stopped.push(path),
+            _ => history.push(line),
+        }
+    }
+
+    (stopped, history)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    #[test]
+    fn stopped_buffers_and_history() {
+        let lines = "stopped\t/foo.rs\nrecent\tbar\nstopped\t/baz.lua\n";
+        assert_eq!(
+            (vec!["/foo.rs", "/baz.lua"], vec!["recent\tbar"]),
+            parse(lines.lines())
+        );
+    }
+}
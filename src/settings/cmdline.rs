@@ -0,0 +1,22 @@
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CmdlineSettings {
+    #[serde(default)]
+    pub enable: bool,
+
+    #[serde(default = "default_history")]
+    pub history: bool,
+}
+
+fn default_history() -> bool { true }
+
+impl Default for CmdlineSettings {
+    fn default() -> Self {
+        CmdlineSettings {
+            enable: false,
+            history: default_history(),
+        }
+    }
+}
@@ -1,15 +1,422 @@
+use mlua::prelude::{Lua, LuaFunction, LuaResult, LuaValue};
 use serde::Deserialize;
 
+use super::sources::{shallow_copy, CompletionSource};
+
+/// The key of the Lua registry where the functions returned by
+/// `take_comparators` are stored, in order.
+pub const COMPARATORS: &str = "compleet_comparators";
+
+/// The key of the Lua registry where the function returned by `take_filter`
+/// is stored.
+pub const FILTER: &str = "compleet_completion_filter";
+
 #[derive(Debug, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct CompletionSettings {
+    #[serde(default)]
+    pub auto_parens: bool,
+
+    #[serde(default = "default_autoshow")]
+    pub autoshow: bool,
+
+    #[serde(default)]
+    pub behavior: ConfirmBehavior,
+
+    /// Whether to close the undo block before inserting a completion, so that
+    /// `u` only removes the completion instead of the whole insertion.
+    #[serde(default = "default_break_undo")]
+    pub break_undo: bool,
+
+    #[serde(default)]
+    pub debounce_ms: u32,
+
+    #[serde(default = "default_dedup")]
+    pub dedup: bool,
+
+    /// Whether to cache the indexes of the sources that are slow to build
+    /// (e.g. the words of a dictionary) to disk, so that they're reused
+    /// across sessions.
+    #[serde(default = "default_disk_cache")]
+    pub disk_cache: bool,
+
+    /// Whether to type the confirmed completions instead of setting the text
+    /// of the buffer, so that they're repeated by `.`.
+    #[serde(default)]
+    pub dot_repeat: bool,
+
+    /// Whether to attach to the buffers entered in floating windows, e.g.
+    /// the input prompts of plugins.
+    #[serde(default)]
+    pub enable_in_floats: bool,
+
+    #[serde(default)]
+    pub buftypes: BufferFilter,
+
+    #[serde(default)]
+    pub filetypes: BufferFilter,
+
+    #[serde(default)]
+    pub insert_on_select: bool,
+
+    #[serde(default)]
+    pub keyword_pattern: Option<String>,
+
+    #[serde(default)]
+    pub max_buffer_size: MaxBufferSize,
+
+    #[serde(default = "default_min_chars")]
+    pub min_chars: u8,
+
+    #[serde(default)]
+    pub preselect: Preselect,
+
+    #[serde(default)]
+    pub sorting: SortingSettings,
+
+    #[serde(default)]
+    pub throttle_ms: u32,
+
     pub while_deleting: bool,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SortingSettings {
+    #[serde(default = "default_comparators")]
+    pub comparators: Vec<Comparator>,
+
+    #[serde(default)]
+    pub source_priority: Vec<CompletionSource>,
+
+    #[serde(default)]
+    pub persist_frecency: bool,
+}
+
+/// The size above which a buffer is considered large. Either limit can be
+/// left unset.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MaxBufferSize {
+    #[serde(default)]
+    pub lines: Option<u32>,
+
+    #[serde(default)]
+    pub bytes: Option<u32>,
+}
+
+impl MaxBufferSize {
+    /// Whether a buffer with the given number of `lines` and `bytes` exceeds
+    /// any of the limits.
+    pub fn is_exceeded(&self, lines: u32, bytes: u32) -> bool {
+        self.lines.is_some_and(|max| lines > max)
+            || self.bytes.is_some_and(|max| bytes > max)
+    }
+}
+
+/// Which values of a buffer option (e.g. its `filetype`) completion is enabled
+/// for. An empty `allow` list allows every value not in the `deny` list.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BufferFilter {
+    #[serde(default)]
+    pub allow: Vec<String>,
+
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+impl BufferFilter {
+    pub fn allows(&self, value: &str) -> bool {
+        (self.allow.is_empty() || self.allow.iter().any(|v| v == value))
+            && !self.deny.iter().any(|v| v == value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfirmBehavior {
+    /// Only insert the part of the completion that's missing.
+    Insert,
+
+    /// Also replace the keyword after the cursor.
+    Replace,
+}
+
+impl Default for ConfirmBehavior {
+    fn default() -> Self { ConfirmBehavior::Insert }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preselect {
+    /// Select the first completion.
+    Always,
+
+    /// Select the first completion marked as `preselect` by its source.
+    Lsp,
+
+    /// Don't select anything.
+    #[default]
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparator {
+    /// Alphabetical order of the completion's text.
+    Alphabetical,
+
+    /// How often and how recently the completion was inserted after a similar
+    /// prefix in buffers of the same filetype.
+    Frecency,
+
+    /// How close to the cursor the completion's text appears in the buffer.
+    Locality,
+
+    /// How recently the completion was inserted.
+    RecentlyUsed,
+
+    /// The score given by the fuzzy matcher.
+    Score,
+
+    /// The position of the completion's source in `source_priority`.
+    SourcePriority,
+
+    /// The `n`-th Lua function in the `comparators` list, which is moved out
+    /// of the config by `take_comparators` beforehand and put back by
+    /// `SortingSettings::insert_lua_comparators`.
+    #[serde(skip)]
+    Lua(usize),
+}
+
+impl SortingSettings {
+    /// Puts back the Lua comparators taken out of the config by
+    /// `take_comparators`, given their positions in the original list.
+    pub fn insert_lua_comparators<P>(&mut self, positions: P)
+    where
+        P: IntoIterator<Item = usize>,
+    {
+        for (n, position) in positions.into_iter().enumerate() {
+            let position = position.min(self.comparators.len());
+            self.comparators.insert(position, Comparator::Lua(n));
+        }
+    }
+}
+
+/// Functions can't be deserialized, so the ones in the
+/// `completion.sorting.comparators` list are taken out of the config. Returns
+/// the new config and the functions together with their positions in the
+/// list.
+pub fn take_comparators<'lua>(
+    lua: &'lua Lua,
+    preferences: LuaValue<'lua>,
+) -> LuaResult<(LuaValue<'lua>, Vec<(usize, LuaFunction<'lua>)>)> {
+    let get_table = |value: LuaValue<'lua>| match value {
+        LuaValue::Table(table) => Some(table),
+        _ => None,
+    };
+
+    let preferences = match get_table(preferences.clone()) {
+        Some(preferences) => preferences,
+        None => return Ok((preferences, Vec::new())),
+    };
+
+    let unchanged = || Ok((LuaValue::Table(preferences.clone()), Vec::new()));
+
+    let completion = match get_table(preferences.get("completion")?) {
+        Some(completion) => completion,
+        None => return unchanged(),
+    };
+
+    let sorting = match get_table(completion.get("sorting")?) {
+        Some(sorting) => sorting,
+        None => return unchanged(),
+    };
+
+    let comparators = match get_table(sorting.get("comparators")?) {
+        Some(comparators) => comparators,
+        None => return unchanged(),
+    };
+
+    let mut functions = Vec::new();
+    let mut others = Vec::new();
+
+    for (position, comparator) in
+        comparators.sequence_values::<LuaValue>().enumerate()
+    {
+        match comparator? {
+            LuaValue::Function(function) => {
+                functions.push((position, function))
+            },
+            other => others.push(other),
+        }
+    }
+
+    if functions.is_empty() {
+        return unchanged();
+    }
+
+    let new_sorting = shallow_copy(lua, &sorting)?;
+    new_sorting.set("comparators", lua.create_sequence_from(others)?)?;
+
+    let new_completion = shallow_copy(lua, &completion)?;
+    new_completion.set("sorting", new_sorting)?;
+
+    let new_preferences = shallow_copy(lua, &preferences)?;
+    new_preferences.set("completion", new_completion)?;
+
+    Ok((LuaValue::Table(new_preferences), functions))
+}
+
+/// Moves the function set as the `completion.filter` option out of the raw
+/// config, returning a copy of the config without it and the function. Like
+/// with `take_predicates`, the user's table is left untouched.
+pub fn take_filter<'lua>(
+    lua: &'lua Lua,
+    preferences: LuaValue<'lua>,
+) -> LuaResult<(LuaValue<'lua>, Option<LuaFunction<'lua>>)> {
+    let preferences = match preferences {
+        LuaValue::Table(preferences) => preferences,
+        other => return Ok((other, None)),
+    };
+
+    let completion = match preferences.get::<_, LuaValue>("completion")? {
+        LuaValue::Table(completion) => completion,
+        _ => return Ok((LuaValue::Table(preferences), None)),
+    };
+
+    let filter = match completion.get::<_, LuaValue>("filter")? {
+        LuaValue::Function(filter) => filter,
+        _ => return Ok((LuaValue::Table(preferences), None)),
+    };
+
+    let new_completion = shallow_copy(lua, &completion)?;
+    new_completion.set("filter", LuaValue::Nil)?;
+
+    let new_preferences = shallow_copy(lua, &preferences)?;
+    new_preferences.set("completion", new_completion)?;
+
+    Ok((LuaValue::Table(new_preferences), Some(filter)))
+}
+
+fn default_autoshow() -> bool { true }
+
+fn default_break_undo() -> bool { true }
+
+fn default_dedup() -> bool { true }
+
+fn default_disk_cache() -> bool { true }
+
+fn default_min_chars() -> u8 { 1 }
+
+fn default_comparators() -> Vec<Comparator> {
+    vec![
+        Comparator::Score,
+        Comparator::Frecency,
+        Comparator::RecentlyUsed,
+        Comparator::Locality,
+        Comparator::SourcePriority,
+        Comparator::Alphabetical,
+    ]
+}
+
 impl Default for CompletionSettings {
     fn default() -> Self {
         CompletionSettings {
+            auto_parens: false,
+            autoshow: default_autoshow(),
+            behavior: ConfirmBehavior::default(),
+            break_undo: default_break_undo(),
+            debounce_ms: 0,
+            dedup: default_dedup(),
+            disk_cache: default_disk_cache(),
+            dot_repeat: false,
+            enable_in_floats: false,
+            buftypes: BufferFilter::default(),
+            filetypes: BufferFilter::default(),
+            insert_on_select: false,
+            keyword_pattern: None,
+            max_buffer_size: MaxBufferSize::default(),
+            min_chars: default_min_chars(),
+            preselect: Preselect::default(),
+            sorting: SortingSettings::default(),
+            throttle_ms: 0,
             while_deleting: false,
         }
     }
 }
+
+impl Default for SortingSettings {
+    fn default() -> Self {
+        SortingSettings {
+            comparators: default_comparators(),
+            source_priority: Vec::new(),
+            persist_frecency: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BufferFilter, Comparator, MaxBufferSize, SortingSettings};
+
+    #[test]
+    fn buffer_filter() {
+        assert!(BufferFilter::default().allows("markdown"));
+
+        let deny = BufferFilter {
+            allow: Vec::new(),
+            deny: vec!["markdown".into()],
+        };
+        assert!(!deny.allows("markdown"));
+        assert!(deny.allows("rust"));
+
+        let allow = BufferFilter {
+            allow: vec!["rust".into(), "lua".into()],
+            deny: vec!["lua".into()],
+        };
+        assert!(allow.allows("rust"));
+        assert!(!allow.allows("lua"));
+        assert!(!allow.allows("markdown"));
+    }
+
+    #[test]
+    fn max_buffer_size() {
+        assert!(!MaxBufferSize::default().is_exceeded(u32::MAX, u32::MAX));
+
+        let lines = MaxBufferSize {
+            lines: Some(100),
+            bytes: None,
+        };
+        assert!(!lines.is_exceeded(100, u32::MAX));
+        assert!(lines.is_exceeded(101, 0));
+
+        let both = MaxBufferSize {
+            lines: Some(100),
+            bytes: Some(1000),
+        };
+        assert!(!both.is_exceeded(50, 500));
+        assert!(both.is_exceeded(50, 1001));
+    }
+
+    #[test]
+    fn lua_comparators_are_put_back() {
+        let mut settings = SortingSettings {
+            comparators: vec![Comparator::Score, Comparator::Alphabetical],
+            source_priority: Vec::new(),
+            persist_frecency: false,
+        };
+        settings.insert_lua_comparators([0, 2, 4]);
+        assert_eq!(
+            vec![
+                Comparator::Lua(0),
+                Comparator::Score,
+                Comparator::Lua(1),
+                Comparator::Alphabetical,
+                Comparator::Lua(2),
+            ],
+            settings.comparators
+        );
+    }
+}
@@ -0,0 +1,10 @@
+use serde::Deserialize;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DebugSettings {
+    /// Whether to record how long every stage of the completion rounds
+    /// takes, reported by the `CompleetProfile` command.
+    #[serde(default)]
+    pub profile: bool,
+}
@@ -0,0 +1,10 @@
+use serde::Deserialize;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExperimentalSettings {
+    /// Whether to show the completions in Neovim's native popup menu instead
+    /// of the floating window of the completion menu.
+    #[serde(default)]
+    pub native_menu: bool,
+}
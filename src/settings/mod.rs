@@ -1,6 +1,13 @@
-mod completion;
+pub mod cmdline;
+pub mod completion;
+pub mod debug;
+pub mod experimental;
+pub mod logging;
 mod settings;
 pub mod sources;
+pub mod terminal;
 pub mod ui;
+mod validate;
 
 pub use settings::Settings;
+pub use validate::{validate, SettingsError};
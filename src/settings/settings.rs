@@ -1,5 +1,10 @@
 use serde::Deserialize;
 
+use super::cmdline::CmdlineSettings;
+use super::debug::DebugSettings;
+use super::experimental::ExperimentalSettings;
+use super::logging::LoggingSettings;
+use super::terminal::TerminalSettings;
 use super::{completion::CompletionSettings, sources, ui::UiSettings};
 use crate::state::Sources;
 
@@ -12,6 +17,21 @@ pub struct Settings {
     #[serde(default)]
     pub completion: CompletionSettings,
 
+    #[serde(default)]
+    pub cmdline: CmdlineSettings,
+
+    #[serde(default)]
+    pub terminal: TerminalSettings,
+
+    #[serde(default)]
+    pub logging: LoggingSettings,
+
+    #[serde(default)]
+    pub debug: DebugSettings,
+
+    #[serde(default)]
+    pub experimental: ExperimentalSettings,
+
     #[serde(default, with = "sources")]
     pub sources: Sources,
 }
@@ -7,8 +7,52 @@ use serde::Deserialize;
 //     Lipsum(crate::completion::sources::Lipsum),
 // }
 
-#[derive(Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum CompletionSource {
+    Buffer,
+    Calc,
+    Dictionary,
+    Digraph,
+    Emoji,
+    Env,
+    Git,
     Lipsum,
+    Lsp,
+    NvimLua,
+    Path,
+    Postfix,
+    Ripgrep,
+    Snippets,
+    Spell,
+    Tags,
+    Tmux,
+    Treesitter,
+}
+
+impl CompletionSource {
+    /// The name of the source as set in the `source` field of the completion
+    /// items it returns.
+    pub fn name(&self) -> &'static str {
+        match self {
+            CompletionSource::Buffer => "Buffer",
+            CompletionSource::Calc => "Calc",
+            CompletionSource::Dictionary => "Dictionary",
+            CompletionSource::Digraph => "Digraph",
+            CompletionSource::Emoji => "Emoji",
+            CompletionSource::Env => "Env",
+            CompletionSource::Git => "Git",
+            CompletionSource::Lipsum => "Lipsum",
+            CompletionSource::Lsp => "Lsp",
+            CompletionSource::NvimLua => "NvimLua",
+            CompletionSource::Path => "Path",
+            CompletionSource::Postfix => "Postfix",
+            CompletionSource::Ripgrep => "Ripgrep",
+            CompletionSource::Snippets => "Snippets",
+            CompletionSource::Spell => "Spell",
+            CompletionSource::Tags => "Tags",
+            CompletionSource::Tmux => "Tmux",
+            CompletionSource::Treesitter => "Treesitter",
+        }
+    }
 }
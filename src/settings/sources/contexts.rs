@@ -0,0 +1,98 @@
+use serde::Deserialize;
+
+/// The `contexts` option of the sources, telling in which syntactic contexts
+/// of the cursor (e.g. `comment` or `string`) a source completes. The
+/// contexts are the names of the treesitter highlight captures at the
+/// cursor, and a context also matches its subcaptures, e.g. `string` matches
+/// `string.escape`. An empty `allow` list allows every context not in the
+/// `deny` list.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Contexts {
+    #[serde(default)]
+    pub allow: Vec<String>,
+
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+impl Contexts {
+    /// Whether the option doesn't restrict anything, in which case the
+    /// captures at the cursor don't have to be queried.
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Whether a source can complete at a position with the given highlight
+    /// `captures`.
+    pub fn allows(&self, captures: &[String]) -> bool {
+        let matches = |context: &String| {
+            captures.iter().any(|capture| is_within(capture, context))
+        };
+
+        (self.allow.is_empty() || self.allow.iter().any(matches))
+            && !self.deny.iter().any(matches)
+    }
+}
+
+/// Whether the `capture` is the `context` or one of its subcaptures. The
+/// leading `@` of the capture names used by Neovim 0.8+ is ignored.
+fn is_within(capture: &str, context: &str) -> bool {
+    let capture = capture.strip_prefix('@').unwrap_or(capture);
+    let context = context.strip_prefix('@').unwrap_or(context);
+
+    capture
+        .strip_prefix(context)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_within, Contexts};
+
+    fn contexts(allow: &[&str], deny: &[&str]) -> Contexts {
+        Contexts {
+            allow: allow.iter().map(|&c| c.into()).collect(),
+            deny: deny.iter().map(|&c| c.into()).collect(),
+        }
+    }
+
+    fn captures(captures: &[&str]) -> Vec<String> {
+        captures.iter().map(|&c| c.into()).collect()
+    }
+
+    #[test]
+    fn subcaptures() {
+        assert!(is_within("string", "string"));
+        assert!(is_within("string.escape", "string"));
+        assert!(is_within("@comment", "comment"));
+        assert!(!is_within("stringify", "string"));
+        assert!(!is_within("string", "string.escape"));
+    }
+
+    #[test]
+    fn empty_contexts_allow_everything() {
+        let contexts = contexts(&[], &[]);
+        assert!(contexts.is_empty());
+        assert!(contexts.allows(&captures(&[])));
+        assert!(contexts.allows(&captures(&["comment"])));
+    }
+
+    #[test]
+    fn allowed_contexts() {
+        let contexts = contexts(&["comment"], &[]);
+        assert!(contexts.allows(&captures(&["comment"])));
+        assert!(contexts.allows(&captures(&["spell", "comment.todo"])));
+        assert!(!contexts.allows(&captures(&["variable"])));
+        assert!(!contexts.allows(&captures(&[])));
+    }
+
+    #[test]
+    fn denied_contexts() {
+        let contexts = contexts(&[], &["comment", "string"]);
+        assert!(contexts.allows(&captures(&[])));
+        assert!(contexts.allows(&captures(&["function.call"])));
+        assert!(!contexts.allows(&captures(&["string.special"])));
+        assert!(!contexts.allows(&captures(&["spell", "comment"])));
+    }
+}
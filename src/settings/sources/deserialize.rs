@@ -3,7 +3,7 @@ use std::sync::Arc;
 
 use serde::de::{Deserializer, MapAccess, Visitor};
 
-use super::CompletionSource;
+use super::{CompletionSource, Contexts, Enable};
 use crate::completion::{self, sources::*};
 use crate::state::Sources;
 
@@ -27,12 +27,202 @@ impl<'de> Visitor<'de> for SourcesVisitor {
 
         while let Some(source) = access.next_key::<CompletionSource>()? {
             match source {
+                CompletionSource::Buffer => {
+                    let buffer = access.next_value::<Buffer>()?;
+                    push(
+                        &mut sources,
+                        "buffer",
+                        buffer.enable.clone(),
+                        buffer.contexts.clone(),
+                        buffer,
+                    );
+                },
+
+                CompletionSource::Calc => {
+                    let calc = access.next_value::<Calc>()?;
+                    push(
+                        &mut sources,
+                        "calc",
+                        calc.enable.clone(),
+                        calc.contexts.clone(),
+                        calc,
+                    );
+                },
+
+                CompletionSource::Dictionary => {
+                    let dictionary = access.next_value::<Dictionary>()?;
+                    push(
+                        &mut sources,
+                        "dictionary",
+                        dictionary.enable.clone(),
+                        dictionary.contexts.clone(),
+                        dictionary,
+                    );
+                },
+
+                CompletionSource::Digraph => {
+                    let digraph = access.next_value::<Digraph>()?;
+                    push(
+                        &mut sources,
+                        "digraph",
+                        digraph.enable.clone(),
+                        digraph.contexts.clone(),
+                        digraph,
+                    );
+                },
+
+                CompletionSource::Emoji => {
+                    let emoji = access.next_value::<Emoji>()?;
+                    push(
+                        &mut sources,
+                        "emoji",
+                        emoji.enable.clone(),
+                        emoji.contexts.clone(),
+                        emoji,
+                    );
+                },
+
+                CompletionSource::Env => {
+                    let env = access.next_value::<Env>()?;
+                    push(
+                        &mut sources,
+                        "env",
+                        env.enable.clone(),
+                        env.contexts.clone(),
+                        env,
+                    );
+                },
+
+                CompletionSource::Git => {
+                    let git = access.next_value::<Git>()?;
+                    push(
+                        &mut sources,
+                        "git",
+                        git.enable.clone(),
+                        git.contexts.clone(),
+                        git,
+                    );
+                },
+
                 CompletionSource::Lipsum => {
                     let lipsum = access.next_value::<Lipsum>()?;
-                    if lipsum.enable {
-                        sources.push(Arc::new(lipsum)
-                            as Arc<dyn completion::CompletionSource>);
-                    }
+                    push(
+                        &mut sources,
+                        "lipsum",
+                        lipsum.enable.clone(),
+                        lipsum.contexts.clone(),
+                        lipsum,
+                    );
+                },
+
+                CompletionSource::Lsp => {
+                    let lsp = access.next_value::<Lsp>()?;
+                    push(
+                        &mut sources,
+                        "lsp",
+                        lsp.enable.clone(),
+                        lsp.contexts.clone(),
+                        lsp,
+                    );
+                },
+
+                CompletionSource::NvimLua => {
+                    let nvim_lua = access.next_value::<NvimLua>()?;
+                    push(
+                        &mut sources,
+                        "nvim_lua",
+                        nvim_lua.enable.clone(),
+                        nvim_lua.contexts.clone(),
+                        nvim_lua,
+                    );
+                },
+
+                CompletionSource::Path => {
+                    let path = access.next_value::<Path>()?;
+                    push(
+                        &mut sources,
+                        "path",
+                        path.enable.clone(),
+                        path.contexts.clone(),
+                        path,
+                    );
+                },
+
+                CompletionSource::Postfix => {
+                    let postfix = access.next_value::<Postfix>()?;
+                    push(
+                        &mut sources,
+                        "postfix",
+                        postfix.enable.clone(),
+                        postfix.contexts.clone(),
+                        postfix,
+                    );
+                },
+
+                CompletionSource::Ripgrep => {
+                    let ripgrep = access.next_value::<Ripgrep>()?;
+                    push(
+                        &mut sources,
+                        "ripgrep",
+                        ripgrep.enable.clone(),
+                        ripgrep.contexts.clone(),
+                        ripgrep,
+                    );
+                },
+
+                CompletionSource::Snippets => {
+                    let snippets = access.next_value::<Snippets>()?;
+                    push(
+                        &mut sources,
+                        "snippets",
+                        snippets.enable.clone(),
+                        snippets.contexts.clone(),
+                        snippets,
+                    );
+                },
+
+                CompletionSource::Spell => {
+                    let spell = access.next_value::<Spell>()?;
+                    push(
+                        &mut sources,
+                        "spell",
+                        spell.enable.clone(),
+                        spell.contexts.clone(),
+                        spell,
+                    );
+                },
+
+                CompletionSource::Tags => {
+                    let tags = access.next_value::<Tags>()?;
+                    push(
+                        &mut sources,
+                        "tags",
+                        tags.enable.clone(),
+                        tags.contexts.clone(),
+                        tags,
+                    );
+                },
+
+                CompletionSource::Tmux => {
+                    let tmux = access.next_value::<Tmux>()?;
+                    push(
+                        &mut sources,
+                        "tmux",
+                        tmux.enable.clone(),
+                        tmux.contexts.clone(),
+                        tmux,
+                    );
+                },
+
+                CompletionSource::Treesitter => {
+                    let treesitter = access.next_value::<Treesitter>()?;
+                    push(
+                        &mut sources,
+                        "treesitter",
+                        treesitter.enable.clone(),
+                        treesitter.contexts.clone(),
+                        treesitter,
+                    );
                 },
             }
         }
@@ -41,6 +231,24 @@ impl<'de> Visitor<'de> for SourcesVisitor {
     }
 }
 
+/// Adds the source deserialized from the `key` section of the config to the
+/// `sources` if it's enabled.
+fn push<S>(
+    sources: &mut Sources,
+    key: &'static str,
+    enable: Enable,
+    contexts: Contexts,
+    source: S,
+) where
+    S: completion::CompletionSource + 'static,
+{
+    if enable.is_enabled() {
+        sources
+            .push(Arc::new(Conditional::new(source, key, &enable, contexts))
+                as Arc<dyn completion::CompletionSource>);
+    }
+}
+
 pub fn deserialize<'de, D>(deserializer: D) -> Result<Sources, D::Error>
 where
     D: Deserializer<'de>,
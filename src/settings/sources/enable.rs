@@ -0,0 +1,179 @@
+use std::fmt;
+
+use mlua::prelude::{Lua, LuaResult, LuaTable, LuaValue};
+use serde::de::{self, Deserialize, Deserializer, MapAccess, Visitor};
+
+/// The key of the Lua registry where the table returned by `take_predicates`
+/// is stored.
+pub const PREDICATES: &str = "compleet_enable_predicates";
+
+/// The `enable` option of the sources, which is either a boolean or a table
+/// of conditions a buffer has to satisfy for the source to attach to it. It
+/// can also be a Lua function, which can't be deserialized and is moved out
+/// of the config by `take_predicates` beforehand.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Enable {
+    Bool(bool),
+    Condition(Condition),
+}
+
+/// Conditions a buffer has to satisfy for a source to be enabled in it. Empty
+/// lists don't restrict anything.
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Condition {
+    /// The filetypes of the buffers.
+    #[serde(default)]
+    pub filetypes: Vec<String>,
+
+    /// The buftypes of the buffers, where `""` is a normal buffer.
+    #[serde(default)]
+    pub buftypes: Vec<String>,
+
+    /// Globs matched against the full paths of the buffers, e.g.
+    /// `"*/src/*.rs"`.
+    #[serde(default)]
+    pub paths: Vec<String>,
+}
+
+impl Default for Enable {
+    fn default() -> Self { Enable::Bool(false) }
+}
+
+impl Enable {
+    /// Whether the source is enabled at all. Sources with conditions are
+    /// enabled, and the conditions are checked every time they're asked to
+    /// attach to a buffer.
+    pub fn is_enabled(&self) -> bool { !matches!(self, Enable::Bool(false)) }
+}
+
+impl Condition {
+    /// Whether a buffer with the given `filetype` and `buftype` satisfies the
+    /// conditions. `matches_path` tells whether one of the `paths` globs
+    /// matches the buffer's path.
+    pub fn is_satisfied<F>(
+        &self,
+        filetype: &str,
+        buftype: &str,
+        matches_path: F,
+    ) -> bool
+    where
+        F: FnOnce(&[String]) -> bool,
+    {
+        (self.filetypes.is_empty()
+            || self.filetypes.iter().any(|f| f == filetype))
+            && (self.buftypes.is_empty()
+                || self.buftypes.iter().any(|b| b == buftype))
+            && (self.paths.is_empty() || matches_path(&self.paths))
+    }
+}
+
+/// Moves the functions set as the `enable` option of the sources out of the
+/// raw config, returning a copy of the config where they're replaced with
+/// `true` and a table of the functions indexed by the name of their source's
+/// section. The user's table is left untouched so that it can be passed to
+/// `setup` again.
+pub fn take_predicates<'lua>(
+    lua: &'lua Lua,
+    preferences: LuaValue<'lua>,
+) -> LuaResult<(LuaValue<'lua>, LuaTable<'lua>)> {
+    let predicates = lua.create_table()?;
+
+    let preferences = match preferences {
+        LuaValue::Table(preferences) => preferences,
+        other => return Ok((other, predicates)),
+    };
+
+    let sources = match preferences.get::<_, LuaValue>("sources")? {
+        LuaValue::Table(sources) => sources,
+        _ => return Ok((LuaValue::Table(preferences), predicates)),
+    };
+
+    let new_sources = shallow_copy(lua, &sources)?;
+
+    for pair in sources.pairs::<LuaValue, LuaValue>() {
+        if let (name, LuaValue::Table(source)) = pair? {
+            if let LuaValue::Function(predicate) = source.get("enable")? {
+                let source = shallow_copy(lua, &source)?;
+                source.set("enable", true)?;
+                predicates.set(name.clone(), predicate)?;
+                new_sources.set(name, source)?;
+            }
+        }
+    }
+
+    let new_preferences = shallow_copy(lua, &preferences)?;
+    new_preferences.set("sources", new_sources)?;
+    Ok((LuaValue::Table(new_preferences), predicates))
+}
+
+pub(crate) fn shallow_copy<'lua>(
+    lua: &'lua Lua,
+    table: &LuaTable<'lua>,
+) -> LuaResult<LuaTable<'lua>> {
+    let copy = lua.create_table()?;
+    for pair in table.clone().pairs::<LuaValue, LuaValue>() {
+        let (key, value) = pair?;
+        copy.set(key, value)?;
+    }
+    Ok(copy)
+}
+
+struct EnableVisitor;
+
+impl<'de> Visitor<'de> for EnableVisitor {
+    type Value = Enable;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "a boolean, a function or a table with `filetypes`, `buftypes` \
+             and `paths` fields"
+        )
+    }
+
+    fn visit_bool<E: de::Error>(self, enable: bool) -> Result<Enable, E> {
+        Ok(Enable::Bool(enable))
+    }
+
+    fn visit_map<M>(self, access: M) -> Result<Enable, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        Condition::deserialize(de::value::MapAccessDeserializer::new(access))
+            .map(Enable::Condition)
+    }
+}
+
+impl<'de> Deserialize<'de> for Enable {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(EnableVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Condition;
+
+    #[test]
+    fn empty_condition_is_always_satisfied() {
+        let condition = Condition::default();
+        assert!(condition.is_satisfied("rust", "", |_| false));
+    }
+
+    #[test]
+    fn all_conditions_must_be_satisfied() {
+        let condition = Condition {
+            filetypes: vec!["rust".into(), "lua".into()],
+            buftypes: vec!["".into()],
+            paths: vec!["*/src/*".into()],
+        };
+        assert!(condition.is_satisfied("lua", "", |_| true));
+        assert!(!condition.is_satisfied("python", "", |_| true));
+        assert!(!condition.is_satisfied("rust", "nofile", |_| true));
+        assert!(!condition.is_satisfied("rust", "", |_| false));
+    }
+}
@@ -1,5 +1,10 @@
 mod completion_source;
+mod contexts;
 mod deserialize;
+mod enable;
 
 pub use completion_source::CompletionSource;
+pub use contexts::Contexts;
 pub use deserialize::deserialize;
+pub(crate) use enable::shallow_copy;
+pub use enable::{take_predicates, Condition, Enable, PREDICATES};
@@ -0,0 +1,22 @@
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TerminalSettings {
+    #[serde(default)]
+    pub enable: bool,
+
+    #[serde(default = "default_scrollback")]
+    pub scrollback: u32,
+}
+
+fn default_scrollback() -> u32 { 1000 }
+
+impl Default for TerminalSettings {
+    fn default() -> Self {
+        TerminalSettings {
+            enable: false,
+            scrollback: default_scrollback(),
+        }
+    }
+}
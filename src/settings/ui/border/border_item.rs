@@ -73,7 +73,7 @@ impl<'de> Deserialize<'de> for BorderItem {
                     .next_element::<String>()?
                     .expect("Already checked that len is > 1");
 
-                if c.chars().count() > 2 {
+                if c.chars().count() > 1 {
                     return Err(de::Error::invalid_value(
                         de::Unexpected::Str(&c),
                         &"either 0 or 1 characters",
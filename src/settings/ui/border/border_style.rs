@@ -1,4 +1,7 @@
+use std::fmt;
+
 use mlua::prelude::{Lua, LuaResult, LuaValue, ToLua};
+use serde::de::{self, Deserializer, Visitor};
 use serde::Deserialize;
 
 use super::BorderItem;
@@ -11,6 +14,10 @@ pub enum BorderStyle {
     // if/when https://github.com/serde-rs/serde/issues/1402 is implemented.
     String(BorderString),
 
+    // A string with the 8 characters of the corners and the edges, starting
+    // from the top-left corner and going clockwise, e.g. "╭─╮│╯─╰│".
+    Chars(BorderChars),
+
     // These variants allow the users to pass a table to customize the
     // characters used in the borders at the corner and edge level, for
     // example:
@@ -48,6 +55,43 @@ pub enum BorderString {
     Shadow,
 }
 
+#[derive(Debug)]
+pub struct BorderChars(Vec<String>);
+
+impl<'de> Deserialize<'de> for BorderChars {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct CharsVisitor;
+
+        impl<'de> Visitor<'de> for CharsVisitor {
+            type Value = BorderChars;
+
+            fn expecting(
+                &self,
+                formatter: &mut fmt::Formatter,
+            ) -> fmt::Result {
+                write!(formatter, "a string of 8 characters")
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                match s.chars().count() {
+                    8 => {
+                        Ok(BorderChars(s.chars().map(String::from).collect()))
+                    },
+                    n => Err(E::invalid_length(n, &"8 characters")),
+                }
+            }
+        }
+
+        deserializer.deserialize_str(CharsVisitor)
+    }
+}
+
 impl BorderString {
     pub fn has_edges(&self) -> bool {
         match self {
@@ -63,6 +107,8 @@ impl BorderStyle {
         match self {
             Self::String(s) => s.has_edges(),
 
+            Self::Chars(_) => true,
+
             Self::Array1([item])
             | Self::Array2([_, item])
             | Self::Array4([_, item, _, _])
@@ -75,6 +121,8 @@ impl BorderStyle {
         match self {
             Self::String(s) => s.has_edges(),
 
+            Self::Chars(_) => true,
+
             Self::Array1([item])
             | Self::Array2([_, item])
             | Self::Array4([_, item, _, _])
@@ -87,6 +135,8 @@ impl BorderStyle {
         match self {
             Self::String(s) => s.has_edges(),
 
+            Self::Chars(_) => true,
+
             Self::Array1([item])
             | Self::Array2([_, item])
             | Self::Array4([_, _, _, item])
@@ -99,6 +149,8 @@ impl BorderStyle {
         match self {
             Self::String(s) => s.has_edges(),
 
+            Self::Chars(_) => true,
+
             Self::Array1([item])
             | Self::Array2([_, item])
             | Self::Array4([_, _, _, item])
@@ -119,6 +171,8 @@ impl BorderStyle {
                 BorderString::Shadow => "shadow".to_lua(lua),
             },
 
+            Self::Chars(BorderChars(chars)) => chars.clone().to_lua(lua),
+
             Self::Array1(a) => a.to_vec().to_lua(lua),
             Self::Array2(a) => a.to_vec().to_lua(lua),
             Self::Array4(a) => a.to_vec().to_lua(lua),
@@ -1,15 +1,55 @@
+use std::num::NonZeroU32;
+
 use serde::{Deserialize, Deserializer};
 
 use super::border::{Border, BorderItem, BorderStyle, IncompleteBorder};
+use super::Winblend;
 
 #[derive(Debug, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct DetailsSettings {
+    #[serde(default)]
+    pub max_height: Option<NonZeroU32>,
+
+    #[serde(default = "default_max_width")]
+    pub max_width: NonZeroU32,
+
+    #[serde(default = "default_markdown")]
+    pub markdown: bool,
+
+    /// The side of the completion menu where the details window is placed
+    /// if there's enough space, otherwise the other side is tried.
+    #[serde(default)]
+    pub position: DetailsPosition,
+
+    /// Whether to wrap the lines longer than `max_width`. The height of the
+    /// window accounts for the wrapped lines.
+    #[serde(default = "default_wrap")]
+    pub wrap: bool,
+
+    /// The `winblend` of the window, defaulting to `ui.transparency`.
+    #[serde(default)]
+    pub winblend: Option<Winblend>,
+
     #[serde(deserialize_with = "deserialize_details_border")]
     #[serde(default = "default_details_border")]
     pub border: Border,
 }
 
+fn default_max_width() -> NonZeroU32 { NonZeroU32::new(79).unwrap() }
+
+fn default_markdown() -> bool { true }
+
+fn default_wrap() -> bool { true }
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DetailsPosition {
+    Left,
+    #[default]
+    Right,
+}
+
 fn default_border_enable() -> bool { true }
 
 fn default_border_style() -> BorderStyle {
@@ -55,6 +95,12 @@ fn deserialize_details_border<'de, D: Deserializer<'de>>(
 impl Default for DetailsSettings {
     fn default() -> Self {
         DetailsSettings {
+            max_height: Option::default(),
+            max_width: default_max_width(),
+            markdown: default_markdown(),
+            position: DetailsPosition::default(),
+            wrap: default_wrap(),
+            winblend: None,
             border: default_details_border(),
         }
     }
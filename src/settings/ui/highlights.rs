@@ -0,0 +1,57 @@
+use mlua::prelude::{Lua, LuaResult, LuaTable};
+use serde::Deserialize;
+
+/// The attributes of a highlight group set in the `ui.highlights` option,
+/// overriding its default definition. See `:h nvim_set_hl` for their
+/// meaning.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HighlightAttrs {
+    #[serde(default)]
+    pub link: Option<String>,
+
+    #[serde(default)]
+    pub fg: Option<String>,
+
+    #[serde(default)]
+    pub bg: Option<String>,
+
+    #[serde(default)]
+    pub sp: Option<String>,
+
+    #[serde(default)]
+    pub bold: Option<bool>,
+
+    #[serde(default)]
+    pub italic: Option<bool>,
+
+    #[serde(default)]
+    pub underline: Option<bool>,
+
+    #[serde(default)]
+    pub undercurl: Option<bool>,
+
+    #[serde(default)]
+    pub strikethrough: Option<bool>,
+
+    #[serde(default)]
+    pub reverse: Option<bool>,
+}
+
+impl HighlightAttrs {
+    /// Returns the table passed to `nvim_set_hl`.
+    pub fn to_table<'lua>(&self, lua: &'lua Lua) -> LuaResult<LuaTable<'lua>> {
+        let opts = lua.create_table()?;
+        opts.set("link", self.link.as_deref())?;
+        opts.set("fg", self.fg.as_deref())?;
+        opts.set("bg", self.bg.as_deref())?;
+        opts.set("sp", self.sp.as_deref())?;
+        opts.set("bold", self.bold)?;
+        opts.set("italic", self.italic)?;
+        opts.set("underline", self.underline)?;
+        opts.set("undercurl", self.undercurl)?;
+        opts.set("strikethrough", self.strikethrough)?;
+        opts.set("reverse", self.reverse)?;
+        Ok(opts)
+    }
+}
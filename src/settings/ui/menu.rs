@@ -1,8 +1,17 @@
+use std::collections::HashMap;
 use std::num::NonZeroU32;
 
+use mlua::prelude::{Lua, LuaFunction, LuaResult, LuaValue};
 use serde::{Deserialize, Deserializer};
 
 use super::border::{Border, BorderString, BorderStyle, IncompleteBorder};
+use super::Winblend;
+use crate::completion::matcher::MATCHING_CHARS_HL;
+use crate::settings::sources::shallow_copy;
+
+/// The key of the Lua registry where the function returned by
+/// `take_formatter` is stored.
+pub const MENU_FORMATTER: &str = "compleet_menu_formatter";
 
 #[derive(Debug, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -13,14 +22,161 @@ pub struct MenuSettings {
     #[serde(default = "default_autoshow")]
     pub autoshow: bool,
 
+    #[serde(default)]
+    pub close_on: CloseOn,
+
+    /// Whether selecting past the last or the first completion goes back to
+    /// no selection, and then around to the other end of the menu.
+    #[serde(default = "default_cycle")]
+    pub cycle: bool,
+
+    /// Whether to show the best completion at the bottom of the menu when
+    /// it's above the cursor.
+    #[serde(default)]
+    pub direction_aware: bool,
+
+    /// A template like `"{kind_icon} {label} {detail}"` used to format the
+    /// lines of the menu. It can also be a Lua function, which is moved out
+    /// of the config by `take_formatter` beforehand.
+    #[serde(default)]
+    pub format: Option<String>,
+
+    #[serde(default)]
+    pub highlights: MenuHighlights,
+
+    /// Whether to prefix the first 9 completions with the number of the
+    /// `<Plug>(compleet-confirm-{n})` mapping inserting them.
+    #[serde(default)]
+    pub index_labels: bool,
+
+    #[serde(default = "default_kind_format")]
+    pub kind_format: String,
+
+    #[serde(default)]
+    pub kind_icons: HashMap<String, String>,
+
+    /// Whether to show a `…` at the end of the cursor line while some
+    /// sources are still computing their completions and the menu is empty.
+    #[serde(default)]
+    pub loading_indicator: bool,
+
     #[serde(default)]
     pub max_height: Option<NonZeroU32>,
 
+    #[serde(default)]
+    pub max_width: Option<NonZeroU32>,
+
+    /// Whether hovering over a completion with the mouse selects it, and
+    /// clicking on it confirms it.
+    #[serde(default)]
+    pub mouse: bool,
+
+    #[serde(default = "default_scrollbar")]
+    pub scrollbar: bool,
+
+    #[serde(default)]
+    pub show_source: bool,
+
+    /// A title shown in the top edge of the border, where `{index}` and
+    /// `{total}` are replaced by the index of the selected completion and
+    /// the number of completions. Needs Neovim 0.9+.
+    #[serde(default)]
+    pub title: Option<String>,
+
+    /// The `winblend` of the window, defaulting to `ui.transparency`. The
+    /// scrollbar uses the same one.
+    #[serde(default)]
+    pub winblend: Option<Winblend>,
+
     #[serde(deserialize_with = "deserialize_menu_border")]
     #[serde(default = "default_menu_border")]
     pub border: Border,
 }
 
+/// How the characters of the completions matching the completion prefix are
+/// highlighted.
+/// The events closing the menu other than leaving insert mode, each one
+/// disabled by default.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CloseOn {
+    /// Moving the cursor out of the keyword being completed without typing,
+    /// e.g. with the arrow keys.
+    #[serde(default)]
+    pub cursor_moved: bool,
+
+    /// Switching from insert mode to another mode without leaving it first,
+    /// e.g. with `<C-o>`.
+    #[serde(default)]
+    pub mode_changed: bool,
+
+    /// Starting to type a different word, e.g. after a trigger character.
+    #[serde(default)]
+    pub new_word: bool,
+
+    /// Leaving the window, e.g. by clicking on another one.
+    #[serde(default)]
+    pub win_leave: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MenuHighlights {
+    /// The highlight group of the matched characters.
+    #[serde(default = "default_matching_hl_group")]
+    pub matching: String,
+
+    /// Whether to highlight everything from the first to the last matched
+    /// character instead of only the matched ones.
+    #[serde(default)]
+    pub full_span: bool,
+
+    /// Overrides for the completions of some sources, indexed by the name of
+    /// the source, e.g. `Lsp` or `Buffer`.
+    #[serde(default)]
+    pub sources: HashMap<String, MatchStyle>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MatchStyle {
+    /// The highlight group of the matched characters, defaulting to the
+    /// `matching` one.
+    #[serde(default)]
+    pub hl_group: Option<String>,
+
+    #[serde(default)]
+    pub bold: bool,
+
+    #[serde(default)]
+    pub underline: bool,
+}
+
+impl MenuHighlights {
+    /// Returns the highlight group of the characters matched by the
+    /// completions of a source. Sources with a style use their own
+    /// `CompleetMenuMatchingChars{source}` group, defined when calling
+    /// `setup`.
+    pub fn matching_hl_group(&self, source: &str) -> String {
+        match self.sources.get(source) {
+            Some(_) => format!("{MATCHING_CHARS_HL}{source}"),
+            None => self.matching.clone(),
+        }
+    }
+}
+
+fn default_matching_hl_group() -> String { MATCHING_CHARS_HL.into() }
+
+impl Default for MenuHighlights {
+    fn default() -> Self {
+        MenuHighlights {
+            matching: default_matching_hl_group(),
+            full_span: false,
+            sources: HashMap::new(),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum MenuAnchor {
@@ -34,6 +190,12 @@ impl Default for MenuAnchor {
 
 fn default_autoshow() -> bool { true }
 
+fn default_cycle() -> bool { true }
+
+fn default_kind_format() -> String { "{icon} {kind}".into() }
+
+fn default_scrollbar() -> bool { true }
+
 fn default_border_enable() -> bool { false }
 
 fn default_border_style() -> BorderStyle {
@@ -76,8 +238,62 @@ impl Default for MenuSettings {
         MenuSettings {
             anchor: MenuAnchor::default(),
             autoshow: default_autoshow(),
+            close_on: CloseOn::default(),
+            cycle: default_cycle(),
+            direction_aware: false,
+            format: None,
+            highlights: MenuHighlights::default(),
+            index_labels: false,
+            kind_format: default_kind_format(),
+            kind_icons: HashMap::new(),
+            loading_indicator: false,
             max_height: Option::default(),
+            max_width: Option::default(),
+            mouse: false,
+            scrollbar: default_scrollbar(),
+            show_source: false,
+            title: None,
+            winblend: None,
             border: default_menu_border(),
         }
     }
 }
+
+/// Moves the function set as the `ui.menu.format` option out of the raw
+/// config, returning a copy of the config without it and the function. Like
+/// with `take_predicates`, the user's table is left untouched.
+pub fn take_formatter<'lua>(
+    lua: &'lua Lua,
+    preferences: LuaValue<'lua>,
+) -> LuaResult<(LuaValue<'lua>, Option<LuaFunction<'lua>>)> {
+    let preferences = match preferences {
+        LuaValue::Table(preferences) => preferences,
+        other => return Ok((other, None)),
+    };
+
+    let ui = match preferences.get::<_, LuaValue>("ui")? {
+        LuaValue::Table(ui) => ui,
+        _ => return Ok((LuaValue::Table(preferences), None)),
+    };
+
+    let menu = match ui.get::<_, LuaValue>("menu")? {
+        LuaValue::Table(menu) => menu,
+        _ => return Ok((LuaValue::Table(preferences), None)),
+    };
+
+    let formatter = match menu.get::<_, LuaValue>("format")? {
+        LuaValue::Function(formatter) => formatter,
+        _ => return Ok((LuaValue::Table(preferences), None)),
+    };
+
+    let new_menu = shallow_copy(lua, &menu)?;
+    new_menu.set("format", LuaValue::Nil)?;
+
+    let new_ui = shallow_copy(lua, &ui)?;
+    new_ui.set("menu", new_menu)?;
+
+    let new_preferences = shallow_copy(lua, &preferences)?;
+    new_preferences.set("ui", new_ui)?;
+
+    Ok((LuaValue::Table(new_preferences), Some(formatter)))
+}
@@ -0,0 +1,41 @@
+use serde::Deserialize;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MessagesSettings {
+    /// Whether to send the messages through `vim.notify` instead of echoing
+    /// them, so that they can be displayed by plugins like `nvim-notify` or
+    /// `noice.nvim`.
+    #[serde(default)]
+    pub notify: bool,
+
+    /// The non-error messages that shouldn't be displayed.
+    #[serde(default)]
+    pub silence: Vec<MessageId>,
+}
+
+/// The messages that can be silenced with the `ui.messages.silence` option.
+/// Errors are always displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageId {
+    /// Completion is already on, either in all buffers or in the current
+    /// one.
+    AlreadyOn,
+
+    /// Completion is already off, either in all buffers or in the current
+    /// one.
+    AlreadyOff,
+
+    /// The indexes cached to disk were deleted by `:Compleet cache clear`.
+    CacheCleared,
+
+    /// No source can attach to the current buffer.
+    NoSources,
+
+    /// Completion was started.
+    Started,
+
+    /// Completion was stopped.
+    Stopped,
+}
@@ -1,7 +1,11 @@
 pub mod border;
-mod details;
+pub mod details;
+pub mod highlights;
 mod hint;
 pub mod menu;
+pub mod messages;
 mod ui;
+mod winblend;
 
 pub use ui::UiSettings;
+pub use winblend::Winblend;
@@ -1,6 +1,8 @@
+use std::collections::HashMap;
+
 use serde::Deserialize;
 
-use super::{details, hint, menu};
+use super::{details, highlights, hint, menu, messages, Winblend};
 
 #[derive(Debug, Default, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -13,4 +15,43 @@ pub struct UiSettings {
 
     #[serde(default)]
     pub hint: hint::HintSettings,
+
+    /// Overrides of the default definitions of the `Compleet*` highlight
+    /// groups, indexed by their names.
+    #[serde(default)]
+    pub highlights: HashMap<String, highlights::HighlightAttrs>,
+
+    #[serde(default)]
+    pub messages: messages::MessagesSettings,
+
+    /// The `winblend` of the floating windows that don't set their own.
+    #[serde(default)]
+    pub transparency: Winblend,
+}
+
+impl UiSettings {
+    /// Sets the `winblend` of the completion menu and of the details window
+    /// to `transparency`, unless they have their own.
+    pub fn inherit_transparency(&mut self) {
+        self.menu.winblend.get_or_insert(self.transparency);
+        self.details.winblend.get_or_insert(self.transparency);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UiSettings;
+    use crate::settings::ui::Winblend;
+
+    #[test]
+    fn inherited_transparency() {
+        let mut settings = UiSettings {
+            transparency: Winblend(20),
+            ..UiSettings::default()
+        };
+        settings.details.winblend = Some(Winblend(0));
+        settings.inherit_transparency();
+        assert_eq!(Some(Winblend(20)), settings.menu.winblend);
+        assert_eq!(Some(Winblend(0)), settings.details.winblend);
+    }
 }
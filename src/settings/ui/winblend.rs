@@ -0,0 +1,20 @@
+use serde::de::{self, Deserialize, Deserializer};
+
+/// The `winblend` of a floating window, from 0 for an opaque window to 100
+/// for a fully transparent one.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Winblend(pub u8);
+
+impl<'de> Deserialize<'de> for Winblend {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        match u8::deserialize(deserializer)? {
+            winblend @ 0..=100 => Ok(Winblend(winblend)),
+            winblend => Err(de::Error::invalid_value(
+                de::Unexpected::Unsigned(winblend.into()),
+                &"a number between 0 and 100",
+            )),
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use mlua::prelude::{Lua, LuaError, LuaResult, LuaTable, LuaValue};
+use serde_path_to_error::Segment;
+
+use super::Settings;
+use crate::completion::KeywordPattern;
+
+/// The maximum number of errors collected from a single config.
+const MAX_ERRORS: usize = 20;
+
+/// An invalid option of the config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SettingsError {
+    /// The full path of the option, e.g. `ui.menu.border.style`.
+    pub path: String,
+
+    /// What's wrong with it, including the offending value.
+    pub msg: String,
+}
+
+/// Deserializes the `Settings` from the raw config, collecting all the
+/// invalid options instead of stopping at the first one. Every time an
+/// option fails to deserialize it's removed from a copy of the config, which
+/// is then deserialized again.
+pub fn validate(
+    lua: &Lua,
+    preferences: LuaValue,
+) -> LuaResult<Result<Settings, Vec<SettingsError>>> {
+    let preferences = match preferences {
+        LuaValue::Table(preferences) => deep_copy(lua, &preferences)?,
+        LuaValue::Nil => return Ok(Ok(Settings::default())),
+        other => {
+            return Ok(Err(vec![SettingsError {
+                path: String::new(),
+                msg: format!(
+                    "expected a table or `nil`, got `{}`",
+                    display(&other)
+                ),
+            }]))
+        },
+    };
+
+    let mut errors = Vec::new();
+
+    let settings = loop {
+        // Using the `serde_path_to_error` crate to get the full path of the
+        // option where the error occured.
+        let err = match serde_path_to_error::deserialize::<_, Settings>(
+            mlua::serde::Deserializer::new(LuaValue::Table(
+                preferences.clone(),
+            )),
+        ) {
+            Ok(settings) => break Some(settings),
+            Err(err) => err,
+        };
+
+        let msg = match err.inner() {
+            LuaError::DeserializeError(msg) => msg.clone(),
+            _ => return Err(err.into_inner()),
+        };
+
+        let mut keys = err
+            .path()
+            .iter()
+            .map(|segment| match segment {
+                Segment::Seq { index } => Some(Key::Index(index + 1)),
+                Segment::Map { key } | Segment::Enum { variant: key } => {
+                    Some(Key::Field(key.clone()))
+                },
+                Segment::Unknown => None,
+            })
+            .collect::<Option<Vec<Key>>>();
+
+        // The path of an unknown field is the one of the table containing
+        // it.
+        if let (Some(keys), Some(field)) = (keys.as_mut(), unknown_field(&msg))
+        {
+            if keys.last() != Some(&Key::Field(field.to_owned())) {
+                keys.push(Key::Field(field.to_owned()));
+            }
+        }
+
+        let path = keys
+            .as_deref()
+            .map_or_else(|| err.path().to_string(), to_path);
+
+        // Serde's messages already include the value for mismatched types
+        // and unknown variants, but not the custom ones of our types.
+        let msg = match keys.as_deref().map(|keys| get(&preferences, keys)) {
+            Some(Ok(Some(value))) if !mentions_value(&msg) => {
+                format!("{msg}, got `{}`", display(&value))
+            },
+            _ => msg,
+        };
+
+        errors.push(SettingsError { path, msg });
+
+        let removed = match keys {
+            Some(keys) => remove(&preferences, &keys)?,
+            None => false,
+        };
+
+        // If the option can't be removed deserializing again would fail in
+        // the same way.
+        if !removed || errors.len() == MAX_ERRORS {
+            break None;
+        }
+    };
+
+    // The floats without their own `winblend` use `ui.transparency`.
+    let settings = settings.map(|mut settings| {
+        settings.ui.inherit_transparency();
+        settings
+    });
+
+    // The keyword pattern is a Vim regex, which can only be checked by
+    // compiling it.
+    if let Some(settings) = &settings {
+        if let Some(pattern) = &settings.completion.keyword_pattern {
+            if KeywordPattern::new(lua, Some(pattern)).is_err() {
+                errors.push(SettingsError {
+                    path: "completion.keyword_pattern".into(),
+                    msg: format!("invalid Vim regex `{pattern}`"),
+                });
+            }
+        }
+    }
+
+    Ok(match (settings, errors.is_empty()) {
+        (Some(settings), true) => Ok(settings),
+        _ => Err(errors),
+    })
+}
+
+/// A key of a Lua table.
+#[derive(Debug, Clone, PartialEq)]
+enum Key {
+    Field(String),
+    Index(usize),
+}
+
+/// Whether a serde error message contains the value that caused it.
+fn mentions_value(msg: &str) -> bool {
+    [
+        "invalid type",
+        "invalid value",
+        "unknown variant",
+        "unknown field",
+    ]
+    .iter()
+    .any(|prefix| msg.starts_with(prefix))
+}
+
+/// Returns the name of the field in an "unknown field" error message.
+fn unknown_field(msg: &str) -> Option<&str> {
+    let rest = msg.strip_prefix("unknown field `")?;
+    rest.find('`').map(|end| &rest[..end])
+}
+
+fn to_path(keys: &[Key]) -> String {
+    let mut path = String::new();
+    for key in keys {
+        match key {
+            Key::Field(field) => {
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(field);
+            },
+            Key::Index(index) => path.push_str(&format!("[{index}]")),
+        }
+    }
+    path
+}
+
+/// Removes the option at the end of the `keys` from the table, returning
+/// whether there was one. Removing an item from a list would shift the
+/// following ones, so the whole list is removed instead.
+fn remove(table: &LuaTable, keys: &[Key]) -> LuaResult<bool> {
+    let keys = match keys.iter().rposition(|key| matches!(key, Key::Field(_)))
+    {
+        Some(last_field) => &keys[..=last_field],
+        None => return Ok(false),
+    };
+
+    let (last, parents) = keys.split_last().expect("there's at least a field");
+
+    let mut table = table.clone();
+    for key in parents {
+        table = match key.get(&table)? {
+            LuaValue::Table(t) => t,
+            _ => return Ok(false),
+        };
+    }
+
+    if let LuaValue::Nil = last.get(&table)? {
+        return Ok(false);
+    }
+
+    if let Key::Field(field) = last {
+        table.set(field.as_str(), LuaValue::Nil)?;
+    }
+
+    Ok(true)
+}
+
+/// Returns the value at the end of the `keys`, if there's one.
+fn get<'lua>(
+    table: &LuaTable<'lua>,
+    keys: &[Key],
+) -> LuaResult<Option<LuaValue<'lua>>> {
+    let mut value = LuaValue::Table(table.clone());
+    for key in keys {
+        value = match value {
+            LuaValue::Table(t) => key.get(&t)?,
+            _ => return Ok(None),
+        };
+    }
+    Ok(match value {
+        LuaValue::Nil => None,
+        value => Some(value),
+    })
+}
+
+impl Key {
+    fn get<'lua>(&self, table: &LuaTable<'lua>) -> LuaResult<LuaValue<'lua>> {
+        match self {
+            Key::Field(field) => table.raw_get(field.as_str()),
+            Key::Index(index) => table.raw_get(*index),
+        }
+    }
+}
+
+fn deep_copy<'lua>(
+    lua: &'lua Lua,
+    table: &LuaTable<'lua>,
+) -> LuaResult<LuaTable<'lua>> {
+    let copy = lua.create_table()?;
+    for pair in table.clone().pairs::<LuaValue, LuaValue>() {
+        let (key, value) = pair?;
+        match value {
+            LuaValue::Table(t) => copy.set(key, deep_copy(lua, &t)?)?,
+            value => copy.set(key, value)?,
+        }
+    }
+    Ok(copy)
+}
+
+/// Formats a Lua value for an error message.
+fn display(value: &LuaValue) -> String {
+    match value {
+        LuaValue::Boolean(b) => b.to_string(),
+        LuaValue::Integer(n) => n.to_string(),
+        LuaValue::Number(n) => n.to_string(),
+        LuaValue::String(s) => format!("{:?}", s.to_string_lossy()),
+        other => other.type_name().to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mentions_value, to_path, unknown_field, Key};
+
+    #[test]
+    fn unknown_fields() {
+        assert_eq!(
+            Some("foo"),
+            unknown_field("unknown field `foo`, expected `bar`")
+        );
+        assert_eq!(None, unknown_field("invalid type: string \"a\""));
+    }
+
+    #[test]
+    fn messages_with_values() {
+        assert!(mentions_value("invalid type: string \"a\", expected a map"));
+        assert!(!mentions_value("expected a boolean or a function"));
+    }
+
+    #[test]
+    fn paths() {
+        assert_eq!(
+            "ui.menu.border.style[2]",
+            to_path(&[
+                Key::Field("ui".into()),
+                Key::Field("menu".into()),
+                Key::Field("border".into()),
+                Key::Field("style".into()),
+                Key::Index(2),
+            ])
+        );
+    }
+}
@@ -0,0 +1,5 @@
+mod session;
+mod snippet;
+
+pub use session::SnippetSession;
+pub use snippet::Snippet;
@@ -0,0 +1,199 @@
+use mlua::prelude::{Lua, LuaFunction, LuaResult};
+use neovim::{Api, Neovim};
+
+use super::Snippet;
+
+/// Keeps track of the tabstops of the last expanded snippet.
+#[derive(Debug)]
+pub struct SnippetSession {
+    /// The namespace id of the extmarks used to track the tabstops.
+    nsid: u32,
+
+    /// The buffer the snippet was expanded in, or `None` if there isn't an
+    /// active snippet.
+    bufnr: Option<u32>,
+
+    /// The number of tabstops of the active snippet. The `i`-th tabstop is
+    /// tracked by the extmark with id `i + 1`.
+    tabstops: usize,
+
+    /// The index of the tabstop the cursor is currently at.
+    current: usize,
+}
+
+impl SnippetSession {
+    pub fn new(api: &Api) -> LuaResult<Self> {
+        Ok(SnippetSession {
+            nsid: api.create_namespace("compleet_snippet_tabstops")?,
+            bufnr: None,
+            tabstops: 0,
+            current: 0,
+        })
+    }
+}
+
+impl SnippetSession {
+    pub fn is_active(&self) -> bool { self.bufnr.is_some() }
+
+    /// Starts a new session for `snippet`, replacing the `start_col..end_col`
+    /// byte range of the `row`-th line of the current buffer with the
+    /// snippet's text. The lines of a multiline snippet after the first one
+    /// are prefixed by `indent`.
+    ///
+    /// Returns a function that has to be scheduled to actually insert the
+    /// snippet and move the cursor to its first tabstop.
+    pub fn expand<'lua>(
+        &mut self,
+        lua: &'lua Lua,
+        api: &Api,
+        snippet: &Snippet,
+        row: u32,
+        (start_col, end_col): (u32, u32),
+        indent: &str,
+    ) -> LuaResult<LuaFunction<'lua>> {
+        self.stop(api)?;
+
+        let lines = snippet
+            .text
+            .split('\n')
+            .enumerate()
+            .map(|(i, line)| match i {
+                0 => line.to_owned(),
+                _ => format!("{indent}{line}"),
+            })
+            .collect::<Vec<String>>();
+
+        let position = |offset| {
+            to_position(&snippet.text, offset, (row, start_col), indent.len())
+        };
+
+        let tabstops = snippet
+            .tabstops
+            .iter()
+            .map(|t| (position(t.range.start), position(t.range.end)))
+            .collect::<Vec<_>>();
+
+        let first = tabstops[0].1;
+
+        // If there's only the final tabstop there's nothing to jump to.
+        if tabstops.len() > 1 {
+            self.bufnr = Some(api.get_current_buf()?);
+            self.tabstops = tabstops.len();
+            self.current = 0;
+        }
+
+        let nsid = self.nsid;
+        let track_tabstops = self.is_active();
+
+        lua.create_function(move |lua, ()| {
+            let api = Neovim::new(lua)?.api;
+
+            api.buf_set_text(0, row, start_col, row, end_col, &lines)?;
+
+            if track_tabstops {
+                for (i, ((row, col), (end_row, end_col))) in
+                    tabstops.iter().enumerate()
+                {
+                    // Text typed at either end of a tabstop becomes part of
+                    // it.
+                    let opts = lua.create_table_with_capacity(0, 5)?;
+                    opts.set("id", i + 1)?;
+                    opts.set("end_row", *end_row)?;
+                    opts.set("end_col", *end_col)?;
+                    opts.set("right_gravity", false)?;
+                    opts.set("end_right_gravity", true)?;
+                    api.buf_set_extmark(0, nsid, *row, *col, opts)?;
+                }
+            }
+
+            api.win_set_cursor(0, first.0 + 1, first.1)
+        })
+    }
+
+    /// Moves the cursor to either the next or the previous tabstop based on
+    /// the value of `step`, ending the session once the final tabstop is
+    /// reached.
+    pub fn jump(&mut self, lua: &Lua, api: &Api, step: i8) -> LuaResult<()> {
+        let bufnr = match self.bufnr {
+            Some(bufnr) if bufnr == api.get_current_buf()? => bufnr,
+            _ => return self.stop(api),
+        };
+
+        let index = match step {
+            1 => self.current + 1,
+            -1 if self.current == 0 => return Ok(()),
+            -1 => self.current - 1,
+            _ => unreachable!(),
+        };
+
+        let opts = lua.create_table_from([("details", true)])?;
+
+        let (row, col) = match api.buf_get_extmark_by_id(
+            bufnr,
+            self.nsid,
+            index as u32 + 1,
+            opts,
+        )? {
+            Some((start, end)) => end.unwrap_or(start),
+            None => return self.stop(api),
+        };
+
+        api.win_set_cursor(0, row + 1, col)?;
+
+        self.current = index;
+
+        if index == self.tabstops - 1 {
+            self.stop(api)?;
+        }
+
+        Ok(())
+    }
+
+    /// Ends the current session, if there is one.
+    pub fn stop(&mut self, api: &Api) -> LuaResult<()> {
+        if let Some(bufnr) = self.bufnr.take() {
+            if api.buf_is_valid(bufnr)? {
+                api.buf_clear_namespace(bufnr, self.nsid as i32, 0, -1)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Converts a byte offset into `text` into a `(row, col)` position in the
+/// buffer, where `text` was inserted at `start` and all its lines after the
+/// first one were prefixed by `indent` bytes of indentation.
+fn to_position(
+    text: &str,
+    offset: usize,
+    start: (u32, u32),
+    indent: usize,
+) -> (u32, u32) {
+    let before = &text[..offset];
+
+    match before.rfind('\n') {
+        Some(newline) => (
+            start.0 + before.matches('\n').count() as u32,
+            (indent + offset - newline - 1) as u32,
+        ),
+        None => (start.0, start.1 + offset as u32),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_position;
+
+    #[test]
+    fn position_on_first_line() {
+        assert_eq!((3, 7), to_position("foo(bar)", 3, (3, 4), 2));
+    }
+
+    #[test]
+    fn position_on_following_lines() {
+        let text = "if {\n\tfoo\n}";
+        assert_eq!((2, 2), to_position(text, 5, (1, 8), 2));
+        assert_eq!((2, 5), to_position(text, 8, (1, 8), 2));
+        assert_eq!((3, 2), to_position(text, 10, (1, 8), 2));
+    }
+}
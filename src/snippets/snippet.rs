@@ -0,0 +1,363 @@
+use std::ops::Range;
+
+/// A snippet written using the LSP snippet syntax, see
+/// https://microsoft.github.io/language-server-protocol/specifications/specification-current/#snippet_syntax.
+#[derive(Debug, Default, PartialEq)]
+pub struct Snippet {
+    /// The text of the snippet, with every placeholder replaced by its
+    /// default value.
+    pub text: String,
+
+    /// The tabstops of the snippet in the order they should be jumped to,
+    /// i.e. sorted by index with the final `$0` tabstop last. There's always
+    /// at least one tabstop.
+    pub tabstops: Vec<Tabstop>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Tabstop {
+    /// The number of the tabstop, e.g. `2` for both `$2` and `${2:foo}`.
+    pub index: u32,
+
+    /// The range of bytes of the snippet's text spanned by the tabstop's
+    /// placeholder. It's empty if the tabstop doesn't have one.
+    pub range: Range<usize>,
+}
+
+impl Snippet {
+    /// Parses a snippet body. Parsing never fails: anything that isn't valid
+    /// snippet syntax is treated as plain text.
+    pub fn parse(body: &str) -> Self {
+        let mut parser = Parser {
+            chars: body.chars().collect(),
+            pos: 0,
+            text: String::with_capacity(body.len()),
+            tabstops: Vec::new(),
+        };
+
+        parser.parse_any(false);
+
+        let Parser {
+            text, mut tabstops, ..
+        } = parser;
+
+        // Jump to the tabstops in increasing order, leaving `$0` for last.
+        tabstops.sort_by_key(|t| (t.index == 0, t.index));
+
+        // If the same tabstop appears more than once we only jump to the first
+        // occurrence that has a placeholder, or to the first one if none of
+        // them have it.
+        let mut dedup = Vec::<Tabstop>::with_capacity(tabstops.len());
+        for tabstop in tabstops {
+            match dedup.last_mut() {
+                Some(last) if last.index == tabstop.index => {
+                    if last.range.is_empty() {
+                        *last = tabstop;
+                    }
+                },
+                _ => dedup.push(tabstop),
+            }
+        }
+
+        // The cursor should end up at the end of the snippet if it doesn't
+        // specify a final tabstop.
+        if dedup.last().map(|t| t.index) != Some(0) {
+            dedup.push(Tabstop {
+                index: 0,
+                range: text.len()..text.len(),
+            });
+        }
+
+        Snippet {
+            text,
+            tabstops: dedup,
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    text: String,
+    tabstops: Vec<Tabstop>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> { self.chars.get(self.pos).copied() }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += c.is_some() as usize;
+        c
+    }
+
+    /// Parses text, tabstops, placeholders and variables until the end of
+    /// the input or, if `inside_braces` is set, until an unescaped `}`.
+    /// Returns whether the closing brace was found.
+    fn parse_any(&mut self, inside_braces: bool) -> bool {
+        while let Some(c) = self.next() {
+            match c {
+                '\\' => match self.peek() {
+                    Some(e @ ('$' | '}' | '\\')) => {
+                        self.pos += 1;
+                        self.text.push(e);
+                    },
+                    _ => self.text.push('\\'),
+                },
+
+                '}' if inside_braces => return true,
+
+                '$' => {
+                    if !self.parse_dollar() {
+                        self.text.push('$');
+                    }
+                },
+
+                _ => self.text.push(c),
+            }
+        }
+
+        false
+    }
+
+    /// Parses whatever follows a `$`. If that's not valid snippet syntax the
+    /// parser is reset to right after the `$` and `false` is returned.
+    fn parse_dollar(&mut self) -> bool {
+        let (pos, text_len, tabstops_len) =
+            (self.pos, self.text.len(), self.tabstops.len());
+
+        let is_valid = match self.peek() {
+            Some('{') => {
+                self.pos += 1;
+                self.parse_braced()
+            },
+
+            Some(c) if c.is_ascii_digit() => {
+                let index = self.parse_int();
+                self.push_tabstop(index, self.text.len());
+                true
+            },
+
+            Some(c) if c == '_' || c.is_ascii_alphabetic() => {
+                let name = self.parse_var_name();
+                self.text.push_str(&name);
+                true
+            },
+
+            _ => false,
+        };
+
+        if !is_valid {
+            self.pos = pos;
+            self.text.truncate(text_len);
+            self.tabstops.truncate(tabstops_len);
+        }
+
+        is_valid
+    }
+
+    /// Parses whatever follows a `${`.
+    fn parse_braced(&mut self) -> bool {
+        match self.peek() {
+            Some(c) if c.is_ascii_digit() => {
+                let index = self.parse_int();
+                let start = self.text.len();
+
+                match self.next() {
+                    // `${1}`
+                    Some('}') => {},
+
+                    // `${1:placeholder}`
+                    Some(':') => {
+                        if !self.parse_any(true) {
+                            return false;
+                        }
+                    },
+
+                    // `${1|one,two,three|}`
+                    Some('|') => match self.parse_choice() {
+                        Some(first) => self.text.push_str(&first),
+                        None => return false,
+                    },
+
+                    _ => return false,
+                }
+
+                self.push_tabstop(index, start);
+                true
+            },
+
+            Some(c) if c == '_' || c.is_ascii_alphabetic() => {
+                let name = self.parse_var_name();
+
+                // We don't resolve any variable, so they're replaced by their
+                // default value if they have one or by their name otherwise.
+                match self.next() {
+                    // `${VAR}`
+                    Some('}') => {
+                        self.text.push_str(&name);
+                        true
+                    },
+
+                    // `${VAR:default}`
+                    Some(':') => self.parse_any(true),
+
+                    // `${VAR/regex/format/options}`
+                    Some('/') => {
+                        self.text.push_str(&name);
+                        self.skip_transform()
+                    },
+
+                    _ => false,
+                }
+            },
+
+            _ => false,
+        }
+    }
+
+    /// Parses the options of a choice, returning the first one.
+    fn parse_choice(&mut self) -> Option<String> {
+        let mut options = vec![String::new()];
+
+        while let Some(c) = self.next() {
+            match c {
+                '\\' => match self.peek() {
+                    Some(e @ ('$' | '}' | '\\' | ',' | '|')) => {
+                        self.pos += 1;
+                        options.last_mut().unwrap().push(e);
+                    },
+                    _ => options.last_mut().unwrap().push('\\'),
+                },
+
+                ',' => options.push(String::new()),
+
+                '|' => {
+                    return (self.next() == Some('}'))
+                        .then(|| options.swap_remove(0));
+                },
+
+                _ => options.last_mut().unwrap().push(c),
+            }
+        }
+
+        None
+    }
+
+    /// Skips the `regex/format/options}` part of a variable transform.
+    fn skip_transform(&mut self) -> bool {
+        // The format string can contain braces of its own, e.g.
+        // `${1:/upcase}`.
+        let (mut slashes, mut depth) = (0, 0);
+
+        while let Some(c) = self.next() {
+            match c {
+                '\\' => self.pos += 1,
+                '/' if depth == 0 => slashes += 1,
+                '{' => depth += 1,
+                '}' if depth > 0 => depth -= 1,
+                '}' if slashes == 2 => return true,
+                _ => {},
+            }
+        }
+
+        false
+    }
+
+    fn parse_int(&mut self) -> u32 {
+        let mut int = 0u32;
+        while let Some(digit) = self.peek().and_then(|c| c.to_digit(10)) {
+            int = int.saturating_mul(10).saturating_add(digit);
+            self.pos += 1;
+        }
+        int
+    }
+
+    fn parse_var_name(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(c) = self
+            .peek()
+            .filter(|&c| c == '_' || c.is_ascii_alphanumeric())
+        {
+            name.push(c);
+            self.pos += 1;
+        }
+        name
+    }
+
+    fn push_tabstop(&mut self, index: u32, start: usize) {
+        self.tabstops.push(Tabstop {
+            index,
+            range: start..self.text.len(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Snippet, Tabstop};
+
+    fn tabstops(snippet: &Snippet) -> Vec<(u32, &str)> {
+        snippet
+            .tabstops
+            .iter()
+            .map(|Tabstop { index, range }| {
+                (*index, &snippet.text[range.clone()])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn plain_text() {
+        let snippet = Snippet::parse("foo");
+        assert_eq!("foo", snippet.text);
+        assert_eq!(
+            vec![Tabstop {
+                index: 0,
+                range: 3..3
+            }],
+            snippet.tabstops
+        );
+    }
+
+    #[test]
+    fn tabstops_and_placeholders() {
+        let snippet = Snippet::parse("foo(${1:bar}, $2)$0");
+        assert_eq!("foo(bar, )", snippet.text);
+        assert_eq!(vec![(1, "bar"), (2, ""), (0, "")], tabstops(&snippet));
+        assert_eq!(9..9, snippet.tabstops[1].range);
+        assert_eq!(10..10, snippet.tabstops[2].range);
+    }
+
+    #[test]
+    fn tabstops_are_sorted_and_deduplicated() {
+        let snippet = Snippet::parse("${2:b} $1 ${1:a} ${0:end}");
+        assert_eq!("b  a end", snippet.text);
+        assert_eq!(vec![(1, "a"), (2, "b"), (0, "end")], tabstops(&snippet));
+    }
+
+    #[test]
+    fn nested_placeholders() {
+        let snippet = Snippet::parse("${1:foo ${2:bar}}");
+        assert_eq!("foo bar", snippet.text);
+        assert_eq!(
+            vec![(1, "foo bar"), (2, "bar"), (0, "")],
+            tabstops(&snippet)
+        );
+    }
+
+    #[test]
+    fn choices_and_variables() {
+        let snippet = Snippet::parse("${1|one,two|} $TM_FOO ${BAR:baz}");
+        assert_eq!("one TM_FOO baz", snippet.text);
+        assert_eq!(vec![(1, "one"), (0, "")], tabstops(&snippet));
+    }
+
+    #[test]
+    fn escapes_and_invalid_syntax() {
+        assert_eq!("$1 }", Snippet::parse(r"\$1 \}").text);
+        assert_eq!("${1:foo", Snippet::parse("${1:foo").text);
+        assert_eq!("a $ b", Snippet::parse("a $ b").text);
+        assert_eq!("FOO", Snippet::parse("${FOO/(.*)/${1:/upcase}/}").text);
+    }
+}
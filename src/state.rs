@@ -1,11 +1,27 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use mlua::prelude::{LuaRegistryKey, LuaResult};
+use mlua::prelude::{Lua, LuaRegistryKey, LuaResult};
 use neovim::Api;
 
-use crate::completion::{CompletionItem, CompletionSource, Cursor};
+use crate::cmdline::Cmdline;
+use crate::completion::{
+    CompletionItem,
+    CompletionSource,
+    Cursor,
+    History,
+    KeywordPattern,
+    MatchedRange,
+    Pipeline,
+    Preview,
+};
+use crate::inline::Inline;
+use crate::logging;
+use crate::phase::{Phase, Transition};
+use crate::settings::completion::MaxBufferSize;
 use crate::settings::Settings;
+use crate::snippets::SnippetSession;
+use crate::terminal::Terminal;
 use crate::ui::Ui;
 
 pub type Sources = Vec<Arc<dyn CompletionSource>>;
@@ -23,33 +39,80 @@ pub struct State {
     /// that buffer.
     pub buffer_local_autocmds: HashMap<u32, Vec<u32>>,
 
+    /// The completion states of the attached buffers and of the current
+    /// one, keyed by buffer number.
+    pub buffers: Buffers,
+
     /// A vector of buffers numbers to be detached on the next call to
     /// `completion::on_bytes`.
     pub buffers_to_be_detached: Vec<u32>,
 
-    /// The currently available completion items.
-    pub completions: Vec<CompletionItem>,
+    /// Holds state about the completions of the command line.
+    pub cmdline: Cmdline,
 
-    /// Holds state about the cursor position in the current buffer.
-    pub cursor: Cursor,
+    /// The buffers completion was stopped in after an error, which are also
+    /// in `stopped_buffers` until `:Compleet restart`.
+    pub crashed_buffers: Vec<u32>,
 
     /// Whether the `require('compleet').setup` function has been called yet.
     pub did_setup: bool,
 
+    /// Holds state about the inline suggestions shown as ghost text.
+    pub inline: Inline,
+
+    /// Keeps track of the inserted completions, used to rank the recently
+    /// used ones higher.
+    pub history: History,
+
+    /// The compiled `completion.keyword_pattern`.
+    pub keyword_pattern: KeywordPattern,
+
+    /// A registry key pointing to the Lua function applying the `Inserted`
+    /// transition, called once a confirmed completion has been inserted.
+    pub on_inserted: Option<LuaRegistryKey>,
+
+    /// The phase of the completions, only changed by `transition`.
+    phase: Phase,
+
+    /// Runs the completion sources, some of them on background threads.
+    pub pipeline: Pipeline,
+
+    /// Holds state about the completion being previewed in the buffer.
+    pub preview: Preview,
+
     /// Used to store the current configuration.
     pub settings: Settings,
 
+    /// Holds state about the tabstops of the last expanded snippet.
+    pub snippet: SnippetSession,
+
+    /// A hashmap where the keys are the numbers of the buffers bigger than
+    /// the `completion.max_buffer_size` option and the values are the names
+    /// of the expensive sources that weren't attached to them.
+    pub skipped_sources: HashMap<u32, Vec<&'static str>>,
+
     /// A hashmap where the keys are the numbers of the currently attached
     /// buffers and the values are the completion sources enabled in that
     /// buffer.
     pub sources: HashMap<u32, Sources>,
 
+    /// The buffers completion was stopped in with `:Compleet stop`, which
+    /// aren't attached to again when they're entered.
+    pub stopped_buffers: Vec<u32>,
+
+    /// Holds state about the completions of terminal buffers.
+    pub terminal: Terminal,
+
     /// A registry key pointing to the `try_buf_attach` Lua function used to
     /// attach to new buffers.
     pub try_buf_attach: Option<LuaRegistryKey>,
 
     /// Holds state about the currently displayed UI.
     pub ui: Ui,
+
+    /// The sources registered from Lua with
+    /// `require("compleet").register_source`.
+    pub user_sources: Sources,
 }
 
 impl State {
@@ -58,14 +121,318 @@ impl State {
             attached_buffers: Vec::new(),
             augroup_id: None,
             buffer_local_autocmds: HashMap::new(),
+            buffers: Buffers::default(),
             buffers_to_be_detached: Vec::new(),
-            completions: Vec::new(),
-            cursor: Cursor::new(),
+            cmdline: Cmdline::new(api)?,
+            crashed_buffers: Vec::new(),
             did_setup: false,
+            history: History::default(),
+            inline: Inline::new(api)?,
+            keyword_pattern: KeywordPattern::default(),
+            on_inserted: None,
+            phase: Phase::default(),
+            pipeline: Pipeline::default(),
+            preview: Preview::default(),
             settings: Settings::default(),
+            skipped_sources: HashMap::new(),
+            snippet: SnippetSession::new(api)?,
             sources: HashMap::new(),
+            stopped_buffers: Vec::new(),
+            terminal: Terminal::new(api)?,
             try_buf_attach: None,
             ui: Ui::new(api)?,
+            user_sources: Vec::new(),
         })
     }
+
+    /// The phase of the completions of the current buffer.
+    pub fn phase(&self) -> Phase { self.phase }
+
+    /// Moves the completions to the phase the `transition` leads to,
+    /// returning whether it's allowed from the current one. Transitions that
+    /// aren't allowed leave the phase as it is.
+    pub fn transition(&mut self, transition: Transition) -> bool {
+        match self.phase.next(transition) {
+            Some(phase) => {
+                self.phase = phase;
+                true
+            },
+            None => {
+                logging::debug(
+                    "phase",
+                    format!("ignored {transition:?} in {:?}", self.phase),
+                );
+                false
+            },
+        }
+    }
+
+    /// Discards the completions if the UI was shown in another window since
+    /// they were computed, since they're for the cursor of that window.
+    pub fn follow_current_window(
+        &mut self,
+        lua: &Lua,
+        api: &Api,
+    ) -> LuaResult<()> {
+        if self.ui.follow_current_window(lua, api)? {
+            self.buffers.current_mut().clear_completions();
+            self.pipeline.stop_loading();
+            self.transition(Transition::Close);
+        }
+        Ok(())
+    }
+
+    /// Updates the UI with the completions of the current buffer, then
+    /// moves to the phase of what it displays.
+    pub fn update_ui(&mut self, lua: &Lua, api: &Api) -> LuaResult<()> {
+        self.follow_current_window(lua, api)?;
+        let is_loading = self.pipeline.is_loading();
+        self.ui.update(
+            lua,
+            api,
+            &self.buffers.current().completions,
+            &self.buffers.current().cursor,
+            &self.settings,
+            is_loading,
+        )?;
+        self.transition(self.ui.displayed(is_loading));
+        Ok(())
+    }
+
+    /// Closes the UI, going back to `Phase::Idle`.
+    pub fn close_ui(&mut self, lua: &Lua, api: &Api) -> LuaResult<()> {
+        self.ui.cleanup(lua, api)?;
+        self.transition(Transition::Close);
+        Ok(())
+    }
+
+    /// The sources enabled in the config followed by the ones registered
+    /// from Lua.
+    pub fn all_sources(
+        &self,
+    ) -> impl Iterator<Item = &Arc<dyn CompletionSource>> {
+        self.settings.sources.iter().chain(&self.user_sources)
+    }
+
+    /// Makes the state of the buffer `bufnr` the current one, keeping the
+    /// state of the previous buffer until it's entered again if it's
+    /// attached. Called when a buffer is entered, so that completions
+    /// computed in a buffer are never shown or inserted in another one. The
+    /// UI is closed when entering a different buffer, which also resets its
+    /// selected and hinted completions.
+    pub fn enter_buffer(
+        &mut self,
+        lua: &Lua,
+        api: &Api,
+        bufnr: u32,
+    ) -> LuaResult<()> {
+        if self.buffers.enter(bufnr, &self.attached_buffers) {
+            self.close_ui(lua, api)?;
+            self.pipeline.stop_loading();
+        }
+        Ok(())
+    }
+
+    /// Detaches from a buffer, which happens the next time
+    /// `completion::on_bytes` is called for it, and deletes its buffer-local
+    /// autocommands.
+    pub fn detach(&mut self, api: &Api, bufnr: u32) -> LuaResult<()> {
+        self.attached_buffers.retain(|&b| b != bufnr);
+        self.buffers.remove(bufnr);
+        self.buffers_to_be_detached.push(bufnr);
+
+        for autocmd_id in self
+            .buffer_local_autocmds
+            .remove(&bufnr)
+            .unwrap_or_default()
+        {
+            api.del_autocmd(autocmd_id)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the sources that want to attach to a buffer, or none if its
+    /// filetype or buftype is excluded by the `completion.filetypes` and
+    /// `completion.buftypes` options. The expensive ones are skipped if the
+    /// buffer exceeds `completion.max_buffer_size`, and their names are
+    /// stored in `skipped_sources`.
+    pub fn attachable_sources(
+        &mut self,
+        lua: &Lua,
+        api: &Api,
+        bufnr: u32,
+    ) -> LuaResult<Sources> {
+        let completion = &self.settings.completion;
+        if !completion
+            .filetypes
+            .allows(&api.buf_get_option::<String>(bufnr, "filetype")?)
+            || !completion
+                .buftypes
+                .allows(&api.buf_get_option::<String>(bufnr, "buftype")?)
+        {
+            self.skipped_sources.remove(&bufnr);
+            return Ok(Vec::new());
+        }
+
+        let max_size = self.settings.completion.max_buffer_size;
+        let is_large = max_size != MaxBufferSize::default() && {
+            let lines = api.buf_line_count(bufnr)?;
+            max_size.is_exceeded(lines, api.buf_get_offset(bufnr, lines)?)
+        };
+
+        let (sources, skipped): (Sources, Sources) = self
+            .all_sources()
+            .filter(|&s| s.attach(lua, api, bufnr).unwrap_or(false))
+            .cloned()
+            .partition(|s| !(is_large && s.is_expensive()));
+
+        match skipped.is_empty() {
+            true => self.skipped_sources.remove(&bufnr),
+            false => self
+                .skipped_sources
+                .insert(bufnr, skipped.iter().map(|s| s.name()).collect()),
+        };
+
+        Ok(sources)
+    }
+}
+
+/// The completion states of the buffers, keyed by buffer number. There's
+/// always a state for the current buffer, even if it's not attached.
+#[derive(Debug)]
+pub struct Buffers {
+    /// The number of the current buffer, or 0 if no buffer has been entered
+    /// yet.
+    current: u32,
+
+    states: HashMap<u32, BufferState>,
+}
+
+impl Default for Buffers {
+    fn default() -> Self {
+        Buffers {
+            current: 0,
+            states: HashMap::from([(0, BufferState::default())]),
+        }
+    }
+}
+
+impl Buffers {
+    /// The number of the current buffer.
+    pub fn current_nr(&self) -> u32 { self.current }
+
+    /// The completion state of the current buffer.
+    pub fn current(&self) -> &BufferState {
+        self.states
+            .get(&self.current)
+            .expect("The current buffer always has a state")
+    }
+
+    /// The completion state of the current buffer.
+    pub fn current_mut(&mut self) -> &mut BufferState {
+        self.states.entry(self.current).or_default()
+    }
+
+    /// Makes `bufnr` the current buffer, forgetting the state of the previous
+    /// one unless it's one of the `attached` buffers. Returns whether it's a
+    /// different buffer.
+    fn enter(&mut self, bufnr: u32, attached: &[u32]) -> bool {
+        let previous = self.current;
+        if previous == bufnr {
+            return false;
+        }
+        if !attached.contains(&previous) {
+            self.states.remove(&previous);
+        }
+        self.current = bufnr;
+        self.states.entry(bufnr).or_default();
+        true
+    }
+
+    /// Forgets the completion state of the buffer `bufnr`, which is reset
+    /// instead if it's the current one.
+    pub fn remove(&mut self, bufnr: u32) {
+        match bufnr == self.current {
+            true => *self.current_mut() = BufferState::default(),
+            false => {
+                self.states.remove(&bufnr);
+            },
+        }
+    }
+
+    /// Forgets the completion states of all the buffers but the current one,
+    /// which is reset.
+    pub fn clear(&mut self) {
+        let current = self.current;
+        self.states.retain(|&bufnr, _| bufnr == current);
+        *self.current_mut() = BufferState::default();
+    }
+}
+
+/// The completion state of a single buffer.
+#[derive(Debug, Default)]
+pub struct BufferState {
+    /// The currently available completion items.
+    pub completions: Vec<CompletionItem>,
+
+    /// Holds state about the cursor position in the buffer.
+    pub cursor: Cursor,
+
+    /// The range of the buffer covering the text matched by the current
+    /// completions, or `None` if there are no completions. Set together with
+    /// them by `set_completions`.
+    pub matched_range: Option<MatchedRange>,
+}
+
+impl BufferState {
+    /// Replaces the current completions with the ones computed at the
+    /// `cursor`, updating their matched range.
+    pub fn set_completions(&mut self, completions: Vec<CompletionItem>) {
+        self.matched_range =
+            MatchedRange::covering(&completions, &self.cursor);
+        self.completions = completions;
+    }
+
+    /// Forgets the current completions and their matched range.
+    pub fn clear_completions(&mut self) {
+        self.completions.clear();
+        self.matched_range = None;
+    }
+
+    /// Whether the text matched by the current completions is still in the
+    /// buffer, i.e. the `line` the cursor is on hasn't been edited inside
+    /// their matched range since they were computed. Confirming a completion
+    /// whose range doesn't hold anymore would replace the wrong text.
+    pub fn is_matched_range_intact(&self, row: u32, line: &str) -> bool {
+        self.matched_range
+            .as_ref()
+            .is_some_and(|range| range.row == row && range.is_intact(line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Buffers;
+
+    #[test]
+    fn buffers_are_keyed_by_number() {
+        let mut buffers = Buffers::default();
+        assert!(buffers.enter(1, &[1]));
+        buffers.current_mut().cursor.row = 10;
+
+        assert!(buffers.enter(2, &[1]));
+        assert_eq!(0, buffers.current().cursor.row);
+        buffers.current_mut().cursor.row = 20;
+
+        // The state of buffer 2 is dropped since it's not attached.
+        assert!(buffers.enter(1, &[1]));
+        assert!(!buffers.enter(1, &[1]));
+        assert_eq!(10, buffers.current().cursor.row);
+        assert!(!buffers.states.contains_key(&2));
+
+        buffers.remove(1);
+        assert_eq!(0, buffers.current().cursor.row);
+        assert_eq!(1, buffers.current_nr());
+    }
 }
@@ -0,0 +1,124 @@
+use std::cmp::Reverse;
+
+use mlua::prelude::LuaResult;
+use neovim::Api;
+
+use crate::completion::{is_keyword_char, matcher, CompletionItem};
+
+/// Words shorter than this many characters aren't worth completing.
+const MIN_WORD_LEN: usize = 3;
+
+/// Returns the completions for the `prefix` typed before the terminal cursor,
+/// taken from the words in the last `scrollback` lines of the terminal
+/// buffer `bufnr`.
+pub fn complete(
+    api: &Api,
+    bufnr: u32,
+    prefix: &str,
+    scrollback: u32,
+) -> LuaResult<Vec<CompletionItem>> {
+    if prefix.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let line_count = api.buf_line_count(bufnr)?;
+    let lines = api.buf_get_lines(
+        bufnr,
+        line_count.saturating_sub(scrollback),
+        -1,
+        false,
+    )?;
+
+    let mut completions = words(&lines)
+        .into_iter()
+        .filter(|&word| word != prefix)
+        .filter_map(|word| {
+            let m = matcher::fuzzy_match(prefix, word)?;
+            Some(CompletionItem {
+                details: None,
+                format: format!(" {word}"),
+                hl_ranges: m.hl_ranges(1),
+                kind: None,
+                matched_bytes: prefix.len() as u32,
+                preselect: false,
+                score: m.score,
+                snippet: None,
+                source: "Terminal",
+                text: word.to_owned(),
+                ..Default::default()
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // The sort is stable, so words with the same score stay in order of
+    // recency.
+    completions.sort_by_key(|completion| Reverse(completion.score));
+
+    Ok(completions)
+}
+
+/// Returns the unique words in `lines`, starting from the ones in the last
+/// line since they're the most recent output of the terminal.
+fn words(lines: &[String]) -> Vec<&str> {
+    let mut words = Vec::<&str>::new();
+
+    for line in lines.iter().rev() {
+        for word in line.split(|c| !is_keyword_char(c)) {
+            if word.chars().count() >= MIN_WORD_LEN && !words.contains(&word) {
+                words.push(word);
+            }
+        }
+    }
+
+    words
+}
+
+/// Returns the bytes to send to the terminal job to replace the typed
+/// `prefix` with `text`. If the text starts with the prefix only the rest of
+/// it is sent, otherwise the prefix is deleted first.
+pub fn keys_to_send(prefix: &str, text: &str) -> String {
+    match text.strip_prefix(prefix) {
+        Some(rest) => rest.to_owned(),
+        None => {
+            let mut keys = "\x7f".repeat(prefix.chars().count());
+            keys.push_str(text);
+            keys
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{keys_to_send, words};
+
+    #[test]
+    fn most_recent_words_first() {
+        let lines = vec![
+            "$ ls".to_owned(),
+            "Cargo.toml src target".to_owned(),
+            "$ cargo build --release".to_owned(),
+        ];
+        assert_eq!(
+            vec![
+                "cargo", "build", "release", "Cargo", "toml", "src", "target"
+            ],
+            words(&lines)
+        );
+    }
+
+    #[test]
+    fn duplicates_and_short_words() {
+        let lines = vec!["git add -A".to_owned(), "$ git push".to_owned()];
+        assert_eq!(vec!["git", "push", "add"], words(&lines));
+    }
+
+    #[test]
+    fn completing_the_typed_prefix() {
+        assert_eq!("go", keys_to_send("car", "cargo"));
+    }
+
+    #[test]
+    fn replacing_a_fuzzy_match() {
+        assert_eq!("\x7f\x7fcargo", keys_to_send("cg", "cargo"));
+    }
+}
@@ -0,0 +1,6 @@
+mod candidates;
+mod setup;
+mod state;
+
+pub use setup::setup;
+pub use state::Terminal;
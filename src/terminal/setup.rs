@@ -0,0 +1,101 @@
+use std::sync::{Arc, Mutex};
+
+use mlua::prelude::{Lua, LuaResult};
+use neovim::{Api, Neovim};
+
+use crate::boundary;
+use crate::state::State;
+
+/// Creates the autocommands used to complete in terminal buffers, together
+/// with the terminal mode versions of the `<Plug>` mappings.
+pub fn setup(
+    lua: &Lua,
+    api: &Api,
+    state: &Arc<Mutex<State>>,
+) -> LuaResult<()> {
+    let _state = state.clone();
+    let on_changed = lua.create_function(move |lua, ()| {
+        boundary::guard(lua, &_state, "terminal_on_changed", |state| {
+            let api = Neovim::new(lua)?.api;
+            // `TextChangedT` is also fired when the output of the job changes
+            // while the terminal isn't in terminal mode.
+            if !state.settings.terminal.enable || api.get_mode()?.0 != "t" {
+                return Ok(());
+            }
+            state.terminal.on_changed(lua, &api, &state.settings)
+        })
+    })?;
+
+    let _state = state.clone();
+    let on_leave = lua.create_function(move |lua, ()| {
+        boundary::guard(lua, &_state, "terminal_on_leave", |state| {
+            let api = Neovim::new(lua)?.api;
+            state.terminal.close(lua, &api)
+        })
+    })?;
+
+    let _state = state.clone();
+    let select_completion = lua.create_function(move |lua, step| {
+        boundary::guard(lua, &_state, "terminal_select_completion", |state| {
+            let api = Neovim::new(lua)?.api;
+            state.terminal.select(lua, &api, step, &state.settings)
+        })
+    })?;
+
+    let _state = state.clone();
+    let confirm_completion = lua.create_function(move |lua, ()| {
+        boundary::guard(lua, &_state, "terminal_confirm_completion", |state| {
+            let api = Neovim::new(lua)?.api;
+            state.terminal.confirm(lua, &api)
+        })
+    })?;
+
+    let _state = state.clone();
+    let abort = lua.create_function(move |lua, ()| {
+        boundary::guard(lua, &_state, "terminal_abort", |state| {
+            let api = Neovim::new(lua)?.api;
+            state.terminal.abort(lua, &api)
+        })
+    })?;
+
+    let opts = lua.create_table_from([("clear", true)])?;
+    let augroup_id = api.create_augroup("CompleetTerminal", opts)?;
+
+    let opts = lua.create_table_with_capacity(0, 2)?;
+    opts.set("group", augroup_id)?;
+
+    opts.set("callback", on_changed)?;
+    api.create_autocmd(&["TextChangedT"], opts.clone())?;
+
+    opts.set("callback", on_leave)?;
+    api.create_autocmd(&["TermLeave"], opts)?;
+
+    let opts = lua.create_table_from([("silent", true)])?;
+
+    opts.set("callback", select_completion.bind(1)?)?;
+    api.set_keymap("t", "<Plug>(compleet-next-completion)", "", opts.clone())?;
+
+    opts.set("callback", select_completion.bind(-1)?)?;
+    api.set_keymap("t", "<Plug>(compleet-prev-completion)", "", opts.clone())?;
+
+    opts.set("callback", confirm_completion.clone())?;
+    api.set_keymap(
+        "t",
+        "<Plug>(compleet-insert-selected-completion)",
+        "",
+        opts.clone(),
+    )?;
+
+    opts.set("callback", confirm_completion)?;
+    api.set_keymap(
+        "t",
+        "<Plug>(compleet-confirm-completion)",
+        "",
+        opts.clone(),
+    )?;
+
+    opts.set("callback", abort)?;
+    api.set_keymap("t", "<Plug>(compleet-abort)", "", opts)?;
+
+    Ok(())
+}
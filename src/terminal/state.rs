@@ -0,0 +1,203 @@
+use mlua::prelude::{Lua, LuaResult, LuaValue};
+use neovim::Api;
+
+use super::candidates;
+use crate::completion::{CompletionItem, Cursor};
+use crate::settings::Settings;
+use crate::ui::menu::{self, positioning, CompletionMenu};
+
+/// Holds the state of the completions of terminal buffers, which are
+/// independent from the ones of the other buffers.
+#[derive(Debug)]
+pub struct Terminal {
+    /// The completions available for the word before the terminal cursor.
+    completions: Vec<CompletionItem>,
+
+    /// A completion menu shown next to the terminal cursor.
+    menu: CompletionMenu,
+
+    /// The word typed before the terminal cursor.
+    typed: String,
+
+    /// The text of the last confirmed completion, until the terminal job has
+    /// finished echoing it back. Those changes shouldn't open the menu again.
+    inserted: Option<String>,
+}
+
+impl Terminal {
+    pub fn new(api: &Api) -> LuaResult<Self> {
+        Ok(Terminal {
+            completions: Vec::new(),
+            menu: CompletionMenu::new(api, "cursor")?,
+            typed: String::new(),
+            inserted: None,
+        })
+    }
+}
+
+impl Terminal {
+    /// Closes the completion menu and forgets the completions. Executed on
+    /// every `TermLeave` event.
+    pub fn close(&mut self, lua: &Lua, api: &Api) -> LuaResult<()> {
+        self.completions.clear();
+        self.inserted = None;
+        self.menu.close(lua, api)
+    }
+
+    /// Whether a completion is currently selected.
+    pub fn is_item_selected(&self) -> bool { self.menu.is_item_selected() }
+
+    /// Whether the completion menu is visible.
+    pub fn is_menu_visible(&self) -> bool { self.menu.is_visible() }
+
+    /// Executed on every `TextChangedT` event. Recomputes the completions for
+    /// the word before the terminal cursor and updates the menu.
+    pub fn on_changed(
+        &mut self,
+        lua: &Lua,
+        api: &Api,
+        settings: &Settings,
+    ) -> LuaResult<()> {
+        // In terminal mode the cursor of the window follows the one of the
+        // terminal, so the menu can be positioned relative to it.
+        let (row, bytes) = api.win_get_cursor(0)?;
+        let cursor = Cursor {
+            bytes,
+            keyword_start: None,
+            line: api.get_current_line()?,
+            row: row - 1,
+        };
+
+        let prefix = cursor.keyword_pre();
+
+        // The change was caused by the terminal job echoing back a confirmed
+        // completion, which can arrive in more than one chunk.
+        if let Some(inserted) = &self.inserted {
+            if inserted.starts_with(prefix) {
+                if inserted == prefix {
+                    self.inserted = None;
+                }
+                return Ok(());
+            }
+            self.inserted = None;
+        }
+
+        self.completions = match prefix.chars().count()
+            >= usize::from(settings.completion.min_chars)
+        {
+            true => candidates::complete(
+                api,
+                api.get_current_buf()?,
+                prefix,
+                settings.terminal.scrollback,
+            )?,
+            false => Vec::new(),
+        };
+
+        self.typed = prefix.to_owned();
+
+        if self.completions.is_empty() {
+            return self.menu.close(lua, api);
+        }
+
+        let lines = menu::render(lua, &self.completions, &settings.ui.menu)?;
+
+        let position = match positioning::get_position(
+            api,
+            &self.completions,
+            &cursor,
+            &lines,
+            &settings.ui.menu,
+        )? {
+            Some(position) => position,
+            None => return self.menu.close(lua, api),
+        };
+
+        if self.menu.is_visible() {
+            self.menu.shift(lua, api, &position)?;
+            self.menu.select(lua, api, None)?;
+        } else if !self.menu.spawn(lua, api, &position, &settings.ui.menu)? {
+            return Ok(());
+        }
+
+        self.menu.fill(
+            lua,
+            api,
+            &self.completions,
+            &lines,
+            &settings.ui.menu.highlights,
+        )?;
+
+        if settings.ui.menu.scrollbar {
+            self.menu
+                .update_scrollbar(lua, api, self.completions.len())?;
+        }
+
+        Ok(())
+    }
+
+    /// Selects either the next (if `step` is `1`) or the previous (if `step`
+    /// is `-1`) completion. Nothing is sent to the terminal job until the
+    /// selection is confirmed.
+    pub fn select(
+        &mut self,
+        lua: &Lua,
+        api: &Api,
+        step: i8,
+        settings: &Settings,
+    ) -> LuaResult<()> {
+        if !self.menu.is_visible() {
+            return Ok(());
+        }
+
+        let new_index = self.menu.next_index(
+            self.completions.len(),
+            step,
+            settings.ui.menu.cycle,
+        );
+
+        self.menu.select(lua, api, new_index)?;
+
+        if settings.ui.menu.scrollbar {
+            self.menu
+                .update_scrollbar(lua, api, self.completions.len())?;
+        }
+
+        Ok(())
+    }
+
+    /// Closes the menu without sending anything to the terminal job.
+    pub fn abort(&mut self, lua: &Lua, api: &Api) -> LuaResult<()> {
+        self.completions.clear();
+        self.menu.close(lua, api)
+    }
+
+    /// Sends the selected completion to the terminal job, replacing the typed
+    /// word, and closes the menu.
+    pub fn confirm(&mut self, lua: &Lua, api: &Api) -> LuaResult<()> {
+        let text = match self
+            .menu
+            .selected_index
+            .and_then(|index| self.completions.get(index))
+        {
+            Some(completion) => completion.text.clone(),
+            None => return self.abort(lua, api),
+        };
+
+        let channel = api.buf_get_option::<u32>(0, "channel")?;
+        let keys = candidates::keys_to_send(&self.typed, &text);
+
+        self.abort(lua, api)?;
+        self.inserted = Some(text);
+
+        api.call_function::<_, u32>(
+            "chansend",
+            vec![
+                LuaValue::Integer(channel.into()),
+                LuaValue::String(lua.create_string(&keys)?),
+            ],
+        )?;
+
+        Ok(())
+    }
+}
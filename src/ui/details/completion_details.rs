@@ -1,7 +1,10 @@
+use std::cmp;
+
 use mlua::{prelude::LuaResult, Lua};
-use neovim::Api;
+use neovim::{Api, Neovim};
 
-use crate::settings::ui::border::Border;
+use crate::completion::Details;
+use crate::settings::ui::{border::Border, details::DetailsSettings};
 use crate::ui::WindowPosition;
 
 #[derive(Debug)]
@@ -10,6 +13,10 @@ pub struct CompletionDetails {
     /// set once on initialization and never changes.
     bufnr: u32,
 
+    /// The lines currently shown, used to scroll back to the top when they
+    /// change.
+    lines: Vec<String>,
+
     /// The handle of the floating window used to show the completion details,
     /// or `None` if the details window is not currently visible.
     winid: Option<u32>,
@@ -19,6 +26,7 @@ impl CompletionDetails {
     pub fn new(api: &Api) -> LuaResult<Self> {
         Ok(CompletionDetails {
             bufnr: api.create_buf(false, true)?,
+            lines: Vec::new(),
             winid: None,
         })
     }
@@ -37,9 +45,66 @@ impl CompletionDetails {
     /// Whether the details window is currently visible.
     pub fn is_visible(&self) -> bool { self.winid.is_some() }
 
-    /// Fills the details window's buffer with a slice of strings.
-    pub fn fill(&mut self, api: &Api, lines: &[String]) -> LuaResult<()> {
-        api.buf_set_lines(self.bufnr, 0, -1, false, lines)
+    /// Scrolls the details window by half its height, down if `step` is `1`
+    /// and up if it's `-1`, without focusing it. Returns whether the window
+    /// was visible.
+    pub fn scroll(&self, lua: &Lua, api: &Api, step: i8) -> LuaResult<bool> {
+        let winid = match self.winid {
+            Some(winid) => winid,
+            None => return Ok(false),
+        };
+
+        let height = api.win_get_height(winid)?;
+        let line_count = api.buf_line_count(self.bufnr)?;
+
+        let scroll = lua.create_function(move |lua, ()| {
+            let api = Neovim::new(lua)?.api;
+            let topline = api.call_function::<_, u32>("line", vec!["w0"])?;
+            set_topline(
+                lua,
+                &api,
+                scrolled_topline(topline, height, line_count, step),
+            )
+        })?;
+
+        api.win_call(winid, scroll)?;
+
+        Ok(true)
+    }
+
+    /// Fills the details window's buffer with the `details`, returning the
+    /// lines that were set. If the details are written in markdown and
+    /// `markdown` is set they're rendered with
+    /// `vim.lsp.util.stylize_markdown`, which also highlights the code
+    /// blocks.
+    pub fn fill(
+        &mut self,
+        lua: &Lua,
+        api: &Api,
+        details: &Details,
+        settings: &DetailsSettings,
+    ) -> LuaResult<Vec<String>> {
+        if details.is_markdown && settings.markdown {
+            let opts = lua.create_table_from([(
+                "max_width",
+                u32::from(settings.max_width),
+            )])?;
+            return Neovim::new(lua)?.lsp()?.util_stylize_markdown(
+                self.bufnr,
+                &details.lines,
+                opts,
+            );
+        }
+
+        // Clear the syntax regions left by the last rendered markdown.
+        let clear_syntax = lua.create_function(|lua, ()| {
+            Neovim::new(lua)?.api.command("syntax clear")
+        })?;
+        api.buf_call(self.bufnr, clear_syntax)?;
+
+        api.buf_set_lines(self.bufnr, 0, -1, false, &details.lines)?;
+
+        Ok(details.lines.clone())
     }
 
     /// Moves the details window to a new position relative to the completion
@@ -76,8 +141,10 @@ impl CompletionDetails {
         api: &Api,
         menu_winid: u32,
         position: &WindowPosition,
-        border: &Border,
+        settings: &DetailsSettings,
     ) -> LuaResult<()> {
+        let border = &settings.border;
+
         let opts = lua.create_table_with_capacity(0, 9)?;
         opts.set("relative", "win")?;
         opts.set("win", menu_winid)?;
@@ -101,6 +168,12 @@ impl CompletionDetails {
              None",
         )?;
         api.win_set_option(winid, "scrolloff", 0)?;
+        api.win_set_option(winid, "wrap", settings.wrap)?;
+        api.win_set_option(
+            winid,
+            "winblend",
+            settings.winblend.unwrap_or_default().0,
+        )?;
 
         self.winid = Some(winid);
 
@@ -112,23 +185,37 @@ impl CompletionDetails {
         &mut self,
         lua: &Lua,
         api: &Api,
-        maybe_lines: Option<&Vec<String>>,
-        border: &Border,
+        maybe_details: Option<&Details>,
+        settings: &DetailsSettings,
         menu_width: u32,
         menu_winid: u32,
         menu_border: &Border,
         force_redraw: bool,
     ) -> LuaResult<()> {
-        if maybe_lines.is_none() {
+        let details = match maybe_details {
+            Some(details) => details,
+            None => {
+                self.close(api)?;
+                return Ok(());
+            },
+        };
+
+        // The buffer is filled first since rendering markdown can change the
+        // size of the text.
+        let lines = self.fill(lua, api, details, settings)?;
+
+        if lines.is_empty() {
             self.close(api)?;
             return Ok(());
         }
 
-        let lines = maybe_lines.expect("Already checked `None` variant");
+        let are_new_lines = lines != self.lines;
+        self.lines = lines.clone();
+
         let maybe_position = super::get_position(
             api,
-            lines,
-            border,
+            &lines,
+            settings,
             menu_winid,
             menu_width,
             menu_border,
@@ -142,18 +229,26 @@ impl CompletionDetails {
             (true, Some(position)) => {
                 if force_redraw {
                     self.close(api)?;
-                    self.spawn(lua, api, menu_winid, &position, border)?
+                    self.spawn(lua, api, menu_winid, &position, settings)?
                 } else {
                     self.shift(lua, api, menu_winid, &position)?;
+
+                    // New details start from the top even if the old ones
+                    // had been scrolled.
+                    if are_new_lines {
+                        let winid = self.winid.expect("The window is visible");
+                        let to_top = lua.create_function(|lua, ()| {
+                            set_topline(lua, &Neovim::new(lua)?.api, 1)
+                        })?;
+                        api.win_call(winid, to_top)?;
+                    }
                 }
-                self.fill(api, lines)?;
             },
 
             // The window wasn't open but now we have a new position. We create
-            // a new one and fill the buffer.
+            // a new one.
             (false, Some(position)) => {
-                self.spawn(lua, api, menu_winid, &position, border)?;
-                self.fill(api, lines)?;
+                self.spawn(lua, api, menu_winid, &position, settings)?;
             },
 
             // The window was open but there's nothing to display anymore. We
@@ -170,3 +265,51 @@ impl CompletionDetails {
         Ok(())
     }
 }
+
+/// Makes the `topline`-th line the first visible one in the current window,
+/// moving the cursor to it.
+fn set_topline(lua: &Lua, api: &Api, topline: u32) -> LuaResult<()> {
+    let view =
+        lua.create_table_from([("topline", topline), ("lnum", topline)])?;
+    api.call_function::<_, ()>("winrestview", vec![view])
+}
+
+/// Returns the 1-indexed first visible line of a window `height` rows tall
+/// showing `line_count` lines after scrolling it by half its height from
+/// `topline`, down if `step` is `1` and up if it's `-1`. It doesn't scroll
+/// past the point where the last line is at the bottom of the window.
+fn scrolled_topline(
+    topline: u32,
+    height: u32,
+    line_count: u32,
+    step: i8,
+) -> u32 {
+    let amount = cmp::max(height / 2, 1);
+    let max_topline = cmp::max(line_count.saturating_sub(height) + 1, 1);
+
+    match step {
+        1 => cmp::min(topline + amount, max_topline),
+        -1 => cmp::max(topline.saturating_sub(amount), 1),
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::scrolled_topline;
+
+    #[test]
+    fn scrolling_down() {
+        assert_eq!(6, scrolled_topline(1, 10, 40, 1));
+        assert_eq!(31, scrolled_topline(28, 10, 40, 1));
+        assert_eq!(1, scrolled_topline(1, 10, 5, 1));
+        assert_eq!(2, scrolled_topline(1, 1, 5, 1));
+    }
+
+    #[test]
+    fn scrolling_up() {
+        assert_eq!(1, scrolled_topline(1, 10, 40, -1));
+        assert_eq!(1, scrolled_topline(4, 10, 40, -1));
+        assert_eq!(15, scrolled_topline(20, 10, 40, -1));
+    }
+}
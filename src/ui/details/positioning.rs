@@ -3,25 +3,52 @@ use std::cmp;
 use mlua::prelude::LuaResult;
 use neovim::Api;
 
+use crate::completion::unicode::display_width;
 use crate::settings::ui::border::Border;
+use crate::settings::ui::details::{DetailsPosition, DetailsSettings};
 use crate::ui::WindowPosition;
 
 pub fn get_position(
     api: &Api,
     lines: &[String],
-    border: &Border,
+    settings: &DetailsSettings,
     menu_winid: u32,
     menu_width: u32,
     menu_border: &Border,
 ) -> LuaResult<Option<WindowPosition>> {
+    let border = &settings.border;
+
     let longest_line = lines
         .iter()
-        .map(|line| line.chars().count())
+        .map(|line| display_width(line))
         .max()
         .expect("There's at least one line");
 
-    let width = cmp::min(longest_line, 79) as u32;
-    let height = lines.len() as u32;
+    let width = cmp::min(longest_line as u32, u32::from(settings.max_width));
+
+    // The details window can't be taller than the number of screen rows
+    // between the top edge of the completion menu and the command line.
+    let rows_available =
+        get_rows_below_menu_top(api, menu_winid, menu_border)?
+            .saturating_sub(if border.has_top_edge() { 1 } else { 0 })
+            .saturating_sub(if border.has_bottom_edge() { 1 } else { 0 });
+
+    // Long lines take up more than one row if they're wrapped.
+    let lines_height = match settings.wrap {
+        true => wrapped_height(lines, width),
+        false => lines.len() as u32,
+    };
+
+    let height = match settings.max_height {
+        None => lines_height,
+        Some(height) => cmp::min(u32::from(height), lines_height),
+    };
+
+    let height = cmp::min(height, rows_available);
+
+    if height == 0 {
+        return Ok(None);
+    }
 
     let total_details_width = width
         + if border.has_left_edge() { 1 } else { 0 }
@@ -30,24 +57,42 @@ pub fn get_position(
     let (cols_before, cols_after) =
         get_cols_before_after_menu(api, menu_winid, menu_width, menu_border)?;
 
+    let right_col = i32::try_from(menu_width).unwrap()
+        + if menu_border.has_right_edge() { 1 } else { 0 };
+
+    let left_col = -i32::try_from(width).unwrap()
+        - if menu_border.has_left_edge() { 1 } else { 0 }
+        - if border.has_right_edge() { 1 } else { 0 }
+        // TODO: why? do I need this
+        - if border.has_left_edge() { 1 } else { 0 };
+
+    let fits_right = cols_after >= total_details_width;
+    let fits_left = cols_before >= total_details_width;
+
+    // In `rightleft` windows the menu grows to the left of the cursor, so
+    // the sides of the `position` option are swapped.
+    let position = match (
+        &settings.position,
+        api.win_get_option::<bool>(0, "rightleft")?,
+    ) {
+        (DetailsPosition::Left, true) => &DetailsPosition::Right,
+        (DetailsPosition::Right, true) => &DetailsPosition::Left,
+        (position, false) => position,
+    };
+
     // Horizontal policy.
     //
-    // First we try to display the details to the right of the completion menu,
-    // if there's not enough space we try to display it to the left of it. If
-    // that also fails we give up and return `None`.
-    let col = if cols_after >= total_details_width {
-        i32::try_from(menu_width).unwrap()
-            + if menu_border.has_right_edge() { 1 } else { 0 }
-    } else if cols_before >= total_details_width {
-        -i32::try_from(width).unwrap()
-            - if menu_border.has_left_edge() { 1 } else { 0 }
-            - if border.has_right_edge() { 1 } else { 0 }
-            // TODO: why? do I need this
-            - if border.has_left_edge() { 1 } else { 0 }
-    } else {
+    // First we try to display the details on the side of the completion menu
+    // set by the `position` option, if there's not enough space we try the
+    // other side. If that also fails we give up and return `None`.
+    let col = match (position, fits_right, fits_left) {
+        (DetailsPosition::Right, true, _) => right_col,
+        (DetailsPosition::Left, _, true) => left_col,
+        (_, true, _) => right_col,
+        (_, _, true) => left_col,
         // TODO: a better fallback behaviour might be to try to place the
         // details window above or below the completion_menu.
-        return Ok(None);
+        _ => return Ok(None),
     };
 
     // Vertical policy.
@@ -65,6 +110,16 @@ pub fn get_position(
     }))
 }
 
+/// Returns the number of screen rows taken up by the `lines` when they're
+/// wrapped at `width` columns.
+fn wrapped_height(lines: &[String], width: u32) -> u32 {
+    let width = cmp::max(width, 1) as usize;
+    lines
+        .iter()
+        .map(|line| cmp::max(1, display_width(line).div_ceil(width)))
+        .sum::<usize>() as u32
+}
+
 /// Returns the number of screen columns before and after the completion menu,
 /// taking into account the menu's horizontal borders.
 fn get_cols_before_after_menu(
@@ -76,17 +131,52 @@ fn get_cols_before_after_menu(
     let total_cols = api.get_option::<u32>("columns")?;
 
     // BUG: the `col` of `win_get_position` is sometimes bigger that the total
-    // number of columns, causing the subtractions to overflow. Open an issue
-    // upstream.
+    // number of columns, so we saturate the subtractions instead of letting
+    // them overflow. Open an issue upstream.
     let mut cols_before = api.win_get_position(menu_winid)?.1;
     let cols_after = total_cols
-        - cols_before
-        - menu_width
-        - if menu_border.has_right_edge() { 1 } else { 0 };
+        .saturating_sub(cols_before)
+        .saturating_sub(menu_width)
+        .saturating_sub(if menu_border.has_right_edge() { 1 } else { 0 });
 
     if menu_border.has_left_edge() {
-        cols_before -= 1;
+        cols_before = cols_before.saturating_sub(1);
     }
 
     Ok((cols_before, cols_after))
 }
+
+/// Returns the number of screen rows between the top edge of the completion
+/// menu (including its border) and the command line.
+fn get_rows_below_menu_top(
+    api: &Api,
+    menu_winid: u32,
+    menu_border: &Border,
+) -> LuaResult<u32> {
+    let total_rows = api.get_option::<u32>("lines")?
+        - api.get_option::<u32>("cmdheight")?;
+
+    let menu_top = api
+        .win_get_position(menu_winid)?
+        .0
+        .saturating_sub(if menu_border.has_top_edge() { 1 } else { 0 });
+
+    Ok(total_rows.saturating_sub(menu_top))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::wrapped_height;
+
+    #[test]
+    fn wrapped_lines() {
+        let lines = ["foo bar baz", "", "foo"]
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<String>>();
+
+        assert_eq!(3, wrapped_height(&lines, 11));
+        assert_eq!(4, wrapped_height(&lines, 10));
+        assert_eq!(5, wrapped_height(&lines, 4));
+    }
+}
@@ -1,9 +1,11 @@
 use mlua::prelude::{Lua, LuaResult};
 use neovim::Api;
 
+use crate::ui::menu::kinds;
+
 /// Creates our highlight groups, linking them to other default groups. This
 /// can be used by colorscheme plugin authors to style the UI.
-pub fn setup(lua: &Lua, api: &Api) -> LuaResult<()> {
+pub fn defaults(lua: &Lua, api: &Api) -> LuaResult<()> {
     let opts = lua.create_table_with_capacity(0, 2)?;
 
     // Don't override existing definitions.
@@ -25,6 +27,29 @@ pub fn setup(lua: &Lua, api: &Api) -> LuaResult<()> {
     opts.set("link", "Statement")?;
     api.set_hl(0, "CompleetMenuMatchingChars", opts.clone())?;
 
+    // `CompleetMenuScrollbar`
+    // Used to highlight the thumb of the completion menu's scrollbar.
+    opts.set("link", "PmenuThumb")?;
+    api.set_hl(0, "CompleetMenuScrollbar", opts.clone())?;
+
+    // `CompleetMenuIndex`
+    // Used to highlight the index labels of the completion menu.
+    opts.set("link", "Number")?;
+    api.set_hl(0, "CompleetMenuIndex", opts.clone())?;
+
+    // `CompleetMenuKind{kind}`
+    // Used to highlight the kind column of the completions of every kind,
+    // e.g. `CompleetMenuKindFunction`.
+    for (kind, _, hl_group) in kinds::KINDS {
+        opts.set("link", hl_group)?;
+        api.set_hl(0, &kinds::hl_group(kind), opts.clone())?;
+    }
+
+    // `CompleetMenuSource`
+    // Used to highlight the source column of the completion menu.
+    opts.set("link", "Comment")?;
+    api.set_hl(0, "CompleetMenuSource", opts.clone())?;
+
     // `CompleetMenuBorder`
     // Used to highlight the border of the completion menu.
     opts.set("link", "FloatBorder")?;
@@ -45,5 +70,15 @@ pub fn setup(lua: &Lua, api: &Api) -> LuaResult<()> {
     opts.set("link", "Comment")?;
     api.set_hl(0, "CompleetHint", opts.clone())?;
 
+    // `CompleetGhostText`
+    // Used to highlight the inline suggestions.
+    opts.set("link", "Comment")?;
+    api.set_hl(0, "CompleetGhostText", opts.clone())?;
+
+    // `CompleetLoading`
+    // Used to highlight the loading indicator.
+    opts.set("link", "Comment")?;
+    api.set_hl(0, "CompleetLoading", opts.clone())?;
+
     Ok(())
 }
@@ -1,9 +1,9 @@
 use mlua::prelude::{Lua, LuaResult};
 use neovim::Api;
 
-/// Sets up the highlight groups used in the error message displayed if any
-/// option passed to the `setup` function is invalid.
-pub fn setup_error_msg(lua: &Lua, api: &Api) -> LuaResult<()> {
+/// Sets up the highlight groups used in the messages displayed to the user,
+/// e.g. if any option passed to the `setup` function is invalid.
+pub fn error_msg(lua: &Lua, api: &Api) -> LuaResult<()> {
     let opts = lua.create_table_with_capacity(0, 2)?;
 
     // Don't override existing definitions.
@@ -14,6 +14,11 @@ pub fn setup_error_msg(lua: &Lua, api: &Api) -> LuaResult<()> {
     opts.set("link", "ErrorMsg")?;
     api.set_hl(0, "CompleetErrorMsgTag", opts.clone())?;
 
+    // `CompleetWarningMsgTag`
+    // Used to highlight the `[nvim-compleet]` part of any warning message.
+    opts.set("link", "WarningMsg")?;
+    api.set_hl(0, "CompleetWarningMsgTag", opts.clone())?;
+
     // `CompleetErrorMsgOptionPath`
     // Used to highlight the path of the option that caused the error message.
     opts.set("link", "Statement")?;
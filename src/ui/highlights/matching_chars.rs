@@ -0,0 +1,47 @@
+use mlua::prelude::{Lua, LuaResult, LuaValue};
+use neovim::Api;
+
+use crate::settings::ui::menu::MenuHighlights;
+
+/// Defines the `CompleetMenuMatchingChars{source}` highlight groups of the
+/// sources with a custom style in the `ui.menu.highlights.sources` option.
+/// Unlike the other groups they aren't defined with `default`, since they
+/// depend on the settings.
+pub fn matching_chars(
+    lua: &Lua,
+    api: &Api,
+    highlights: &MenuHighlights,
+) -> LuaResult<()> {
+    for (source, style) in &highlights.sources {
+        let base = style.hl_group.as_ref().unwrap_or(&highlights.matching);
+        let name = highlights.matching_hl_group(source);
+
+        // Links can't be combined with other attributes, so the ones of the
+        // base group are copied instead.
+        let opts = match style.bold || style.underline {
+            true => {
+                let opts = api.get_hl_by_name(base, true)?;
+                // Cleared groups have a `[true] = 6` entry which
+                // `nvim_set_hl` doesn't accept.
+                opts.set(true, LuaValue::Nil)?;
+                if style.bold {
+                    opts.set("bold", true)?;
+                }
+                if style.underline {
+                    opts.set("underline", true)?;
+                }
+                opts
+            },
+
+            false => {
+                let opts = lua.create_table_with_capacity(0, 1)?;
+                opts.set("link", base.as_str())?;
+                opts
+            },
+        };
+
+        api.set_hl(0, &name, opts)?;
+    }
+
+    Ok(())
+}
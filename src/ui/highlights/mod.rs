@@ -0,0 +1,25 @@
+mod defaults;
+mod error_msg;
+mod matching_chars;
+
+pub use error_msg::error_msg;
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Api;
+
+use crate::settings::ui::UiSettings;
+
+/// Defines all the `Compleet*` highlight groups, linking them to the default
+/// groups unless they've been overridden in the `ui.highlights` option.
+/// Called on every `setup` and on every `ColorScheme` event, since changing
+/// colorscheme clears the groups that aren't defined by it.
+pub fn apply(lua: &Lua, api: &Api, settings: &UiSettings) -> LuaResult<()> {
+    error_msg(lua, api)?;
+    defaults::defaults(lua, api)?;
+    matching_chars::matching_chars(lua, api, &settings.menu.highlights)?;
+
+    for (name, attrs) in &settings.highlights {
+        api.set_hl(0, name, attrs.to_table(lua)?)?;
+    }
+
+    Ok(())
+}
@@ -1,7 +1,8 @@
 use mlua::prelude::{Lua, LuaResult};
 use neovim::Api;
 
-use crate::completion::Cursor;
+use crate::completion::unicode::{display_width, is_composing};
+use crate::completion::{CompletionItem, Cursor, MatchedRange};
 
 #[derive(Debug)]
 pub struct CompletionHint {
@@ -35,7 +36,7 @@ impl CompletionHint {
         lua: &Lua,
         api: &Api,
         text: &str,
-        cursor: &Cursor,
+        range: &MatchedRange,
         index: usize,
     ) -> LuaResult<()> {
         let opts = lua.create_table_with_capacity(0, 3)?;
@@ -43,10 +44,103 @@ impl CompletionHint {
         opts.set("virt_text", [[text, "CompleetHint"]])?;
         opts.set("virt_text_pos", "overlay")?;
 
-        api.buf_set_extmark(0, self.nsid, cursor.row, cursor.bytes, opts)?;
+        api.buf_set_extmark(0, self.nsid, range.row, range.end, opts)?;
 
         self.hinted_index = Some(index);
 
         Ok(())
     }
+
+    /// Hints the completion at `index`, or erases the current hint if that
+    /// completion can't be hinted. The hint is drawn at the cursor, over the
+    /// `matched_bytes_post` the completion replaces, and only if those reach
+    /// the end of the line (or it would cover the text after them) and the
+    /// completion starts with the matched text.
+    pub fn update(
+        &mut self,
+        lua: &Lua,
+        api: &Api,
+        completions: &[CompletionItem],
+        index: usize,
+        cursor: &Cursor,
+    ) -> LuaResult<()> {
+        let completion = completions.get(index);
+        let range = completion.map(|c| MatchedRange::new(c, cursor));
+
+        let text =
+            completion
+                .zip(range.as_ref())
+                .and_then(|(completion, range)| {
+                    hint_text(completion, range, cursor)
+                });
+
+        match text.zip(range.as_ref()) {
+            Some((text, range)) => self.set(lua, api, &text, range, index),
+            None if self.is_visible() => self.erase(api),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The text hinting the `completion` whose matched `range` is around the
+/// `cursor`, or `None` if it can't be hinted. It's padded to hide all the
+/// text after the cursor the completion replaces.
+fn hint_text(
+    completion: &CompletionItem,
+    range: &MatchedRange,
+    cursor: &Cursor,
+) -> Option<String> {
+    if cursor.bytes as usize + range.post_text.len() != cursor.line.len() {
+        return None;
+    }
+
+    let text = completion
+        .text_after_match(cursor)
+        // Virtual text can't span multiple lines, so only the first line of
+        // a multiline completion is shown.
+        .and_then(|text| text.lines().next())
+        // A hint starting with a combining character (e.g. the accent of an
+        // `é` whose `e` has been typed) can't be drawn on its own.
+        .filter(|text| !text.is_empty() && !text.starts_with(is_composing))?;
+
+    let padding =
+        display_width(&range.post_text).saturating_sub(display_width(text));
+    Some(format!("{text}{}", " ".repeat(padding)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::hint_text;
+    use crate::completion::{CompletionItem, Cursor, MatchedRange};
+
+    fn hint(line: &str, bytes: u32, text: &str, post: u32) -> Option<String> {
+        let cursor = Cursor {
+            bytes,
+            keyword_start: None,
+            line: line.into(),
+            row: 0,
+        };
+        let completion = CompletionItem {
+            matched_bytes: 3,
+            matched_bytes_post: post,
+            text: text.into(),
+            ..Default::default()
+        };
+        let range = MatchedRange::new(&completion, &cursor);
+        hint_text(&completion, &range, &cursor)
+    }
+
+    #[test]
+    fn hints_at_the_end_of_the_line() {
+        assert_eq!(Some("lo".into()), hint("hel", 3, "hello", 0));
+        assert_eq!(None, hint("hel world", 3, "hello", 0));
+        assert_eq!(None, hint("hel", 3, "hel", 0));
+    }
+
+    #[test]
+    fn hints_over_the_bytes_after_the_cursor() {
+        assert_eq!(Some("lo".into()), hint("helxo", 3, "hello", 2));
+        assert_eq!(Some("p  ".into()), hint("helxyz", 3, "help", 3));
+        assert_eq!(None, hint("helxo world", 3, "hello", 2));
+    }
 }
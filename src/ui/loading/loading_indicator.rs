@@ -0,0 +1,67 @@
+use mlua::prelude::{Lua, LuaResult};
+use neovim::Api;
+
+use crate::completion::Cursor;
+
+/// The text shown at the end of the cursor line.
+const INDICATOR: &str = " …";
+
+/// A virtual text shown next to the cursor while some sources (e.g. a slow
+/// language server) are still computing their completions and there's
+/// nothing in the menu yet.
+#[derive(Debug)]
+pub struct LoadingIndicator {
+    /// The namespace id associated to the loading indicator.
+    nsid: u32,
+
+    /// The buffer the indicator is shown in, or `None` if it's not visible.
+    bufnr: Option<u32>,
+}
+
+impl LoadingIndicator {
+    pub fn new(api: &Api) -> LuaResult<Self> {
+        Ok(LoadingIndicator {
+            nsid: api.create_namespace("compleet_loading_indicator")?,
+            bufnr: None,
+        })
+    }
+}
+
+impl LoadingIndicator {
+    pub fn erase(&mut self, api: &Api) -> LuaResult<()> {
+        if let Some(bufnr) = self.bufnr.take() {
+            if api.buf_is_valid(bufnr)? {
+                api.buf_clear_namespace(bufnr, self.nsid as i32, 0, -1)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_visible(&self) -> bool { self.bufnr.is_some() }
+
+    /// Shows the indicator on the cursor line, or moves it there if it's
+    /// already visible.
+    pub fn show(
+        &mut self,
+        lua: &Lua,
+        api: &Api,
+        cursor: &Cursor,
+    ) -> LuaResult<()> {
+        let bufnr = api.get_current_buf()?;
+
+        if self.bufnr.is_some_and(|old| old != bufnr) {
+            self.erase(api)?;
+        }
+
+        let opts = lua.create_table_with_capacity(0, 3)?;
+        opts.set("id", 1)?;
+        opts.set("virt_text", [[INDICATOR, "CompleetLoading"]])?;
+        opts.set("virt_text_pos", "eol")?;
+
+        api.buf_set_extmark(bufnr, self.nsid, cursor.row, 0, opts)?;
+
+        self.bufnr = Some(bufnr);
+
+        Ok(())
+    }
+}
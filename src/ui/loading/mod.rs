@@ -0,0 +1,3 @@
+mod loading_indicator;
+
+pub use loading_indicator::LoadingIndicator;
@@ -0,0 +1,234 @@
+use std::ops::Range;
+
+use super::{kinds, truncation};
+use crate::completion::unicode::display_width;
+use crate::completion::CompletionItem;
+use crate::settings::ui::menu::MenuSettings;
+
+/// A line of the completion menu.
+#[derive(Debug, PartialEq)]
+pub struct Line {
+    /// The text of the line.
+    pub text: String,
+
+    /// The byte range of the kind column in `text`, or `None` if the
+    /// completion doesn't have a kind.
+    pub kind: Option<Range<usize>>,
+
+    /// The byte range of the source column in `text`, or `None` if the
+    /// `show_source` option is disabled.
+    pub source: Option<Range<usize>>,
+
+    /// The byte ranges in `text` to highlight, i.e. the matched characters
+    /// of the completion clipped to its possibly truncated label.
+    pub hl_ranges: Vec<(Range<usize>, &'static str)>,
+}
+
+/// Returns the lines displayed in the completion menu, one for every
+/// completion. The formatted completions are followed by their kind, aligned
+/// in a column after the longest one, and by the name of their source,
+/// aligned to the right edge of the menu. Completions wider than the
+/// `max_width` option are truncated with an ellipsis.
+pub fn lines(
+    completions: &[CompletionItem],
+    settings: &MenuSettings,
+) -> Vec<Line> {
+    let formats = completions
+        .iter()
+        .map(|c| truncation::truncate(c, settings.max_width))
+        .collect::<Vec<(String, Vec<(Range<usize>, &'static str)>)>>();
+
+    let kinds = completions
+        .iter()
+        .map(|c| c.kind.map(|kind| kinds::format(kind, settings)))
+        .collect::<Vec<Option<String>>>();
+
+    // If no completion has a kind there's no need to pad the formats.
+    let format_width = match kinds.iter().any(Option::is_some) {
+        true => formats
+            .iter()
+            .map(|(format, _)| display_width(format))
+            .max()
+            .unwrap_or(0),
+        false => 0,
+    };
+
+    let mut lines = formats
+        .into_iter()
+        .zip(kinds)
+        .map(|((format, hl_ranges), kind)| match kind {
+            Some(kind) => {
+                // `format!`'s padding counts chars, not cells.
+                let padding = format_width - display_width(&format);
+                let mut text = format!("{}{} ", format, " ".repeat(padding));
+                let start = text.len();
+                text.push_str(&kind);
+                Line {
+                    kind: Some(start..text.len()),
+                    source: None,
+                    text,
+                    hl_ranges,
+                }
+            },
+            None => Line {
+                text: format,
+                kind: None,
+                source: None,
+                hl_ranges,
+            },
+        })
+        .collect::<Vec<Line>>();
+
+    if !settings.show_source {
+        return lines;
+    }
+
+    let line_width = lines
+        .iter()
+        .map(|line| display_width(&line.text))
+        .max()
+        .unwrap_or(0);
+
+    let source_width = completions
+        .iter()
+        .map(|c| display_width(&source_label(c.source)))
+        .max()
+        .unwrap_or(0);
+
+    for (line, completion) in lines.iter_mut().zip(completions) {
+        let label = source_label(completion.source);
+        let padding =
+            line_width - display_width(&line.text) + 1 + source_width
+                - display_width(&label);
+        line.text.push_str(&" ".repeat(padding));
+        let start = line.text.len();
+        line.text.push_str(&label);
+        line.source = Some(start..line.text.len());
+    }
+
+    lines
+}
+
+/// Returns the label shown in the source column for the completions of the
+/// source with the given name.
+pub(super) fn source_label(source: &str) -> String {
+    match source {
+        "Buffer" => "[Buf]".into(),
+        "Lsp" => "[LSP]".into(),
+        _ => format!("[{source}]"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lines, Line};
+    use crate::completion::CompletionItem;
+    use crate::settings::ui::menu::MenuSettings;
+
+    fn item(
+        format: &str,
+        kind: Option<&'static str>,
+        source: &'static str,
+    ) -> CompletionItem {
+        CompletionItem {
+            format: format.into(),
+            kind,
+            source,
+            text: format.trim().into(),
+            ..Default::default()
+        }
+    }
+
+    fn settings() -> MenuSettings {
+        MenuSettings {
+            kind_format: "{kind}".into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn no_kinds() {
+        assert_eq!(
+            vec![Line {
+                text: " foo".into(),
+                kind: None,
+                source: None,
+                hl_ranges: Vec::new(),
+            }],
+            lines(&[item(" foo", None, "Lsp")], &settings())
+        );
+    }
+
+    #[test]
+    fn kinds_are_aligned() {
+        assert_eq!(
+            vec![
+                Line {
+                    text: " foo    Text".into(),
+                    kind: Some(8..12),
+                    source: None,
+                    hl_ranges: Vec::new(),
+                },
+                Line {
+                    text: " föobar Function".into(),
+                    kind: Some(9..17),
+                    source: None,
+                    hl_ranges: Vec::new(),
+                },
+                Line {
+                    text: " baz".into(),
+                    kind: None,
+                    source: None,
+                    hl_ranges: Vec::new(),
+                },
+                Line {
+                    text: " 日本   Text".into(),
+                    kind: Some(10..14),
+                    source: None,
+                    hl_ranges: Vec::new(),
+                },
+            ],
+            lines(
+                &[
+                    item(" foo", Some("Text"), "Lsp"),
+                    item(" föobar", Some("Function"), "Lsp"),
+                    item(" baz", None, "Lsp"),
+                    item(" 日本", Some("Text"), "Lsp"),
+                ],
+                &settings()
+            )
+        );
+    }
+
+    #[test]
+    fn sources_are_right_aligned() {
+        let settings = MenuSettings {
+            show_source: true,
+            ..settings()
+        };
+
+        assert_eq!(
+            vec![
+                Line {
+                    text: " foo   Text  [Buf]".into(),
+                    kind: Some(7..11),
+                    source: Some(13..18),
+                    hl_ranges: Vec::new(),
+                },
+                Line {
+                    text: " ./foo      [Path]".into(),
+                    kind: None,
+                    source: Some(12..18),
+                    hl_ranges: Vec::new(),
+                },
+            ],
+            lines(
+                &[
+                    item(" foo", Some("Text"), "Buffer"),
+                    item(" ./foo", None, "Path"),
+                ],
+                &settings
+            )
+        );
+    }
+}
@@ -1,8 +1,16 @@
-use mlua::{prelude::LuaResult, Lua};
-use neovim::Api;
+use std::cmp;
+use std::ops::Range;
 
+use mlua::prelude::{Lua, LuaFunction, LuaResult, LuaTable, LuaValue};
+use neovim::{Api, Neovim};
+
+use super::scrollbar::Scrollbar;
+use super::{kinds, Line};
+use crate::completion::matcher::MATCHING_CHARS_HL;
 use crate::completion::CompletionItem;
-use crate::settings::ui::border::Border;
+use crate::logging;
+use crate::settings::completion::Preselect;
+use crate::settings::ui::menu::{MenuHighlights, MenuSettings};
 use crate::ui::WindowPosition;
 
 #[derive(Debug)]
@@ -16,6 +24,49 @@ pub struct CompletionMenu {
     /// never changed.
     mc_nsid: u32,
 
+    /// The lines currently in the buffer, used to only rewrite the ones that
+    /// changed when new completions arrive.
+    lines: Vec<String>,
+
+    /// A scrollbar shown when there are more completion items than the menu
+    /// can display at once.
+    scrollbar: Scrollbar,
+
+    /// What the position of the menu is relative to, either `cursor` or
+    /// `editor` (see `:h nvim_open_win`).
+    relative: &'static str,
+
+    /// Whether Neovim supports the `title` option of floating windows.
+    supports_title: bool,
+
+    /// Whether Neovim supports the `hide` option of floating windows, used to
+    /// keep the window of the menu alive while it's closed.
+    supports_hide: bool,
+
+    /// The handle of the window hidden by the last `close`, shown again by
+    /// the next `spawn` instead of opening a new one, together with whether
+    /// it has a title. `None` if there's no hidden window.
+    hidden: Option<(u32, bool)>,
+
+    /// Whether Neovim has the `mousemoveevent` option, which was added in
+    /// 0.8 and is needed to get `<MouseMove>` events.
+    supports_mousemoveevent: bool,
+
+    /// The value of the `mousemoveevent` option before `spawn` set it for the
+    /// `ui.menu.mouse` option, restored by `close`. `None` if it wasn't set.
+    saved_mousemoveevent: Option<bool>,
+
+    /// The template of the title shown in the top edge of the border, or
+    /// `None` if the menu doesn't have a title.
+    title: Option<String>,
+
+    /// The number of completion items in the menu.
+    items: usize,
+
+    /// Whether the items are shown from the bottom up, so that the best
+    /// completion is next to the cursor when the menu is above it.
+    reversed: bool,
+
     /// The index of the currently selected completion item, or `None` if no
     /// completion is selected.
     pub selected_index: Option<usize>,
@@ -30,11 +81,65 @@ pub struct CompletionMenu {
     pub winid: Option<u32>,
 }
 
+/// The key of the Lua registry where the function returned by
+/// `set_highlights` is stored.
+const SET_HIGHLIGHTS: &str = "compleet_menu_set_highlights";
+
+/// Sets the extmarks of all the highlights of the menu, so that they take a
+/// single call into Lua instead of one per highlight. The highlights are a
+/// flat list of `row, start_col, end_col, hl_group, priority` fields, so
+/// that no table is created per highlight.
+const SET_HIGHLIGHTS_CHUNK: &str = r#"
+local bufnr, nsid, highlights = ...
+local set_extmark = vim.api.nvim_buf_set_extmark
+for i = 1, #highlights, 5 do
+  set_extmark(bufnr, nsid, highlights[i], highlights[i + 1], {
+    end_row = highlights[i],
+    end_col = highlights[i + 2],
+    hl_group = highlights[i + 3],
+    priority = highlights[i + 4],
+  })
+end
+"#;
+
+/// The priority of the extmarks highlighting the matched characters, which
+/// have to win over the `cursorline` of the selected completion.
+const MATCH_PRIORITY: u16 = 10000;
+
+/// The default priority of extmarks.
+const DEFAULT_PRIORITY: u16 = 4096;
+
+/// A highlighted byte range in a row of the menu.
+#[derive(Debug, PartialEq)]
+struct Highlight {
+    row: usize,
+    range: Range<usize>,
+    hl_group: String,
+    priority: u16,
+}
+
 impl CompletionMenu {
-    pub fn new(api: &Api) -> LuaResult<Self> {
+    pub fn new(api: &Api, relative: &'static str) -> LuaResult<Self> {
         Ok(CompletionMenu {
             bufnr: api.create_buf(false, true)?,
             mc_nsid: api.create_namespace("compleet_matched_chars")?,
+            lines: Vec::new(),
+            relative,
+            scrollbar: Scrollbar::new(api)?,
+            supports_title: api
+                .call_function::<_, u8>("has", vec!["nvim-0.9"])?
+                == 1,
+            supports_hide: api
+                .call_function::<_, u8>("has", vec!["nvim-0.10"])?
+                == 1,
+            hidden: None,
+            supports_mousemoveevent: api
+                .get_all_options_info()?
+                .contains_key("mousemoveevent")?,
+            saved_mousemoveevent: None,
+            title: None,
+            items: 0,
+            reversed: false,
             selected_index: None,
             width: None,
             winid: None,
@@ -44,8 +149,12 @@ impl CompletionMenu {
 
 impl CompletionMenu {
     /// Closes the completion menu, while also resetting the selected
-    /// completion and the window position to `None`.
-    pub fn close(&mut self, api: &Api) -> LuaResult<()> {
+    /// completion and the window position to `None`. Menus are opened and
+    /// closed all the time while typing, so if Neovim supports it the window
+    /// is only hidden, to be shown again by the next `spawn`. The highlights
+    /// of the matched characters are cleared either way, since they belong
+    /// to the closed menu.
+    pub fn close(&mut self, lua: &Lua, api: &Api) -> LuaResult<()> {
         if let Some(winid) = self.winid {
             // For some reason it's necessary to reset the cursor before
             // closing the floating window, or the next window will have the
@@ -56,52 +165,203 @@ impl CompletionMenu {
             // maybe that's why we need this.
             api.win_set_cursor(winid, 1, 0)?;
 
-            api.win_hide(winid)?;
+            match self.supports_hide {
+                true => {
+                    let opts = lua.create_table_from([("hide", true)])?;
+                    api.win_set_config(winid, opts)?;
+                    self.hidden = Some((winid, self.title.is_some()));
+                },
+                false => api.win_hide(winid)?,
+            }
+
             self.winid = None;
+            api.buf_clear_namespace(self.bufnr, self.mc_nsid as i32, 0, -1)?;
         }
+        self.scrollbar.close(api)?;
         self.selected_index = None;
         self.width = None;
+        if let Some(mousemoveevent) = self.saved_mousemoveevent.take() {
+            api.set_option("mousemoveevent", mousemoveevent)?;
+        }
         Ok(())
     }
 
-    /// Fills the completion buffer with the completion results.
+    /// Fills the completion buffer with the `lines` of the completion
+    /// results. Only the lines that changed since the last call are
+    /// rewritten, so that the results of slower sources can be merged into a
+    /// visible menu without redrawing all of it. The lines are set by a
+    /// single batch of API calls, and their highlights by a single call to
+    /// `SET_HIGHLIGHTS_CHUNK`.
     pub fn fill(
         &mut self,
         lua: &Lua,
         api: &Api,
         completions: &[CompletionItem],
+        lines: &[Line],
+        highlights: &MenuHighlights,
     ) -> LuaResult<()> {
-        let lines = completions
+        let mut text = lines
             .iter()
-            .map(|c| c.format.as_ref())
+            .map(|line| line.text.as_ref())
             .collect::<Vec<&str>>();
 
-        api.buf_set_lines(self.bufnr, 0, -1, false, &lines)?;
-
-        // Highlight the matching characters of every completion item.
-        let mut id = 0u16;
-        let opts = lua.create_table_with_capacity(0, 4)?;
-        for (row, completion) in completions.iter().enumerate() {
-            for (range, hl_group) in &completion.hl_ranges {
-                id += 1;
-                opts.set("id", id)?;
-                opts.set("end_row", row)?;
-                opts.set("end_col", range.end)?;
-                opts.set("hl_group", *hl_group)?;
-                opts.set("priority", 10000)?;
-                api.buf_set_extmark(
-                    self.bufnr,
-                    self.mc_nsid,
-                    row as u32,
-                    range.start as u32,
-                    opts.clone(),
-                )?;
-            }
+        if self.reversed {
+            text.reverse();
+        }
+
+        let mut batch = api.batch(lua);
+
+        // A new scratch buffer contains a single empty line.
+        if self.lines.is_empty() {
+            batch.call(
+                "nvim_buf_set_lines",
+                (self.bufnr, 0, -1, false, text.clone()),
+            )?;
+        } else if let Some((start, old_end, new_end)) =
+            changed_lines(&self.lines, &text)
+        {
+            batch.call(
+                "nvim_buf_set_lines",
+                (self.bufnr, start, old_end, false, &text[start..new_end]),
+            )?;
+        }
+
+        // Fuzzy matches can highlight any number of ranges per item, so the
+        // extmarks of the previous items have to go.
+        batch.call(
+            "nvim_buf_clear_namespace",
+            (self.bufnr, self.mc_nsid, 0, -1),
+        )?;
+
+        self.lines = text.iter().map(|&line| line.to_owned()).collect();
+        self.items = completions.len();
+
+        batch.flush()?;
+
+        let highlights =
+            menu_highlights(completions, lines, highlights, |i| self.row(i));
+
+        let fields =
+            lua.create_table_with_capacity(5 * highlights.len() as i32, 0)?;
+        for (i, hl) in highlights.into_iter().enumerate() {
+            fields.raw_set(5 * i + 1, hl.row)?;
+            fields.raw_set(5 * i + 2, hl.range.start)?;
+            fields.raw_set(5 * i + 3, hl.range.end)?;
+            fields.raw_set(5 * i + 4, hl.hl_group)?;
+            fields.raw_set(5 * i + 5, hl.priority)?;
+        }
+
+        set_highlights(lua)?.call::<_, ()>((
+            self.bufnr,
+            self.mc_nsid,
+            fields,
+        ))?;
+        self.update_title(lua, api)?;
+
+        // The rows of a reversed menu depend on the number of items, so the
+        // cursor is moved to the selected one, or to the best completions at
+        // the bottom if nothing is selected.
+        if let (true, Some(winid)) = (self.reversed, self.winid) {
+            let row = match self.selected_index {
+                Some(index) => self.row(index) + 1,
+                None => self.items,
+            };
+            api.win_set_cursor(winid, row as u32, 0)?;
         }
 
         Ok(())
     }
 
+    /// Returns the index of the completion that would be selected by moving
+    /// the selection by `step` (either `1` or `-1`) in a menu showing `items`
+    /// completions. Moving past either end goes back to no selection.
+    pub fn next_index(
+        &self,
+        items: usize,
+        step: i8,
+        cycle: bool,
+    ) -> Option<usize> {
+        step_index(self.selected_index, items, step, cycle)
+    }
+
+    /// Returns the index of the completion that would be selected by moving
+    /// the selection by one page (as many completions as there are visible
+    /// rows) forward if `step` is `1` or backward if it's `-1`, in a menu
+    /// showing `items` completions. The selection stops at either end.
+    pub fn next_page_index(
+        &self,
+        api: &Api,
+        items: usize,
+        step: i8,
+    ) -> LuaResult<usize> {
+        let winid = self
+            .winid
+            .expect("The completion menu is visible so it has a window id");
+
+        let height = api.win_get_height(winid)? as usize;
+
+        Ok(page_index(self.selected_index, items, height, step))
+    }
+
+    /// Returns the index of the completion under the mouse, or `None` if the
+    /// mouse isn't over the menu. The menu isn't focusable, so its window
+    /// doesn't get mouse events and the position of the mouse on the screen
+    /// is translated into one of its rows.
+    pub fn hovered_index(
+        &self,
+        lua: &Lua,
+        api: &Api,
+    ) -> LuaResult<Option<usize>> {
+        let winid = match self.winid {
+            Some(winid) => winid,
+            None => return Ok(None),
+        };
+
+        let mouse =
+            api.call_function::<_, LuaTable>("getmousepos", Vec::<u8>::new())?;
+        let screen_pos = (mouse.get("screenrow")?, mouse.get("screencol")?);
+
+        let row = hovered_row(
+            screen_pos,
+            api.win_get_position(winid)?,
+            (api.win_get_height(winid)?, api.win_get_width(winid)?),
+            self.topline(lua, api, winid)?,
+        );
+
+        // Mapping a row to an index is the same as mapping an index to a
+        // row, even when the menu is reversed.
+        Ok(row.filter(|&row| row < self.items).map(|row| self.row(row)))
+    }
+
+    /// Returns the 0-indexed first line of the menu that's currently visible.
+    fn topline(&self, lua: &Lua, api: &Api, winid: u32) -> LuaResult<usize> {
+        Ok(api.call_function::<_, usize>(
+            "line",
+            vec![
+                LuaValue::String(lua.create_string("w0")?),
+                LuaValue::Integer(winid.into()),
+            ],
+        )? - 1)
+    }
+
+    /// Returns the 0-indexed row of the menu showing the `index`-th
+    /// completion.
+    pub fn row(&self, index: usize) -> usize {
+        match self.reversed {
+            true => self.items.saturating_sub(index + 1),
+            false => index,
+        }
+    }
+
+    /// Sets whether the items are shown from the bottom up. Changing it
+    /// rewrites all the lines the next time the menu is filled.
+    pub fn set_reversed(&mut self, reversed: bool) {
+        if reversed != self.reversed {
+            self.reversed = reversed;
+            self.lines.clear();
+        }
+    }
+
     /// Whether a completion item is currently selected.
     pub fn is_item_selected(&self) -> bool { self.selected_index.is_some() }
 
@@ -120,7 +380,7 @@ impl CompletionMenu {
             .expect("The completion menu is visible so it has a window id.");
 
         let opts = lua.create_table_with_capacity(0, 5)?;
-        opts.set("relative", "cursor")?;
+        opts.set("relative", self.relative)?;
         opts.set("height", position.height)?;
         opts.set("width", position.width)?;
         opts.set("row", position.row)?;
@@ -133,29 +393,87 @@ impl CompletionMenu {
         Ok(())
     }
 
-    /// Spawns the completion menu at a specified position.
+    /// Spawns the completion menu at a specified position, returning whether
+    /// its window could be opened.
     pub fn spawn(
         &mut self,
         lua: &Lua,
         api: &Api,
         position: &WindowPosition,
-        border: &Border,
-    ) -> LuaResult<()> {
-        let opts = lua.create_table_with_capacity(0, 9)?;
-        opts.set("relative", "cursor")?;
+        settings: &MenuSettings,
+    ) -> LuaResult<bool> {
+        let border = &settings.border;
+
+        // `<MouseMove>` is only sent with the `mousemoveevent` option set.
+        if settings.mouse
+            && self.supports_mousemoveevent
+            && self.saved_mousemoveevent.is_none()
+        {
+            self.saved_mousemoveevent =
+                Some(api.get_option("mousemoveevent")?);
+            api.set_option("mousemoveevent", true)?;
+        }
+
+        let opts = lua.create_table_with_capacity(0, 10)?;
+        opts.set("relative", self.relative)?;
         opts.set("height", position.height)?;
         opts.set("width", position.width)?;
         opts.set("row", position.row)?;
         opts.set("col", position.col)?;
         opts.set("focusable", false)?;
         opts.set("style", "minimal")?;
-        opts.set("noautocmd", true)?;
 
-        if border.enable {
-            opts.set("border", border.style.to_lua(lua)?)?;
+        match border.enable {
+            true => opts.set("border", border.style.to_lua(lua)?)?,
+            // A hidden window could still have the border of old settings.
+            false => opts.set("border", "none")?,
+        }
+
+        // Titles are only shown in the top edge of the border.
+        self.title = settings
+            .title
+            .clone()
+            .filter(|_| self.supports_title && border.has_top_edge());
+
+        if let Some(title) = &self.title {
+            opts.set("title", format_title(title, None, self.items))?;
+        }
+
+        // The items are drawn from right to left in `rightleft` windows, like
+        // the text they complete. The command line is always left to right.
+        let rightleft = self.relative == "cursor"
+            && api.win_get_option::<bool>(0, "rightleft")?;
+
+        let winblend = settings.winblend.unwrap_or_default().0;
+
+        if let Some(winid) = self.take_hidden(api)? {
+            // Setting the `minimal` style again also resets `cursorline`.
+            opts.set("hide", false)?;
+            api.win_set_config(winid, opts)?;
+            api.win_set_option(winid, "rightleft", rightleft)?;
+            api.win_set_option(winid, "winblend", winblend)?;
+
+            self.width = Some(position.width);
+            self.winid = Some(winid);
+
+            return Ok(true);
         }
 
-        let winid = api.open_win(self.bufnr, false, opts)?;
+        opts.set("noautocmd", true)?;
+
+        // Floating windows can't be opened in some contexts, e.g. in the
+        // command-line window.
+        let winid = match api.open_win(self.bufnr, false, opts) {
+            Ok(winid) => winid,
+            Err(err) => {
+                logging::warn(
+                    "menu",
+                    format!("couldn't open the menu: {err}"),
+                );
+                return Ok(false);
+            },
+        };
+
         api.win_set_option(
             winid,
             "winhl",
@@ -163,16 +481,58 @@ impl CompletionMenu {
              Normal:CompleetMenu,Search:None",
         )?;
         api.win_set_option(winid, "scrolloff", 0)?;
+        api.win_set_option(winid, "rightleft", rightleft)?;
+        api.win_set_option(winid, "winblend", winblend)?;
 
         self.width = Some(position.width);
         self.winid = Some(winid);
 
-        Ok(())
+        Ok(true)
+    }
+
+    /// Returns the window hidden by the last `close` if it can be shown again
+    /// with the current settings, closing it otherwise. Floating windows
+    /// can't be moved to other tabpages, and titles can't be removed.
+    fn take_hidden(&mut self, api: &Api) -> LuaResult<Option<u32>> {
+        let (winid, has_title) = match self.hidden.take() {
+            Some(hidden) => hidden,
+            None => return Ok(None),
+        };
+
+        if !api.win_is_valid(winid)? {
+            return Ok(None);
+        }
+
+        if has_title == self.title.is_some()
+            && api.win_get_tabpage(winid)? == api.get_current_tabpage()?
+        {
+            return Ok(Some(winid));
+        }
+
+        api.win_close(winid, true)?;
+        Ok(None)
+    }
+
+    /// Updates the scrollbar to reflect which of the `items` completion items
+    /// are currently visible in the menu.
+    pub fn update_scrollbar(
+        &mut self,
+        lua: &Lua,
+        api: &Api,
+        items: usize,
+    ) -> LuaResult<()> {
+        let winid = self
+            .winid
+            .expect("The completion menu is visible so it has a window id");
+
+        self.scrollbar.update(lua, api, winid, items)
     }
 
-    /// Selects a new completion.
+    /// Selects a new completion, scrolling the menu if the new selection is
+    /// outside of the visible rows.
     pub fn select(
         &mut self,
+        lua: &Lua,
         api: &Api,
         new_selected_index: Option<usize>,
     ) -> LuaResult<()> {
@@ -182,7 +542,25 @@ impl CompletionMenu {
 
         match new_selected_index {
             Some(index) => {
-                api.win_set_cursor(winid, (index + 1).try_into().unwrap(), 0)?;
+                let height = api.win_get_height(winid)? as usize;
+                let row = self.row(index);
+
+                let topline = self.topline(lua, api, winid)?;
+                let topline = get_topline(topline, height, row);
+
+                // Move the cursor and set the first visible line at the same
+                // time, so that Neovim doesn't have to guess how to scroll.
+                let set_view = lua.create_function(move |lua, ()| {
+                    let view = lua.create_table_from([
+                        ("lnum", row + 1),
+                        ("topline", topline + 1),
+                    ])?;
+                    Neovim::new(lua)?
+                        .api
+                        .call_function::<_, ()>("winrestview", vec![view])
+                })?;
+                api.win_call(winid, set_view)?;
+
                 if self.selected_index.is_none() {
                     api.win_set_option(winid, "cursorline", true)?;
                 }
@@ -193,6 +571,475 @@ impl CompletionMenu {
 
         self.selected_index = new_selected_index;
 
+        self.update_title(lua, api)
+    }
+
+    /// Updates the title of the menu with the current selection and number
+    /// of items.
+    fn update_title(&self, lua: &Lua, api: &Api) -> LuaResult<()> {
+        if let (Some(title), Some(winid)) = (&self.title, self.winid) {
+            let opts = lua.create_table_with_capacity(0, 1)?;
+            opts.set(
+                "title",
+                format_title(title, self.selected_index, self.items),
+            )?;
+            api.win_set_config(winid, opts)?;
+        }
         Ok(())
     }
 }
+
+/// Replaces the `{index}` placeholder of a title template with the 1-based
+/// index of the selected completion (or `-` if none is selected) and the
+/// `{total}` one with the number of completions.
+fn format_title(
+    template: &str,
+    selected: Option<usize>,
+    total: usize,
+) -> String {
+    let index = selected.map_or_else(|| "-".into(), |i| (i + 1).to_string());
+    template
+        .replace("{index}", &index)
+        .replace("{total}", &total.to_string())
+}
+
+/// Returns the index of the completion to select when the menu is opened,
+/// according to the `completion.preselect` setting.
+pub fn preselected_index(
+    completions: &[CompletionItem],
+    preselect: Preselect,
+) -> Option<usize> {
+    match preselect {
+        Preselect::Always => (!completions.is_empty()).then_some(0),
+        Preselect::Lsp => completions.iter().position(|c| c.preselect),
+        Preselect::None => None,
+    }
+}
+
+/// Compares the `old` lines of the menu with the `new` ones, returning the
+/// `(start, old_end, new_end)` tuple such that replacing `old[start..old_end]`
+/// with `new[start..new_end]` turns the former into the latter, or `None` if
+/// they're the same.
+fn changed_lines(
+    old: &[String],
+    new: &[&str],
+) -> Option<(usize, usize, usize)> {
+    let start = old.iter().zip(new).take_while(|(o, n)| o == *n).count();
+
+    if start == old.len() && start == new.len() {
+        return None;
+    }
+
+    let suffix = old[start..]
+        .iter()
+        .rev()
+        .zip(new[start..].iter().rev())
+        .take_while(|(o, n)| o == *n)
+        .count();
+
+    Some((start, old.len() - suffix, new.len() - suffix))
+}
+
+/// Returns the index of the first visible completion of a menu with `height`
+/// rows after selecting the `index`-th completion, where `topline` is the
+/// index of the first visible completion before the selection. The menu only
+/// scrolls by the minimum amount needed to make the selection visible.
+fn get_topline(topline: usize, height: usize, index: usize) -> usize {
+    if index < topline {
+        index
+    } else if index >= topline + height {
+        index + 1 - height
+    } else {
+        topline
+    }
+}
+
+/// Returns the ranges of a line to highlight with the highlight groups set
+/// in the `ui.menu.highlights` option. The characters matched by the
+/// completion prefix use the group of the completion's source, and can be
+/// merged into a single span. Other ranges are left as they are.
+fn highlight_matches(
+    hl_ranges: &[(Range<usize>, &'static str)],
+    source: &str,
+    highlights: &MenuHighlights,
+) -> Vec<(Range<usize>, String)> {
+    let is_match = |hl_group: &str| hl_group == MATCHING_CHARS_HL;
+    let hl_group = highlights.matching_hl_group(source);
+
+    let mut matches = hl_ranges
+        .iter()
+        .filter(|(_, group)| is_match(group))
+        .map(|(range, _)| (range.clone(), hl_group.clone()))
+        .collect::<Vec<(Range<usize>, String)>>();
+
+    if highlights.full_span && matches.len() > 1 {
+        let start = matches.iter().map(|(r, _)| r.start).min().unwrap();
+        let end = matches.iter().map(|(r, _)| r.end).max().unwrap();
+        matches = vec![(start..end, hl_group)];
+    }
+
+    hl_ranges
+        .iter()
+        .filter(|(_, group)| !is_match(group))
+        .map(|(range, group)| (range.clone(), (*group).to_owned()))
+        .chain(matches)
+        .collect()
+}
+
+/// Returns the function loaded from `SET_HIGHLIGHTS_CHUNK`, loading it the
+/// first time it's needed.
+fn set_highlights(lua: &Lua) -> LuaResult<LuaFunction<'_>> {
+    if let LuaValue::Function(set_highlights) =
+        lua.named_registry_value::<_, LuaValue>(SET_HIGHLIGHTS)?
+    {
+        return Ok(set_highlights);
+    }
+
+    let set_highlights = lua.load(SET_HIGHLIGHTS_CHUNK).into_function()?;
+    lua.set_named_registry_value(SET_HIGHLIGHTS, set_highlights.clone())?;
+    Ok(set_highlights)
+}
+
+/// Returns the highlights of the menu showing the `completions` in the
+/// `lines`, where `row` maps the index of a completion to its row: the
+/// matched characters of every completion, its kind column with the
+/// highlight group of the kind and the source column with
+/// `CompleetMenuSource`. Sources can also use the kind column to show a
+/// glyph, which has no highlight group.
+fn menu_highlights(
+    completions: &[CompletionItem],
+    lines: &[Line],
+    highlights: &MenuHighlights,
+    row: impl Fn(usize) -> usize,
+) -> Vec<Highlight> {
+    let mut menu_highlights = Vec::new();
+
+    for (index, (completion, line)) in
+        completions.iter().zip(lines).enumerate()
+    {
+        let row = row(index);
+
+        let matches =
+            highlight_matches(&line.hl_ranges, completion.source, highlights)
+                .into_iter()
+                .map(|(range, hl_group)| (range, hl_group, MATCH_PRIORITY));
+
+        let kind = completion
+            .kind
+            .zip(line.kind.as_ref())
+            .filter(|(kind, _)| kinds::KINDS.iter().any(|(k, _, _)| k == kind))
+            .map(|(kind, range)| {
+                (range.clone(), kinds::hl_group(kind), DEFAULT_PRIORITY)
+            });
+
+        let source = line.source.as_ref().map(|range| {
+            (
+                range.clone(),
+                "CompleetMenuSource".to_owned(),
+                DEFAULT_PRIORITY,
+            )
+        });
+
+        menu_highlights.extend(matches.chain(kind).chain(source).map(
+            |(range, hl_group, priority)| Highlight {
+                row,
+                range,
+                hl_group,
+                priority,
+            },
+        ));
+    }
+
+    menu_highlights
+}
+
+/// Returns the 0-indexed row of the buffer shown at the 1-indexed
+/// `(row, col)` screen position, or `None` if it's outside the window whose
+/// 0-indexed top left corner is at `origin`, with the `(height, width)`
+/// size and the `topline`-th line at the top.
+fn hovered_row(
+    (row, col): (u32, u32),
+    origin: (u32, u32),
+    (height, width): (u32, u32),
+    topline: usize,
+) -> Option<usize> {
+    let (row, col) = (row.checked_sub(1)?, col.checked_sub(1)?);
+
+    let is_inside = (origin.0..origin.0 + height).contains(&row)
+        && (origin.1..origin.1 + width).contains(&col);
+
+    is_inside.then(|| topline + (row - origin.0) as usize)
+}
+
+/// Returns the index selected by moving `step` items away from `selected`.
+/// Going past either end of the menu goes back to no selection if `cycle` is
+/// set, and keeps the first or last completion selected if it isn't.
+fn step_index(
+    selected: Option<usize>,
+    items: usize,
+    step: i8,
+    cycle: bool,
+) -> Option<usize> {
+    let last_index = items - 1;
+    match step {
+        // Selecting the next completion
+        1 => match selected {
+            Some(index) if index == last_index && !cycle => Some(index),
+            Some(index) if index == last_index => None,
+            Some(index) => Some(index + 1),
+            None => Some(0),
+        },
+
+        // Selecting the previous completion
+        -1 => match selected {
+            Some(0) if !cycle => Some(0),
+            Some(0) => None,
+            Some(index) => Some(index - 1),
+            None => Some(last_index),
+        },
+
+        _ => unreachable!(),
+    }
+}
+
+/// Returns the index selected by moving `height` items away from
+/// `selected`, forward if `step` is `1` and backward if it's `-1`, clamped to
+/// the first and last completion. With no selection paging forward selects
+/// the last completion of the first page, and paging backward the first one
+/// of the last page.
+fn page_index(
+    selected: Option<usize>,
+    items: usize,
+    height: usize,
+    step: i8,
+) -> usize {
+    let last_index = items - 1;
+    let height = height.max(1);
+    match (step, selected) {
+        (1, Some(index)) => cmp::min(index + height, last_index),
+        (1, None) => cmp::min(height - 1, last_index),
+        (-1, Some(index)) => index.saturating_sub(height),
+        (-1, None) => items.saturating_sub(height),
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        changed_lines,
+        format_title,
+        get_topline,
+        highlight_matches,
+        hovered_row,
+        menu_highlights,
+        page_index,
+        preselected_index,
+        step_index,
+    };
+    use crate::completion::CompletionItem;
+    use crate::settings::completion::Preselect;
+    use crate::settings::ui::menu::{MatchStyle, MenuHighlights};
+    use crate::ui::menu::Line;
+
+    #[test]
+    fn titles() {
+        assert_eq!(" 3/42 ", format_title(" {index}/{total} ", Some(2), 42));
+        assert_eq!("-/42", format_title("{index}/{total}", None, 42));
+    }
+
+    #[test]
+    fn matches_default_highlights() {
+        let ranges = [
+            (1..2, "CompleetMenuMatchingChars"),
+            (3..5, "CompleetMenuMatchingChars"),
+        ];
+
+        assert_eq!(
+            vec![
+                (1..2, "CompleetMenuMatchingChars".to_owned()),
+                (3..5, "CompleetMenuMatchingChars".to_owned()),
+            ],
+            highlight_matches(&ranges, "Lsp", &MenuHighlights::default())
+        );
+    }
+
+    #[test]
+    fn matches_full_span_per_source() {
+        let highlights = MenuHighlights {
+            full_span: true,
+            sources: [("Buffer".into(), MatchStyle::default())].into(),
+            ..Default::default()
+        };
+
+        let ranges = [
+            (1..2, "CompleetMenuMatchingChars"),
+            (3..5, "CompleetMenuMatchingChars"),
+            (6..7, "Comment"),
+        ];
+
+        assert_eq!(
+            vec![
+                (6..7, "Comment".to_owned()),
+                (1..5, "CompleetMenuMatchingCharsBuffer".to_owned()),
+            ],
+            highlight_matches(&ranges, "Buffer", &highlights)
+        );
+    }
+
+    #[test]
+    fn highlights_of_reversed_menu() {
+        let item = |kind| CompletionItem {
+            format: " foo".into(),
+            kind,
+            source: "Lsp",
+            text: "foo".into(),
+            ..Default::default()
+        };
+
+        let line = |kind| Line {
+            text: " foo Function [LSP]".into(),
+            kind,
+            source: Some(14..19),
+            hl_ranges: vec![(1..2, "CompleetMenuMatchingChars")],
+        };
+
+        let highlights = menu_highlights(
+            &[item(Some("Function")), item(None)],
+            &[line(Some(5..13)), line(None)],
+            &MenuHighlights::default(),
+            |index| 1 - index,
+        );
+
+        let rows_and_groups = highlights
+            .iter()
+            .map(|hl| (hl.row, hl.range.clone(), hl.hl_group.as_str()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                (1, 1..2, "CompleetMenuMatchingChars"),
+                (1, 5..13, "CompleetMenuKindFunction"),
+                (1, 14..19, "CompleetMenuSource"),
+                (0, 1..2, "CompleetMenuMatchingChars"),
+                (0, 14..19, "CompleetMenuSource"),
+            ],
+            rows_and_groups
+        );
+        assert_eq!(10000, highlights[0].priority);
+    }
+
+    #[test]
+    fn mouse_over_the_menu() {
+        assert_eq!(Some(0), hovered_row((6, 11), (5, 10), (3, 20), 0));
+        assert_eq!(Some(7), hovered_row((8, 29), (5, 10), (3, 20), 5));
+        assert_eq!(None, hovered_row((9, 11), (5, 10), (3, 20), 0));
+        assert_eq!(None, hovered_row((6, 31), (5, 10), (3, 20), 0));
+        assert_eq!(None, hovered_row((0, 0), (5, 10), (3, 20), 0));
+    }
+
+    #[test]
+    fn cycling_selection() {
+        assert_eq!(None, step_index(Some(2), 3, 1, true));
+        assert_eq!(None, step_index(Some(0), 3, -1, true));
+        assert_eq!(Some(2), step_index(None, 3, -1, true));
+    }
+
+    #[test]
+    fn clamped_selection() {
+        assert_eq!(Some(2), step_index(Some(2), 3, 1, false));
+        assert_eq!(Some(0), step_index(Some(0), 3, -1, false));
+        assert_eq!(Some(0), step_index(None, 3, 1, false));
+        assert_eq!(Some(2), step_index(Some(1), 3, 1, false));
+    }
+
+    #[test]
+    fn paging_forward() {
+        assert_eq!(4, page_index(None, 20, 5, 1));
+        assert_eq!(7, page_index(Some(2), 20, 5, 1));
+        assert_eq!(19, page_index(Some(17), 20, 5, 1));
+        assert_eq!(2, page_index(None, 3, 5, 1));
+    }
+
+    #[test]
+    fn paging_backward() {
+        assert_eq!(15, page_index(None, 20, 5, -1));
+        assert_eq!(2, page_index(Some(7), 20, 5, -1));
+        assert_eq!(0, page_index(Some(3), 20, 5, -1));
+        assert_eq!(0, page_index(None, 3, 5, -1));
+    }
+
+    #[test]
+    fn selection_already_visible() {
+        assert_eq!(0, get_topline(0, 5, 4));
+        assert_eq!(3, get_topline(3, 5, 3));
+    }
+
+    #[test]
+    fn scroll_down() {
+        assert_eq!(1, get_topline(0, 5, 5));
+        // Wrapping around from the first to the last completion.
+        assert_eq!(15, get_topline(0, 5, 19));
+    }
+
+    #[test]
+    fn scroll_up() {
+        assert_eq!(2, get_topline(3, 5, 2));
+        // Wrapping around from the last to the first completion.
+        assert_eq!(0, get_topline(15, 5, 0));
+    }
+
+    fn changed(old: &[&str], new: &[&str]) -> Option<(usize, usize, usize)> {
+        let old = old.iter().map(|&s| s.to_owned()).collect::<Vec<String>>();
+        changed_lines(&old, new)
+    }
+
+    #[test]
+    fn unchanged_lines() {
+        assert_eq!(None, changed(&["a", "b"], &["a", "b"]));
+    }
+
+    #[test]
+    fn appended_lines() {
+        assert_eq!(
+            Some((2, 2, 4)),
+            changed(&["a", "b"], &["a", "b", "c", "d"])
+        );
+    }
+
+    #[test]
+    fn inserted_lines() {
+        assert_eq!(Some((1, 1, 2)), changed(&["a", "c"], &["a", "b", "c"]));
+    }
+
+    #[test]
+    fn replaced_lines() {
+        assert_eq!(
+            Some((1, 2, 2)),
+            changed(&["a", "b", "c"], &["a", "x", "c"])
+        );
+        assert_eq!(Some((0, 2, 1)), changed(&["a", "b"], &["c"]));
+    }
+
+    #[test]
+    fn preselect() {
+        let item = |preselect| CompletionItem {
+            format: " foo".into(),
+            preselect,
+            source: "Lsp",
+            text: "foo".into(),
+            ..Default::default()
+        };
+
+        let completions = [item(false), item(true), item(true)];
+
+        assert_eq!(
+            Some(0),
+            preselected_index(&completions, Preselect::Always)
+        );
+        assert_eq!(Some(1), preselected_index(&completions, Preselect::Lsp));
+        assert_eq!(None, preselected_index(&completions, Preselect::None));
+        assert_eq!(None, preselected_index(&completions[..1], Preselect::Lsp));
+        assert_eq!(None, preselected_index(&[], Preselect::Always));
+    }
+}
@@ -0,0 +1,83 @@
+use crate::settings::ui::menu::MenuSettings;
+
+/// The `(kind, icon, hl_group)` tuples of all the completion kinds defined by
+/// the LSP spec, where `hl_group` is the highlight group the kind's
+/// `CompleetMenuKind{kind}` highlight group links to by default.
+pub const KINDS: [(&str, &str, &str); 25] = [
+    ("Text", "\u{ea93}", "String"),
+    ("Method", "\u{ea8c}", "Function"),
+    ("Function", "\u{ea8c}", "Function"),
+    ("Constructor", "\u{ea8c}", "Function"),
+    ("Field", "\u{eb5f}", "Identifier"),
+    ("Variable", "\u{ea88}", "Identifier"),
+    ("Class", "\u{eb5b}", "Type"),
+    ("Interface", "\u{eb61}", "Type"),
+    ("Module", "\u{ea8b}", "Include"),
+    ("Property", "\u{eb65}", "Identifier"),
+    ("Unit", "\u{ea96}", "Number"),
+    ("Value", "\u{ea95}", "Number"),
+    ("Enum", "\u{ea95}", "Type"),
+    ("Keyword", "\u{eb62}", "Keyword"),
+    ("Snippet", "\u{eb66}", "Special"),
+    ("Color", "\u{eb5c}", "Special"),
+    ("File", "\u{eb60}", "Directory"),
+    ("Reference", "\u{eb36}", "Special"),
+    ("Folder", "\u{ea83}", "Directory"),
+    ("EnumMember", "\u{eb5e}", "Constant"),
+    ("Constant", "\u{eb5d}", "Constant"),
+    ("Struct", "\u{ea91}", "Type"),
+    ("Event", "\u{ea86}", "Special"),
+    ("Operator", "\u{eb64}", "Operator"),
+    ("TypeParameter", "\u{ea92}", "Type"),
+];
+
+/// Returns the name of the highlight group used to highlight the kind column
+/// of the completions of the given kind.
+pub fn hl_group(kind: &str) -> String { format!("CompleetMenuKind{kind}") }
+
+/// Returns the text shown in the kind column for a completion of the given
+/// kind, obtained by replacing the `{icon}` and `{kind}` placeholders of the
+/// `kind_format` template with the kind's icon and name.
+pub fn format(kind: &str, settings: &MenuSettings) -> String {
+    settings
+        .kind_format
+        .replace("{icon}", icon(kind, settings))
+        .replace("{kind}", kind)
+}
+
+/// Returns the icon of a kind, which is either the one set in the
+/// `kind_icons` option or the default one. Kinds not defined by the LSP spec
+/// don't have a default icon.
+pub fn icon<'a>(kind: &str, settings: &'a MenuSettings) -> &'a str {
+    match settings.kind_icons.get(kind) {
+        Some(icon) => icon.as_str(),
+        None => KINDS
+            .iter()
+            .find(|(k, _, _)| *k == kind)
+            .map(|(_, icon, _)| *icon)
+            .unwrap_or(""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format;
+    use crate::settings::ui::menu::MenuSettings;
+
+    #[test]
+    fn default_format() {
+        let settings = MenuSettings::default();
+        assert_eq!("\u{ea8c} Function", format("Function", &settings));
+    }
+
+    #[test]
+    fn custom_format_and_icons() {
+        let settings = MenuSettings {
+            kind_format: "({kind}) {icon}".into(),
+            kind_icons: [("Function".into(), "ƒ".into())].into(),
+            ..Default::default()
+        };
+        assert_eq!("(Function) ƒ", format("Function", &settings));
+        assert_eq!("(Foo) ", format("Foo", &settings));
+    }
+}
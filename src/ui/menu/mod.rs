@@ -1,4 +1,13 @@
+mod columns;
 mod completion_menu;
+pub mod kinds;
+pub mod native;
 pub mod positioning;
+mod scrollbar;
+mod template;
+mod truncation;
 
-pub use completion_menu::CompletionMenu;
+pub use columns::Line;
+pub use completion_menu::{preselected_index, CompletionMenu};
+pub use template::{render, INDEX_LABELS};
+pub use truncation::details;
@@ -0,0 +1,112 @@
+use mlua::prelude::{Lua, LuaFunction, LuaResult, LuaTable, LuaValue};
+use neovim::Api;
+
+use crate::completion::{CompletionItem, Cursor};
+
+/// The key of the Lua registry where the user's `completeopt` is saved while
+/// the native popup menu is open.
+const SAVED_COMPLETEOPT: &str = "compleet_saved_completeopt";
+
+/// Shows the completions in Neovim's native popup menu with `complete()`.
+/// Used with the `experimental.native_menu` option set and when the floating
+/// window of the menu can't be opened, e.g. in the command-line window, so
+/// that the completions aren't lost. The `user_data` of every item is the
+/// index of its completion, read back by `completed_item`.
+pub fn show(
+    lua: &Lua,
+    api: &Api,
+    completions: &[CompletionItem],
+    cursor: &Cursor,
+) -> LuaResult<()> {
+    let mut items = Vec::with_capacity(completions.len());
+    for (index, completion) in completions.iter().enumerate() {
+        let item = lua.create_table_with_capacity(0, 7)?;
+        item.set("word", completion.text.as_str())?;
+        item.set("kind", completion.kind.unwrap_or(""))?;
+        item.set("menu", format!("[{}]", completion.source))?;
+        if let Some(details) = &completion.details {
+            item.set("info", details.lines.join("\n"))?;
+        }
+        item.set("dup", 1)?;
+        item.set("empty", 1)?;
+        item.set("user_data", index)?;
+        items.push(item);
+    }
+
+    // `complete()` takes the 1-indexed column where the completed text
+    // starts.
+    let matched_bytes = completions.first().map_or(0, |c| c.matched_bytes);
+    let col = cursor.bytes - matched_bytes + 1;
+
+    // Without `noinsert` the first completion would be inserted right away.
+    // The popup menu reads the option while it's open, so the user's value is
+    // only restored by `restore_completeopt` on `CompleteDone`.
+    if lua
+        .named_registry_value::<_, Option<String>>(SAVED_COMPLETEOPT)?
+        .is_none()
+    {
+        let completeopt = api.get_option::<String>("completeopt")?;
+        lua.set_named_registry_value(SAVED_COMPLETEOPT, completeopt)?;
+    }
+    api.set_option("completeopt", "menuone,noinsert,noselect")?;
+
+    function(lua, "complete")?
+        .call::<_, ()>((col, lua.create_sequence_from(items)?))
+}
+
+/// Restores the `completeopt` the user had before `show` opened the native
+/// popup menu, if it's been changed.
+pub fn restore_completeopt(lua: &Lua, api: &Api) -> LuaResult<()> {
+    let completeopt =
+        lua.named_registry_value::<_, Option<String>>(SAVED_COMPLETEOPT)?;
+    if let Some(completeopt) = completeopt {
+        api.set_option("completeopt", completeopt)?;
+        lua.unset_named_registry_value(SAVED_COMPLETEOPT)?;
+    }
+    Ok(())
+}
+
+/// Closes the native popup menu opened by `show`.
+pub fn close(lua: &Lua, api: &Api) -> LuaResult<()> {
+    let col = api.win_get_cursor(0)?.1 + 1;
+    function(lua, "complete")?.call((col, lua.create_table()?))
+}
+
+/// Whether the native popup menu is visible.
+pub fn is_visible(lua: &Lua) -> LuaResult<bool> {
+    Ok(function(lua, "pumvisible")?.call::<_, u8>(())? == 1)
+}
+
+/// Whether an item of the native popup menu is selected, in which case
+/// Neovim has already inserted its text in the buffer.
+pub fn is_item_selected(lua: &Lua) -> LuaResult<bool> {
+    let what = lua.create_sequence_from(["selected"])?;
+    let info = function(lua, "complete_info")?.call::<_, LuaTable>(what)?;
+    Ok(info
+        .get::<_, Option<i32>>("selected")?
+        .is_some_and(|i| i != -1))
+}
+
+/// The index and the text of the completion confirmed in the native popup
+/// menu, read from `v:completed_item` on `CompleteDone`. It's `None` if the
+/// menu was closed without confirming anything or if the item wasn't set by
+/// `show`.
+pub fn completed_item(api: &Api) -> LuaResult<Option<(usize, String)>> {
+    let item = api.get_vvar::<LuaTable>("completed_item")?;
+    let index = match item.get::<_, LuaValue>("user_data")? {
+        LuaValue::Integer(index) => usize::try_from(index).ok(),
+        LuaValue::Number(index) if index >= 0.0 => Some(index as usize),
+        _ => None,
+    };
+    match index {
+        Some(index) => Ok(Some((index, item.get("word")?))),
+        None => Ok(None),
+    }
+}
+
+fn function<'lua>(lua: &'lua Lua, name: &str) -> LuaResult<LuaFunction<'lua>> {
+    lua.globals()
+        .get::<_, LuaTable>("vim")?
+        .get::<_, LuaTable>("fn")?
+        .get(name)
+}
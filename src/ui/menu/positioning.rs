@@ -3,25 +3,32 @@ use std::cmp;
 use mlua::prelude::LuaResult;
 use neovim::Api;
 
-use crate::completion::CompletionItem;
+use super::Line;
+use crate::completion::unicode::display_width;
+use crate::completion::{CompletionItem, Cursor};
 use crate::settings::ui::menu::{MenuAnchor, MenuSettings};
 use crate::ui::WindowPosition;
 
 /// Figures out where to position the floating window used to display the
-/// completion menu.
+/// completion menu, whose `lines` are obtained from the `completions`. In
+/// windows with the `rightleft` option set the text before the cursor is on
+/// its right, so the menu is mirrored to grow to the left of the cursor.
 pub fn get_position(
     api: &Api,
     completions: &[CompletionItem],
+    cursor: &Cursor,
+    lines: &[Line],
     settings: &MenuSettings,
 ) -> LuaResult<Option<WindowPosition>> {
-    let longest_line = completions
+    let longest_line = lines
         .iter()
-        .map(|c| c.format.chars().count() - 1)
+        .map(|line| display_width(&line.text))
         .max()
         .expect("There's at least one completion");
 
-    // The `+ 2` is to pad each item with a space.
-    let width: u32 = (longest_line + 2).try_into().unwrap();
+    // The lines already contain all the columns and start with a space, the
+    // `+ 1` is to also pad them with a space on the right.
+    let width: u32 = (longest_line + 1).try_into().unwrap();
 
     let height = match settings.max_height {
         None => completions.len() as u32,
@@ -35,24 +42,60 @@ pub fn get_position(
         // The `- 1` is because every completion is formatted with a leading
         // space.
         // TODO: picking the first completion is arbitrary.
-        MenuAnchor::Match =>
-            -i32::try_from(completions[0].matched_bytes).unwrap() - 1,
+        MenuAnchor::Match => {
+            let line_pre = &cursor.line[..cursor.bytes as usize];
+            let matched = &line_pre
+                [line_pre.len() - completions[0].matched_bytes as usize..];
+            -i32::try_from(display_width(matched)).unwrap() - 1
+        },
     }
     // If the left edge of the border is present we need to offset it by
     // placing the menu one more column to the left.
     - if border.has_left_edge() { 1 } else { 0 };
 
+    let border_width = if border.has_left_edge() { 1 } else { 0 }
+        + if border.has_right_edge() { 1 } else { 0 };
+
+    let col = match api.win_get_option::<bool>(0, "rightleft")? {
+        true => mirror(col, width + border_width),
+        false => col,
+    };
+
+    let (cursor_col, total_cols) = get_cursor_screen_col(api)?;
+    let (col, width) = match clamp_horizontally(
+        col,
+        width,
+        border_width,
+        cursor_col,
+        total_cols,
+    ) {
+        Some(col_width) => col_width,
+        None => return Ok(None),
+    };
+
     let (rows_above, rows_below) = get_rows_above_below_cursor(api)?;
-    let total_menu_height = height
-        + if border.has_bottom_edge() { 1 } else { 0 }
+    let border_height = if border.has_bottom_edge() { 1 } else { 0 }
         + if border.has_top_edge() { 1 } else { 0 };
 
-    let row = if rows_below >= total_menu_height {
-        1
-    } else if rows_above >= total_menu_height {
-        -i32::try_from(total_menu_height).unwrap()
+    // If the menu doesn't fit either below or above the cursor we shrink it
+    // to fit on the side with the most room, and the selection will scroll
+    // through the completions that aren't visible.
+    let (below, height) = if rows_below >= height + border_height {
+        (true, height)
+    } else if rows_above >= height + border_height {
+        (false, height)
     } else {
-        return Ok(None);
+        let below = rows_below >= rows_above;
+        let rows = if below { rows_below } else { rows_above };
+        match rows.checked_sub(border_height) {
+            Some(height) if height > 0 => (below, height),
+            _ => return Ok(None),
+        }
+    };
+
+    let row = match below {
+        true => 1,
+        false => -i32::try_from(height + border_height).unwrap(),
     };
 
     Ok(Some(WindowPosition {
@@ -63,6 +106,51 @@ pub fn get_position(
     }))
 }
 
+/// Returns the column relative to the cursor where a window `total_width`
+/// columns wide (border included) has to be placed for its right edge to be
+/// as far to the right of the cursor as `col` would put its left edge to the
+/// left of it.
+fn mirror(col: i32, total_width: u32) -> i32 {
+    1 - col - i32::try_from(total_width).unwrap()
+}
+
+/// Shifts the menu to the left if it would go past the right edge of the
+/// screen, and to the right if it would go past the left one. A menu wider
+/// than the screen is shrunk to fit. `col` is relative to the cursor, which is
+/// on the `cursor_col`-th (0-indexed) screen column. Returns the new `(col,
+/// width)`, or `None` if there's no room for the menu at all.
+fn clamp_horizontally(
+    col: i32,
+    width: u32,
+    border_width: u32,
+    cursor_col: u32,
+    total_cols: u32,
+) -> Option<(i32, u32)> {
+    let width = cmp::min(width, total_cols.checked_sub(border_width)?);
+    if width == 0 {
+        return None;
+    }
+
+    let cursor_col = i32::try_from(cursor_col).unwrap();
+
+    // The screen column of the left edge of the window, border included,
+    // if it's placed `col` columns after the cursor.
+    let start = cursor_col + col;
+    let max_start = i32::try_from(total_cols - width - border_width).unwrap();
+
+    Some((start.clamp(0, max_start) - cursor_col, width))
+}
+
+/// Returns the 0-indexed screen column of the cursor and the total number of
+/// screen columns.
+fn get_cursor_screen_col(api: &Api) -> LuaResult<(u32, u32)> {
+    let total_cols = api.get_option::<u32>("columns")?;
+    let cursor_col =
+        api.call_function::<u8, u32>("screencol", Vec::new())? - 1;
+
+    Ok((cursor_col, total_cols))
+}
+
 /// Returns the number of screen rows above and below the current cursor
 /// position.
 fn get_rows_above_below_cursor(api: &Api) -> LuaResult<(u32, u32)> {
@@ -72,3 +160,41 @@ fn get_rows_above_below_cursor(api: &Api) -> LuaResult<(u32, u32)> {
 
     Ok((rows_above, total_rows - rows_above - 1))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{clamp_horizontally, mirror};
+
+    #[test]
+    fn fits_on_screen() {
+        assert_eq!(Some((-2, 10)), clamp_horizontally(-2, 10, 0, 20, 80));
+    }
+
+    #[test]
+    fn shifted_left_near_the_right_edge() {
+        // The menu and its border end exactly on the last column.
+        assert_eq!(Some((-17, 20)), clamp_horizontally(-1, 20, 2, 75, 80));
+    }
+
+    #[test]
+    fn shifted_right_near_the_left_edge() {
+        assert_eq!(Some((-1, 10)), clamp_horizontally(-3, 10, 0, 1, 80));
+    }
+
+    #[test]
+    fn mirrored_for_rightleft_windows() {
+        // Anchored to the cursor, the menu ends on the cursor's column.
+        assert_eq!(-9, mirror(0, 10));
+        // Anchored to a 3 columns wide match, the leading space of the
+        // items is one column past its start.
+        assert_eq!(-5, mirror(-4, 10));
+        // A border on both sides is mirrored too.
+        assert_eq!(-10, mirror(-1, 12));
+    }
+
+    #[test]
+    fn shrunk_if_wider_than_the_screen() {
+        assert_eq!(Some((-40, 78)), clamp_horizontally(-1, 100, 2, 40, 80));
+        assert_eq!(None, clamp_horizontally(0, 10, 2, 0, 2));
+    }
+}
@@ -0,0 +1,137 @@
+use std::cmp;
+
+use mlua::prelude::{Lua, LuaResult, LuaValue};
+use neovim::Api;
+
+#[derive(Debug)]
+pub struct Scrollbar {
+    /// The handle of the buffer used to draw the scrollbar's thumb. It is set
+    /// once on initialization and never changes.
+    bufnr: u32,
+
+    /// The handle of the floating window used to draw the scrollbar's thumb,
+    /// or `None` if the scrollbar is not currently visible.
+    winid: Option<u32>,
+}
+
+impl Scrollbar {
+    pub fn new(api: &Api) -> LuaResult<Self> {
+        Ok(Scrollbar {
+            bufnr: api.create_buf(false, true)?,
+            winid: None,
+        })
+    }
+}
+
+impl Scrollbar {
+    /// Closes the scrollbar.
+    pub fn close(&mut self, api: &Api) -> LuaResult<()> {
+        if let Some(winid) = self.winid {
+            api.win_hide(winid)?;
+            self.winid = None;
+        }
+        Ok(())
+    }
+
+    /// Draws the scrollbar's thumb over the last column of the completion
+    /// menu, or closes it if all the `items` fit inside the menu.
+    pub fn update(
+        &mut self,
+        lua: &Lua,
+        api: &Api,
+        menu_winid: u32,
+        items: usize,
+    ) -> LuaResult<()> {
+        let height = api.win_get_height(menu_winid)? as usize;
+
+        if items <= height {
+            return self.close(api);
+        }
+
+        // The first line of the menu that's currently visible.
+        let topline = api.call_function::<_, usize>(
+            "line",
+            vec![
+                LuaValue::String(lua.create_string("w0")?),
+                LuaValue::Integer(menu_winid.into()),
+            ],
+        )?;
+
+        let (row, size) = get_thumb(height, items, topline - 1);
+
+        let opts = lua.create_table_with_capacity(0, 10)?;
+        opts.set("relative", "win")?;
+        opts.set("win", menu_winid)?;
+        opts.set("width", 1)?;
+        opts.set("height", size)?;
+        opts.set("row", row)?;
+        opts.set("col", api.win_get_width(menu_winid)? - 1)?;
+
+        match self.winid {
+            Some(winid) => api.win_set_config(winid, opts)?,
+
+            None => {
+                opts.set("focusable", false)?;
+                opts.set("style", "minimal")?;
+                opts.set("noautocmd", true)?;
+                // Make sure the thumb is drawn on top of the menu.
+                opts.set("zindex", 51)?;
+
+                let winid = api.open_win(self.bufnr, false, opts)?;
+                api.win_set_option(
+                    winid,
+                    "winhl",
+                    "Normal:CompleetMenuScrollbar",
+                )?;
+                // The thumb is as transparent as the menu it's drawn over.
+                api.win_set_option(
+                    winid,
+                    "winblend",
+                    api.win_get_option::<u8>(menu_winid, "winblend")?,
+                )?;
+                self.winid = Some(winid);
+            },
+        }
+
+        api.buf_set_lines(self.bufnr, 0, -1, false, &vec![" "; size])
+    }
+}
+
+/// Returns the `(row, size)` of the scrollbar's thumb for a menu of `height`
+/// rows displaying `items` items, the first visible one being the `top`-th.
+fn get_thumb(height: usize, items: usize, top: usize) -> (usize, usize) {
+    let size = cmp::max(1, height * height / items);
+
+    // The thumb should always reach the bottom of the menu when the last item
+    // is visible, no matter how the divisions rounded.
+    let row = match top + height >= items {
+        true => height - size,
+        false => cmp::min(top * height / items, height - size),
+    };
+
+    (row, size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::get_thumb;
+
+    #[test]
+    fn thumb_at_the_top() { assert_eq!((0, 5), get_thumb(10, 20, 0)) }
+
+    #[test]
+    fn thumb_at_the_bottom() {
+        assert_eq!((5, 5), get_thumb(10, 20, 10));
+        assert_eq!((7, 3), get_thumb(10, 30, 20));
+    }
+
+    #[test]
+    fn thumb_in_the_middle() { assert_eq!((3, 3), get_thumb(10, 30, 10)) }
+
+    #[test]
+    fn thumb_has_at_least_one_row() {
+        assert_eq!((0, 1), get_thumb(5, 1000, 0));
+        assert_eq!((2, 1), get_thumb(5, 1000, 500));
+        assert_eq!((4, 1), get_thumb(5, 1000, 995));
+    }
+}
@@ -0,0 +1,510 @@
+use std::ops::Range;
+
+use mlua::prelude::{Lua, LuaFunction, LuaResult, LuaValue};
+
+use super::columns::{self, source_label, Line};
+use super::{kinds, truncation};
+use crate::completion::unicode::display_width;
+use crate::completion::CompletionItem;
+use crate::settings::ui::menu::{MenuSettings, MENU_FORMATTER};
+
+/// The highlight group of the index labels shown by `ui.menu.index_labels`.
+const INDEX_LABEL_HL: &str = "CompleetMenuIndex";
+
+/// The number of completions that get an index label, i.e. the ones that can
+/// be inserted by `<Plug>(compleet-confirm-1)` through `-9`.
+pub const INDEX_LABELS: usize = 9;
+
+/// A piece of the `format` template.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Segment<'a> {
+    /// Text copied as is.
+    Literal(&'a str),
+
+    /// A `{placeholder}` replaced by a field of the completion.
+    Field(Field),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Detail,
+    Kind,
+    KindIcon,
+    Label,
+    Source,
+}
+
+/// The values the placeholders of the template are replaced with for a
+/// completion.
+struct Fields {
+    detail: String,
+    kind: String,
+    kind_icon: String,
+    label: String,
+
+    /// The ranges to highlight in the `label`.
+    label_hl_ranges: Vec<(Range<usize>, &'static str)>,
+
+    source: String,
+}
+
+/// Returns the lines displayed in the completion menu, formatted by the Lua
+/// function set as the `ui.menu.format` option, by its template if it's a
+/// string, or by the default layout if it's not set.
+pub fn render(
+    lua: &Lua,
+    completions: &[CompletionItem],
+    settings: &MenuSettings,
+) -> LuaResult<Vec<Line>> {
+    let mut lines = if let LuaValue::Function(formatter) =
+        lua.named_registry_value::<_, LuaValue>(MENU_FORMATTER)?
+    {
+        completions
+            .iter()
+            .map(|completion| {
+                call_formatter(lua, &formatter, completion, settings)
+            })
+            .collect::<LuaResult<Vec<Line>>>()?
+    } else {
+        match &settings.format {
+            Some(template) => lines(completions, settings, template),
+            None => columns::lines(completions, settings),
+        }
+    };
+
+    if settings.index_labels {
+        add_index_labels(&mut lines);
+    }
+
+    Ok(lines)
+}
+
+/// Prefixes the first `INDEX_LABELS` lines with their 1-indexed position in
+/// the menu, and pads the other ones to keep them aligned.
+fn add_index_labels(lines: &mut [Line]) {
+    for (index, line) in lines.iter_mut().enumerate() {
+        let label = match index < INDEX_LABELS {
+            true => format!("{} ", index + 1),
+            false => "  ".into(),
+        };
+
+        let shift = label.len();
+        line.text.insert_str(0, &label);
+
+        let shifted = |r: &Range<usize>| r.start + shift..r.end + shift;
+        line.kind = line.kind.as_ref().map(shifted);
+        line.source = line.source.as_ref().map(shifted);
+        for (range, _) in line.hl_ranges.iter_mut() {
+            *range = shifted(range);
+        }
+
+        if index < INDEX_LABELS {
+            line.hl_ranges.push((0..1, INDEX_LABEL_HL));
+        }
+    }
+}
+
+/// Returns the lines formatted by the `template`. Every placeholder is
+/// aligned in a column, so `{label} {kind}` looks like the default layout.
+fn lines(
+    completions: &[CompletionItem],
+    settings: &MenuSettings,
+    template: &str,
+) -> Vec<Line> {
+    let segments = parse(template);
+
+    let fields = completions
+        .iter()
+        .map(|completion| fields(completion, settings))
+        .collect::<Vec<Fields>>();
+
+    // The width of every segment, i.e. of its widest value.
+    let widths = segments
+        .iter()
+        .map(|segment| match segment {
+            Segment::Literal(_) => 0,
+            Segment::Field(field) => fields
+                .iter()
+                .map(|f| display_width(f.get(*field)))
+                .max()
+                .unwrap_or(0),
+        })
+        .collect::<Vec<usize>>();
+
+    fields
+        .into_iter()
+        .map(|fields| {
+            let mut text = String::new();
+            let mut kind: Option<Range<usize>> = None;
+            let mut source = None;
+            let mut hl_ranges = Vec::new();
+
+            for (segment, width) in segments.iter().zip(&widths) {
+                let field = match segment {
+                    Segment::Literal(literal) => {
+                        text.push_str(literal);
+                        continue;
+                    },
+                    Segment::Field(field) => *field,
+                };
+
+                let value = fields.get(field);
+                let start = text.len();
+                text.push_str(value);
+                let range = start..text.len();
+                text.push_str(&" ".repeat(width - display_width(value)));
+
+                match field {
+                    Field::Label => hl_ranges.extend(
+                        fields.label_hl_ranges.iter().map(|(r, hl_group)| {
+                            (r.start + start..r.end + start, *hl_group)
+                        }),
+                    ),
+                    Field::Kind | Field::KindIcon if !value.is_empty() => {
+                        kind = Some(match kind {
+                            Some(kind) => kind.start..range.end,
+                            None => range,
+                        })
+                    },
+                    Field::Source => source = Some(range),
+                    _ => {},
+                }
+            }
+
+            // The padding of the last column isn't needed.
+            text.truncate(text.trim_end_matches(' ').len());
+
+            Line {
+                kind: kind.map(|r| r.start..r.end.min(text.len())),
+                source: source.map(|r| r.start..r.end.min(text.len())),
+                text,
+                hl_ranges,
+            }
+        })
+        .collect()
+}
+
+/// Calls the Lua function set as the `ui.menu.format` option with a table of
+/// the fields of a completion, expecting the line to display. The matched
+/// characters, the kind and the source are highlighted if they appear in the
+/// line.
+fn call_formatter(
+    lua: &Lua,
+    formatter: &LuaFunction,
+    completion: &CompletionItem,
+    settings: &MenuSettings,
+) -> LuaResult<Line> {
+    let fields = fields(completion, settings);
+
+    let item = lua.create_table_with_capacity(0, 6)?;
+    item.set("detail", fields.detail.as_str())?;
+    item.set("kind", fields.kind.as_str())?;
+    item.set("kind_icon", fields.kind_icon.as_str())?;
+    item.set("label", fields.label.as_str())?;
+    item.set("source", completion.source)?;
+    item.set("text", completion.text.as_str())?;
+
+    let text = formatter.call::<_, String>(item)?;
+
+    Ok(formatted_line(text, &fields, completion.source))
+}
+
+/// Finds the fields of a completion in the `text` returned by the Lua
+/// formatter, so that it's highlighted like the lines of the templates. The
+/// `source` can appear either as its label (e.g. `[LSP]`) or as its name.
+fn formatted_line(text: String, fields: &Fields, source: &str) -> Line {
+    let label = text
+        .find(&fields.label)
+        .map(|start| start..start + fields.label.len());
+
+    // The first occurrence of `value` that isn't part of the label.
+    let find = |value: &str| {
+        if value.is_empty() {
+            return None;
+        }
+        text.match_indices(value)
+            .map(|(start, _)| start..start + value.len())
+            .find(|range| {
+                label.as_ref().map_or(true, |label| {
+                    range.end <= label.start || range.start >= label.end
+                })
+            })
+    };
+
+    let kind = match (find(&fields.kind_icon), find(&fields.kind)) {
+        (Some(icon), Some(kind)) => {
+            Some(icon.start.min(kind.start)..icon.end.max(kind.end))
+        },
+        (icon, kind) => icon.or(kind),
+    };
+
+    let source = find(&fields.source).or_else(|| find(source));
+
+    let hl_ranges = match &label {
+        Some(label) => fields
+            .label_hl_ranges
+            .iter()
+            .map(|(r, hl_group)| {
+                (r.start + label.start..r.end + label.start, *hl_group)
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+
+    Line {
+        text,
+        kind,
+        source,
+        hl_ranges,
+    }
+}
+
+fn fields(completion: &CompletionItem, settings: &MenuSettings) -> Fields {
+    let (format, hl_ranges) =
+        truncation::truncate(completion, settings.max_width);
+
+    // The formats of the sources start with a space separating them from the
+    // left edge of the menu, which is up to the template now.
+    let (label, label_hl_ranges) = match format.strip_prefix(' ') {
+        Some(label) => (
+            label.to_owned(),
+            hl_ranges
+                .into_iter()
+                .filter(|(r, _)| r.end > 0)
+                .map(|(r, hl_group)| {
+                    (r.start.saturating_sub(1)..r.end - 1, hl_group)
+                })
+                .collect(),
+        ),
+        None => (format, hl_ranges),
+    };
+
+    let detail = completion
+        .details
+        .as_ref()
+        .and_then(|details| details.lines.first())
+        .cloned()
+        .unwrap_or_default();
+
+    Fields {
+        detail,
+        kind: completion.kind.unwrap_or_default().to_owned(),
+        kind_icon: completion
+            .kind
+            .map_or("", |kind| kinds::icon(kind, settings))
+            .to_owned(),
+        label,
+        label_hl_ranges,
+        source: source_label(completion.source),
+    }
+}
+
+impl Fields {
+    fn get(&self, field: Field) -> &str {
+        match field {
+            Field::Detail => &self.detail,
+            Field::Kind => &self.kind,
+            Field::KindIcon => &self.kind_icon,
+            Field::Label => &self.label,
+            Field::Source => &self.source,
+        }
+    }
+}
+
+/// Splits a template into its segments. Unknown placeholders are kept as
+/// literal text.
+fn parse(template: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+
+    // The start of the literal text before the next placeholder.
+    let mut literal_start = 0;
+    let mut pos = 0;
+
+    while let Some(open) = template[pos..].find('{').map(|i| pos + i) {
+        let close = match template[open..].find('}') {
+            Some(close) => open + close,
+            None => break,
+        };
+
+        let field = match &template[open + 1..close] {
+            "detail" => Field::Detail,
+            "kind" => Field::Kind,
+            "kind_icon" => Field::KindIcon,
+            "label" => Field::Label,
+            "source" => Field::Source,
+            _ => {
+                pos = close + 1;
+                continue;
+            },
+        };
+
+        if open > literal_start {
+            segments.push(Segment::Literal(&template[literal_start..open]));
+        }
+        segments.push(Segment::Field(field));
+        literal_start = close + 1;
+        pos = close + 1;
+    }
+
+    if literal_start < template.len() {
+        segments.push(Segment::Literal(&template[literal_start..]));
+    }
+
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        add_index_labels,
+        fields,
+        formatted_line,
+        lines,
+        parse,
+        Field,
+        Segment,
+    };
+    use crate::completion::{CompletionItem, Details};
+    use crate::settings::ui::menu::MenuSettings;
+    use crate::ui::menu::Line;
+
+    fn item(
+        format: &str,
+        kind: Option<&'static str>,
+        detail: Option<&str>,
+    ) -> CompletionItem {
+        CompletionItem {
+            details: detail.map(|detail| Details {
+                lines: vec![detail.into()],
+                is_markdown: false,
+            }),
+            format: format.into(),
+            hl_ranges: vec![(1..2, "CompleetMenuMatchingChars")],
+            kind,
+            matched_bytes: 1,
+            source: "Lsp",
+            text: format.trim().into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parse_template() {
+        assert_eq!(
+            vec![
+                Segment::Field(Field::KindIcon),
+                Segment::Literal(" "),
+                Segment::Field(Field::Label),
+                Segment::Literal(" {foo} "),
+                Segment::Field(Field::Detail),
+                Segment::Literal(" {"),
+            ],
+            parse("{kind_icon} {label} {foo} {detail} {")
+        );
+    }
+
+    #[test]
+    fn columns_are_aligned() {
+        let settings = MenuSettings {
+            kind_icons: [("Function".into(), "ƒ".into())].into(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            vec![
+                Line {
+                    text: "ƒ foo    fn foo()".into(),
+                    kind: Some(0..2),
+                    source: None,
+                    hl_ranges: vec![(3..4, "CompleetMenuMatchingChars")],
+                },
+                Line {
+                    text: "  barbaz".into(),
+                    kind: None,
+                    source: None,
+                    hl_ranges: vec![(2..3, "CompleetMenuMatchingChars")],
+                },
+            ],
+            lines(
+                &[
+                    item(" foo", Some("Function"), Some("fn foo()")),
+                    item(" barbaz", None, None),
+                ],
+                &settings,
+                "{kind_icon} {label} {detail}"
+            )
+        );
+    }
+
+    #[test]
+    fn sources_and_kinds() {
+        assert_eq!(
+            vec![Line {
+                text: "[LSP] foo Text".into(),
+                kind: Some(10..14),
+                source: Some(0..5),
+                hl_ranges: vec![(6..7, "CompleetMenuMatchingChars")],
+            }],
+            lines(
+                &[item(" foo", Some("Text"), None)],
+                &MenuSettings::default(),
+                "{source} {label} {kind}"
+            )
+        );
+    }
+
+    #[test]
+    fn lua_formatter_ranges() {
+        let settings = MenuSettings {
+            kind_icons: [("Function".into(), "ƒ".into())].into(),
+            ..Default::default()
+        };
+        let foo = fields(&item(" foo", Some("Function"), None), &settings);
+
+        assert_eq!(
+            Line {
+                text: "ƒ Function foo [LSP]".into(),
+                kind: Some(0..11),
+                source: Some(16..21),
+                hl_ranges: vec![(12..13, "CompleetMenuMatchingChars")],
+            },
+            formatted_line("ƒ Function foo [LSP]".into(), &foo, "Lsp")
+        );
+
+        // The source isn't looked for inside the label.
+        let lsp = fields(&item(" Lsp", None, None), &settings);
+        assert_eq!(
+            Line {
+                text: "Lsp Lsp".into(),
+                kind: None,
+                source: Some(4..7),
+                hl_ranges: vec![(0..1, "CompleetMenuMatchingChars")],
+            },
+            formatted_line("Lsp Lsp".into(), &lsp, "Lsp")
+        );
+    }
+
+    #[test]
+    fn index_labels() {
+        let items = (0..10)
+            .map(|_| item(" foo", None, None))
+            .collect::<Vec<_>>();
+        let mut lines = lines(&items, &MenuSettings::default(), "{label}");
+        add_index_labels(&mut lines);
+
+        assert_eq!("1 foo", lines[0].text);
+        assert_eq!(
+            vec![
+                (2..3, "CompleetMenuMatchingChars"),
+                (0..1, "CompleetMenuIndex")
+            ],
+            lines[0].hl_ranges
+        );
+        assert_eq!("9 foo", lines[8].text);
+        assert_eq!("  foo", lines[9].text);
+        assert_eq!(
+            vec![(2..3, "CompleetMenuMatchingChars")],
+            lines[9].hl_ranges
+        );
+    }
+}
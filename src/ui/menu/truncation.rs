@@ -0,0 +1,210 @@
+use std::num::NonZeroU32;
+use std::ops::Range;
+
+use crate::completion::unicode::{display_width, grapheme_end};
+use crate::completion::{CompletionItem, Details};
+use crate::settings::ui::menu::MenuSettings;
+
+/// The character appended to the truncated labels.
+const ELLIPSIS: char = '…';
+
+/// The marker appended to the labels of the completions spanning multiple
+/// lines.
+const MULTILINE_MARKER: &str = " ↵";
+
+/// Returns the label of a completion and the ranges to highlight in it,
+/// truncating the label with an ellipsis if it's wider than `max_width`
+/// columns. The highlighted ranges are clipped to the truncated label.
+pub fn truncate(
+    completion: &CompletionItem,
+    max_width: Option<NonZeroU32>,
+) -> (String, Vec<(Range<usize>, &'static str)>) {
+    let (format, hl_ranges) = collapse(completion);
+    let format = format.as_str();
+
+    let max_width = match max_width {
+        Some(width) if display_width(format) > width.get() as usize => {
+            width.get() as usize
+        },
+        _ => return (format.to_owned(), hl_ranges),
+    };
+
+    // Only whole grapheme clusters are kept, leaving room for the ellipsis.
+    let (mut cut, mut width) = (0, 0);
+    while let Some(c) = format[cut..].chars().next() {
+        let end = grapheme_end(format, cut + c.len_utf8());
+        let grapheme_width = display_width(&format[cut..end]);
+        if width + grapheme_width + 1 > max_width {
+            break;
+        }
+        width += grapheme_width;
+        cut = end;
+    }
+
+    let mut label = format[..cut].to_owned();
+    label.push(ELLIPSIS);
+
+    (label, clip(&hl_ranges, cut))
+}
+
+/// Returns the format of a completion and the ranges to highlight in it. The
+/// menu can only show one line per completion, so multi-line completions
+/// show the first line of their format followed by a marker.
+fn collapse(
+    completion: &CompletionItem,
+) -> (String, Vec<(Range<usize>, &'static str)>) {
+    let format = &completion.format;
+
+    if !completion.text.contains('\n') && !format.contains('\n') {
+        return (format.clone(), completion.hl_ranges.clone());
+    }
+
+    let first_line = format.lines().next().unwrap_or("").trim_end();
+    let hl_ranges = clip(&completion.hl_ranges, first_line.len());
+
+    (format!("{first_line}{MULTILINE_MARKER}"), hl_ranges)
+}
+
+/// Clips the highlighted ranges to the first `len` bytes of the label.
+fn clip(
+    hl_ranges: &[(Range<usize>, &'static str)],
+    len: usize,
+) -> Vec<(Range<usize>, &'static str)> {
+    hl_ranges
+        .iter()
+        .filter(|(range, _)| range.start < len)
+        .map(|(range, hl_group)| (range.start..range.end.min(len), *hl_group))
+        .collect()
+}
+
+/// Returns the details to show for a completion. The full label of the
+/// completions whose label is truncated in the menu is shown above their
+/// details.
+pub fn details(
+    completion: &CompletionItem,
+    settings: &MenuSettings,
+) -> Option<Details> {
+    let is_truncated = matches!(
+        settings.max_width,
+        Some(width) if display_width(&completion.format) > width.get() as usize
+    );
+
+    if !is_truncated {
+        return completion.details.clone();
+    }
+
+    let label = completion.format.trim().to_owned();
+
+    Some(match &completion.details {
+        Some(details) => Details {
+            lines: [label, String::new()]
+                .into_iter()
+                .chain(details.lines.iter().cloned())
+                .collect(),
+            is_markdown: details.is_markdown,
+        },
+        None => Details {
+            lines: vec![label],
+            is_markdown: false,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use super::{details, truncate};
+    use crate::completion::{CompletionItem, Details};
+    use crate::settings::ui::menu::MenuSettings;
+
+    fn item(format: &str, hl_ranges: &[(usize, usize)]) -> CompletionItem {
+        CompletionItem {
+            format: format.into(),
+            hl_ranges: hl_ranges
+                .iter()
+                .map(|&(start, end)| (start..end, "Hl"))
+                .collect(),
+            source: "Buffer",
+            text: format.trim().into(),
+            ..Default::default()
+        }
+    }
+
+    fn truncated(
+        format: &str,
+        hl_ranges: &[(usize, usize)],
+        max_width: u32,
+    ) -> (String, Vec<(usize, usize)>) {
+        let (label, ranges) =
+            truncate(&item(format, hl_ranges), NonZeroU32::new(max_width));
+        (
+            label,
+            ranges.into_iter().map(|(r, _)| (r.start, r.end)).collect(),
+        )
+    }
+
+    #[test]
+    fn short_labels_are_untouched() {
+        assert_eq!(
+            (" foo".into(), vec![(1, 2)]),
+            truncated(" foo", &[(1, 2)], 4)
+        );
+    }
+
+    #[test]
+    fn ranges_are_clipped() {
+        assert_eq!(
+            (" foo…".into(), vec![(1, 2), (3, 4)]),
+            truncated(" foobar", &[(1, 2), (3, 5), (6, 7)], 5)
+        );
+    }
+
+    #[test]
+    fn wide_chars_are_not_split() {
+        // `日` and `本` take up two columns each.
+        assert_eq!(
+            (" 日…".into(), vec![(1, 4)]),
+            truncated(" 日本語", &[(1, 7)], 5)
+        );
+    }
+
+    #[test]
+    fn multiline_labels_are_collapsed() {
+        let completion = CompletionItem {
+            text: "if x {\n}".into(),
+            ..item(" if x {\n}", &[(1, 3), (7, 9)])
+        };
+
+        assert_eq!(
+            (" if x { ↵".into(), vec![(1..3, "Hl")]),
+            truncate(&completion, None)
+        );
+    }
+
+    #[test]
+    fn full_label_in_details() {
+        let settings = MenuSettings {
+            max_width: NonZeroU32::new(4),
+            ..Default::default()
+        };
+
+        let completion = CompletionItem {
+            details: Some(Details {
+                lines: vec!["fn foobar()".into()],
+                is_markdown: false,
+            }),
+            ..item(" foobar", &[])
+        };
+
+        assert_eq!(
+            Some(Details {
+                lines: vec!["foobar".into(), "".into(), "fn foobar()".into()],
+                is_markdown: false,
+            }),
+            details(&completion, &settings)
+        );
+
+        assert_eq!(None, details(&item(" foo", &[]), &settings));
+    }
+}
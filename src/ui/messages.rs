@@ -0,0 +1,143 @@
+use mlua::prelude::{Lua, LuaFunction, LuaResult, LuaTable};
+use neovim::api::{Api, LogLevel};
+
+use crate::settings::ui::messages::{MessageId, MessagesSettings};
+
+/// A message displayed to the user, made of chunks of text with optional
+/// highlight groups. Messages are echoed with their highlights or sent
+/// through `vim.notify` as plain text, depending on the `ui.messages`
+/// option.
+#[derive(Debug)]
+pub struct Message {
+    level: LogLevel,
+
+    /// The id used to silence the message, or `None` if it can't be
+    /// silenced.
+    id: Option<MessageId>,
+
+    chunks: Vec<(String, Option<&'static str>)>,
+}
+
+impl Message {
+    pub fn error() -> Self { Message::new(LogLevel::Error, None) }
+
+    pub fn warn(id: MessageId) -> Self {
+        Message::new(LogLevel::Warn, Some(id))
+    }
+
+    pub fn info(id: MessageId) -> Self {
+        Message::new(LogLevel::Info, Some(id))
+    }
+
+    fn new(level: LogLevel, id: Option<MessageId>) -> Self {
+        Message {
+            level,
+            id,
+            chunks: Vec::new(),
+        }
+    }
+
+    /// Appends some text without highlighting.
+    pub fn text<S: Into<String>>(mut self, text: S) -> Self {
+        self.chunks.push((text.into(), None));
+        self
+    }
+
+    /// Appends some text highlighted with `hl_group`.
+    pub fn hl<S: Into<String>>(
+        mut self,
+        text: S,
+        hl_group: &'static str,
+    ) -> Self {
+        self.chunks.push((text.into(), Some(hl_group)));
+        self
+    }
+
+    /// Appends some text, highlighting the parts enclosed by backticks with
+    /// `CompleetErrorMsgField`.
+    pub fn fields(mut self, text: &str) -> Self {
+        for (i, chunk) in text.split('`').enumerate() {
+            if i % 2 == 1 {
+                self = self
+                    .text("`")
+                    .hl(chunk, "CompleetErrorMsgField")
+                    .text("`");
+            } else if !chunk.is_empty() {
+                self = self.text(chunk);
+            }
+        }
+        self
+    }
+
+    /// The text of the message without highlights.
+    fn plain_text(&self) -> String {
+        self.chunks.iter().map(|(text, _)| text.as_str()).collect()
+    }
+
+    /// The highlight group of the `[nvim-compleet]` tag of echoed messages.
+    fn tag_hl_group(&self) -> Option<&'static str> {
+        match self.level {
+            LogLevel::Error => Some("CompleetErrorMsgTag"),
+            LogLevel::Warn => Some("CompleetWarningMsgTag"),
+            _ => None,
+        }
+    }
+}
+
+/// Displays a message, unless it's been silenced by the `ui.messages.silence`
+/// option.
+pub fn send(
+    lua: &Lua,
+    api: &Api,
+    settings: &MessagesSettings,
+    message: Message,
+) -> LuaResult<()> {
+    if message.id.is_some_and(|id| settings.silence.contains(&id)) {
+        return Ok(());
+    }
+
+    if settings.notify {
+        let opts = lua.create_table_from([("title", "nvim-compleet")])?;
+        return lua
+            .globals()
+            .get::<_, LuaTable>("vim")?
+            .get::<_, LuaFunction>("notify")?
+            .call((message.plain_text(), message.level as u8, opts));
+    }
+
+    let mut chunks =
+        vec![("[nvim-compleet]", message.tag_hl_group()), (" ", None)];
+    chunks.extend(
+        message
+            .chunks
+            .iter()
+            .map(|(text, hl_group)| (text.as_str(), *hl_group)),
+    );
+    api.echo(&chunks, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Message;
+
+    #[test]
+    fn fields_are_highlighted() {
+        let message =
+            Message::error().fields("invalid type `foo`, expected `bar`");
+
+        assert_eq!(
+            vec![
+                ("invalid type ".to_owned(), None),
+                ("`".to_owned(), None),
+                ("foo".to_owned(), Some("CompleetErrorMsgField")),
+                ("`".to_owned(), None),
+                (", expected ".to_owned(), None),
+                ("`".to_owned(), None),
+                ("bar".to_owned(), Some("CompleetErrorMsgField")),
+                ("`".to_owned(), None),
+            ],
+            message.chunks
+        );
+        assert_eq!("invalid type `foo`, expected `bar`", message.plain_text());
+    }
+}
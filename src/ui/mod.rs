@@ -1,8 +1,11 @@
 pub mod details;
+pub mod highlights;
 pub mod hint;
+pub mod loading;
 pub mod menu;
+pub mod messages;
 mod ui;
 mod window_position;
 
 pub use ui::Ui;
-use window_position::WindowPosition;
+pub use window_position::WindowPosition;
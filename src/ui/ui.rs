@@ -1,4 +1,5 @@
 use std::cmp;
+use std::time::Instant;
 
 use mlua::{prelude::LuaResult, Lua};
 use neovim::Api;
@@ -6,9 +7,14 @@ use neovim::Api;
 use super::{
     details::CompletionDetails,
     hint::CompletionHint,
-    menu::{self, CompletionMenu},
+    loading::LoadingIndicator,
+    menu::{self, CompletionMenu, Line},
+    WindowPosition,
 };
 use crate::completion::{CompletionItem, Cursor};
+use crate::events::{self, Event};
+use crate::phase::Transition;
+use crate::profiling::{self, Stage};
 use crate::settings::Settings;
 
 /// `nvim-compleet`'s UI is composed of the following 3 independent pieces.
@@ -25,23 +31,43 @@ pub struct Ui {
     /// A details pane used to show some informations about the currently
     /// selected completion item.
     pub completion_details: CompletionDetails,
+
+    /// A virtual text shown while the completions are still being computed
+    /// and the menu is empty.
+    pub loading_indicator: LoadingIndicator,
+
+    /// Whether the completions are shown in the native popup menu, either
+    /// because of the `experimental.native_menu` option or because the
+    /// completion menu's window couldn't be opened.
+    is_native_menu_visible: bool,
+
+    /// The window the UI was last shown in, or `None` if nothing is shown.
+    /// The same buffer can be displayed in several windows, and the UI only
+    /// belongs to one of them.
+    winid: Option<u32>,
 }
 
 impl Ui {
     pub fn new(api: &Api) -> LuaResult<Self> {
         Ok(Ui {
-            completion_menu: CompletionMenu::new(api)?,
+            completion_menu: CompletionMenu::new(api, "cursor")?,
             completion_hint: CompletionHint::new(api)?,
             completion_details: CompletionDetails::new(api)?,
+            loading_indicator: LoadingIndicator::new(api)?,
+            is_native_menu_visible: false,
+            winid: None,
         })
     }
 }
 
 impl Ui {
     /// Executed on every `InsertLeave` event in attached buffers.
-    pub fn cleanup(&mut self, api: &Api) -> LuaResult<()> {
+    pub fn cleanup(&mut self, lua: &Lua, api: &Api) -> LuaResult<()> {
+        self.winid = None;
+
         if self.completion_menu.is_visible() {
-            self.completion_menu.close(api)?;
+            self.completion_menu.close(lua, api)?;
+            events::fire(lua, Event::MenuClosed)?;
 
             // The details window can only be visible if the completion menu is
             // visible.
@@ -54,10 +80,93 @@ impl Ui {
             self.completion_hint.erase(api)?;
         }
 
+        if self.loading_indicator.is_visible() {
+            self.loading_indicator.erase(api)?;
+        }
+
+        // The native popup menu is closed by Neovim when leaving insert mode
+        // and when one of its items is confirmed.
+        if self.is_native_menu_visible {
+            self.is_native_menu_visible = false;
+            if api.get_mode()?.0.starts_with('i')
+                && menu::native::is_visible(lua)?
+            {
+                menu::native::close(lua, api)?;
+            }
+        }
+
         Ok(())
     }
 
+    /// Moves the UI to the current window, closing it and forgetting its
+    /// selection if it was shown in another one displaying the same buffer.
+    /// Returns whether it was, in which case the completions computed for
+    /// the cursor of the other window are outdated.
+    pub fn follow_current_window(
+        &mut self,
+        lua: &Lua,
+        api: &Api,
+    ) -> LuaResult<bool> {
+        let winid = api.get_current_win()?;
+        let has_moved = self.winid.is_some_and(|old| old != winid);
+        if has_moved {
+            self.cleanup(lua, api)?;
+            self.completion_menu.selected_index = None;
+        }
+        self.winid = Some(winid);
+        Ok(has_moved)
+    }
+
+    /// The `Display` transition describing what the UI is showing, given
+    /// whether some sources are still computing their completions.
+    pub fn displayed(&self, is_loading: bool) -> Transition {
+        let menu = &self.completion_menu;
+        Transition::Display {
+            menu: menu.is_visible(),
+            selected: menu.selected_index,
+            hint: self.completion_hint.is_visible(),
+            is_loading,
+        }
+    }
+
+    /// Whether the completions are shown in Neovim's native popup menu.
+    pub fn is_native_menu_visible(&self) -> bool {
+        self.is_native_menu_visible
+    }
+
+    /// Opens the completion menu at `position` and fills it with the
+    /// completions. With the `experimental.native_menu` option set, or if its
+    /// window can't be opened, the completions are shown in the native popup
+    /// menu instead.
+    #[allow(clippy::too_many_arguments)]
+    pub fn spawn_menu(
+        &mut self,
+        lua: &Lua,
+        api: &Api,
+        position: &WindowPosition,
+        completions: &[CompletionItem],
+        lines: &[Line],
+        cursor: &Cursor,
+        settings: &Settings,
+    ) -> LuaResult<()> {
+        let menu = &mut self.completion_menu;
+
+        if settings.experimental.native_menu
+            || !menu.spawn(lua, api, position, &settings.ui.menu)?
+        {
+            self.is_native_menu_visible = true;
+            return menu::native::show(lua, api, completions, cursor);
+        }
+
+        menu.fill(lua, api, completions, lines, &settings.ui.menu.highlights)?;
+        let items = completions.len();
+        events::fire(lua, Event::MenuOpened { items })?;
+        self.preselect(lua, api, completions, cursor, settings)
+    }
+
     /// Executed on every `CursorMovedI` event in attached buffers.
+    /// `is_loading` is whether some sources are still computing their
+    /// completions.
     pub fn update(
         &mut self,
         lua: &Lua,
@@ -65,12 +174,39 @@ impl Ui {
         completions: &[CompletionItem],
         cursor: &Cursor,
         settings: &Settings,
+        is_loading: bool,
+    ) -> LuaResult<()> {
+        let start = Instant::now();
+        self.update_windows(lua, api, completions, cursor, settings)?;
+
+        // The indicator is removed once there's something in the menu.
+        let indicator = &mut self.loading_indicator;
+        if is_loading
+            && settings.ui.menu.loading_indicator
+            && !self.completion_menu.is_visible()
+        {
+            indicator.show(lua, api, cursor)?;
+        } else if indicator.is_visible() {
+            indicator.erase(api)?;
+        }
+
+        profiling::record(Stage::Rendering, start.elapsed());
+        Ok(())
+    }
+
+    fn update_windows(
+        &mut self,
+        lua: &Lua,
+        api: &Api,
+        completions: &[CompletionItem],
+        cursor: &Cursor,
+        settings: &Settings,
     ) -> LuaResult<()> {
         // If there are no completions to display simply cleanup the UI and
         // return early.
         if completions.is_empty() {
             // TODO: reset selected & hinted indexes.
-            self.cleanup(api)?;
+            self.cleanup(lua, api)?;
             return Ok(());
         }
 
@@ -84,20 +220,43 @@ impl Ui {
         }
 
         // Let's first update the completion hint.
-        if settings.ui.hint.enable && cursor.is_at_eol() {
+        // If no completion is selected we hint the first one.
+        if settings.ui.hint.enable {
             let index = menu.selected_index.unwrap_or(0);
-            let completion = &completions[index];
-            let text = &completion.text[(completion.matched_bytes as usize)..];
-            hint.set(lua, api, text, cursor, index)?;
+            hint.update(lua, api, completions, index, cursor)?;
         } else if hint.is_visible() {
             hint.erase(api)?;
         }
 
+        // If the menu isn't shown automatically it's only updated once it's
+        // been opened by `<Plug>(compleet-show-completions)`.
+        if !menu.is_visible()
+            && !self.is_native_menu_visible
+            && !settings.ui.menu.autoshow
+        {
+            return Ok(());
+        }
+
+        // The native popup menu doesn't need to be positioned. Once one of
+        // its items is selected Neovim has inserted its text, and the menu is
+        // left alone until it's closed.
+        if settings.experimental.native_menu {
+            if menu::native::is_item_selected(lua)? {
+                return Ok(());
+            }
+            self.is_native_menu_visible = true;
+            return menu::native::show(lua, api, completions, cursor);
+        }
+
         // Now the completion menu. The first step is to compute how big it
         // should be and where it should be placed relative to the cursor.
+        let lines = menu::render(lua, completions, &settings.ui.menu)?;
+
         let menu_position = match menu::positioning::get_position(
             api,
             completions,
+            cursor,
+            &lines,
             &settings.ui.menu,
         )? {
             Some(position) => position,
@@ -105,12 +264,20 @@ impl Ui {
             // If it wasn't possible to get a position for the menu we just
             // clean the menu and the details window, then return.
             None => {
-                menu.close(api)?;
+                if menu.is_visible() {
+                    menu.close(lua, api)?;
+                    events::fire(lua, Event::MenuClosed)?;
+                }
                 details.close(api)?;
                 return Ok(());
             },
         };
 
+        // Show the best completion next to the cursor if the menu is above it.
+        menu.set_reversed(
+            settings.ui.menu.direction_aware && menu_position.row < 0,
+        );
+
         // If the menu was already visible we move it to its new position.
         if let Some(winid) = menu.winid {
             menu.shift(lua, api, &menu_position)?;
@@ -128,15 +295,17 @@ impl Ui {
                 api.win_set_option(winid, "cursorline", true)?;
 
                 // Set the cursor row to the selected completion.
-                api.win_set_cursor(winid, (index + 1).try_into().unwrap(), 0)?;
+                let row = menu.row(index) + 1;
+                api.win_set_cursor(winid, row.try_into().unwrap(), 0)?;
 
                 // Update the completion details.
-                let lines = completions[index].details.as_ref();
+                let lines =
+                    menu::details(&completions[index], &settings.ui.menu);
                 details.update(
                     lua,
                     api,
-                    lines,
-                    &settings.ui.details.border,
+                    lines.as_ref(),
+                    &settings.ui.details,
                     menu_position.width,
                     winid,
                     &settings.ui.menu.border,
@@ -150,11 +319,90 @@ impl Ui {
         }
         // If the menu wasn't visible we create a new window.
         else {
-            menu.spawn(lua, api, &menu_position, &settings.ui.menu.border)?;
+            return self.spawn_menu(
+                lua,
+                api,
+                &menu_position,
+                completions,
+                &lines,
+                cursor,
+                settings,
+            );
         }
 
         // Finally, we fill the menu's buffer with the new completion items.
-        menu.fill(lua, api, completions)?;
+        menu.fill(
+            lua,
+            api,
+            completions,
+            &lines,
+            &settings.ui.menu.highlights,
+        )?;
+
+        if settings.ui.menu.scrollbar {
+            menu.update_scrollbar(lua, api, completions.len())?;
+        }
+
+        Ok(())
+    }
+
+    /// Executed right after the completion menu is opened. Selects the
+    /// completion picked by the `completion.preselect` setting, updating the
+    /// details window and the hint accordingly.
+    pub fn preselect(
+        &mut self,
+        lua: &Lua,
+        api: &Api,
+        completions: &[CompletionItem],
+        cursor: &Cursor,
+        settings: &Settings,
+    ) -> LuaResult<()> {
+        let menu = &mut self.completion_menu;
+
+        let index = menu::preselected_index(
+            completions,
+            settings.completion.preselect,
+        );
+
+        if index.is_some() {
+            menu.select(lua, api, index)?;
+        }
+
+        if settings.ui.menu.scrollbar {
+            menu.update_scrollbar(lua, api, completions.len())?;
+        }
+
+        let index = match index {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+
+        let winid = menu
+            .winid
+            .expect("The menu is visible so it has a window id");
+        let width = menu.width.expect("The menu is visible so it has a width");
+
+        let lines = menu::details(&completions[index], &settings.ui.menu);
+        self.completion_details.update(
+            lua,
+            api,
+            lines.as_ref(),
+            &settings.ui.details,
+            width,
+            winid,
+            &settings.ui.menu.border,
+            false,
+        )?;
+
+        if settings.ui.hint.enable {
+            self.completion_hint.update(
+                lua,
+                api,
+                completions,
+                index,
+                cursor,
+            )?;
+        }
 
         Ok(())
     }
@@ -0,0 +1,110 @@
+//! Runs the Lua specs in `tests/lua` in headless Neovim instances with the
+//! compiled library loaded. The specs script the keys typed in insert mode
+//! and assert on the contents of the completion menu. They're skipped if
+//! there's no `nvim` executable in `$PATH`, except in CI (i.e. when `$CI` is
+//! set), where they fail instead so that they can't silently stop running.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use std::{env, fs, thread};
+
+/// How long a spec can run before it's considered stuck, e.g. because an
+/// error kept Neovim from quitting.
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+fn has_nvim() -> bool {
+    Command::new("nvim")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Copies the library built by Cargo to a new directory as `compleet.so`, so
+/// that it can be loaded with `require("compleet")`.
+fn install_library(spec: &str) -> PathBuf {
+    // The test executable is in `target/<profile>/deps`.
+    let profile_dir = env::current_exe()
+        .unwrap()
+        .parent()
+        .and_then(Path::parent)
+        .expect("the test executable is in `target/<profile>/deps`")
+        .to_owned();
+
+    let library = ["libcompleet.so", "libcompleet.dylib"]
+        .iter()
+        .map(|name| profile_dir.join(name))
+        .find(|path| path.exists())
+        .expect("the library has been built");
+
+    let dir = env::temp_dir()
+        .join(format!("compleet-headless-{}-{spec}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::copy(library, dir.join("compleet.so")).unwrap();
+    dir
+}
+
+fn run_spec(spec: &str) {
+    if !has_nvim() {
+        if env::var_os("CI").is_some() {
+            panic!("`{spec}` can't run: `nvim` isn't in $PATH");
+        }
+        eprintln!("skipping `{spec}`: `nvim` isn't in $PATH");
+        return;
+    }
+
+    let lib_dir = install_library(spec);
+    let lua_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/lua");
+
+    let mut child = Command::new("nvim")
+        .args(["--headless", "--clean", "-n", "-i", "NONE"])
+        .arg("--cmd")
+        .arg(format!(
+            "lua package.path = '{}/?.lua;' .. package.path",
+            lua_dir.display()
+        ))
+        .arg("-c")
+        .arg(format!("lua require('harness').run('{spec}')"))
+        .env("COMPLEET_LIB_DIR", &lib_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("couldn't spawn `nvim`");
+
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break Some(status);
+        }
+        if start.elapsed() > TIMEOUT {
+            child.kill().unwrap();
+            break None;
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+
+    let output = child.wait_with_output().unwrap();
+    let _ = fs::remove_dir_all(&lib_dir);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    match status {
+        Some(status) if status.success() => {},
+        Some(_) => panic!("`{spec}` failed:\n{stderr}"),
+        None => panic!("`{spec}` timed out:\n{stderr}"),
+    }
+}
+
+#[test]
+fn spawn_menu() { run_spec("spawn_spec") }
+
+#[test]
+fn select_completion() { run_spec("select_spec") }
+
+#[test]
+fn confirm_completion() { run_spec("confirm_spec") }
+
+#[test]
+fn abort_completion() { run_spec("abort_spec") }
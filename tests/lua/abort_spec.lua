@@ -0,0 +1,11 @@
+local h = require("harness")
+
+local compleet = h.setup({ completion = { insert_on_select = true } })
+
+h.insert("fo<C-n><C-e>", function()
+  h.eq(nil, h.menu_winid())
+  h.eq(false, compleet.is_menu_visible())
+
+  -- The previewed completion is replaced by the typed text.
+  h.eq({ "fo" }, vim.api.nvim_buf_get_lines(0, 0, -1, false))
+end)
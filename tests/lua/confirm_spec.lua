@@ -0,0 +1,17 @@
+local h = require("harness")
+
+local compleet = h.setup()
+
+local confirmed
+vim.api.nvim_create_autocmd("User", {
+  pattern = "CompleetItemConfirmed",
+  callback = function(args)
+    confirmed = args.data.text
+  end,
+})
+
+h.insert("fo<C-n><C-y>", function()
+  h.eq(true, confirmed == "foobar" or confirmed == "foobaz")
+  h.eq({ confirmed }, vim.api.nvim_buf_get_lines(0, 0, -1, false))
+  h.eq(false, compleet.is_completion_selected())
+end)
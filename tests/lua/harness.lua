@@ -0,0 +1,120 @@
+-- Helpers used by the specs run by `tests/headless.rs`. Every spec runs in
+-- a new Neovim instance, which exits with a non-zero status if any of its
+-- assertions failed.
+
+local M = {}
+
+local failures = {}
+
+local function termcodes(keys)
+  return vim.api.nvim_replace_termcodes(keys, true, false, true)
+end
+
+-- Loads the compiled library from the directory set by the Rust runner and
+-- sets it up with `config`. A Lua source completing `words` is registered,
+-- and the `<Plug>` mappings are mapped to `<C-n>`, `<C-y>` and `<C-e>`.
+function M.setup(config, words)
+  package.cpath = os.getenv("COMPLEET_LIB_DIR") .. "/?.so;" .. package.cpath
+
+  local compleet = require("compleet")
+  compleet.setup(config or {})
+
+  compleet.register_source({
+    name = "Words",
+    complete = function()
+      return words or { "foobar", "foobaz", "qux" }
+    end,
+  })
+
+  vim.keymap.set("i", "<C-n>", "<Plug>(compleet-next-completion)")
+  vim.keymap.set("i", "<C-y>", "<Plug>(compleet-confirm-completion)")
+  vim.keymap.set("i", "<C-e>", "<Plug>(compleet-abort)")
+
+  -- The buffer has to be attached again now that there's a source.
+  vim.cmd("enew")
+
+  return compleet
+end
+
+-- Types `keys` in insert mode starting from the end of the buffer, then
+-- calls `check` once the completions have been computed and the UI has been
+-- updated, before going back to normal mode.
+function M.insert(keys, check)
+  vim.defer_fn(function()
+    local ok, err = pcall(check)
+    if not ok then
+      table.insert(failures, err)
+    end
+    vim.api.nvim_feedkeys(termcodes("<Esc>"), "n", false)
+  end, 200)
+
+  -- The `!` keeps Neovim in insert mode until `<Esc>` is fed.
+  vim.api.nvim_feedkeys(termcodes("GA" .. keys), "mtx!", false)
+end
+
+-- Returns the handle of the completion menu's window, which is the visible
+-- floating window containing the highlights of the matched characters, or
+-- `nil` if the menu is closed. Closed menus are only hidden on Neovim 0.10+.
+function M.menu_winid()
+  local nsid = vim.api.nvim_get_namespaces()["compleet_matched_chars"]
+  for _, winid in ipairs(vim.api.nvim_list_wins()) do
+    local config = vim.api.nvim_win_get_config(winid)
+    if config.relative ~= "" and not config.hide then
+      local bufnr = vim.api.nvim_win_get_buf(winid)
+      if #vim.api.nvim_buf_get_extmarks(bufnr, nsid, 0, -1, {}) > 0 then
+        return winid
+      end
+    end
+  end
+end
+
+-- Returns the lines of the completion menu, with the surrounding whitespace
+-- trimmed.
+function M.menu_lines()
+  local winid = M.menu_winid()
+  if not winid then
+    return {}
+  end
+  local bufnr = vim.api.nvim_win_get_buf(winid)
+  return vim.tbl_map(
+    vim.trim,
+    vim.api.nvim_buf_get_lines(bufnr, 0, -1, false)
+  )
+end
+
+-- Returns the `{ row, start_col, end_col }` ranges highlighted as matching
+-- the typed text in the menu, with 0-indexed rows.
+function M.menu_matches()
+  local winid = M.menu_winid()
+  local nsid = vim.api.nvim_get_namespaces()["compleet_matched_chars"]
+  local bufnr = vim.api.nvim_win_get_buf(winid)
+  return vim.tbl_map(function(extmark)
+    return { extmark[2], extmark[3], extmark[4].end_col }
+  end, vim.api.nvim_buf_get_extmarks(bufnr, nsid, 0, -1, { details = true }))
+end
+
+function M.eq(expected, actual)
+  if not vim.deep_equal(expected, actual) then
+    error(
+      ("expected %s, got %s"):format(vim.inspect(expected), vim.inspect(actual)),
+      2
+    )
+  end
+end
+
+-- Runs the spec in `tests/lua/<spec>.lua` and quits, with a non-zero status
+-- if it raised an error or if any of its checks failed.
+function M.run(spec)
+  local ok, err = pcall(require, spec)
+  if not ok then
+    table.insert(failures, err)
+  end
+
+  for _, failure in ipairs(failures) do
+    io.stderr:write(tostring(failure) .. "\n")
+  end
+
+  vim.cmd(#failures == 0 and "qall!" or "cquit! 1")
+end
+
+return M
@@ -0,0 +1,16 @@
+local h = require("harness")
+
+local compleet = h.setup()
+
+h.insert("fo<C-n>", function()
+  h.eq(true, compleet.is_completion_selected())
+  h.eq(h.menu_lines()[1], compleet.status().label)
+
+  local winid = h.menu_winid()
+  h.eq(true, vim.api.nvim_win_get_option(winid, "cursorline"))
+  h.eq(1, vim.api.nvim_win_get_cursor(winid)[1])
+end)
+
+h.insert(" fo<C-n><C-n>", function()
+  h.eq(h.menu_lines()[2], compleet.status().label)
+end)
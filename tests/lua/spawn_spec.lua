@@ -0,0 +1,11 @@
+local h = require("harness")
+
+h.setup()
+
+h.insert("fo", function()
+  h.eq({ "foobar", "foobaz" }, h.menu_lines())
+  h.eq({ { 0, 1, 3 }, { 1, 1, 3 } }, h.menu_matches())
+end)
+
+-- Leaving insert mode closes the menu.
+h.eq(nil, h.menu_winid())